@@ -0,0 +1,110 @@
+// This file is part of the uutils findutils package.
+//
+// For the full copyright and license information, please view the LICENSE
+// file that was distributed with this source code.
+
+//! How much space the system allows for the arguments of a command, as used
+//! by find's `-exec ... {} +` and by xargs.
+
+use std::ffi::OsStr;
+
+/// Count the space an argument or environment string takes up when running a
+/// command.
+#[cfg(windows)]
+pub fn count_osstr_chars_for_exec(s: &OsStr) -> usize {
+    use std::os::windows::ffi::OsStrExt;
+    // Include +1 for either the null terminator or trailing space.
+    s.encode_wide().count() + 1
+}
+
+/// Count the space an argument or environment string takes up when running a
+/// command.
+#[cfg(unix)]
+pub fn count_osstr_chars_for_exec(s: &OsStr) -> usize {
+    use std::os::unix::ffi::OsStrExt;
+    // Include +1 for the null terminator.
+    s.as_bytes().len() + 1
+}
+
+/// The space each argument or environment string takes up besides its
+/// characters.  Linux counts the argv/envp pointers against ARG_MAX too.
+#[cfg(unix)]
+const POINTER_SIZE: usize = std::mem::size_of::<usize>();
+#[cfg(windows)]
+const POINTER_SIZE: usize = 0;
+
+/// Count the space an argument takes up against the system's limit on the
+/// size of a command (unlike `count_osstr_chars_for_exec()`, which is how
+/// xargs -s counts).
+pub fn count_osstr_size_for_exec(s: &OsStr) -> usize {
+    count_osstr_chars_for_exec(s) + POINTER_SIZE
+}
+
+/// The limits the system places on the size of a command.
+pub struct SystemLimits {
+    /// The space taken up by the environment passed to each command.
+    pub env_size: usize,
+    /// The system's upper limit on the size of arguments and environment.
+    pub arg_max: usize,
+    /// The space left for the command and its arguments, as counted by
+    /// `count_osstr_size_for_exec()`.
+    pub max_command_size: usize,
+}
+
+impl SystemLimits {
+    /// The smallest value of `ARG_MAX` that POSIX allows.
+    pub const POSIX_ARG_MAX: usize = 4096;
+
+    /// Get the limits for commands run with the given environment.
+    pub fn new<K, V>(env: impl IntoIterator<Item = (K, V)>) -> Self
+    where
+        K: AsRef<OsStr>,
+        V: AsRef<OsStr>,
+    {
+        let mut env_size = 0;
+        let mut env_vars = 0;
+        for (var, value) in env {
+            env_size += count_osstr_chars_for_exec(var.as_ref())
+                + count_osstr_chars_for_exec(value.as_ref());
+            env_vars += 1;
+        }
+
+        Self::with_env_size(env_size, env_vars * POINTER_SIZE)
+    }
+
+    /// Get the limits for commands run with our own environment.
+    pub fn current() -> Self {
+        Self::new(std::env::vars_os())
+    }
+
+    #[cfg(windows)]
+    fn with_env_size(env_size: usize, _env_pointers_size: usize) -> Self {
+        // Taken from the CreateProcess docs.
+        const MAX_CMDLINE: usize = 32767;
+        Self {
+            env_size,
+            arg_max: MAX_CMDLINE,
+            max_command_size: MAX_CMDLINE,
+        }
+    }
+
+    #[cfg(unix)]
+    fn with_env_size(env_size: usize, env_pointers_size: usize) -> Self {
+        // POSIX requires that we leave 2048 bytes of space so that the child processes
+        // can have room to set their own environment variables.
+        const ARG_HEADROOM: usize = 2048;
+        let arg_max = unsafe { uucore::libc::sysconf(uucore::libc::_SC_ARG_MAX) };
+        // sysconf() returns -1 if there is no definite limit, so pick the POSIX minimum.
+        let arg_max = if arg_max > 0 {
+            arg_max as usize
+        } else {
+            Self::POSIX_ARG_MAX
+        };
+
+        Self {
+            env_size,
+            arg_max,
+            max_command_size: arg_max.saturating_sub(ARG_HEADROOM + env_size + env_pointers_size),
+        }
+    }
+}
@@ -0,0 +1,9 @@
+// This file is part of the uutils findutils package.
+//
+// For the full copyright and license information, please view the LICENSE
+// file that was distributed with this source code.
+
+//! Code shared between find and xargs.
+
+pub mod limits;
+pub mod prompt;
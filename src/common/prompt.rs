@@ -0,0 +1,88 @@
+// This file is part of the uutils findutils package.
+//
+// For the full copyright and license information, please view the LICENSE
+// file that was distributed with this source code.
+
+//! Asking the user whether to run a command, for find's `-ok` and `-okdir`
+//! and xargs `-p`.
+
+use std::fs::File;
+use std::io::{self, stdin, BufRead, BufReader};
+
+use regex::Regex;
+
+#[cfg(unix)]
+pub const TTY_PATH: &str = "/dev/tty";
+
+#[cfg(windows)]
+pub const TTY_PATH: &str = "CON";
+
+/// Open the controlling terminal for reading.
+pub fn open_tty() -> Result<File, String> {
+    File::open(TTY_PATH).map_err(|e| format!("Failed to open {TTY_PATH} for reading: {e}"))
+}
+
+/// Get the regular expression that matches affirmative answers in the
+/// current locale (the `LC_MESSAGES` category).
+#[cfg(any(target_os = "linux", target_os = "macos", target_os = "freebsd"))]
+fn yes_expr() -> String {
+    use std::ffi::CStr;
+    use uucore::libc::{nl_langinfo, YESEXPR};
+
+    let expr = unsafe { nl_langinfo(YESEXPR) };
+    if expr.is_null() {
+        return "^[yY]".to_string();
+    }
+    let expr = unsafe { CStr::from_ptr(expr) }.to_string_lossy();
+    if expr.is_empty() {
+        "^[yY]".to_string()
+    } else {
+        expr.into_owned()
+    }
+}
+
+#[cfg(not(any(target_os = "linux", target_os = "macos", target_os = "freebsd")))]
+fn yes_expr() -> String {
+    "^[yY]".to_string()
+}
+
+/// Check whether a response to a prompt counts as "yes", using the same
+/// locale-dependent rules as GNU's `yesno()`.
+pub fn is_affirmative(response: &str) -> bool {
+    let response = response.trim_end_matches(['\r', '\n']);
+    match Regex::new(&yes_expr()) {
+        Ok(re) => re.is_match(response),
+        Err(_) => response.starts_with(['y', 'Y']),
+    }
+}
+
+/// Reads a single line in response to a prompt. The answer comes from
+/// standard input if possible, otherwise from the controlling terminal.
+/// Returns None at end of file.
+pub fn read_response() -> io::Result<Option<String>> {
+    let mut response = vec![];
+    let bytes_read = match stdin().lock().read_until(b'\n', &mut response) {
+        Ok(bytes_read) => bytes_read,
+        // stdin has been closed, so fall back to asking the terminal directly
+        Err(_) => BufReader::new(File::open(TTY_PATH)?).read_until(b'\n', &mut response)?,
+    };
+    if bytes_read == 0 {
+        return Ok(None);
+    }
+    Ok(Some(String::from_utf8_lossy(&response).into_owned()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn affirmative_responses() {
+        // Tests run in the C locale, where only answers starting with y/Y count.
+        assert!(is_affirmative("y\n"));
+        assert!(is_affirmative("Yes\n"));
+        assert!(!is_affirmative("n\n"));
+        assert!(!is_affirmative("\n"));
+        assert!(!is_affirmative(" y\n"));
+    }
+}
@@ -0,0 +1,155 @@
+// This file is part of the uutils findutils package.
+//
+// For the full copyright and license information, please view the LICENSE
+// file that was distributed with this source code.
+
+//! Debugging aids (-D).
+
+use std::io::{stderr, Write};
+use std::path::Path;
+use std::sync::atomic::{AtomicU64, Ordering};
+
+/// Something -D can show.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum DebugOption {
+    /// Commands run by -exec, -execdir, -ok and -okdir.
+    Exec,
+    /// How the optimizer rewrote the expression.
+    Opt,
+    /// How often each test or action succeeded.
+    Rates,
+    /// The files visited while walking directory trees.
+    Search,
+    /// Every stat() call, and how many there were.
+    Stat,
+    /// The expression as parsed and as optimized.
+    Tree,
+}
+
+impl DebugOption {
+    /// All the options, with their names and descriptions for -D help.
+    const ALL: [(Self, &'static str, &'static str); 6] = [
+        (
+            Self::Exec,
+            "exec",
+            "Show the commands run by -exec, -execdir, -ok and -okdir",
+        ),
+        (
+            Self::Opt,
+            "opt",
+            "Show how the expression was optimized (see -O)",
+        ),
+        (
+            Self::Rates,
+            "rates",
+            "Show how often each test or action succeeded",
+        ),
+        (Self::Search, "search", "Trace the directory tree traversal"),
+        (Self::Stat, "stat", "Show every stat() call, and count them"),
+        (
+            Self::Tree,
+            "tree",
+            "Show the expression tree, before and after optimization",
+        ),
+    ];
+
+    fn bit(self) -> u8 {
+        1 << self as u8
+    }
+}
+
+/// A set of [DebugOption]s.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct DebugFlags(u8);
+
+impl DebugFlags {
+    /// Parses a single argument to -D, which is either an option's name or
+    /// "all".
+    pub fn parse(name: &str) -> Option<Self> {
+        if name == "all" {
+            let all = DebugOption::ALL
+                .iter()
+                .fold(0, |bits, (o, _, _)| bits | o.bit());
+            return Some(Self(all));
+        }
+        DebugOption::ALL
+            .iter()
+            .find(|(_, n, _)| *n == name)
+            .map(|(option, _, _)| Self(option.bit()))
+    }
+
+    pub fn insert(&mut self, flags: Self) {
+        self.0 |= flags.0;
+    }
+
+    pub fn is_empty(self) -> bool {
+        self.0 == 0
+    }
+
+    pub fn contains(self, option: DebugOption) -> bool {
+        self.0 & option.bit() != 0
+    }
+}
+
+/// Writes a line of debugging output for `option`.
+pub fn log(option: DebugOption, message: impl std::fmt::Display) {
+    let name = DebugOption::ALL
+        .iter()
+        .find(|(o, _, _)| *o == option)
+        .map_or("", |(_, name, _)| name);
+    writeln!(&mut stderr(), "-D {name}: {message}").unwrap();
+}
+
+/// What a single run of find keeps track of for -D, shared by the walk
+/// (including its worker threads) and the entries it produces.
+#[derive(Debug)]
+pub struct Debugger {
+    /// The options that were given, for the debugging output that comes from
+    /// deep inside the walk or the matchers.
+    flags: DebugFlags,
+    /// The number of stat() calls made so far, by any thread.
+    stat_calls: AtomicU64,
+}
+
+impl Debugger {
+    pub fn new(flags: DebugFlags) -> Self {
+        Self {
+            flags,
+            stat_calls: AtomicU64::new(0),
+        }
+    }
+
+    /// Checks whether `option` was given.
+    pub fn enabled(&self, option: DebugOption) -> bool {
+        self.flags.contains(option)
+    }
+
+    /// Records a stat() (or lstat(), fstat() etc.) call for `path`.
+    pub fn count_stat(&self, path: &Path) {
+        self.stat_calls.fetch_add(1, Ordering::Relaxed);
+        if self.enabled(DebugOption::Stat) {
+            log(DebugOption::Stat, format_args!("stat({})", path.display()));
+        }
+    }
+
+    /// Gets the number of stat() calls made so far.
+    pub fn stat_calls(&self) -> u64 {
+        self.stat_calls.load(Ordering::Relaxed)
+    }
+}
+
+/// Records a stat() call for `path`, if this run is being debugged.
+pub fn count_stat(debugger: Option<&Debugger>, path: &Path) {
+    if let Some(debugger) = debugger {
+        debugger.count_stat(path);
+    }
+}
+
+/// Lists the options for -D help.
+pub fn print_help() {
+    println!("Valid arguments for -D:");
+    for (_, name, description) in DebugOption::ALL {
+        println!("{name:<8}{description}");
+    }
+    println!("all     Enable all of the above");
+}
@@ -0,0 +1,684 @@
+// This file is part of the uutils findutils package.
+//
+// For the full copyright and license information, please view the LICENSE
+// file that was distributed with this source code.
+
+//! A directory walker that keeps the directories it is walking open, and
+//! accesses everything inside them relative to their file descriptors with
+//! openat(), fstatat() etc.  Unlike looking files up by their full path, this
+//! works however deep the tree is, and a directory can't be swapped for a
+//! symlink behind our back while we're inside it.  The open directory is
+//! passed on to matchers through [WalkEntry::dir_fd()].
+
+use std::collections::VecDeque;
+use std::ffi::OsStr;
+use std::fs::Metadata;
+use std::io;
+use std::os::unix::ffi::OsStrExt;
+use std::os::unix::fs::MetadataExt;
+use std::os::unix::io::AsRawFd;
+use std::path::{Path, PathBuf};
+use std::rc::Rc;
+use std::sync::Arc;
+use std::vec;
+
+use nix::dir::Dir;
+use nix::errno::Errno;
+use nix::fcntl::{AtFlags, OFlag};
+use nix::sys::stat::{fstat, fstatat, FileStat, Mode};
+
+use super::debug::{count_stat, Debugger};
+use super::matchers::{DirFd, FileType, Follow, WalkEntry, WalkError};
+use super::Config;
+
+/// The most directories to keep open at once.  Directories further up the
+/// tree are closed, and re-opened once we get back to them.
+pub const MAX_OPEN_DIRS: usize = 64;
+
+/// The parts of [Config] that affect a walk, along with the state of the
+/// run it's part of.
+#[derive(Clone)]
+pub struct WalkOptions {
+    pub follow: Follow,
+    pub min_depth: usize,
+    pub max_depth: usize,
+    pub contents_first: bool,
+    pub same_file_system: bool,
+    pub sorted: bool,
+    /// Where to count stat() calls, for -D stat.
+    pub debugger: Option<Arc<Debugger>>,
+}
+
+impl WalkOptions {
+    pub fn new(config: &Config, debugger: Option<Arc<Debugger>>) -> Self {
+        Self {
+            follow: config.follow,
+            min_depth: config.min_depth,
+            max_depth: config.max_depth,
+            contents_first: config.depth_first,
+            same_file_system: config.same_file_system,
+            sorted: config.sorted_output,
+            debugger,
+        }
+    }
+}
+
+/// An entry read from a directory.
+pub struct Child {
+    name: Box<OsStr>,
+    /// The type from the directory entry, if the file system reports it.
+    file_type: Option<FileType>,
+}
+
+/// What we know about an entry after looking at it.
+pub struct ChildInfo {
+    /// The file type, following symlinks as necessary.
+    pub file_type: FileType,
+    pub is_symlink: bool,
+    /// The stat() result, if we needed it.
+    stat: Option<FileStat>,
+}
+
+impl ChildInfo {
+    /// Get the device and inode numbers of the file, if known.
+    pub fn id(&self) -> Option<(u64, u64)> {
+        self.stat.as_ref().map(file_id)
+    }
+
+    /// Check whether the walk should descend into this entry.
+    pub fn should_descend(&self, depth: usize, options: &WalkOptions, root_dev: u64) -> bool {
+        self.file_type.is_dir()
+            && depth < options.max_depth
+            && !(options.same_file_system && self.id().is_some_and(|id| id.0 != root_dev))
+    }
+}
+
+/// A directory entry that will be produced once its contents have been.
+pub enum Deferred {
+    /// A starting point, with its metadata if we needed it.
+    Root(Option<Metadata>),
+    Child {
+        file_type: FileType,
+        is_symlink: bool,
+    },
+}
+
+impl Deferred {
+    /// Makes the entry for the directory at `path`, which was found in `dir`.
+    pub fn into_entry(
+        self,
+        path: PathBuf,
+        depth: usize,
+        dir: Option<Rc<DirFd>>,
+        follow: Follow,
+    ) -> WalkEntry {
+        match (self, dir) {
+            (Self::Root(Some(meta)), _) => WalkEntry::with_metadata(path, 0, follow, Ok(meta)),
+            (Self::Root(None), _) => {
+                WalkEntry::with_file_type(path, 0, follow, FileType::Directory)
+            }
+            (
+                Self::Child {
+                    file_type,
+                    is_symlink,
+                },
+                Some(dir),
+            ) => WalkEntry::at(dir, path, depth, follow, file_type, is_symlink),
+            (Self::Child { .. }, None) => WalkEntry::new(path, depth, follow),
+        }
+    }
+}
+
+/// Get the device and inode numbers from a stat() result.
+#[allow(clippy::unnecessary_cast)]
+fn file_id(stat: &FileStat) -> (u64, u64) {
+    (stat.st_dev as u64, stat.st_ino as u64)
+}
+
+/// The flags to open a directory with.
+fn dir_flags(follow: bool) -> OFlag {
+    let flags = OFlag::O_RDONLY | OFlag::O_DIRECTORY | OFlag::O_CLOEXEC;
+    if follow {
+        flags
+    } else {
+        flags | OFlag::O_NOFOLLOW
+    }
+}
+
+/// Opens the directory at `path`, relative to its parent directory if that's
+/// open.
+pub fn open_dir(parent: Option<&DirFd>, path: &Path, followed: bool) -> Result<Dir, Errno> {
+    let flags = dir_flags(followed);
+    match (parent, path.file_name()) {
+        (Some(parent), Some(name)) => {
+            Dir::openat(Some(parent.as_raw_fd()), name, flags, Mode::empty())
+        }
+        _ => Dir::open(path, flags, Mode::empty()),
+    }
+}
+
+/// Re-opens the directory at `path`, whose contents are at `depth`, after it
+/// was closed to save file descriptors.  It's found through `child` (one of
+/// its subdirectories that's still open, and wasn't reached through a
+/// symlink) if possible, and must still be the directory with the given ID.
+pub fn reopen_dir(
+    path: &Path,
+    depth: usize,
+    followed: bool,
+    id: Option<(u64, u64)>,
+    child: Option<&DirFd>,
+    debugger: Option<&Debugger>,
+) -> Result<DirFd, WalkError> {
+    let dir = match child {
+        Some(dir) => Dir::openat(Some(dir.as_raw_fd()), "..", dir_flags(false), Mode::empty()),
+        None => Dir::open(path, dir_flags(followed), Mode::empty()),
+    };
+    let dir = dir.and_then(|dir| Ok((dir_id(&dir, path, debugger)?, dir)));
+
+    match dir {
+        Ok((dir_id, dir)) if Some(dir_id) == id => Ok(DirFd::new(dir)),
+        // The directory was moved or replaced while we weren't looking,
+        // so don't walk any more of it.
+        result => {
+            let e = result.err().unwrap_or(Errno::ENOENT);
+            Err(WalkError::from_io_at(&e.into(), path, depth - 1))
+        }
+    }
+}
+
+/// Gets the device and inode numbers of an open directory.
+pub fn dir_id(
+    dir: &impl AsRawFd,
+    path: &Path,
+    debugger: Option<&Debugger>,
+) -> Result<(u64, u64), Errno> {
+    count_stat(debugger, path);
+    Ok(file_id(&fstat(dir.as_raw_fd())?))
+}
+
+/// Reads everything in an open directory.
+pub fn read_dir(
+    dir: &mut Dir,
+    path: &Path,
+    depth: usize,
+    sorted: bool,
+) -> Vec<Result<Child, WalkError>> {
+    let mut entries: Vec<_> = dir
+        .iter()
+        .filter_map(|entry| {
+            let entry = match entry {
+                Ok(entry) => entry,
+                Err(e) => return Some(Err(WalkError::from_io_at(&e.into(), path, depth))),
+            };
+            let name = entry.file_name().to_bytes();
+            if name == b"." || name == b".." {
+                return None;
+            }
+            Some(Ok(Child {
+                name: OsStr::from_bytes(name).into(),
+                file_type: entry.file_type().map(FileType::from),
+            }))
+        })
+        .collect();
+
+    if sorted {
+        entries.sort_by(|a, b| match (a, b) {
+            (Ok(a), Ok(b)) => a.name.cmp(&b.name),
+            // Put errors first, to keep the order deterministic.
+            (Ok(_), Err(_)) => std::cmp::Ordering::Greater,
+            (Err(_), Ok(_)) => std::cmp::Ordering::Less,
+            (Err(_), Err(_)) => std::cmp::Ordering::Equal,
+        });
+    }
+    entries
+}
+
+impl Child {
+    /// Get the name of this entry in its directory.
+    pub fn name(&self) -> &OsStr {
+        &self.name
+    }
+
+    /// Get the path of this entry in the directory at `dir_path`.
+    pub fn path_in(&self, dir_path: &Path) -> PathBuf {
+        dir_path.join(&*self.name)
+    }
+
+    /// Gets the type of this entry, which is at `path` in the open directory
+    /// `dir`, along with its stat() result if that was needed to find it out.
+    pub fn stat(
+        &self,
+        dir: &DirFd,
+        path: &Path,
+        depth: usize,
+        options: &WalkOptions,
+    ) -> Result<ChildInfo, WalkError> {
+        let stat = |flags| {
+            count_stat(options.debugger.as_deref(), path);
+            fstatat(Some(dir.as_raw_fd()), &*self.name, flags)
+                .map_err(|e| WalkError::from_io_at(&e.into(), path, depth))
+        };
+
+        let (mut file_type, mut st) = match self.file_type {
+            Some(file_type) => (file_type, None),
+            None => {
+                let st = stat(AtFlags::AT_SYMLINK_NOFOLLOW)?;
+                (FileType::from(&st), Some(st))
+            }
+        };
+        let is_symlink = file_type.is_symlink();
+        let follows = options.follow.follow_at_depth(depth);
+
+        if is_symlink && follows {
+            match stat(AtFlags::empty()) {
+                Ok(target) => {
+                    file_type = FileType::from(&target);
+                    st = Some(target);
+                }
+                // Broken symlinks are reported as symlinks
+                Err(e) if e.is_not_found() => {}
+                Err(e) => return Err(e),
+            }
+        }
+
+        // Directories we might descend into need their ID, to check for
+        // loops and file system boundaries.
+        let needs_id = follows || options.same_file_system;
+        if file_type.is_dir() && st.is_none() && depth < options.max_depth && needs_id {
+            let flags = if follows {
+                AtFlags::empty()
+            } else {
+                AtFlags::AT_SYMLINK_NOFOLLOW
+            };
+            st = Some(stat(flags)?);
+        }
+
+        Ok(ChildInfo {
+            file_type,
+            is_symlink,
+            stat: st,
+        })
+    }
+}
+
+/// A starting point, after looking at it.
+pub enum Root {
+    /// A directory, which has been opened without needing to stat() it.
+    Dir(Dir),
+    /// Anything else, or a directory we couldn't open (which is reported once
+    /// we try to read it).
+    Other(Metadata),
+}
+
+impl Root {
+    /// Looks at a starting point, or gets the error to report for it.
+    /// Directories are opened straight away, which saves a stat() call.
+    pub fn open(root: &Path, options: &WalkOptions) -> Result<Self, WalkError> {
+        if let Ok(dir) = open_dir(None, root, options.follow.follow_at_depth(0)) {
+            return Ok(Self::Dir(dir));
+        }
+        root_metadata(root, options).map(Self::Other)
+    }
+
+    pub fn is_dir(&self) -> bool {
+        match self {
+            Self::Dir(_) => true,
+            Self::Other(meta) => meta.is_dir(),
+        }
+    }
+
+    /// Gets the device and inode numbers, if the walk needs them to check
+    /// for loops and file system boundaries.
+    pub fn id(&self, root: &Path, options: &WalkOptions) -> Result<Option<(u64, u64)>, WalkError> {
+        match self {
+            Self::Other(meta) => Ok(Some((meta.dev(), meta.ino()))),
+            Self::Dir(dir) if options.follow == Follow::Always || options.same_file_system => {
+                let id = dir_id(dir, root, options.debugger.as_deref())
+                    .map_err(|e| WalkError::from_io_at(&e.into(), root, 0))?;
+                Ok(Some(id))
+            }
+            Self::Dir(_) => Ok(None),
+        }
+    }
+
+    /// Makes the entry for the starting point.
+    pub fn entry(&self, root: PathBuf, follow: Follow) -> WalkEntry {
+        self.deferred().into_entry(root, 0, None, follow)
+    }
+
+    /// Makes the entry for the starting point, for once its contents have
+    /// been walked.
+    pub fn deferred(&self) -> Deferred {
+        match self {
+            Self::Dir(_) => Deferred::Root(None),
+            Self::Other(meta) => Deferred::Root(Some(meta.clone())),
+        }
+    }
+}
+
+/// A starting point, once it has been looked at.
+pub enum Start {
+    /// A directory to walk, with its device and inode numbers if the walk
+    /// needs them (see [Root::id()]).
+    Dir(Root, Option<(u64, u64)>),
+    /// Anything else, or a directory that the walk doesn't go inside
+    /// (-maxdepth 0), which is the only entry to produce.
+    Leaf(WalkEntry),
+}
+
+impl Start {
+    /// Looks at a starting point, or gets the error to report for it.
+    pub fn new(root: &Path, options: &WalkOptions) -> Result<Self, WalkError> {
+        let opened = Root::open(root, options)?;
+        if !opened.is_dir() || options.max_depth == 0 {
+            return Ok(Self::Leaf(opened.entry(root.to_path_buf(), options.follow)));
+        }
+        let id = opened.id(root, options)?;
+        Ok(Self::Dir(opened, id))
+    }
+}
+
+/// Produces an entry, unless it's shallower than -mindepth.
+pub fn produce(entry: WalkEntry, options: &WalkOptions) -> Option<Result<WalkEntry, WalkError>> {
+    if entry.depth() < options.min_depth {
+        None
+    } else {
+        Some(Ok(entry.with_debugger(options.debugger.clone())))
+    }
+}
+
+/// Gets the metadata of a starting point, or the error to report for it.
+pub fn root_metadata(root: &Path, options: &WalkOptions) -> Result<Metadata, WalkError> {
+    options
+        .follow
+        .counted_metadata_at_depth(root, 0, options.debugger.as_deref())
+        .map_err(|e| {
+            let e = io::Error::from(e);
+            WalkError::from_io_at(&e, root, 0)
+        })
+}
+
+/// The error for a directory that would be its own ancestor.
+pub fn loop_error(path: &Path, depth: usize) -> WalkError {
+    let e = io::Error::from_raw_os_error(uucore::libc::ELOOP);
+    WalkError::from_io_at(&e, path, depth)
+}
+
+/// A directory that is being walked.
+struct Frame {
+    path: PathBuf,
+    /// The depth of the directory's contents.
+    depth: usize,
+    /// The open directory, unless it hasn't been opened yet or has been
+    /// closed to save file descriptors.
+    dir: Option<Rc<DirFd>>,
+    /// The directory, if it has been opened but not read yet.
+    unread: Option<Dir>,
+    /// The directory's contents, once it has been read.
+    entries: Option<vec::IntoIter<Result<Child, WalkError>>>,
+    /// The directory's device and inode numbers, if known.  They're only
+    /// looked up when needed, e.g. to make sure a directory that was closed
+    /// is still the same when it's re-opened.
+    id: Option<(u64, u64)>,
+    /// Whether the directory was reached through a symlink, in which case
+    /// its ".." may not lead back to its parent.
+    followed: bool,
+    /// With -depth, the directory itself, which comes after its contents.
+    deferred: Option<Deferred>,
+}
+
+/// Walks a single starting point depth-first, in the same order as walkdir
+/// would.
+pub struct FdWalk {
+    root: Option<PathBuf>,
+    root_dev: u64,
+    options: WalkOptions,
+    stack: Vec<Frame>,
+    /// Whether the innermost directory was the last thing produced, i.e.
+    /// whether there is anything for skip_current_dir() to skip.
+    entered: bool,
+    /// Directories that have been left, with the depth of their contents.
+    finished: VecDeque<(PathBuf, usize)>,
+}
+
+impl FdWalk {
+    pub fn new(root: &Path, options: WalkOptions) -> Self {
+        Self {
+            root: Some(root.to_path_buf()),
+            root_dev: 0,
+            options,
+            stack: vec![],
+            entered: false,
+            finished: VecDeque::new(),
+        }
+    }
+
+    /// Takes the next directory that the walk has left (in the order they
+    /// were left), along with the depth of its contents.  Everything in it
+    /// was produced before the entry that came after it.
+    pub fn pop_finished_dir(&mut self) -> Option<(PathBuf, usize)> {
+        self.finished.pop_front()
+    }
+
+    /// Doesn't descend into the directory that was just produced (-prune).
+    /// Does nothing if the last entry wasn't a directory that would have
+    /// been descended into, or with -depth, when it's too late.
+    pub fn skip_current_dir(&mut self) {
+        if self.entered {
+            self.entered = false;
+            if let Some(frame) = self.stack.last_mut() {
+                frame.entries = Some(vec![].into_iter());
+            }
+        }
+    }
+
+    /// Opens and reads the innermost directory.
+    fn open_top(&mut self) {
+        let parent = match self.stack.len() {
+            0 | 1 => None,
+            n => self.stack[n - 2].dir.clone(),
+        };
+        let frame = self.stack.last_mut().unwrap();
+
+        let opened = match frame.unread.take() {
+            Some(dir) => Ok(dir),
+            None => open_dir(parent.as_deref(), &frame.path, frame.followed),
+        };
+        let mut dir = match opened {
+            Ok(dir) => dir,
+            Err(e) => {
+                let e = WalkError::from_io_at(&e.into(), &frame.path, frame.depth - 1);
+                frame.entries = Some(vec![Err(e)].into_iter());
+                return;
+            }
+        };
+
+        let entries = read_dir(&mut dir, &frame.path, frame.depth, self.options.sorted);
+        frame.entries = Some(entries.into_iter());
+        frame.dir = Some(Rc::new(DirFd::new(dir)));
+
+        // Entries that are still in use keep their directory open until
+        // they're dropped, but that's at most a few.
+        let open = self.stack.iter().filter(|f| f.dir.is_some()).count();
+        if open > MAX_OPEN_DIRS {
+            if let Some(oldest) = self.stack.iter_mut().find(|f| f.dir.is_some()) {
+                let dir = oldest.dir.take().unwrap();
+                if oldest.id.is_none() {
+                    // Without its ID, it won't be re-opened
+                    oldest.id = dir_id(&*dir, &oldest.path, self.options.debugger.as_deref()).ok();
+                }
+            }
+        }
+    }
+
+    /// Re-opens the innermost directory after it was closed to save file
+    /// descriptors, going through `child` if possible.
+    fn reopen_top(&mut self, child: &Frame) -> Result<(), WalkError> {
+        let frame = self.stack.last_mut().unwrap();
+        let child_dir = child.dir.as_deref().filter(|_| !child.followed);
+        let dir = reopen_dir(
+            &frame.path,
+            frame.depth,
+            frame.followed,
+            frame.id,
+            child_dir,
+            self.options.debugger.as_deref(),
+        )?;
+        frame.dir = Some(Rc::new(dir));
+        Ok(())
+    }
+
+    /// Handles an entry that has just been read from the innermost
+    /// directory, returning it if it should be produced now.
+    fn visit_child(&mut self, child: Child) -> Option<Result<WalkEntry, WalkError>> {
+        let frame = self.stack.last().unwrap();
+        let path = child.path_in(&frame.path);
+        let depth = frame.depth;
+        let dir = frame.dir.clone().expect("directory should be open");
+        let follow = self.options.follow;
+
+        let info = match child.stat(&dir, &path, depth, &self.options) {
+            Ok(info) => info,
+            Err(e) => return Some(Err(e)),
+        };
+        let (file_type, is_symlink) = (info.file_type, info.is_symlink);
+
+        if !info.should_descend(depth, &self.options, self.root_dev) {
+            let entry = WalkEntry::at(dir, path, depth, follow, file_type, is_symlink);
+            return produce(entry, &self.options);
+        }
+
+        let follows = follow.follow_at_depth(depth);
+        let id = info.id();
+        if follows && self.stack.iter().any(|frame| frame.id == id) {
+            return Some(Err(loop_error(&path, depth)));
+        }
+
+        let mut frame = Frame {
+            path: path.clone(),
+            depth: depth + 1,
+            dir: None,
+            unread: None,
+            entries: None,
+            id,
+            followed: is_symlink && follows,
+            deferred: None,
+        };
+
+        if self.options.contents_first {
+            frame.deferred = Some(Deferred::Child {
+                file_type,
+                is_symlink,
+            });
+            self.stack.push(frame);
+            None
+        } else {
+            self.stack.push(frame);
+            let entry = WalkEntry::at(dir, path, depth, follow, file_type, is_symlink);
+            self.produce_dir(entry)
+        }
+    }
+
+    /// Handles the starting point, returning it if it should be produced now.
+    fn visit_root(&mut self, root: PathBuf) -> Option<Result<WalkEntry, WalkError>> {
+        let (opened, id) = match Start::new(&root, &self.options) {
+            Ok(Start::Dir(opened, id)) => (opened, id),
+            Ok(Start::Leaf(entry)) => return produce(entry, &self.options),
+            Err(e) => return Some(Err(e)),
+        };
+        self.root_dev = id.map_or(0, |id| id.0);
+        let follow = self.options.follow;
+
+        let mut frame = Frame {
+            path: root.clone(),
+            depth: 1,
+            dir: None,
+            unread: None,
+            entries: None,
+            id,
+            followed: follow.follow_at_depth(0),
+            deferred: None,
+        };
+        let entry = opened.entry(root, follow);
+        let deferred = opened.deferred();
+        if let Root::Dir(dir) = opened {
+            frame.unread = Some(dir);
+        }
+
+        if self.options.contents_first {
+            frame.deferred = Some(deferred);
+            self.stack.push(frame);
+            None
+        } else {
+            self.stack.push(frame);
+            self.produce_dir(entry)
+        }
+    }
+
+    /// Finishes walking the innermost directory, returning anything that
+    /// should be produced now.
+    fn pop(&mut self) -> Option<Result<WalkEntry, WalkError>> {
+        let frame = self.stack.pop().unwrap();
+        self.finished.push_back((frame.path.clone(), frame.depth));
+
+        if let Some(parent) = self.stack.last() {
+            if parent.dir.is_none() && parent.entries.is_some() {
+                if let Err(e) = self.reopen_top(&frame) {
+                    let parent = self.stack.last_mut().unwrap();
+                    parent.entries = Some(vec![Err(e)].into_iter());
+                }
+            }
+        }
+
+        let parent_dir = self.stack.last().and_then(|parent| parent.dir.clone());
+        let entry = frame.deferred?.into_entry(
+            frame.path,
+            frame.depth - 1,
+            parent_dir,
+            self.options.follow,
+        );
+        produce(entry, &self.options)
+    }
+
+    /// Produces a directory that has just been entered.
+    fn produce_dir(&mut self, entry: WalkEntry) -> Option<Result<WalkEntry, WalkError>> {
+        let result = produce(entry, &self.options);
+        self.entered = result.is_some();
+        result
+    }
+}
+
+impl Iterator for FdWalk {
+    type Item = Result<WalkEntry, WalkError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.entered = false;
+        if let Some(root) = self.root.take() {
+            if let Some(result) = self.visit_root(root) {
+                return Some(result);
+            }
+        }
+
+        loop {
+            let frame = self.stack.last_mut()?;
+            if frame.entries.is_none() {
+                self.open_top();
+            }
+
+            let frame = self.stack.last_mut().unwrap();
+            match frame.entries.as_mut().unwrap().next() {
+                Some(Ok(child)) => {
+                    if let Some(result) = self.visit_child(child) {
+                        return Some(result);
+                    }
+                }
+                Some(Err(e)) => return Some(Err(e)),
+                None => {
+                    if let Some(result) = self.pop() {
+                        return Some(result);
+                    }
+                }
+            }
+        }
+    }
+}
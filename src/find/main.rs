@@ -10,6 +10,12 @@ fn main() {
     // the downstream software of the standard output stream closes the pipe and triggers a panic.
     uucore::panic::mute_sigpipe_panic();
 
+    // Use the user's locale to interpret answers to -ok/-okdir prompts.
+    #[cfg(unix)]
+    unsafe {
+        uucore::libc::setlocale(uucore::libc::LC_MESSAGES, c"".as_ptr());
+    }
+
     let args = std::env::args().collect::<Vec<String>>();
     let strs: Vec<&str> = args.iter().map(std::convert::AsRef::as_ref).collect();
     let deps = findutils::find::StandardDependencies::new();
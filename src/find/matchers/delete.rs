@@ -10,7 +10,7 @@
 use std::fs;
 use std::io::{self, stderr, Write};
 
-use super::{Matcher, MatcherIO, WalkEntry};
+use super::{Cost, Matcher, MatcherIO, WalkEntry};
 
 pub struct DeleteMatcher;
 
@@ -20,7 +20,24 @@ impl DeleteMatcher {
     }
 
     fn delete(&self, entry: &WalkEntry) -> io::Result<()> {
-        if entry.file_type().is_dir() && !entry.path_is_symlink() {
+        let is_dir = entry.file_type().is_dir() && !entry.path_is_symlink();
+
+        // Delete relative to the parent directory if it's open, so that it
+        // can't be swapped for a symlink to somewhere else in the meantime.
+        #[cfg(unix)]
+        if let Some(dir) = entry.dir_fd() {
+            use nix::unistd::{unlinkat, UnlinkatFlags};
+            use std::os::unix::io::AsRawFd;
+
+            let flag = if is_dir {
+                UnlinkatFlags::RemoveDir
+            } else {
+                UnlinkatFlags::NoRemoveDir
+            };
+            return Ok(unlinkat(Some(dir.as_raw_fd()), entry.file_name(), flag)?);
+        }
+
+        if is_dir {
             fs::remove_dir(entry.path())
         } else {
             fs::remove_file(entry.path())
@@ -42,6 +59,8 @@ impl Matcher for DeleteMatcher {
 
         match self.delete(file_info) {
             Ok(()) => true,
+            // Someone else got there first
+            Err(e) if matcher_io.ignores_io_error(&e, file_info) => true,
             Err(e) => {
                 matcher_io.set_exit_code(1);
                 writeln!(&mut stderr(), "Failed to delete {path_str}: {e}").unwrap();
@@ -53,6 +72,10 @@ impl Matcher for DeleteMatcher {
     fn has_side_effects(&self) -> bool {
         true
     }
+
+    fn cost(&self) -> Cost {
+        Cost::Type
+    }
 }
 
 #[cfg(test)]
@@ -20,10 +20,11 @@ impl EmptyMatcher {
 }
 
 impl Matcher for EmptyMatcher {
-    fn matches(&self, file_info: &WalkEntry, _: &mut MatcherIO) -> bool {
+    fn matches(&self, file_info: &WalkEntry, matcher_io: &mut MatcherIO) -> bool {
         if file_info.file_type().is_file() {
             match file_info.metadata() {
                 Ok(meta) => meta.len() == 0,
+                Err(_) if matcher_io.ignores_error_for(file_info) => false,
                 Err(err) => {
                     writeln!(
                         &mut stderr(),
@@ -38,6 +39,7 @@ impl Matcher for EmptyMatcher {
         } else if file_info.file_type().is_dir() {
             match read_dir(file_info.path()) {
                 Ok(mut it) => it.next().is_none(),
+                Err(err) if matcher_io.ignores_io_error(&err, file_info) => false,
                 Err(err) => {
                     writeln!(
                         &mut stderr(),
@@ -8,19 +8,61 @@ use std::fs::{self, Metadata};
 use std::io::{self, ErrorKind};
 #[cfg(unix)]
 use std::os::unix::fs::FileTypeExt;
+#[cfg(unix)]
+use std::os::unix::io::{AsRawFd, RawFd};
 use std::path::{Path, PathBuf};
+#[cfg(unix)]
+use std::rc::Rc;
+use std::sync::Arc;
+
+#[cfg(unix)]
+use nix::dir::Dir;
 
 use walkdir::DirEntry;
 
 use super::Follow;
+use crate::find::debug::{count_stat, Debugger};
 
 /// Wrapper for a directory entry.
 #[derive(Debug)]
 enum Entry {
     /// Wraps an explicit path and depth.
     Explicit(PathBuf, usize),
+    /// Wraps an explicit path and depth whose file type is already known
+    /// (following symlinks as necessary).
+    Typed(PathBuf, usize, FileType),
     /// Wraps a WalkDir entry.
     WalkDir(DirEntry),
+    /// Wraps a path found in an open directory, along with its file type
+    /// (following symlinks as necessary) and whether it's a symlink itself.
+    #[cfg(unix)]
+    At {
+        dir: Rc<DirFd>,
+        path: PathBuf,
+        depth: usize,
+        file_type: FileType,
+        is_symlink: bool,
+    },
+}
+
+/// An open directory, which the files inside it can be accessed relative to
+/// with openat() and friends.
+#[cfg(unix)]
+#[derive(Debug)]
+pub struct DirFd(Dir);
+
+#[cfg(unix)]
+impl DirFd {
+    pub fn new(dir: Dir) -> Self {
+        Self(dir)
+    }
+}
+
+#[cfg(unix)]
+impl AsRawFd for DirFd {
+    fn as_raw_fd(&self) -> RawFd {
+        self.0.as_raw_fd()
+    }
 }
 
 /// File types.
@@ -37,19 +79,57 @@ pub enum FileType {
 }
 
 impl FileType {
+    /// Check whether this is a directory.
     pub fn is_dir(self) -> bool {
         self == Self::Directory
     }
 
+    /// Check whether this is a regular file.
     pub fn is_file(self) -> bool {
         self == Self::Regular
     }
 
+    /// Check whether this is a symbolic link.
     pub fn is_symlink(self) -> bool {
         self == Self::Symlink
     }
 }
 
+#[cfg(unix)]
+impl From<nix::dir::Type> for FileType {
+    fn from(t: nix::dir::Type) -> FileType {
+        use nix::dir::Type;
+
+        match t {
+            Type::Fifo => FileType::Fifo,
+            Type::CharacterDevice => FileType::CharDevice,
+            Type::Directory => FileType::Directory,
+            Type::BlockDevice => FileType::BlockDevice,
+            Type::File => FileType::Regular,
+            Type::Symlink => FileType::Symlink,
+            Type::Socket => FileType::Socket,
+        }
+    }
+}
+
+#[cfg(unix)]
+impl From<&nix::sys::stat::FileStat> for FileType {
+    fn from(stat: &nix::sys::stat::FileStat) -> FileType {
+        use nix::sys::stat::SFlag;
+
+        match SFlag::from_bits_truncate(stat.st_mode) & SFlag::S_IFMT {
+            SFlag::S_IFIFO => FileType::Fifo,
+            SFlag::S_IFCHR => FileType::CharDevice,
+            SFlag::S_IFDIR => FileType::Directory,
+            SFlag::S_IFBLK => FileType::BlockDevice,
+            SFlag::S_IFREG => FileType::Regular,
+            SFlag::S_IFLNK => FileType::Symlink,
+            SFlag::S_IFSOCK => FileType::Socket,
+            _ => FileType::Unknown,
+        }
+    }
+}
+
 impl From<fs::FileType> for FileType {
     fn from(t: fs::FileType) -> FileType {
         if t.is_dir() {
@@ -91,9 +171,29 @@ pub struct WalkError {
     depth: Option<usize>,
     /// The io::Error::raw_os_error(), if known.
     raw: Option<i32>,
+    /// The kind and description of errors that didn't come from the OS.
+    other: Option<(ErrorKind, String)>,
+}
+
+/// Get the kind and description of an I/O error that didn't come from the OS.
+fn other_error(e: &io::Error) -> Option<(ErrorKind, String)> {
+    match e.raw_os_error() {
+        Some(_) => None,
+        None => Some((e.kind(), e.to_string())),
+    }
 }
 
 impl WalkError {
+    /// Create an error for an I/O error that occurred on a specific path.
+    pub fn from_io_at(e: &io::Error, path: impl Into<PathBuf>, depth: usize) -> WalkError {
+        WalkError {
+            path: Some(path.into()),
+            depth: Some(depth),
+            raw: e.raw_os_error(),
+            other: other_error(e),
+        }
+    }
+
     /// Get the path this error occurred on, if known.
     pub fn path(&self) -> Option<&Path> {
         self.path.as_deref()
@@ -161,6 +261,7 @@ impl From<&io::Error> for WalkError {
             path: None,
             depth: None,
             raw: e.raw_os_error(),
+            other: other_error(e),
         }
     }
 }
@@ -177,6 +278,7 @@ impl From<&walkdir::Error> for WalkError {
             path: e.path().map(|p| p.to_owned()),
             depth: Some(e.depth()),
             raw: e.io_error().and_then(|e| e.raw_os_error()),
+            other: e.io_error().and_then(other_error),
         }
     }
 }
@@ -189,9 +291,11 @@ impl From<WalkError> for io::Error {
 
 impl From<&WalkError> for io::Error {
     fn from(e: &WalkError) -> io::Error {
-        e.raw
-            .map(io::Error::from_raw_os_error)
-            .unwrap_or_else(|| ErrorKind::Other.into())
+        match (e.raw, &e.other) {
+            (Some(raw), _) => io::Error::from_raw_os_error(raw),
+            (None, Some((kind, message))) => io::Error::new(*kind, message.as_str()),
+            (None, None) => ErrorKind::Other.into(),
+        }
     }
 }
 
@@ -204,6 +308,8 @@ pub struct WalkEntry {
     follow: Follow,
     /// Cached metadata.
     meta: OnceCell<Result<Metadata, WalkError>>,
+    /// Where to count stat() calls, for -D stat.
+    debugger: Option<Arc<Debugger>>,
 }
 
 impl WalkEntry {
@@ -213,9 +319,88 @@ impl WalkEntry {
             inner: Entry::Explicit(path.into(), depth),
             follow,
             meta: OnceCell::new(),
+            debugger: None,
         }
     }
 
+    /// Create a new WalkEntry for a specific file whose metadata has already
+    /// been fetched (following symbolic links as `follow` requires).
+    pub fn with_metadata(
+        path: impl Into<PathBuf>,
+        depth: usize,
+        follow: Follow,
+        meta: Result<Metadata, WalkError>,
+    ) -> Self {
+        Self {
+            inner: Entry::Explicit(path.into(), depth),
+            follow,
+            meta: meta.into(),
+            debugger: None,
+        }
+    }
+
+    /// Create a new WalkEntry for a specific file whose type is already known
+    /// (following symbolic links as `follow` requires), without fetching its
+    /// metadata until it's needed.
+    pub fn with_file_type(
+        path: impl Into<PathBuf>,
+        depth: usize,
+        follow: Follow,
+        file_type: FileType,
+    ) -> Self {
+        Self {
+            inner: Entry::Typed(path.into(), depth, file_type),
+            follow,
+            meta: OnceCell::new(),
+            debugger: None,
+        }
+    }
+
+    /// Create a new WalkEntry for a file in an open directory.  `file_type`
+    /// should already follow symbolic links if `follow` requires it.
+    #[cfg(unix)]
+    pub(crate) fn at(
+        dir: Rc<DirFd>,
+        path: impl Into<PathBuf>,
+        depth: usize,
+        follow: Follow,
+        file_type: FileType,
+        is_symlink: bool,
+    ) -> Self {
+        Self {
+            inner: Entry::At {
+                dir,
+                path: path.into(),
+                depth,
+                file_type,
+                is_symlink,
+            },
+            follow,
+            meta: OnceCell::new(),
+            debugger: None,
+        }
+    }
+
+    /// Use metadata that was fetched ahead of time (e.g. on another thread)
+    /// for this entry.
+    #[cfg(unix)]
+    pub(crate) fn with_prefetched_metadata(self, meta: Result<Metadata, WalkError>) -> Self {
+        Self {
+            meta: meta.into(),
+            ..self
+        }
+    }
+
+    /// Count the stat() calls made for this entry in `debugger`.
+    pub(crate) fn with_debugger(self, debugger: Option<Arc<Debugger>>) -> Self {
+        Self { debugger, ..self }
+    }
+
+    /// Get where to count stat() calls made for this entry, if anywhere.
+    pub(crate) fn debugger(&self) -> Option<&Debugger> {
+        self.debugger.as_deref()
+    }
+
     /// Convert a [walkdir::DirEntry] to a [WalkEntry].  Errors due to broken symbolic links will be
     /// converted to valid entries, but other errors will be propagated.
     pub fn from_walkdir(
@@ -234,6 +419,7 @@ impl WalkEntry {
                         inner: Entry::WalkDir(entry),
                         follow,
                         meta: OnceCell::new(),
+                        debugger: None,
                     }
                 };
                 Ok(ret)
@@ -246,6 +432,7 @@ impl WalkEntry {
                             inner: Entry::Explicit(path.into(), depth),
                             follow: Follow::Never,
                             meta: Ok(meta).into(),
+                            debugger: None,
                         });
                     }
                 }
@@ -259,26 +446,32 @@ impl WalkEntry {
     /// Get the path to this entry.
     pub fn path(&self) -> &Path {
         match &self.inner {
-            Entry::Explicit(path, _) => path.as_path(),
+            Entry::Explicit(path, _) | Entry::Typed(path, _, _) => path.as_path(),
             Entry::WalkDir(ent) => ent.path(),
+            #[cfg(unix)]
+            Entry::At { path, .. } => path.as_path(),
         }
     }
 
     /// Get the path to this entry.
     pub fn into_path(self) -> PathBuf {
         match self.inner {
-            Entry::Explicit(path, _) => path,
+            Entry::Explicit(path, _) | Entry::Typed(path, _, _) => path,
             Entry::WalkDir(ent) => ent.into_path(),
+            #[cfg(unix)]
+            Entry::At { path, .. } => path,
         }
     }
 
     /// Get the name of this entry.
     pub fn file_name(&self) -> &OsStr {
         match &self.inner {
-            Entry::Explicit(path, _) => {
+            #[cfg(unix)]
+            Entry::At { path, .. } => path.file_name().unwrap_or(path.as_os_str()),
+            Entry::Explicit(path, _) | Entry::Typed(path, _, _) => {
                 // Path::file_name() only works if the last component is normal
                 path.components()
-                    .last()
+                    .next_back()
                     .map(|c| c.as_os_str())
                     .unwrap_or_else(|| path.as_os_str())
             }
@@ -289,8 +482,10 @@ impl WalkEntry {
     /// Get the depth of this entry below the root.
     pub fn depth(&self) -> usize {
         match &self.inner {
-            Entry::Explicit(_, depth) => *depth,
+            Entry::Explicit(_, depth) | Entry::Typed(_, depth, _) => *depth,
             Entry::WalkDir(ent) => ent.depth(),
+            #[cfg(unix)]
+            Entry::At { depth, .. } => *depth,
         }
     }
 
@@ -299,21 +494,57 @@ impl WalkEntry {
         self.follow.follow_at_depth(self.depth())
     }
 
+    /// Get the directory this entry was found in, if it's open.  Operating on
+    /// [Self::file_name()] relative to it is safe even if the directories
+    /// above it are renamed or replaced with symlinks in the meantime.
+    #[cfg(unix)]
+    pub(crate) fn dir_fd(&self) -> Option<&Rc<DirFd>> {
+        match &self.inner {
+            Entry::At { dir, .. } => Some(dir),
+            _ => None,
+        }
+    }
+
     /// Get the metadata on a cache miss.
     fn get_metadata(&self) -> Result<Metadata, WalkError> {
-        self.follow.metadata_at_depth(self.path(), self.depth())
+        #[cfg(unix)]
+        if let Entry::At { dir, .. } = &self.inner {
+            return Ok(metadata_at(
+                dir,
+                self.file_name(),
+                self.path(),
+                self.follow(),
+                self.debugger(),
+            )?);
+        }
+
+        self.follow
+            .counted_metadata_at_depth(self.path(), self.depth(), self.debugger())
     }
 
     /// Get the [Metadata] for this entry, following symbolic links if appropriate.
     /// Multiple calls to this function will cache and re-use the same [Metadata].
     pub fn metadata(&self) -> Result<&Metadata, WalkError> {
         let result = self.meta.get_or_init(|| match &self.inner {
-            Entry::Explicit(_, _) => Ok(self.get_metadata()?),
-            Entry::WalkDir(ent) => Ok(ent.metadata()?),
+            Entry::Explicit(_, _) | Entry::Typed(_, _, _) => Ok(self.get_metadata()?),
+            Entry::WalkDir(ent) => {
+                count_stat(self.debugger(), ent.path());
+                Ok(ent.metadata()?)
+            }
+            #[cfg(unix)]
+            Entry::At { .. } => Ok(self.get_metadata()?),
         });
         result.as_ref().map_err(|e| e.clone())
     }
 
+    /// Check whether this file was deleted after its directory was read, i.e.
+    /// getting its [Metadata] already failed with NotFound.  Starting points
+    /// don't count, as they weren't found by reading a directory.
+    pub fn vanished(&self) -> bool {
+        self.depth() > 0
+            && matches!(self.meta.get(), Some(Err(e)) if e.kind() == ErrorKind::NotFound)
+    }
+
     /// Get the file type of this entry.
     pub fn file_type(&self) -> FileType {
         match &self.inner {
@@ -321,7 +552,10 @@ impl WalkEntry {
                 .metadata()
                 .map(|m| m.file_type().into())
                 .unwrap_or(FileType::Unknown),
+            Entry::Typed(_, _, file_type) => *file_type,
             Entry::WalkDir(ent) => ent.file_type().into(),
+            #[cfg(unix)]
+            Entry::At { file_type, .. } => *file_type,
         }
     }
 
@@ -329,8 +563,9 @@ impl WalkEntry {
     /// are being followed.
     pub fn path_is_symlink(&self) -> bool {
         match &self.inner {
-            Entry::Explicit(path, _) => {
+            Entry::Explicit(path, _) | Entry::Typed(path, _, _) => {
                 if self.follow() {
+                    count_stat(self.debugger(), path);
                     path.symlink_metadata()
                         .is_ok_and(|m| m.file_type().is_symlink())
                 } else {
@@ -338,6 +573,111 @@ impl WalkEntry {
                 }
             }
             Entry::WalkDir(ent) => ent.path_is_symlink(),
+            #[cfg(unix)]
+            Entry::At { is_symlink, .. } => *is_symlink,
+        }
+    }
+}
+
+/// Get the metadata for a file in an open directory.  std can't make a
+/// [Metadata] from the result of fstatat(), but it can fstat() an O_PATH file
+/// descriptor, which doesn't need any permissions on the file itself.
+#[cfg(any(target_os = "linux", target_os = "android"))]
+pub(crate) fn metadata_at(
+    dir: &DirFd,
+    name: &OsStr,
+    path: &Path,
+    follow: bool,
+    debugger: Option<&Debugger>,
+) -> io::Result<Metadata> {
+    use nix::fcntl::{openat, OFlag};
+    use nix::sys::stat::Mode;
+    use std::os::fd::{FromRawFd, OwnedFd};
+
+    let open = |flags: OFlag| -> io::Result<Metadata> {
+        let flags = flags | OFlag::O_PATH | OFlag::O_CLOEXEC;
+        let fd = openat(Some(dir.as_raw_fd()), name, flags, Mode::empty())?;
+        count_stat(debugger, path);
+        fs::File::from(unsafe { OwnedFd::from_raw_fd(fd) }).metadata()
+    };
+
+    if follow {
+        match open(OFlag::empty()) {
+            Ok(meta) => return Ok(meta),
+            Err(e) if !WalkError::from(&e).is_not_found() => return Err(e),
+            _ => {}
+        }
+    }
+
+    open(OFlag::O_NOFOLLOW)
+}
+
+/// Get the metadata for a file in an open directory.  Without O_PATH, std
+/// can only make a [Metadata] from a path or an open file, so fstatat() finds
+/// out which file is the right one, and then either its full path or (if that
+/// leads somewhere else, or is too long) the file itself gets its [Metadata].
+/// Only regular files and directories can be opened safely, so other files
+/// deeper than PATH_MAX still can't be examined.
+#[cfg(all(unix, not(any(target_os = "linux", target_os = "android"))))]
+pub(crate) fn metadata_at(
+    dir: &DirFd,
+    name: &OsStr,
+    path: &Path,
+    follow: bool,
+    debugger: Option<&Debugger>,
+) -> io::Result<Metadata> {
+    use nix::fcntl::{openat, AtFlags, OFlag};
+    use nix::sys::stat::{fstatat, Mode, SFlag};
+    use std::os::fd::{FromRawFd, OwnedFd};
+    use std::os::unix::fs::MetadataExt;
+
+    let stat_at = |flags: AtFlags| -> io::Result<nix::sys::stat::FileStat> {
+        count_stat(debugger, path);
+        Ok(fstatat(Some(dir.as_raw_fd()), name, flags)?)
+    };
+
+    let mut follow = follow;
+    let st = match follow.then(|| stat_at(AtFlags::empty())) {
+        Some(Ok(st)) => st,
+        Some(Err(e)) if !WalkError::from(&e).is_not_found() => return Err(e),
+        // Broken symlinks are reported as symlinks
+        _ => {
+            follow = false;
+            stat_at(AtFlags::AT_SYMLINK_NOFOLLOW)?
         }
+    };
+    // dev_t isn't u64 everywhere
+    #[allow(clippy::unnecessary_cast)]
+    let is_same_file = |meta: &Metadata| meta.dev() == st.st_dev as u64 && meta.ino() == st.st_ino;
+
+    count_stat(debugger, path);
+    let by_path = if follow {
+        fs::metadata(path)
+    } else {
+        fs::symlink_metadata(path)
+    };
+    match by_path {
+        Ok(ref meta) if is_same_file(meta) => return by_path,
+        _ => {}
+    }
+
+    // Opening other kinds of files can have side effects, like rewinding tapes
+    let file_type = SFlag::from_bits_truncate(st.st_mode) & SFlag::S_IFMT;
+    if file_type == SFlag::S_IFREG || file_type == SFlag::S_IFDIR {
+        let mut flags = OFlag::O_RDONLY | OFlag::O_NONBLOCK | OFlag::O_NOCTTY | OFlag::O_CLOEXEC;
+        if !follow {
+            flags |= OFlag::O_NOFOLLOW;
+        }
+        if let Ok(fd) = openat(Some(dir.as_raw_fd()), name, flags, Mode::empty()) {
+            let meta = fs::File::from(unsafe { OwnedFd::from_raw_fd(fd) }).metadata()?;
+            if is_same_file(&meta) {
+                return Ok(meta);
+            }
+        }
+    }
+
+    match by_path {
+        Err(e) => Err(e),
+        Ok(_) => Err(io::Error::other("file changed while it was being examined")),
     }
 }
@@ -4,23 +4,103 @@
 // license that can be found in the LICENSE file or at
 // https://opensource.org/licenses/MIT.
 
+use std::cell::RefCell;
 use std::error::Error;
-use std::ffi::OsString;
-use std::io::{stderr, Write};
-use std::path::Path;
-use std::process::Command;
+use std::ffi::{OsStr, OsString};
+use std::io::{self, stderr, Write};
+use std::path::{Path, PathBuf};
+use std::process::{Command, Stdio};
+#[cfg(unix)]
+use std::rc::Rc;
 
+use super::expr::shell_quote;
+#[cfg(unix)]
+use super::DirFd;
 use super::{Matcher, MatcherIO, WalkEntry};
+use crate::common::limits::{count_osstr_size_for_exec, SystemLimits};
+use crate::common::prompt::{is_affirmative, read_response};
+use crate::find::debug::{self, DebugOption};
 
 enum Arg {
     FileArg(Vec<OsString>),
     LiteralArg(OsString),
 }
 
+/// Get the path to pass to an -execdir command, i.e. ./name.
+fn path_in_parent_dir(path: &Path) -> PathBuf {
+    if let Some(f) = path.file_name() {
+        Path::new(".").join(f)
+    } else {
+        Path::new(".").join(path)
+    }
+}
+
+/// Get the directory an -execdir command should be run from, or None if
+/// there's no need to change directory at all.
+fn parent_dir(path: &Path) -> Option<&Path> {
+    match path.parent() {
+        // Root paths like "/" have no parent.  Run them from the root to match GNU find.
+        None => Some(path),
+        // Paths like "foo" have a parent of "".  Avoid chdir("").
+        Some(parent) if parent == Path::new("") => None,
+        Some(parent) => Some(parent),
+    }
+}
+
+/// Makes a command run in `dir`, for -execdir.  If the directory is open, the
+/// command changes to it with fchdir() instead, so that it ends up in the same
+/// directory the file was found in even if the path now leads elsewhere.
+fn set_current_dir(
+    command: &mut Command,
+    dir: Option<&Path>,
+    #[cfg(unix)] dir_fd: Option<&Rc<DirFd>>,
+) {
+    #[cfg(unix)]
+    if let Some(dir_fd) = dir_fd {
+        use std::os::unix::io::AsRawFd;
+        use std::os::unix::process::CommandExt;
+
+        // The caller keeps the directory open until the command has started.
+        let fd = dir_fd.as_raw_fd();
+        // SAFETY: fchdir() is async-signal-safe.
+        unsafe {
+            command.pre_exec(move || match uucore::libc::fchdir(fd) {
+                0 => Ok(()),
+                _ => Err(io::Error::last_os_error()),
+            });
+        }
+        return;
+    }
+
+    if let Some(dir) = dir {
+        command.current_dir(dir);
+    }
+}
+
+/// Shows a command that's about to run (in `dir`, if it's an -execdir
+/// command), for -D exec.
+fn log_command(command: &Command, dir: Option<&Path>, matcher_io: &MatcherIO) {
+    if !matcher_io.debug_enabled(DebugOption::Exec) {
+        return;
+    }
+
+    let mut line = shell_quote(&command.get_program().to_string_lossy()).into_owned();
+    for arg in command.get_args() {
+        line.push(' ');
+        line.push_str(&shell_quote(&arg.to_string_lossy()));
+    }
+    if let Some(dir) = dir {
+        line.push_str(&format!(" (in {})", dir.display()));
+    }
+    debug::log(DebugOption::Exec, line);
+}
+
 pub struct SingleExecMatcher {
     executable: String,
     args: Vec<Arg>,
     exec_in_parent_dir: bool,
+    /// Whether to ask for confirmation before running the command (-ok/-okdir).
+    interactive: bool,
 }
 
 impl SingleExecMatcher {
@@ -46,19 +126,51 @@ impl SingleExecMatcher {
             executable: executable.to_string(),
             args: transformed_args,
             exec_in_parent_dir,
+            interactive: false,
         })
     }
+
+    /// Creates a matcher for -ok and -okdir, which prompt the user before
+    /// running the command for each file.
+    pub fn new_interactive(
+        executable: &str,
+        args: &[&str],
+        exec_in_parent_dir: bool,
+    ) -> Result<Self, Box<dyn Error>> {
+        let mut matcher = Self::new(executable, args, exec_in_parent_dir)?;
+        matcher.interactive = true;
+        Ok(matcher)
+    }
+
+    /// Asks the user whether to run the command on the given file, in the
+    /// same format as GNU find.
+    fn confirm(&self, path_to_file: &Path) -> bool {
+        let mut stderr = stderr();
+        write!(
+            &mut stderr,
+            "< {} ... {} > ? ",
+            self.executable,
+            path_to_file.display()
+        )
+        .unwrap();
+        stderr.flush().unwrap();
+
+        match read_response() {
+            Ok(Some(response)) => is_affirmative(&response),
+            Ok(None) => false,
+            Err(e) => {
+                writeln!(&mut stderr, "Failed to read response: {e}").unwrap();
+                false
+            }
+        }
+    }
 }
 
 impl Matcher for SingleExecMatcher {
-    fn matches(&self, file_info: &WalkEntry, _: &mut MatcherIO) -> bool {
+    fn matches(&self, file_info: &WalkEntry, matcher_io: &mut MatcherIO) -> bool {
         let mut command = Command::new(&self.executable);
         let path_to_file = if self.exec_in_parent_dir {
-            if let Some(f) = file_info.path().file_name() {
-                Path::new(".").join(f)
-            } else {
-                Path::new(".").join(file_info.path())
-            }
+            path_in_parent_dir(file_info.path())
         } else {
             file_info.path().to_path_buf()
         };
@@ -70,19 +182,25 @@ impl Matcher for SingleExecMatcher {
             };
         }
         if self.exec_in_parent_dir {
-            match file_info.path().parent() {
-                None => {
-                    // Root paths like "/" have no parent.  Run them from the root to match GNU find.
-                    command.current_dir(file_info.path());
-                }
-                Some(parent) if parent == Path::new("") => {
-                    // Paths like "foo" have a parent of "".  Avoid chdir("").
-                }
-                Some(parent) => {
-                    command.current_dir(parent);
-                }
+            set_current_dir(
+                &mut command,
+                parent_dir(file_info.path()),
+                #[cfg(unix)]
+                file_info.dir_fd(),
+            );
+        }
+        if self.interactive {
+            if !self.confirm(&path_to_file) {
+                return false;
             }
+            // The prompt owns stdin, so don't let the command consume it.
+            command.stdin(Stdio::null());
         }
+        let dir = self
+            .exec_in_parent_dir
+            .then(|| parent_dir(file_info.path()))
+            .flatten();
+        log_command(&command, dir, matcher_io);
         match command.status() {
             Ok(status) => status.success(),
             Err(e) => {
@@ -97,6 +215,150 @@ impl Matcher for SingleExecMatcher {
     }
 }
 
+/// The paths collected so far for the next invocation of the command.
+#[derive(Default)]
+struct Batch {
+    paths: Vec<OsString>,
+    /// The directory to run the command in (-execdir only).
+    dir: Option<PathBuf>,
+    /// The same directory, if it's open.
+    #[cfg(unix)]
+    dir_fd: Option<Rc<DirFd>>,
+    /// The space taken up by the paths, as counted by `count_osstr_size_for_exec`.
+    size: usize,
+}
+
+/// This matcher implements `-exec command {} +` and `-execdir command {} +`.
+/// Instead of running the command for every file, matching paths are
+/// collected and passed to as few invocations of the command as the system's
+/// command line length limit allows. For -execdir, a batch only ever contains
+/// files from a single directory.
+pub struct MultiExecMatcher {
+    executable: String,
+    args: Vec<OsString>,
+    exec_in_parent_dir: bool,
+    /// The number of bytes available for the paths of a single batch.
+    max_batch_size: usize,
+    batch: RefCell<Batch>,
+}
+
+impl MultiExecMatcher {
+    pub fn new(
+        executable: &str,
+        args: &[&str],
+        exec_in_parent_dir: bool,
+    ) -> Result<Self, Box<dyn Error>> {
+        if let Some(arg) = args.iter().find(|a| a.contains("{}")) {
+            return Err(From::from(format!(
+                "In '{} ... {{}} +' the '{{}}' must appear by itself, but you specified '{}'",
+                if exec_in_parent_dir {
+                    "-execdir"
+                } else {
+                    "-exec"
+                },
+                arg
+            )));
+        }
+
+        let args: Vec<OsString> = args.iter().map(OsString::from).collect();
+        let base_size = count_osstr_size_for_exec(OsStr::new(executable))
+            + args
+                .iter()
+                .map(|a| count_osstr_size_for_exec(a))
+                .sum::<usize>();
+
+        Ok(Self {
+            executable: executable.to_string(),
+            args,
+            exec_in_parent_dir,
+            max_batch_size: SystemLimits::current()
+                .max_command_size
+                .saturating_sub(base_size),
+            batch: RefCell::new(Batch::default()),
+        })
+    }
+
+    /// Runs the command for the pending batch (if any), recording a failure
+    /// in the exit code the way GNU find does.
+    fn run_batch(&self, matcher_io: &mut MatcherIO) {
+        let batch = self.batch.take();
+        if batch.paths.is_empty() {
+            return;
+        }
+
+        let mut command = Command::new(&self.executable);
+        command.args(&self.args).args(&batch.paths);
+        set_current_dir(
+            &mut command,
+            batch.dir.as_deref(),
+            #[cfg(unix)]
+            batch.dir_fd.as_ref(),
+        );
+
+        log_command(&command, batch.dir.as_deref(), matcher_io);
+        match command.status() {
+            Ok(status) => {
+                if !status.success() {
+                    matcher_io.set_exit_code(1);
+                }
+            }
+            Err(e) => {
+                writeln!(&mut stderr(), "Failed to run {}: {}", self.executable, e).unwrap();
+                matcher_io.set_exit_code(1);
+            }
+        }
+    }
+}
+
+impl Matcher for MultiExecMatcher {
+    fn matches(&self, file_info: &WalkEntry, matcher_io: &mut MatcherIO) -> bool {
+        let (path_to_file, dir) = if self.exec_in_parent_dir {
+            (
+                path_in_parent_dir(file_info.path()),
+                parent_dir(file_info.path()).map(Path::to_path_buf),
+            )
+        } else {
+            (file_info.path().to_path_buf(), None)
+        };
+        let path_to_file = path_to_file.into_os_string();
+        let size = count_osstr_size_for_exec(&path_to_file);
+
+        let must_flush = {
+            let batch = self.batch.borrow();
+            !batch.paths.is_empty() && (batch.dir != dir || batch.size + size > self.max_batch_size)
+        };
+        if must_flush {
+            self.run_batch(matcher_io);
+        }
+
+        let mut batch = self.batch.borrow_mut();
+        batch.paths.push(path_to_file);
+        batch.dir = dir;
+        #[cfg(unix)]
+        if self.exec_in_parent_dir {
+            batch.dir_fd = file_info.dir_fd().cloned();
+        }
+        batch.size += size;
+
+        // The command's exit status isn't known yet, so `{} +` always matches.
+        true
+    }
+
+    fn has_side_effects(&self) -> bool {
+        true
+    }
+
+    fn finished_dir(&self, _finished_directory: &Path, matcher_io: &mut MatcherIO) {
+        if self.exec_in_parent_dir {
+            self.run_batch(matcher_io);
+        }
+    }
+
+    fn finished(&self, matcher_io: &mut MatcherIO) {
+        self.run_batch(matcher_io);
+    }
+}
+
 #[cfg(test)]
 /// No tests here, because we need to call out to an external executable. See
 /// `tests/exec_unit_tests.rs` instead.
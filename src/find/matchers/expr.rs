@@ -0,0 +1,593 @@
+// This file is part of the uutils findutils package.
+//
+// For the full copyright and license information, please view the LICENSE
+// file that was distributed with this source code.
+
+//! The syntax tree of a find expression, and the parser that builds it from
+//! command-line arguments.  Turning the tree into [Matcher](super::Matcher)s
+//! is a separate step (see [lower()](super::lower)), so expressions can be
+//! inspected, rewritten or printed first.
+
+use std::borrow::Cow;
+use std::error::Error;
+use std::fmt::{self, Display, Formatter};
+
+/// A range of command-line arguments, as indices into the arguments that were
+/// parsed, from `start` up to (but not including) `end`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Span {
+    pub start: usize,
+    pub end: usize,
+}
+
+impl Span {
+    pub fn new(start: usize, end: usize) -> Self {
+        Self { start, end }
+    }
+
+    /// The span of the single argument at `index`.
+    pub fn at(index: usize) -> Self {
+        Self::new(index, index + 1)
+    }
+
+    /// The span covering both this span and `other`.
+    #[must_use]
+    pub fn to(self, other: Self) -> Self {
+        Self::new(self.start.min(other.start), self.end.max(other.end))
+    }
+
+    /// This span, moved `offset` arguments to the right.
+    #[must_use]
+    pub fn shifted(self, offset: usize) -> Self {
+        Self::new(self.start + offset, self.end + offset)
+    }
+
+    pub fn contains(self, index: usize) -> bool {
+        (self.start..self.end).contains(&index)
+    }
+}
+
+/// A node in the syntax tree, with the arguments it was parsed from.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Expr {
+    pub kind: ExprKind,
+    pub span: Span,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum ExprKind {
+    /// A test, action or option with its arguments, e.g. `-name '*.rs'` or
+    /// `-exec rm {} ;` (including the terminating `;` or `+`).
+    Primary { name: String, args: Vec<String> },
+    /// `! expr` or `-not expr`.
+    Not(Box<Expr>),
+    /// `expr1 expr2`, `expr1 -a expr2` or `expr1 -and expr2`.  An empty list
+    /// (for an empty expression) is always true.
+    And(Vec<Expr>),
+    /// `expr1 -o expr2` or `expr1 -or expr2`.
+    Or(Vec<Expr>),
+    /// `expr1 , expr2`.
+    List(Vec<Expr>),
+    /// `( expr )`.
+    Group(Box<Expr>),
+}
+
+impl Expr {
+    pub fn new(kind: ExprKind, span: Span) -> Self {
+        Self { kind, span }
+    }
+
+    /// Converts the expression back to command-line arguments, which parse to
+    /// the same expression (though not necessarily with the same spans, e.g.
+    /// if `-a` was given explicitly).
+    pub fn to_args(&self) -> Vec<String> {
+        let mut args = vec![];
+        self.push_args(&mut args);
+        args
+    }
+
+    fn push_args(&self, args: &mut Vec<String>) {
+        let mut push_all = |exprs: &[Expr], operator: Option<&str>| {
+            for (i, expr) in exprs.iter().enumerate() {
+                if let (true, Some(operator)) = (i > 0, operator) {
+                    args.push(operator.to_string());
+                }
+                expr.push_args(args);
+            }
+        };
+
+        match &self.kind {
+            ExprKind::Primary {
+                name,
+                args: primary_args,
+            } => {
+                args.push(name.clone());
+                args.extend(primary_args.iter().cloned());
+            }
+            ExprKind::Not(expr) => {
+                args.push("!".to_string());
+                expr.push_args(args);
+            }
+            ExprKind::And(exprs) => push_all(exprs, None),
+            ExprKind::Or(exprs) => push_all(exprs, Some("-o")),
+            ExprKind::List(exprs) => push_all(exprs, Some(",")),
+            ExprKind::Group(expr) => {
+                args.push("(".to_string());
+                expr.push_args(args);
+                args.push(")".to_string());
+            }
+        }
+    }
+}
+
+/// Writes the expression as shell words, e.g. `-name '*.rs' -o -type d`.
+impl Display for Expr {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        for (i, arg) in self.to_args().iter().enumerate() {
+            if i > 0 {
+                f.write_str(" ")?;
+            }
+            f.write_str(&shell_quote(arg))?;
+        }
+        Ok(())
+    }
+}
+
+/// Quotes an argument for a POSIX shell, if necessary.
+pub(super) fn shell_quote(arg: &str) -> Cow<'_, str> {
+    let is_safe = |c: char| c.is_alphanumeric() || "-_./+=:,%@^{}".contains(c);
+    if !arg.is_empty() && arg.chars().all(is_safe) {
+        Cow::Borrowed(arg)
+    } else {
+        Cow::Owned(format!("'{}'", arg.replace('\'', r"'\''")))
+    }
+}
+
+/// An error in an expression, with the arguments that caused it.
+#[derive(Debug)]
+pub struct ExprError {
+    message: String,
+    span: Span,
+}
+
+impl ExprError {
+    pub fn new(message: impl Into<String>, span: Span) -> Self {
+        Self {
+            message: message.into(),
+            span,
+        }
+    }
+
+    pub fn span(&self) -> Span {
+        self.span
+    }
+
+    /// Moves the span `offset` arguments to the right, e.g. to make it relative
+    /// to all the command-line arguments rather than just the expression.
+    #[must_use]
+    pub fn shifted(self, offset: usize) -> Self {
+        Self::new(self.message, self.span.shifted(offset))
+    }
+
+    /// Shows where the error is, by writing out `args` (the arguments that
+    /// were parsed) with the offending ones underlined, e.g.
+    ///
+    /// ```text
+    ///   . -type f -o
+    ///             ^^
+    /// ```
+    pub fn highlight(&self, args: &[&str]) -> String {
+        let mut line = String::from(" ");
+        let mut marks = String::from(" ");
+        for (i, arg) in args.iter().enumerate() {
+            let mark = if self.span.contains(i) { '^' } else { ' ' };
+            let gap = if i > 0 && self.span.contains(i - 1) {
+                mark
+            } else {
+                ' '
+            };
+            let arg = shell_quote(arg);
+            line.push(' ');
+            line.push_str(&arg);
+            marks.push(gap);
+            marks.extend(std::iter::repeat_n(mark, arg.chars().count()));
+        }
+        if self.span.start >= args.len() {
+            // Past the end, e.g. for a missing argument
+            marks.push_str(" ^");
+        }
+        format!("{line}\n{}\n", marks.trim_end())
+    }
+}
+
+impl Display for ExprError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.message)
+    }
+}
+
+impl Error for ExprError {}
+
+/// How many arguments a primary takes.
+enum Arity {
+    Fixed(usize),
+    /// Everything up to a `;`, or a `{} +` (-exec, -execdir).
+    Exec,
+    /// Everything up to a `;` (-ok, -okdir).
+    Ok,
+}
+
+fn arity(name: &str) -> Option<Arity> {
+    let arity = match name {
+        "-print" | "-print0" | "-ls" | "-true" | "-false" | "-readable" | "-delete" | "-empty"
+        | "-nouser" | "-nogroup" | "-executable" | "-prune" | "-quit" | "-writable" | "-follow"
+        | "-daystart" | "-noleaf" | "-d" | "-depth" | "-mount" | "-xdev" | "-sorted" | "-help"
+        | "--help" | "-version" | "--version" => Arity::Fixed(0),
+        "-ignore_readdir_race" | "-noignore_readdir_race" => Arity::Fixed(0),
+        "-printf" | "-fprint" | "-fprint0" | "-fls" | "-lname" | "-ilname" | "-name" | "-iname"
+        | "-path" | "-ipath" | "-wholename" | "-iwholename" | "-regextype" | "-regex"
+        | "-iregex" | "-type" | "-xtype" | "-fstype" | "-newer" | "-mtime" | "-atime"
+        | "-ctime" | "-Btime" | "-amin" | "-cmin" | "-mmin" | "-Bmin" | "-size" | "-inum"
+        | "-links" | "-samefile" | "-user" | "-uid" | "-group" | "-gid" | "-perm" | "-maxdepth"
+        | "-mindepth" | "-files0-from" => Arity::Fixed(1),
+        "-fprintf" => Arity::Fixed(2),
+        "-exec" | "-execdir" => Arity::Exec,
+        "-ok" | "-okdir" => Arity::Ok,
+        _ if super::parse_str_to_newer_args(name).is_some() => Arity::Fixed(1),
+        _ => return None,
+    };
+    Some(arity)
+}
+
+fn is_binary_operator(arg: &str) -> bool {
+    matches!(arg, "-a" | "-and" | "-o" | "-or" | ",")
+}
+
+/// A recursive descent parser, for the grammar (in order of increasing
+/// precedence):
+///
+/// ```text
+/// list    := or ( "," or )*
+/// or      := and ( ( "-o" | "-or" ) and )*
+/// and     := unary ( ( "-a" | "-and" )? unary )*
+/// unary   := ( "!" | "-not" ) unary | "(" list ")" | primary
+/// ```
+struct Parser<'a> {
+    args: &'a [&'a str],
+    pos: usize,
+    /// Set after -help or -version, after which everything is ignored.
+    stopped: bool,
+}
+
+impl<'a> Parser<'a> {
+    fn peek(&self) -> Option<&'a str> {
+        if self.stopped {
+            None
+        } else {
+            self.args.get(self.pos).copied()
+        }
+    }
+
+    /// Skips a binary operator, making sure there's something after it.
+    fn operator(&mut self) -> Result<(), ExprError> {
+        let operator = self.args[self.pos];
+        self.pos += 1;
+        match self.peek() {
+            None | Some(")") => Err(ExprError::new(
+                format!("expected an expression after {operator}"),
+                Span::at(self.pos - 1),
+            )),
+            _ => Ok(()),
+        }
+    }
+
+    fn list(&mut self) -> Result<Expr, ExprError> {
+        let mut exprs = vec![self.or()?];
+        while self.peek() == Some(",") {
+            self.operator()?;
+            exprs.push(self.or()?);
+        }
+        Ok(combine(exprs, ExprKind::List))
+    }
+
+    fn or(&mut self) -> Result<Expr, ExprError> {
+        let mut exprs = vec![self.and()?];
+        while let Some("-o" | "-or") = self.peek() {
+            self.operator()?;
+            exprs.push(self.and()?);
+        }
+        Ok(combine(exprs, ExprKind::Or))
+    }
+
+    fn and(&mut self) -> Result<Expr, ExprError> {
+        let mut exprs = vec![self.unary()?];
+        loop {
+            match self.peek() {
+                None | Some(")" | "," | "-o" | "-or") => break,
+                Some("-a" | "-and") => self.operator()?,
+                _ => {}
+            }
+            exprs.push(self.unary()?);
+        }
+        Ok(combine(exprs, ExprKind::And))
+    }
+
+    fn unary(&mut self) -> Result<Expr, ExprError> {
+        let start = self.pos;
+        let arg = self.args[start];
+        self.pos += 1;
+
+        match arg {
+            "!" | "-not" => {
+                if let None | Some(")") = self.peek() {
+                    return Err(ExprError::new(
+                        format!("expected an expression after {arg}"),
+                        Span::at(start),
+                    ));
+                }
+                let expr = self.unary()?;
+                let span = Span::at(start).to(expr.span);
+                Ok(Expr::new(ExprKind::Not(Box::new(expr)), span))
+            }
+            "(" => {
+                let expr = match self.peek() {
+                    Some(")") => {
+                        return Err(ExprError::new(
+                            "invalid expression; empty parentheses are not allowed.",
+                            Span::new(start, start + 2),
+                        ))
+                    }
+                    Some(_) => self.list()?,
+                    None if self.stopped => {
+                        return Ok(Expr::new(ExprKind::And(vec![]), Span::at(start)))
+                    }
+                    None => return Err(missing_bracket(start)),
+                };
+                if self.stopped {
+                    let span = Span::at(start).to(expr.span);
+                    return Ok(Expr::new(ExprKind::Group(Box::new(expr)), span));
+                }
+                if self.peek() != Some(")") {
+                    return Err(missing_bracket(start));
+                }
+                self.pos += 1;
+                let span = Span::new(start, self.pos);
+                Ok(Expr::new(ExprKind::Group(Box::new(expr)), span))
+            }
+            ")" => Err(ExprError::new("you have too many ')'", Span::at(start))),
+            _ if is_binary_operator(arg) => Err(ExprError::new(
+                format!(
+                    "invalid expression; you have used a binary operator '{arg}' with nothing \
+                     before it."
+                ),
+                Span::at(start),
+            )),
+            _ => self.primary(start, arg),
+        }
+    }
+
+    fn primary(&mut self, start: usize, name: &str) -> Result<Expr, ExprError> {
+        let Some(arity) = arity(name) else {
+            return Err(ExprError::new(
+                format!("Unrecognized flag: '{name}'"),
+                Span::at(start),
+            ));
+        };
+        let missing = || ExprError::new(format!("missing argument to {name}"), Span::at(start));
+
+        let end = match arity {
+            Arity::Fixed(n) if self.pos + n <= self.args.len() => self.pos + n,
+            Arity::Fixed(_) => return Err(missing()),
+            Arity::Exec | Arity::Ok => {
+                let mut end = self.pos;
+                while end < self.args.len() && self.args[end] != ";" {
+                    let is_multi =
+                        end > self.pos + 1 && self.args[end - 1] == "{}" && self.args[end] == "+";
+                    if matches!(arity, Arity::Exec) && is_multi {
+                        break;
+                    }
+                    end += 1;
+                }
+                // At the minimum we need the executable and the terminator
+                if end == self.pos || end == self.args.len() {
+                    return Err(missing());
+                }
+                end + 1
+            }
+        };
+
+        let args = self.args[self.pos..end]
+            .iter()
+            .map(|arg| arg.to_string())
+            .collect();
+        self.pos = end;
+        if matches!(name, "-help" | "--help" | "-version" | "--version") {
+            self.stopped = true;
+        }
+
+        let kind = ExprKind::Primary {
+            name: name.to_string(),
+            args,
+        };
+        Ok(Expr::new(kind, Span::new(start, end)))
+    }
+}
+
+fn missing_bracket(start: usize) -> ExprError {
+    ExprError::new(
+        "invalid expression; I was expecting to find a ')' somewhere but did not see one.",
+        Span::at(start),
+    )
+}
+
+/// Makes an And, Or or List of some expressions, unless there's only one.
+fn combine(mut exprs: Vec<Expr>, kind: fn(Vec<Expr>) -> ExprKind) -> Expr {
+    if exprs.len() == 1 {
+        return exprs.pop().unwrap();
+    }
+    let span = exprs[0].span.to(exprs[exprs.len() - 1].span);
+    Expr::new(kind(exprs), span)
+}
+
+/// Parses an expression given as separate command-line arguments.  An empty
+/// expression parses to an empty [ExprKind::And].
+pub fn parse(args: &[&str]) -> Result<Expr, ExprError> {
+    if args.is_empty() {
+        return Ok(Expr::new(ExprKind::And(vec![]), Span::new(0, 0)));
+    }
+
+    let mut parser = Parser {
+        args,
+        pos: 0,
+        stopped: false,
+    };
+    let expr = parser.list()?;
+    if parser.peek().is_some() {
+        // The only thing that stops a list early
+        return Err(ExprError::new(
+            "you have too many ')'",
+            Span::at(parser.pos),
+        ));
+    }
+    Ok(expr)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn primary(name: &str, args: &[&str], span: Span) -> Expr {
+        let kind = ExprKind::Primary {
+            name: name.to_string(),
+            args: args.iter().map(|arg| arg.to_string()).collect(),
+        };
+        Expr::new(kind, span)
+    }
+
+    #[test]
+    fn parse_precedence() {
+        let expr = parse(&[
+            "!", "-name", "a", "-o", "-type", "d", "-a", "-print", ",", "-quit",
+        ])
+        .unwrap();
+
+        let not = Expr::new(
+            ExprKind::Not(Box::new(primary("-name", &["a"], Span::new(1, 3)))),
+            Span::new(0, 3),
+        );
+        let and = Expr::new(
+            ExprKind::And(vec![
+                primary("-type", &["d"], Span::new(4, 6)),
+                primary("-print", &[], Span::at(7)),
+            ]),
+            Span::new(4, 8),
+        );
+        let or = Expr::new(ExprKind::Or(vec![not, and]), Span::new(0, 8));
+        let list = Expr::new(
+            ExprKind::List(vec![or, primary("-quit", &[], Span::at(9))]),
+            Span::new(0, 10),
+        );
+        assert_eq!(expr, list);
+    }
+
+    #[test]
+    fn parse_groups_and_exec() {
+        let expr = parse(&[
+            "(", "-exec", "test", "{}", ";", ")", "-execdir", "ls", "{}", "+",
+        ])
+        .unwrap();
+
+        let exec = primary("-exec", &["test", "{}", ";"], Span::new(1, 5));
+        let group = Expr::new(ExprKind::Group(Box::new(exec)), Span::new(0, 6));
+        let execdir = primary("-execdir", &["ls", "{}", "+"], Span::new(6, 10));
+        assert_eq!(
+            expr,
+            Expr::new(ExprKind::And(vec![group, execdir]), Span::new(0, 10))
+        );
+    }
+
+    #[test]
+    fn parse_errors_have_spans() {
+        for (args, message, span) in [
+            (&["-name"][..], "missing argument to -name", Span::at(0)),
+            (
+                &["-true", "-o"],
+                "expected an expression after -o",
+                Span::at(1),
+            ),
+            (&["-a", "-true"], "binary operator '-a'", Span::at(0)),
+            (
+                &["-true", "(", "-false"],
+                "expecting to find a ')'",
+                Span::at(1),
+            ),
+            (&["-true", ")"], "too many ')'", Span::at(1)),
+            (&["-true", "(", ")"], "empty parentheses", Span::new(1, 3)),
+            (
+                &["-true", "-bogus"],
+                "Unrecognized flag: '-bogus'",
+                Span::at(1),
+            ),
+            (
+                &["-exec", "ls", "{}"],
+                "missing argument to -exec",
+                Span::at(0),
+            ),
+        ] {
+            let e = parse(args).unwrap_err();
+            assert!(e.to_string().contains(message), "{args:?}: {e}");
+            assert_eq!(e.span(), span, "{args:?}");
+        }
+    }
+
+    #[test]
+    fn parse_stops_after_help() {
+        let expr = parse(&["(", "-true", "-help", ")", ")", "-bogus"]).unwrap();
+        assert_eq!(expr.to_args(), ["(", "-true", "-help", ")"]);
+    }
+
+    #[test]
+    fn round_trip() {
+        for args in [
+            &[][..],
+            &[
+                "-name", "*.rs", "-o", "!", "(", "-type", "d", "-o", "-empty", ")",
+            ],
+            &["-true", ",", "-exec", "echo", "it's", "{}", "+", "-print"],
+            &["-printf", "%p\\n", "-newermt", "jan 01, 2025"],
+        ] {
+            let expr = parse(args).unwrap();
+            assert_eq!(expr.to_args(), args);
+        }
+
+        let expr = parse(&["-not", "-name", "a", "-and", "-type", "f"]).unwrap();
+        let args = expr.to_args();
+        assert_eq!(args, ["!", "-name", "a", "-type", "f"]);
+        let args: Vec<&str> = args.iter().map(String::as_str).collect();
+        assert_eq!(parse(&args).unwrap().to_args(), args);
+    }
+
+    #[test]
+    fn display_quotes_arguments() {
+        let expr = parse(&["-name", "*.rs", "-exec", "echo", "it's", "{}", ";"]).unwrap();
+        assert_eq!(
+            expr.to_string(),
+            r"-name '*.rs' -exec echo 'it'\''s' {} ';'"
+        );
+    }
+
+    #[test]
+    fn highlight_errors() {
+        let e = parse(&["-type", "f", "-o"]).unwrap_err();
+        assert_eq!(
+            e.highlight(&["-type", "f", "-o"]),
+            "  -type f -o\n          ^^\n"
+        );
+
+        let e = ExprError::new("bad", Span::new(0, 2));
+        assert_eq!(
+            e.highlight(&["-type", "x", "-print"]),
+            "  -type x -print\n  ^^^^^^^\n"
+        );
+    }
+}
@@ -98,6 +98,7 @@ impl FileSystemMatcher {
 impl Matcher for FileSystemMatcher {
     #[cfg(unix)]
     fn matches(&self, file_info: &WalkEntry, _: &mut MatcherIO) -> bool {
+        crate::find::debug::count_stat(file_info.debugger(), file_info.path());
         match get_file_system_type(file_info.path(), &self.cache) {
             Ok(result) => result == self.fs_text,
             Err(_) => {
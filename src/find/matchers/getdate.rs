@@ -0,0 +1,1215 @@
+// This file is part of the uutils findutils package.
+//
+// For the full copyright and license information, please view the LICENSE
+// file that was distributed with this source code.
+
+//! Parses dates and times written the way GNU getdate (or `date -d`)
+//! understands them, for -newerXt.  Some examples:
+//!
+//! - `2024-03-01 12:00:00`, `2024-03-01T12:00:00+02:00`, `jan 1, 2000`,
+//!   `1/2/2000`, `1 jan 2000 1:30 pm`
+//! - `2 days ago`, `yesterday`, `last monday`, `next week`, `1.5 seconds ago`
+//! - `@1700000000`, a number of seconds since the epoch
+//! - `12:00 EST`, `12:00 UTC+5`, `12:00 -0530`
+//!
+//! A date is made of items (a time of day, a calendar date, a day of the
+//! week, a time zone and relative times) in any order, and parts that aren't
+//! given come from the current time.  The date is the start of the day if
+//! only relative times are missing, but the current time of day if those are
+//! the only items.
+//!
+//! Unlike GNU getdate, a leading `TZ="Zone"` naming a time zone from the tz
+//! database isn't supported; use a numeric offset like `-0500` instead.
+
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use chrono::{
+    DateTime, Datelike, Local, LocalResult, NaiveDate, NaiveDateTime, NaiveTime, TimeZone,
+    Timelike, Utc,
+};
+
+const HOUR: i64 = 60 * 60;
+const BILLION: i64 = 1_000_000_000;
+
+/// A number, along with how many digits it was written with, which matters
+/// for years ("92" is 1992) and times ("1230" is 12:30).
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+struct TextInt {
+    value: i64,
+    digits: usize,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum Meridian {
+    Am,
+    Pm,
+    Hour24,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum Unit {
+    Year,
+    Month,
+    Day,
+    Hour,
+    Minute,
+    Second,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum Token {
+    Unsigned(TextInt),
+    /// A number with a + or - in front.
+    Signed(TextInt),
+    /// Seconds and nanoseconds, e.g. 1.5.
+    UnsignedDecimal(i64, i64),
+    SignedDecimal(i64, i64),
+    Meridian(Meridian),
+    Month(i64),
+    /// A day of the week, from 0 for Sunday.
+    Weekday(i64),
+    /// A time zone's offset from UTC in seconds.
+    Zone(i64),
+    /// A time zone during daylight saving time, by its standard offset.
+    DayZone(i64),
+    Dst,
+    /// A unit of relative time, and how many of it (e.g. a week is 7 days).
+    Unit(Unit, i64),
+    /// "last", "this", "next", "first", "third" and so on.
+    Ordinal(i64),
+    /// "yesterday", "today" or "tomorrow".
+    DayShift(i64),
+    /// "ago" (-1) or "hence" (1).
+    Ago(i64),
+    /// The T between the date and time in ISO 8601, or else a military time
+    /// zone.
+    T,
+    Char(u8),
+}
+
+const MONTHS_AND_DAYS: &[(&str, Token)] = &[
+    ("JANUARY", Token::Month(1)),
+    ("FEBRUARY", Token::Month(2)),
+    ("MARCH", Token::Month(3)),
+    ("APRIL", Token::Month(4)),
+    ("MAY", Token::Month(5)),
+    ("JUNE", Token::Month(6)),
+    ("JULY", Token::Month(7)),
+    ("AUGUST", Token::Month(8)),
+    ("SEPTEMBER", Token::Month(9)),
+    ("SEPT", Token::Month(9)),
+    ("OCTOBER", Token::Month(10)),
+    ("NOVEMBER", Token::Month(11)),
+    ("DECEMBER", Token::Month(12)),
+    ("SUNDAY", Token::Weekday(0)),
+    ("MONDAY", Token::Weekday(1)),
+    ("TUESDAY", Token::Weekday(2)),
+    ("TUES", Token::Weekday(2)),
+    ("WEDNESDAY", Token::Weekday(3)),
+    ("WEDNES", Token::Weekday(3)),
+    ("THURSDAY", Token::Weekday(4)),
+    ("THUR", Token::Weekday(4)),
+    ("THURS", Token::Weekday(4)),
+    ("FRIDAY", Token::Weekday(5)),
+    ("SATURDAY", Token::Weekday(6)),
+];
+
+const TIME_ZONES: &[(&str, Token)] = &[
+    ("GMT", Token::Zone(0)),
+    ("UT", Token::Zone(0)),
+    ("UTC", Token::Zone(0)),
+    ("WET", Token::Zone(0)),
+    ("WEST", Token::DayZone(0)),
+    ("BST", Token::DayZone(0)),
+    ("ART", Token::Zone(-3 * HOUR)),
+    ("BRT", Token::Zone(-3 * HOUR)),
+    ("BRST", Token::DayZone(-3 * HOUR)),
+    ("NST", Token::Zone(-(3 * HOUR + 30 * 60))),
+    ("NDT", Token::DayZone(-(3 * HOUR + 30 * 60))),
+    ("AST", Token::Zone(-4 * HOUR)),
+    ("ADT", Token::DayZone(-4 * HOUR)),
+    ("CLT", Token::Zone(-4 * HOUR)),
+    ("CLST", Token::DayZone(-4 * HOUR)),
+    ("EST", Token::Zone(-5 * HOUR)),
+    ("EDT", Token::DayZone(-5 * HOUR)),
+    ("CST", Token::Zone(-6 * HOUR)),
+    ("CDT", Token::DayZone(-6 * HOUR)),
+    ("MST", Token::Zone(-7 * HOUR)),
+    ("MDT", Token::DayZone(-7 * HOUR)),
+    ("PST", Token::Zone(-8 * HOUR)),
+    ("PDT", Token::DayZone(-8 * HOUR)),
+    ("AKST", Token::Zone(-9 * HOUR)),
+    ("AKDT", Token::DayZone(-9 * HOUR)),
+    ("HST", Token::Zone(-10 * HOUR)),
+    ("HAST", Token::Zone(-10 * HOUR)),
+    ("HADT", Token::DayZone(-10 * HOUR)),
+    ("SST", Token::Zone(-12 * HOUR)),
+    ("WAT", Token::Zone(HOUR)),
+    ("CET", Token::Zone(HOUR)),
+    ("CEST", Token::DayZone(HOUR)),
+    ("MET", Token::Zone(HOUR)),
+    ("MEZ", Token::Zone(HOUR)),
+    ("MEST", Token::DayZone(HOUR)),
+    ("MESZ", Token::DayZone(HOUR)),
+    ("EET", Token::Zone(2 * HOUR)),
+    ("EEST", Token::DayZone(2 * HOUR)),
+    ("CAT", Token::Zone(2 * HOUR)),
+    ("SAST", Token::Zone(2 * HOUR)),
+    ("EAT", Token::Zone(3 * HOUR)),
+    ("MSK", Token::Zone(3 * HOUR)),
+    ("MSD", Token::DayZone(3 * HOUR)),
+    ("IST", Token::Zone(5 * HOUR + 30 * 60)),
+    ("SGT", Token::Zone(8 * HOUR)),
+    ("KST", Token::Zone(9 * HOUR)),
+    ("JST", Token::Zone(9 * HOUR)),
+    ("GST", Token::Zone(10 * HOUR)),
+    ("NZST", Token::Zone(12 * HOUR)),
+    ("NZDT", Token::DayZone(12 * HOUR)),
+];
+
+const TIME_UNITS: &[(&str, Token)] = &[
+    ("YEAR", Token::Unit(Unit::Year, 1)),
+    ("MONTH", Token::Unit(Unit::Month, 1)),
+    ("FORTNIGHT", Token::Unit(Unit::Day, 14)),
+    ("WEEK", Token::Unit(Unit::Day, 7)),
+    ("DAY", Token::Unit(Unit::Day, 1)),
+    ("HOUR", Token::Unit(Unit::Hour, 1)),
+    ("MINUTE", Token::Unit(Unit::Minute, 1)),
+    ("MIN", Token::Unit(Unit::Minute, 1)),
+    ("SECOND", Token::Unit(Unit::Second, 1)),
+    ("SEC", Token::Unit(Unit::Second, 1)),
+];
+
+const RELATIVE_TIMES: &[(&str, Token)] = &[
+    ("TOMORROW", Token::DayShift(1)),
+    ("YESTERDAY", Token::DayShift(-1)),
+    ("TODAY", Token::DayShift(0)),
+    ("NOW", Token::DayShift(0)),
+    ("LAST", Token::Ordinal(-1)),
+    ("THIS", Token::Ordinal(0)),
+    ("NEXT", Token::Ordinal(1)),
+    ("FIRST", Token::Ordinal(1)),
+    // "SECOND" is a unit
+    ("THIRD", Token::Ordinal(3)),
+    ("FOURTH", Token::Ordinal(4)),
+    ("FIFTH", Token::Ordinal(5)),
+    ("SIXTH", Token::Ordinal(6)),
+    ("SEVENTH", Token::Ordinal(7)),
+    ("EIGHTH", Token::Ordinal(8)),
+    ("NINTH", Token::Ordinal(9)),
+    ("TENTH", Token::Ordinal(10)),
+    ("ELEVENTH", Token::Ordinal(11)),
+    ("TWELFTH", Token::Ordinal(12)),
+    ("AGO", Token::Ago(-1)),
+    ("HENCE", Token::Ago(1)),
+];
+
+fn lookup(table: &[(&str, Token)], word: &str) -> Option<Token> {
+    table
+        .iter()
+        .find(|(name, _)| *name == word)
+        .map(|&(_, token)| token)
+}
+
+/// Looks up a (upper case) word.
+fn lookup_word(word: &str) -> Option<Token> {
+    match word {
+        "AM" | "A.M." => return Some(Token::Meridian(Meridian::Am)),
+        "PM" | "P.M." => return Some(Token::Meridian(Meridian::Pm)),
+        "DST" => return Some(Token::Dst),
+        _ => {}
+    }
+
+    // Months and days can be abbreviated to three letters, with or without a
+    // period
+    let abbrev = word.len() == 3 || (word.len() == 4 && word.ends_with('.'));
+    let month_or_day = MONTHS_AND_DAYS.iter().find(|(name, _)| {
+        if abbrev {
+            name[..3] == word[..3]
+        } else {
+            *name == word
+        }
+    });
+    if let Some(&(_, token)) = month_or_day {
+        return Some(token);
+    }
+
+    let singular = word.strip_suffix('S').unwrap_or("");
+    if let Some(token) = lookup(TIME_ZONES, word)
+        .or_else(|| lookup(TIME_UNITS, word))
+        .or_else(|| lookup(TIME_UNITS, singular))
+        .or_else(|| lookup(RELATIVE_TIMES, word))
+    {
+        return Some(token);
+    }
+
+    // Military time zones: A to M (but not J) are ahead of UTC, N to Y
+    // behind it
+    if let &[letter] = word.as_bytes() {
+        let hours = match letter {
+            b'A'..=b'I' => i64::from(letter - b'A') + 1,
+            b'K'..=b'M' => i64::from(letter - b'K') + 10,
+            b'N'..=b'Y' if letter != b'T' => -(i64::from(letter - b'N') + 1),
+            b'Z' => 0,
+            b'T' => return Some(Token::T),
+            _ => return None,
+        };
+        return Some(Token::Zone(hours * HOUR));
+    }
+
+    // Time zones can have periods in them, like e.s.t.
+    if word.contains('.') {
+        return lookup(TIME_ZONES, &word.replace('.', ""));
+    }
+    None
+}
+
+/// Splits a date string into tokens.  Text in parentheses is a comment.
+fn tokenize(input: &str) -> Option<Vec<Token>> {
+    let bytes = input.as_bytes();
+    let is_digit = |i: usize| bytes.get(i).is_some_and(u8::is_ascii_digit);
+    let mut tokens = vec![];
+    let mut i = 0;
+    while i < bytes.len() {
+        let c = bytes[i];
+        if c.is_ascii_whitespace() {
+            i += 1;
+        } else if c.is_ascii_digit() || c == b'-' || c == b'+' {
+            let negative = c == b'-';
+            let signed = !c.is_ascii_digit();
+            if signed {
+                i += 1;
+                while bytes.get(i).is_some_and(u8::is_ascii_whitespace) {
+                    i += 1;
+                }
+                if !is_digit(i) {
+                    // A sign on its own is ignored
+                    continue;
+                }
+            }
+
+            let start = i;
+            let mut value: i64 = 0;
+            while is_digit(i) {
+                value = value
+                    .checked_mul(10)?
+                    .checked_add(i64::from(bytes[i] - b'0'))?;
+                i += 1;
+            }
+            let digits = i - start;
+            if negative {
+                value = -value;
+            }
+
+            if matches!(bytes.get(i), Some(b'.' | b',')) && is_digit(i + 1) {
+                // Fractions of seconds, to the nanosecond, rounded down
+                i += 1;
+                let mut nanos = 0;
+                for _ in 0..9 {
+                    nanos *= 10;
+                    if is_digit(i) {
+                        nanos += i64::from(bytes[i] - b'0');
+                        i += 1;
+                    }
+                }
+                let rest = i;
+                while is_digit(i) {
+                    i += 1;
+                }
+                if negative && bytes[rest..i].iter().any(|&b| b != b'0') {
+                    nanos += 1;
+                }
+                if negative && nanos != 0 {
+                    value = value.checked_sub(1)?;
+                    nanos = BILLION - nanos;
+                }
+                tokens.push(if signed {
+                    Token::SignedDecimal(value, nanos)
+                } else {
+                    Token::UnsignedDecimal(value, nanos)
+                });
+            } else {
+                let number = TextInt { value, digits };
+                tokens.push(if signed {
+                    Token::Signed(number)
+                } else {
+                    Token::Unsigned(number)
+                });
+            }
+        } else if c.is_ascii_alphabetic() {
+            let start = i;
+            while bytes
+                .get(i)
+                .is_some_and(|&b| b.is_ascii_alphabetic() || b == b'.')
+            {
+                i += 1;
+            }
+            tokens.push(lookup_word(&input[start..i].to_ascii_uppercase())?);
+        } else if c == b'(' {
+            let mut depth = 0;
+            while let Some(&c) = bytes.get(i) {
+                i += 1;
+                match c {
+                    b'(' => depth += 1,
+                    b')' => depth -= 1,
+                    _ => {}
+                }
+                if depth == 0 {
+                    break;
+                }
+            }
+        } else {
+            tokens.push(Token::Char(c));
+            i += 1;
+        }
+    }
+    Some(tokens)
+}
+
+/// An amount of relative time.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+struct Relative {
+    years: i64,
+    months: i64,
+    days: i64,
+    hours: i64,
+    minutes: i64,
+    seconds: i64,
+    nanos: i64,
+}
+
+impl Relative {
+    fn new(unit: Unit, count: i64) -> Self {
+        let mut rel = Self::default();
+        *match unit {
+            Unit::Year => &mut rel.years,
+            Unit::Month => &mut rel.months,
+            Unit::Day => &mut rel.days,
+            Unit::Hour => &mut rel.hours,
+            Unit::Minute => &mut rel.minutes,
+            Unit::Second => &mut rel.seconds,
+        } = count;
+        rel
+    }
+
+    /// Adds `factor` (1 or -1) times `other` to this.
+    fn add(&mut self, other: Self, factor: i64) -> Option<()> {
+        let add = |a: &mut i64, b: i64| -> Option<()> {
+            *a = a.checked_add(b.checked_mul(factor)?)?;
+            Some(())
+        };
+        add(&mut self.years, other.years)?;
+        add(&mut self.months, other.months)?;
+        add(&mut self.days, other.days)?;
+        add(&mut self.hours, other.hours)?;
+        add(&mut self.minutes, other.minutes)?;
+        add(&mut self.seconds, other.seconds)?;
+        add(&mut self.nanos, other.nanos)
+    }
+}
+
+/// A time of day, on the 24-hour clock.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+struct Time {
+    hour: i64,
+    minutes: i64,
+    seconds: i64,
+    nanos: i64,
+}
+
+/// A date and time in the getdate format, which may be relative to the
+/// current time.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct DateSpec {
+    /// Seconds and nanoseconds since the epoch, from "@seconds".
+    timestamp: Option<(i64, i64)>,
+    year: Option<TextInt>,
+    month_day: Option<(i64, i64)>,
+    time: Option<Time>,
+    /// The day of the week, and which one (e.g. "next" is 1).
+    weekday: Option<(i64, i64)>,
+    /// The time zone's offset from UTC in seconds, if not local time.
+    zone: Option<i64>,
+    relative: Option<Relative>,
+}
+
+struct Parser {
+    tokens: Vec<Token>,
+    pos: usize,
+    spec: DateSpec,
+    times_seen: usize,
+    dates_seen: usize,
+    days_seen: usize,
+    zones_seen: usize,
+}
+
+impl Parser {
+    fn peek(&self) -> Option<Token> {
+        self.tokens.get(self.pos).copied()
+    }
+
+    fn next(&mut self) -> Option<Token> {
+        let token = self.peek()?;
+        self.pos += 1;
+        Some(token)
+    }
+
+    fn eat(&mut self, token: Token) -> bool {
+        if self.peek() == Some(token) {
+            self.pos += 1;
+            true
+        } else {
+            false
+        }
+    }
+
+    fn unsigned(&mut self) -> Option<TextInt> {
+        match self.next()? {
+            Token::Unsigned(n) => Some(n),
+            _ => None,
+        }
+    }
+
+    fn parse(mut self) -> Option<DateSpec> {
+        if self.eat(Token::Char(b'@')) {
+            let timestamp = match self.tokens[self.pos..] {
+                [Token::Unsigned(n) | Token::Signed(n)] => (n.value, 0),
+                [Token::UnsignedDecimal(s, ns) | Token::SignedDecimal(s, ns)] => (s, ns),
+                _ => return None,
+            };
+            self.spec.timestamp = Some(timestamp);
+            return Some(self.spec);
+        }
+
+        while self.pos < self.tokens.len() {
+            self.item()?;
+        }
+        if self.times_seen > 1 || self.dates_seen > 1 || self.days_seen > 1 || self.zones_seen > 1 {
+            return None;
+        }
+        Some(self.spec)
+    }
+
+    fn item(&mut self) -> Option<()> {
+        match self.next()? {
+            Token::Unsigned(n) => self.after_number(n),
+            Token::Signed(n) => match self.next()? {
+                Token::Unit(unit, count) => {
+                    self.relative_unit(Relative::new(unit, n.value.checked_mul(count)?))
+                }
+                _ => None,
+            },
+            Token::UnsignedDecimal(seconds, nanos) | Token::SignedDecimal(seconds, nanos) => {
+                match self.next()? {
+                    Token::Unit(Unit::Second, _) => self.relative_unit(Relative {
+                        seconds,
+                        nanos,
+                        ..Relative::default()
+                    }),
+                    _ => None,
+                }
+            }
+            Token::Month(month) => match self.next()? {
+                // JUN-17-1992
+                Token::Signed(day) => match self.next()? {
+                    Token::Signed(year) => self.set_date(
+                        Some(TextInt {
+                            value: -year.value,
+                            digits: year.digits,
+                        }),
+                        month,
+                        -day.value,
+                    ),
+                    _ => None,
+                },
+                Token::Unsigned(day) => {
+                    let year = if self.eat(Token::Char(b',')) {
+                        Some(self.unsigned()?)
+                    } else {
+                        None
+                    };
+                    self.set_date(year, month, day.value)
+                }
+                _ => None,
+            },
+            Token::Weekday(day) => {
+                self.eat(Token::Char(b','));
+                self.set_weekday(0, day)
+            }
+            Token::Ordinal(n) => match self.next()? {
+                Token::Weekday(day) => self.set_weekday(n, day),
+                Token::Unit(unit, count) => {
+                    self.relative_unit(Relative::new(unit, n.checked_mul(count)?))
+                }
+                _ => None,
+            },
+            Token::Unit(unit, count) => self.relative_unit(Relative::new(unit, count)),
+            Token::DayShift(days) => self.add_relative(Relative::new(Unit::Day, days), 1),
+            Token::Zone(offset) => match self.peek() {
+                Some(Token::Signed(n)) => {
+                    self.pos += 1;
+                    if let Some(Token::Unit(unit, count)) = self.peek() {
+                        // UTC+1 hour
+                        self.pos += 1;
+                        self.set_zone(offset)?;
+                        self.add_relative(Relative::new(unit, n.value.checked_mul(count)?), 1)
+                    } else {
+                        let minutes = self.colon_minutes()?;
+                        let hours_minutes = self.hours_minutes(n, minutes);
+                        self.set_zone(offset + hours_minutes)
+                    }
+                }
+                Some(Token::Dst) => {
+                    self.pos += 1;
+                    self.set_zone(offset + HOUR)
+                }
+                _ => self.set_zone(offset),
+            },
+            Token::T => {
+                self.set_zone(-7 * HOUR)?;
+                match self.tokens[self.pos..] {
+                    [Token::Signed(n), Token::Unit(unit, count), ..] => {
+                        self.pos += 2;
+                        self.add_relative(Relative::new(unit, n.value.checked_mul(count)?), 1)
+                    }
+                    _ => Some(()),
+                }
+            }
+            Token::DayZone(offset) => self.set_zone(offset + HOUR),
+            _ => None,
+        }
+    }
+
+    /// Parses what can follow an unsigned number.
+    fn after_number(&mut self, n: TextInt) -> Option<()> {
+        match self.peek() {
+            Some(Token::Meridian(meridian)) => {
+                self.pos += 1;
+                self.set_time(n.value, 0, (0, 0), meridian)
+            }
+            Some(Token::Char(b':')) => {
+                self.pos += 1;
+                let minutes = self.unsigned()?.value;
+                let seconds = if self.eat(Token::Char(b':')) {
+                    self.seconds()?
+                } else {
+                    (0, 0)
+                };
+                if let Some(Token::Meridian(meridian)) = self.peek() {
+                    self.pos += 1;
+                    self.set_time(n.value, minutes, seconds, meridian)
+                } else {
+                    self.zone_offset()?;
+                    self.set_time(n.value, minutes, seconds, Meridian::Hour24)
+                }
+            }
+            Some(Token::Char(b'/')) => {
+                self.pos += 1;
+                let second = self.unsigned()?;
+                if self.eat(Token::Char(b'/')) {
+                    let third = self.unsigned()?;
+                    // YYYY/MM/DD, or else MM/DD/YY
+                    if n.digits >= 4 {
+                        self.set_date(Some(n), second.value, third.value)
+                    } else {
+                        self.set_date(Some(third), n.value, second.value)
+                    }
+                } else {
+                    self.set_date(None, n.value, second.value)
+                }
+            }
+            // 17 JUN, 17 JUN 1992 or 17-JUN-1992
+            Some(Token::Month(month)) => {
+                self.pos += 1;
+                let year = match self.peek() {
+                    Some(Token::Unsigned(year)) => Some(year),
+                    Some(Token::Signed(year)) => Some(TextInt {
+                        value: -year.value,
+                        digits: year.digits,
+                    }),
+                    _ => None,
+                };
+                if year.is_some() {
+                    self.pos += 1;
+                }
+                self.set_date(year, month, n.value)
+            }
+            Some(Token::Weekday(day)) => {
+                self.pos += 1;
+                self.set_weekday(n.value, day)
+            }
+            Some(Token::Unit(unit, count)) => {
+                self.pos += 1;
+                self.relative_unit(Relative::new(unit, n.value.checked_mul(count)?))
+            }
+            Some(Token::Signed(second)) => {
+                self.pos += 1;
+                match self.peek() {
+                    // ISO 8601: YYYY-MM-DD, maybe with a T and a time
+                    Some(Token::Signed(third)) => {
+                        self.pos += 1;
+                        self.set_date(Some(n), -second.value, -third.value)?;
+                        if self.eat(Token::T) {
+                            self.iso_time()?;
+                        }
+                        Some(())
+                    }
+                    Some(Token::Unit(unit, count)) => {
+                        self.pos += 1;
+                        self.number(n);
+                        self.add_relative(Relative::new(unit, second.value.checked_mul(count)?), 1)
+                    }
+                    // An hour and a time zone offset
+                    _ => {
+                        let minutes = self.colon_minutes()?;
+                        self.set_offset(second, minutes)?;
+                        self.set_time(n.value, 0, (0, 0), Meridian::Hour24)
+                    }
+                }
+            }
+            _ => {
+                self.number(n);
+                Some(())
+            }
+        }
+    }
+
+    /// Parses the time after the T in ISO 8601.
+    fn iso_time(&mut self) -> Option<()> {
+        let hour = self.unsigned()?.value;
+        match self.next()? {
+            Token::Char(b':') => {
+                let minutes = self.unsigned()?.value;
+                let seconds = if self.eat(Token::Char(b':')) {
+                    self.seconds()?
+                } else {
+                    (0, 0)
+                };
+                self.zone_offset()?;
+                self.set_time(hour, minutes, seconds, Meridian::Hour24)
+            }
+            Token::Signed(offset) => {
+                let minutes = self.colon_minutes()?;
+                self.set_offset(offset, minutes)?;
+                self.set_time(hour, 0, (0, 0), Meridian::Hour24)
+            }
+            _ => None,
+        }
+    }
+
+    fn seconds(&mut self) -> Option<(i64, i64)> {
+        match self.next()? {
+            Token::Unsigned(n) => Some((n.value, 0)),
+            Token::UnsignedDecimal(seconds, nanos) => Some((seconds, nanos)),
+            _ => None,
+        }
+    }
+
+    /// Parses the minutes after a colon in a time zone offset, if there is
+    /// one.
+    fn colon_minutes(&mut self) -> Option<Option<i64>> {
+        if self.eat(Token::Char(b':')) {
+            Some(Some(self.unsigned()?.value))
+        } else {
+            Some(None)
+        }
+    }
+
+    /// Parses the time zone offset that can follow a time, if there is one.
+    fn zone_offset(&mut self) -> Option<()> {
+        if let Some(Token::Signed(offset)) = self.peek() {
+            self.pos += 1;
+            let minutes = self.colon_minutes()?;
+            self.set_offset(offset, minutes)?;
+        }
+        Some(())
+    }
+
+    /// Converts a time zone offset like +5, +0530 or +05:30 to seconds.
+    fn hours_minutes(&mut self, n: TextInt, minutes: Option<i64>) -> i64 {
+        let total = match minutes {
+            None if n.digits <= 2 => n.value * 60,
+            None => n.value / 100 * 60 + n.value % 100,
+            Some(minutes) if n.value < 0 => n.value * 60 - minutes,
+            Some(minutes) => n.value * 60 + minutes,
+        };
+        if total.abs() > 24 * 60 {
+            // Make sure it's rejected
+            self.zones_seen += 1;
+        }
+        total * 60
+    }
+
+    fn set_offset(&mut self, n: TextInt, minutes: Option<i64>) -> Option<()> {
+        let offset = self.hours_minutes(n, minutes);
+        self.set_zone(offset)
+    }
+
+    /// Parses a relative time's "ago" or "hence", if there is one.
+    fn relative_unit(&mut self, rel: Relative) -> Option<()> {
+        let factor = match self.peek() {
+            Some(Token::Ago(factor)) => {
+                self.pos += 1;
+                factor
+            }
+            _ => 1,
+        };
+        self.add_relative(rel, factor)
+    }
+
+    fn add_relative(&mut self, rel: Relative, factor: i64) -> Option<()> {
+        self.spec
+            .relative
+            .get_or_insert_with(Relative::default)
+            .add(rel, factor)
+    }
+
+    /// Works out what a number on its own is: a year after a date, a date
+    /// like 20240301, or a time like 1230.
+    fn number(&mut self, n: TextInt) {
+        if self.dates_seen > 0
+            && self.spec.year.is_none()
+            && self.spec.relative.is_none()
+            && (self.times_seen > 0 || n.digits > 2)
+        {
+            self.spec.year = Some(n);
+        } else if n.digits > 4 {
+            self.dates_seen += 1;
+            self.spec.year = Some(TextInt {
+                value: n.value / 10000,
+                digits: n.digits - 4,
+            });
+            self.spec.month_day = Some((n.value / 100 % 100, n.value % 100));
+        } else {
+            let (hour, minutes) = if n.digits <= 2 {
+                (n.value, 0)
+            } else {
+                (n.value / 100, n.value % 100)
+            };
+            self.times_seen += 1;
+            self.spec.time = Some(Time {
+                hour,
+                minutes,
+                seconds: 0,
+                nanos: 0,
+            });
+        }
+    }
+
+    fn set_time(
+        &mut self,
+        hour: i64,
+        minutes: i64,
+        (seconds, nanos): (i64, i64),
+        meridian: Meridian,
+    ) -> Option<()> {
+        let hour = match meridian {
+            Meridian::Hour24 if (0..24).contains(&hour) => hour,
+            Meridian::Am if (1..=12).contains(&hour) => hour % 12,
+            Meridian::Pm if (1..=12).contains(&hour) => hour % 12 + 12,
+            _ => return None,
+        };
+        self.times_seen += 1;
+        self.spec.time = Some(Time {
+            hour,
+            minutes,
+            seconds,
+            nanos,
+        });
+        Some(())
+    }
+
+    fn set_date(&mut self, year: Option<TextInt>, month: i64, day: i64) -> Option<()> {
+        self.dates_seen += 1;
+        if year.is_some() {
+            self.spec.year = year;
+        }
+        self.spec.month_day = Some((month, day));
+        Some(())
+    }
+
+    fn set_weekday(&mut self, ordinal: i64, day: i64) -> Option<()> {
+        self.days_seen += 1;
+        self.spec.weekday = Some((ordinal, day));
+        Some(())
+    }
+
+    fn set_zone(&mut self, offset: i64) -> Option<()> {
+        self.zones_seen += 1;
+        self.spec.zone = Some(offset);
+        Some(())
+    }
+}
+
+/// Adds a number of days to a date.
+fn add_days(date: NaiveDate, days: i64) -> Option<NaiveDate> {
+    date.checked_add_signed(chrono::Duration::try_days(days)?)
+}
+
+fn system_time(seconds: i64, nanos: i64) -> Option<SystemTime> {
+    let nanos = Duration::from_nanos(u64::try_from(nanos).ok()?);
+    if seconds >= 0 {
+        UNIX_EPOCH.checked_add(Duration::from_secs(seconds.unsigned_abs()) + nanos)
+    } else {
+        UNIX_EPOCH
+            .checked_sub(Duration::from_secs(seconds.unsigned_abs()))?
+            .checked_add(nanos)
+    }
+}
+
+impl DateSpec {
+    /// Parses a date, returning None if it isn't valid.
+    pub fn parse(input: &str) -> Option<Self> {
+        Parser {
+            tokens: tokenize(input)?,
+            pos: 0,
+            spec: Self::default(),
+            times_seen: 0,
+            dates_seen: 0,
+            days_seen: 0,
+            zones_seen: 0,
+        }
+        .parse()
+    }
+
+    /// Works out the time that this date stands for, given the current time,
+    /// in the local time zone.  Returns None if there's no such time, like
+    /// "feb 29" outside of a leap year.
+    pub fn resolve(&self, now: SystemTime) -> Option<SystemTime> {
+        self.resolve_in(now, &Local)
+    }
+
+    fn resolve_in<Tz: TimeZone>(&self, now: SystemTime, tz: &Tz) -> Option<SystemTime> {
+        if let Some((seconds, nanos)) = self.timestamp {
+            return system_time(seconds, nanos);
+        }
+
+        let now = DateTime::<Utc>::from(now).with_timezone(tz);
+        let year = match self.year {
+            // XPG says that 69-99 are 1969-1999, and 00-68 are 2000-2068
+            Some(TextInt { value, digits: 2 }) if value < 69 => value + 2000,
+            Some(TextInt { value, digits: 2 }) => value + 1900,
+            Some(year) => year.value,
+            None => now.year().into(),
+        };
+        let (month, day) = self
+            .month_day
+            .unwrap_or((now.month().into(), now.day().into()));
+        let time = match self.time {
+            Some(time) => time,
+            // With only relative times, they're relative to now
+            None if self.relative.is_some()
+                && self.month_day.is_none()
+                && self.weekday.is_none() =>
+            {
+                Time {
+                    hour: now.hour().into(),
+                    minutes: now.minute().into(),
+                    seconds: now.second().into(),
+                    nanos: now.nanosecond().into(),
+                }
+            }
+            None => Time {
+                hour: 0,
+                minutes: 0,
+                seconds: 0,
+                nanos: 0,
+            },
+        };
+
+        let mut date = NaiveDate::from_ymd_opt(
+            year.try_into().ok()?,
+            month.try_into().ok()?,
+            day.try_into().ok()?,
+        )?;
+        let time_of_day = NaiveTime::from_hms_opt(
+            time.hour.try_into().ok()?,
+            time.minutes.try_into().ok()?,
+            time.seconds.try_into().ok()?,
+        )?;
+
+        // The day of the week is counted from the date, e.g. "next monday"
+        // is a week after "monday", which may be today
+        let mut adjusted = false;
+        if let Some((ordinal, day)) = self.weekday.filter(|_| self.month_day.is_none()) {
+            let today = i64::from(date.weekday().num_days_from_sunday());
+            let weeks = ordinal - i64::from(ordinal > 0 && today != day);
+            date = add_days(date, weeks * 7 + (day - today + 7) % 7)?;
+            adjusted = true;
+        }
+
+        let rel = self.relative.unwrap_or_default();
+        if rel.years != 0 || rel.months != 0 || rel.days != 0 {
+            // Days past the end of the month roll over, like mktime()
+            let months = i64::from(date.year())
+                .checked_add(rel.years)?
+                .checked_mul(12)?
+                .checked_add(i64::from(date.month0()))?
+                .checked_add(rel.months)?;
+            let first = NaiveDate::from_ymd_opt(
+                months.div_euclid(12).try_into().ok()?,
+                u32::try_from(months.rem_euclid(12)).ok()? + 1,
+                1,
+            )?;
+            date = add_days(first, i64::from(date.day0()).checked_add(rel.days)?)?;
+            adjusted = true;
+        }
+
+        let local = NaiveDateTime::new(date, time_of_day);
+        let start = match self.zone {
+            Some(offset) => local.and_utc().timestamp().checked_sub(offset)?,
+            None => match tz.from_local_datetime(&local) {
+                LocalResult::Single(t) | LocalResult::Ambiguous(t, _) => t.timestamp(),
+                // A time skipped by a change to daylight saving time is only
+                // an error if it was asked for directly
+                LocalResult::None if adjusted => tz
+                    .from_local_datetime(&(local + chrono::Duration::try_hours(1)?))
+                    .earliest()?
+                    .timestamp(),
+                LocalResult::None => return None,
+            },
+        };
+
+        let nanos = time.nanos.checked_add(rel.nanos)?;
+        let seconds = start
+            .checked_add(rel.hours.checked_mul(HOUR)?)?
+            .checked_add(rel.minutes.checked_mul(60)?)?
+            .checked_add(rel.seconds)?
+            .checked_add(nanos.div_euclid(BILLION))?;
+        system_time(seconds, nanos.rem_euclid(BILLION))
+    }
+}
+
+impl From<SystemTime> for DateSpec {
+    fn from(time: SystemTime) -> Self {
+        let (seconds, nanos) = match time.duration_since(UNIX_EPOCH) {
+            Ok(d) => (d.as_secs() as i64, i64::from(d.subsec_nanos())),
+            Err(e) => {
+                let d = e.duration();
+                let seconds = -(d.as_secs() as i64);
+                match d.subsec_nanos() {
+                    0 => (seconds, 0),
+                    nanos => (seconds - 1, BILLION - i64::from(nanos)),
+                }
+            }
+        };
+        Self {
+            timestamp: Some((seconds, nanos)),
+            ..Self::default()
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::FixedOffset;
+
+    use super::*;
+
+    /// Thursday, 2026-10-15 11:10:32.5 UTC.
+    const NOW: i64 = 1_792_062_632;
+
+    /// Parses a date relative to [NOW] in a time zone `offset` hours from
+    /// UTC, and formats it in UTC.
+    fn parse_in(input: &str, offset: i32) -> Option<String> {
+        let now = system_time(NOW, BILLION / 2).unwrap();
+        let tz = FixedOffset::east_opt(offset * 3600).unwrap();
+        let time = DateSpec::parse(input)?.resolve_in(now, &tz)?;
+        Some(
+            DateTime::<Utc>::from(time)
+                .format("%Y-%m-%d %H:%M:%S%.f")
+                .to_string(),
+        )
+    }
+
+    fn parse(input: &str) -> Option<String> {
+        parse_in(input, 0)
+    }
+
+    #[test]
+    fn absolute_dates() {
+        for (input, expected) in [
+            ("", "2026-10-15 00:00:00"),
+            ("jan 01, 2025 00:00:01", "2025-01-01 00:00:01"),
+            ("jan 01, 2000", "2000-01-01 00:00:00"),
+            ("2024-03-01", "2024-03-01 00:00:00"),
+            ("2024-03-01 12:34:56", "2024-03-01 12:34:56"),
+            ("2024-03-01T12:34:56.25", "2024-03-01 12:34:56.250"),
+            ("20240301", "2024-03-01 00:00:00"),
+            ("20240301 1200", "2024-03-01 12:00:00"),
+            ("1992/06/17", "1992-06-17 00:00:00"),
+            ("06/17/92", "1992-06-17 00:00:00"),
+            ("6/17", "2026-06-17 00:00:00"),
+            ("17-JUN-1992", "1992-06-17 00:00:00"),
+            ("JUN-17-1992", "1992-06-17 00:00:00"),
+            ("17 jun 92", "1992-06-17 00:00:00"),
+            ("Sep. 5", "2026-09-05 00:00:00"),
+            ("jan 1 2000 1230", "2000-01-01 12:30:00"),
+            ("12:00 jan 1 2024", "2024-01-01 12:00:00"),
+            ("jan 1, 68", "2068-01-01 00:00:00"),
+            ("jan 1, 69", "1969-01-01 00:00:00"),
+            ("jan 1 (a comment) 2000", "2000-01-01 00:00:00"),
+        ] {
+            assert_eq!(parse(input).as_deref(), Some(expected), "{input}");
+        }
+    }
+
+    #[test]
+    fn times_of_day() {
+        for (input, expected) in [
+            ("1230", "2026-10-15 12:30:00"),
+            ("123", "2026-10-15 01:23:00"),
+            ("10:00pm", "2026-10-15 22:00:00"),
+            ("10 a.m.", "2026-10-15 10:00:00"),
+            ("12am", "2026-10-15 00:00:00"),
+            ("12:30:00 PM", "2026-10-15 12:30:00"),
+            ("12:00:59.9999999999", "2026-10-15 12:00:59.999999999"),
+        ] {
+            assert_eq!(parse(input).as_deref(), Some(expected), "{input}");
+        }
+    }
+
+    #[test]
+    fn relative_dates() {
+        for (input, expected) in [
+            ("now", "2026-10-15 11:10:32.500"),
+            ("2 days ago", "2026-10-13 11:10:32.500"),
+            ("yesterday", "2026-10-14 11:10:32.500"),
+            ("tomorrow 10:00", "2026-10-16 10:00:00"),
+            ("next week", "2026-10-22 11:10:32.500"),
+            ("fortnight ago", "2026-10-01 11:10:32.500"),
+            ("last year", "2025-10-15 11:10:32.500"),
+            ("3 days hence", "2026-10-18 11:10:32.500"),
+            ("+ 3 days", "2026-10-18 11:10:32.500"),
+            ("2 hours ago 3 minutes", "2026-10-15 09:13:32.500"),
+            ("1.5 seconds ago", "2026-10-15 11:10:31"),
+            ("-1.25 seconds", "2026-10-15 11:10:31.250"),
+            ("jan 31 + 1 month", "2026-03-03 00:00:00"),
+            ("2024-03-01 -1 day", "2024-02-29 00:00:00"),
+            ("10 -2 days", "2026-10-13 10:00:00"),
+        ] {
+            assert_eq!(parse(input).as_deref(), Some(expected), "{input}");
+        }
+    }
+
+    #[test]
+    fn days_of_the_week() {
+        for (input, expected) in [
+            ("thursday", "2026-10-15 00:00:00"),
+            ("this thursday", "2026-10-15 00:00:00"),
+            ("next thursday", "2026-10-22 00:00:00"),
+            ("tues", "2026-10-20 00:00:00"),
+            ("last monday", "2026-10-12 00:00:00"),
+            ("third friday", "2026-10-30 00:00:00"),
+            ("2 thursday", "2026-10-29 00:00:00"),
+            ("monday 12:00", "2026-10-19 12:00:00"),
+            ("sunday 2 days ago", "2026-10-16 00:00:00"),
+            // A date takes precedence
+            ("monday jan 1", "2026-01-01 00:00:00"),
+        ] {
+            assert_eq!(parse(input).as_deref(), Some(expected), "{input}");
+        }
+    }
+
+    #[test]
+    fn time_zones() {
+        for (input, expected) in [
+            ("2024-03-01T12:00:00Z", "2024-03-01 12:00:00"),
+            ("2024-03-01T12:00:00.5+02:00", "2024-03-01 10:00:00.500"),
+            ("2024-03-01T12+05", "2024-03-01 07:00:00"),
+            ("2024-03-01 12:00 +0530", "2024-03-01 06:30:00"),
+            ("12:00 EST", "2026-10-15 17:00:00"),
+            ("12:00 EDT", "2026-10-15 16:00:00"),
+            ("12:00 EST DST", "2026-10-15 16:00:00"),
+            ("12:00 e.s.t.", "2026-10-15 17:00:00"),
+            ("12:00 UTC+5", "2026-10-15 07:00:00"),
+            ("12:00 UTC-05:30", "2026-10-15 17:30:00"),
+            ("12:00 +2400", "2026-10-14 12:00:00"),
+            ("12 +5", "2026-10-15 07:00:00"),
+            ("12:00 A", "2026-10-15 11:00:00"),
+            ("12:00 N", "2026-10-15 13:00:00"),
+            ("12:00 T", "2026-10-15 19:00:00"),
+            ("GMT+2", "2026-10-14 22:00:00"),
+            ("UTC+1 hour", "2026-10-15 12:10:32.500"),
+            // The offset, then a day
+            ("12:00 -1 day", "2026-10-16 13:00:00"),
+        ] {
+            assert_eq!(parse(input).as_deref(), Some(expected), "{input}");
+        }
+    }
+
+    #[test]
+    fn local_time_zone() {
+        // Dates are in the local time zone, unless they say otherwise
+        assert_eq!(
+            parse_in("2024-03-01 12:00", 2).as_deref(),
+            Some("2024-03-01 10:00:00")
+        );
+        assert_eq!(
+            parse_in("2024-03-01 12:00 UTC", 2).as_deref(),
+            Some("2024-03-01 12:00:00")
+        );
+        // Including "today", which starts at midnight local time, and may
+        // not be today in UTC
+        assert_eq!(parse_in("", -5).as_deref(), Some("2026-10-15 05:00:00"));
+        assert_eq!(parse_in("", 14).as_deref(), Some("2026-10-15 10:00:00"));
+        // Relative times aren't affected
+        assert_eq!(
+            parse_in("1 hour ago", 9).as_deref(),
+            Some("2026-10-15 10:10:32.500")
+        );
+    }
+
+    #[test]
+    fn timestamps() {
+        assert_eq!(parse("@0").as_deref(), Some("1970-01-01 00:00:00"));
+        assert_eq!(parse("@1700000000").as_deref(), Some("2023-11-14 22:13:20"));
+        assert_eq!(parse("@ 5").as_deref(), Some("1970-01-01 00:00:05"));
+        assert_eq!(parse("@-1.5").as_deref(), Some("1969-12-31 23:59:58.500"));
+        assert_eq!(parse("@1 day"), None);
+
+        let now = system_time(NOW, 123).unwrap();
+        assert_eq!(DateSpec::from(now).resolve(UNIX_EPOCH), Some(now));
+        let before = system_time(-10, 123).unwrap();
+        assert_eq!(DateSpec::from(before).resolve(UNIX_EPOCH), Some(before));
+    }
+
+    #[test]
+    fn invalid_dates() {
+        for input in [
+            "2037, jan 01",
+            "jan 01,2000",
+            "Sept. 5",
+            "feb 30",
+            "jan 2024",
+            "2024 jan 1",
+            "12345",
+            "24:00",
+            "0am",
+            "13pm",
+            "00:00 am",
+            "sep",
+            "hence",
+            "20 ago",
+            "last",
+            "1 2",
+            "12:00 1:00",
+            "monday tuesday",
+            "12:00 UTC EST",
+            "12:00 +2401",
+            "12:00 utc+25",
+            "12:00 T DST",
+            "2024-03-01 T",
+            "12.30",
+            "10 -2 days ago",
+            "A.M.",
+            "J",
+            "jan 1 !",
+            "tomorrowish",
+            "99999999999999999999",
+            "TZ=\"America/New_York\" 2024-03-01",
+        ] {
+            assert_eq!(parse(input), None, "{input}");
+        }
+    }
+}
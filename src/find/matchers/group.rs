@@ -3,7 +3,7 @@
 // For the full copyright and license information, please view the LICENSE
 // file that was distributed with this source code.
 
-use super::{Matcher, MatcherIO, WalkEntry};
+use super::{Cost, Matcher, MatcherIO, WalkEntry};
 
 #[cfg(unix)]
 use nix::unistd::Group;
@@ -76,6 +76,10 @@ impl Matcher for GroupMatcher {
         // so it is somewhat difficult to implement it. :(
         false
     }
+
+    fn cost(&self) -> Cost {
+        Cost::Stat
+    }
 }
 
 pub struct NoGroupMatcher {}
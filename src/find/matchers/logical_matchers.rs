@@ -12,7 +12,7 @@
 use std::error::Error;
 use std::path::Path;
 
-use super::{Matcher, MatcherIO, WalkEntry};
+use super::{Cost, Matcher, MatcherIO, WalkEntry};
 
 /// This matcher contains a collection of other matchers. A file only matches
 /// if it matches ALL the contained sub-matchers. For sub-matchers that have
@@ -51,15 +51,27 @@ impl Matcher for AndMatcher {
             .any(super::Matcher::has_side_effects)
     }
 
-    fn finished_dir(&self, dir: &Path) {
+    fn is_pure(&self) -> bool {
+        self.submatchers.iter().all(|m| m.is_pure())
+    }
+
+    fn cost(&self) -> Cost {
+        self.submatchers
+            .iter()
+            .map(|m| m.cost())
+            .max()
+            .unwrap_or(Cost::Free)
+    }
+
+    fn finished_dir(&self, dir: &Path, matcher_io: &mut MatcherIO) {
         for m in &self.submatchers {
-            m.finished_dir(dir);
+            m.finished_dir(dir, matcher_io);
         }
     }
 
-    fn finished(&self) {
+    fn finished(&self, matcher_io: &mut MatcherIO) {
         for m in &self.submatchers {
-            m.finished();
+            m.finished(matcher_io);
         }
     }
 }
@@ -127,15 +139,27 @@ impl Matcher for OrMatcher {
             .any(super::Matcher::has_side_effects)
     }
 
-    fn finished_dir(&self, dir: &Path) {
+    fn is_pure(&self) -> bool {
+        self.submatchers.iter().all(|m| m.is_pure())
+    }
+
+    fn cost(&self) -> Cost {
+        self.submatchers
+            .iter()
+            .map(|m| m.cost())
+            .max()
+            .unwrap_or(Cost::Free)
+    }
+
+    fn finished_dir(&self, dir: &Path, matcher_io: &mut MatcherIO) {
         for m in &self.submatchers {
-            m.finished_dir(dir);
+            m.finished_dir(dir, matcher_io);
         }
     }
 
-    fn finished(&self) {
+    fn finished(&self, matcher_io: &mut MatcherIO) {
         for m in &self.submatchers {
-            m.finished();
+            m.finished(matcher_io);
         }
     }
 }
@@ -222,15 +246,27 @@ impl Matcher for ListMatcher {
             .any(super::Matcher::has_side_effects)
     }
 
-    fn finished_dir(&self, dir: &Path) {
+    fn is_pure(&self) -> bool {
+        self.submatchers.iter().all(|m| m.is_pure())
+    }
+
+    fn cost(&self) -> Cost {
+        self.submatchers
+            .iter()
+            .map(|m| m.cost())
+            .max()
+            .unwrap_or(Cost::Free)
+    }
+
+    fn finished_dir(&self, dir: &Path, matcher_io: &mut MatcherIO) {
         for m in &self.submatchers {
-            m.finished_dir(dir);
+            m.finished_dir(dir, matcher_io);
         }
     }
 
-    fn finished(&self) {
+    fn finished(&self, matcher_io: &mut MatcherIO) {
         for m in &self.submatchers {
-            m.finished();
+            m.finished(matcher_io);
         }
     }
 }
@@ -248,25 +284,6 @@ impl ListMatcherBuilder {
             .new_and_condition(matcher);
     }
 
-    pub fn new_or_condition(&mut self, arg: &str) -> Result<(), Box<dyn Error>> {
-        self.submatchers.last_mut().unwrap().new_or_condition(arg)
-    }
-
-    pub fn check_new_and_condition(&mut self) -> Result<(), Box<dyn Error>> {
-        {
-            let child_or_matcher = &self.submatchers.last().unwrap();
-            let grandchild_and_matcher = &child_or_matcher.submatchers.last().unwrap();
-
-            if grandchild_and_matcher.submatchers.is_empty() {
-                return Err(From::from(
-                    "invalid expression; you have used a binary operator '-a' \
-                     with nothing before it.",
-                ));
-            }
-        }
-        Ok(())
-    }
-
     pub fn new_list_condition(&mut self) -> Result<(), Box<dyn Error>> {
         {
             let child_or_matcher = &self.submatchers.last().unwrap();
@@ -313,6 +330,14 @@ impl Matcher for TrueMatcher {
     fn matches(&self, _dir_entry: &WalkEntry, _: &mut MatcherIO) -> bool {
         true
     }
+
+    fn cost(&self) -> Cost {
+        Cost::Free
+    }
+
+    fn constant(&self) -> Option<bool> {
+        Some(true)
+    }
 }
 
 /// A simple matcher that never matches.
@@ -322,6 +347,14 @@ impl Matcher for FalseMatcher {
     fn matches(&self, _dir_entry: &WalkEntry, _: &mut MatcherIO) -> bool {
         false
     }
+
+    fn cost(&self) -> Cost {
+        Cost::Free
+    }
+
+    fn constant(&self) -> Option<bool> {
+        Some(false)
+    }
 }
 
 /// Matcher that wraps another matcher and inverts matching criteria.
@@ -346,17 +379,24 @@ impl Matcher for NotMatcher {
         self.submatcher.has_side_effects()
     }
 
-    fn finished_dir(&self, dir: &Path) {
-        self.submatcher.finished_dir(dir);
+    fn is_pure(&self) -> bool {
+        self.submatcher.is_pure()
+    }
+
+    fn cost(&self) -> Cost {
+        self.submatcher.cost()
     }
 
-    fn finished(&self) {
-        self.submatcher.finished();
+    fn finished_dir(&self, dir: &Path, matcher_io: &mut MatcherIO) {
+        self.submatcher.finished_dir(dir, matcher_io);
+    }
+
+    fn finished(&self, matcher_io: &mut MatcherIO) {
+        self.submatcher.finished(matcher_io);
     }
 }
 
 #[cfg(test)]
-
 mod tests {
     use super::*;
     use crate::find::matchers::quit::QuitMatcher;
@@ -478,6 +518,22 @@ mod tests {
         assert!(builder.build().has_side_effects());
     }
 
+    #[test]
+    fn and_is_pure_works() {
+        let matcher = AndMatcher::new(vec![TrueMatcher.into_box(), FalseMatcher.into_box()]);
+        assert!(matcher.is_pure());
+        assert_eq!(matcher.cost(), Cost::Free);
+
+        // -quit has no side effects, but isn't pure either
+        let matcher = AndMatcher::new(vec![TrueMatcher.into_box(), QuitMatcher.into_box()]);
+        assert!(!matcher.is_pure());
+        assert!(!NotMatcher::new(matcher).is_pure());
+
+        let matcher = AndMatcher::new(vec![TrueMatcher.into_box(), HasSideEffects.into_box()]);
+        assert!(!matcher.is_pure());
+        assert_eq!(matcher.cost(), Cost::Expensive);
+    }
+
     #[test]
     fn or_has_side_effects_works() {
         let mut builder = OrMatcherBuilder::new();
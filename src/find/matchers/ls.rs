@@ -6,10 +6,11 @@
 use chrono::DateTime;
 use std::{
     fs::File,
-    io::{stderr, Write},
+    io::{stderr, IsTerminal, Write},
 };
 
-use super::{Matcher, MatcherIO, WalkEntry};
+use super::quoting::{quote, QuotingStyle};
+use super::{printer::write_os_str, Matcher, MatcherIO, WalkEntry};
 
 #[cfg(unix)]
 fn format_permissions(mode: uucore::libc::mode_t) -> String {
@@ -111,15 +112,25 @@ fn format_permissions(file_attributes: u32) -> String {
 
 pub struct Ls {
     output_file: Option<File>,
+    output_file_is_terminal: bool,
 }
 
 impl Ls {
     pub fn new(output_file: Option<File>) -> Self {
-        Self { output_file }
+        Self {
+            output_file_is_terminal: output_file.as_ref().is_some_and(File::is_terminal),
+            output_file,
+        }
     }
 
     #[cfg(unix)]
-    fn print(&self, file_info: &WalkEntry, mut out: impl Write, print_error_message: bool) {
+    fn print(
+        &self,
+        file_info: &WalkEntry,
+        mut out: impl Write,
+        style: QuotingStyle,
+        print_error_message: bool,
+    ) {
         use nix::unistd::{Gid, Group, Uid, User};
         use std::os::unix::fs::{MetadataExt, PermissionsExt};
 
@@ -158,11 +169,9 @@ impl Ls {
             let now_utc: DateTime<chrono::Utc> = system_time.into();
             now_utc.format("%b %e %H:%M")
         };
-        let path = file_info.path().to_string_lossy();
-
-        match writeln!(
+        match write!(
             out,
-            " {:<4} {:>6} {:<10} {:>3} {:<8} {:<8} {:>8} {} {}",
+            " {:<4} {:>6} {:<10} {:>3} {:<8} {:<8} {:>8} {} ",
             inode_number,
             number_of_blocks,
             permission,
@@ -171,8 +180,10 @@ impl Ls {
             group,
             size,
             last_modified,
-            path,
-        ) {
+        )
+        .and_then(|()| write_os_str(&mut out, &quote(file_info.path().as_os_str(), style)))
+        .and_then(|()| writeln!(out))
+        {
             Ok(_) => {}
             Err(e) => {
                 if print_error_message {
@@ -190,7 +201,13 @@ impl Ls {
     }
 
     #[cfg(windows)]
-    fn print(&self, file_info: &WalkEntry, mut out: impl Write, print_error_message: bool) {
+    fn print(
+        &self,
+        file_info: &WalkEntry,
+        mut out: impl Write,
+        style: QuotingStyle,
+        print_error_message: bool,
+    ) {
         use std::os::windows::fs::MetadataExt;
 
         let metadata = file_info.metadata().unwrap();
@@ -221,11 +238,9 @@ impl Ls {
             let now_utc: DateTime<chrono::Utc> = system_time.into();
             now_utc.format("%b %e %H:%M")
         };
-        let path = file_info.path().to_string_lossy();
-
         match write!(
             out,
-            " {:<4} {:>6} {:<10} {:>3} {:<8} {:<8} {:>8} {} {}\n",
+            " {:<4} {:>6} {:<10} {:>3} {:<8} {:<8} {:>8} {} ",
             inode_number,
             number_of_blocks,
             permission,
@@ -234,8 +249,10 @@ impl Ls {
             group,
             size,
             last_modified,
-            path,
-        ) {
+        )
+        .and_then(|()| write_os_str(&mut out, &quote(file_info.path().as_os_str(), style)))
+        .and_then(|()| writeln!(out))
+        {
             Ok(_) => {}
             Err(e) => {
                 if print_error_message {
@@ -255,12 +272,33 @@ impl Ls {
 
 impl Matcher for Ls {
     fn matches(&self, file_info: &WalkEntry, matcher_io: &mut MatcherIO) -> bool {
+        if let Err(e) = file_info.metadata() {
+            if !matcher_io.ignores_error_for(file_info) {
+                writeln!(
+                    &mut stderr(),
+                    "Error getting metadata for {}: {}",
+                    file_info.path().to_string_lossy(),
+                    e
+                )
+                .unwrap();
+                matcher_io.set_exit_code(1);
+            }
+            return true;
+        }
+
         if let Some(file) = &self.output_file {
-            self.print(file_info, file, true);
+            let style =
+                QuotingStyle::for_output(self.output_file_is_terminal, QuotingStyle::Escape);
+            self.print(file_info, file, style, true);
         } else {
+            let style = QuotingStyle::for_output(
+                matcher_io.deps.output_is_terminal(),
+                QuotingStyle::Escape,
+            );
             self.print(
                 file_info,
                 &mut *matcher_io.deps.get_output().borrow_mut(),
+                style,
                 false,
             );
         }
@@ -9,20 +9,25 @@ mod delete;
 mod empty;
 mod entry;
 pub mod exec;
+pub mod expr;
 pub mod fs;
-mod glob;
+pub mod getdate;
+pub mod glob;
 mod group;
 mod lname;
 mod logical_matchers;
 mod ls;
 mod name;
+mod optimize;
 mod path;
 mod perm;
 mod printer;
 mod printf;
 mod prune;
 mod quit;
-mod regex;
+mod quoting;
+mod rates;
+pub mod regex;
 mod samefile;
 mod size;
 #[cfg(unix)]
@@ -32,24 +37,25 @@ mod type_matcher;
 mod user;
 
 use ::regex::Regex;
-use chrono::{DateTime, Datelike, NaiveDateTime, Utc};
 use fs::FileSystemMatcher;
+use getdate::DateSpec;
 use ls::Ls;
 use std::fs::{File, Metadata};
+use std::io;
 use std::path::Path;
+use std::sync::Arc;
 use std::time::SystemTime;
 use std::{error::Error, str::FromStr};
 
 use self::access::AccessMatcher;
 use self::delete::DeleteMatcher;
 use self::empty::EmptyMatcher;
-use self::exec::SingleExecMatcher;
+use self::exec::{MultiExecMatcher, SingleExecMatcher};
+use self::expr::{Expr, ExprError, ExprKind};
 use self::group::{GroupMatcher, NoGroupMatcher};
 use self::lname::LinkNameMatcher;
-use self::logical_matchers::{
-    AndMatcherBuilder, FalseMatcher, ListMatcherBuilder, NotMatcher, TrueMatcher,
-};
 use self::name::NameMatcher;
+use self::optimize::{Node, Optimizer};
 use self::path::PathMatcher;
 use self::perm::PermMatcher;
 use self::printer::{PrintDelimiter, Printer};
@@ -68,9 +74,15 @@ use self::time::{
 use self::type_matcher::{TypeMatcher, XtypeMatcher};
 use self::user::{NoUserMatcher, UserMatcher};
 
+use super::debug::{self, count_stat, DebugOption, Debugger};
 use super::{Config, Dependencies};
 
+#[cfg(unix)]
+pub(crate) use entry::{metadata_at, DirFd};
 pub use entry::{FileType, WalkEntry, WalkError};
+pub use logical_matchers::{
+    AndMatcher, FalseMatcher, ListMatcher, NotMatcher, OrMatcher, TrueMatcher,
+};
 
 /// Symlink following mode.
 #[derive(Clone, Copy, Debug, Eq, PartialEq)]
@@ -105,7 +117,7 @@ impl Follow {
             // Broken symlink, re-use cached metadata
             entry.metadata().cloned()
         } else {
-            self.metadata_at_depth(entry.path(), entry.depth())
+            self.counted_metadata_at_depth(entry.path(), entry.depth(), entry.debugger())
         }
     }
 
@@ -120,13 +132,23 @@ impl Follow {
         path: impl AsRef<Path>,
         depth: usize,
     ) -> Result<Metadata, WalkError> {
-        let path = path.as_ref();
+        self.counted_metadata_at_depth(path.as_ref(), depth, None)
+    }
 
+    /// Like [Self::metadata_at_depth()], counting the stat() calls in
+    /// `debugger` for -D stat.
+    pub(crate) fn counted_metadata_at_depth(
+        self,
+        path: &Path,
+        depth: usize,
+        debugger: Option<&Debugger>,
+    ) -> Result<Metadata, WalkError> {
+        count_stat(debugger, path);
         if self.follow_at_depth(depth) {
             match path.metadata().map_err(WalkError::from) {
                 Ok(meta) => return Ok(meta),
                 Err(e) if !e.is_not_found() => return Err(e),
-                _ => {}
+                _ => count_stat(debugger, path),
             }
         }
 
@@ -140,7 +162,10 @@ pub struct MatcherIO<'a> {
     should_skip_dir: bool,
     exit_code: i32,
     quit: bool,
+    ignore_readdir_race: bool,
     deps: &'a dyn Dependencies,
+    /// What -D keeps track of during this run, if anything.
+    debugger: Option<Arc<Debugger>>,
 }
 
 impl<'a> MatcherIO<'a> {
@@ -149,10 +174,44 @@ impl<'a> MatcherIO<'a> {
             should_skip_dir: false,
             exit_code: 0,
             quit: false,
+            ignore_readdir_race: false,
             deps,
+            debugger: None,
         }
     }
 
+    /// Write debugging output as `debugger` was told to (-D).
+    pub(crate) fn set_debugger(&mut self, debugger: Option<Arc<Debugger>>) {
+        self.debugger = debugger;
+    }
+
+    /// Check whether `option` was given to -D.
+    pub(crate) fn debug_enabled(&self, option: DebugOption) -> bool {
+        self.debugger.as_ref().is_some_and(|d| d.enabled(option))
+    }
+
+    /// Keep quiet about files that are deleted while they're being looked at
+    /// (-ignore_readdir_race).
+    pub fn set_ignore_readdir_race(&mut self, ignore_readdir_race: bool) {
+        self.ignore_readdir_race = ignore_readdir_race;
+    }
+
+    /// Check whether an error for this file should be kept quiet, because
+    /// the file was deleted after its directory was read and
+    /// -ignore_readdir_race is in effect.
+    #[must_use]
+    pub fn ignores_error_for(&self, file_info: &WalkEntry) -> bool {
+        self.ignore_readdir_race && file_info.vanished()
+    }
+
+    /// Check whether an error from operating on this file should be kept
+    /// quiet, because the file was deleted after its directory was read and
+    /// -ignore_readdir_race is in effect.
+    #[must_use]
+    pub fn ignores_io_error(&self, e: &io::Error, file_info: &WalkEntry) -> bool {
+        self.ignore_readdir_race && file_info.depth() > 0 && e.kind() == io::ErrorKind::NotFound
+    }
+
     pub fn mark_current_dir_to_be_skipped(&mut self) {
         self.should_skip_dir = true;
     }
@@ -212,13 +271,33 @@ pub trait Matcher: 'static {
         false
     }
 
+    /// Returns whether matches() only decides whether the file matches,
+    /// without affecting anything else, so that it can be reordered or
+    /// skipped when optimizing.  Matchers that don't have side-effects can
+    /// still be impure (e.g. -prune, -quit).
+    fn is_pure(&self) -> bool {
+        !self.has_side_effects()
+    }
+
+    /// Returns roughly how expensive matches() is, for deciding which tests to
+    /// run first when optimizing.
+    fn cost(&self) -> Cost {
+        Cost::Expensive
+    }
+
+    /// Returns the result of matches() if it's always the same, whatever the
+    /// file.
+    fn constant(&self) -> Option<bool> {
+        None
+    }
+
     /// Notification that find has finished processing a given directory.
-    fn finished_dir(&self, _finished_directory: &Path) {}
+    fn finished_dir(&self, _finished_directory: &Path, _matcher_io: &mut MatcherIO) {}
 
     /// Notification that find has finished processing all directories -
     /// allowing for any cleanup that isn't suitable for destructors (e.g.
     /// blocking calls, I/O etc.)
-    fn finished(&self) {}
+    fn finished(&self, _matcher_io: &mut MatcherIO) {}
 }
 
 impl Matcher for Box<dyn Matcher> {
@@ -234,15 +313,44 @@ impl Matcher for Box<dyn Matcher> {
         (**self).has_side_effects()
     }
 
-    fn finished_dir(&self, finished_directory: &Path) {
-        (**self).finished_dir(finished_directory);
+    fn is_pure(&self) -> bool {
+        (**self).is_pure()
+    }
+
+    fn cost(&self) -> Cost {
+        (**self).cost()
+    }
+
+    fn constant(&self) -> Option<bool> {
+        (**self).constant()
+    }
+
+    fn finished_dir(&self, finished_directory: &Path, matcher_io: &mut MatcherIO) {
+        (**self).finished_dir(finished_directory, matcher_io);
     }
 
-    fn finished(&self) {
-        (**self).finished();
+    fn finished(&self, matcher_io: &mut MatcherIO) {
+        (**self).finished(matcher_io);
     }
 }
 
+/// How expensive a matcher is to evaluate, from cheapest to most expensive.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Cost {
+    /// Doesn't look at the file at all (e.g. -true).
+    Free,
+    /// Only looks at the file's name or path (e.g. -name, -path).
+    Name,
+    /// Looks at the file's type, which usually comes with its directory entry
+    /// (e.g. -type).
+    Type,
+    /// Needs the file's metadata, i.e. a stat() call (e.g. -size, -mtime).
+    Stat,
+    /// Needs more than a stat() (e.g. -fstype, -samefile, -empty), or a lot of
+    /// computation (e.g. -regex).
+    Expensive,
+}
+
 pub enum ComparableValue {
     MoreThan(u64),
     EqualTo(u64),
@@ -273,22 +381,38 @@ impl ComparableValue {
 pub fn build_top_level_matcher(
     args: &[&str],
     config: &mut Config,
-) -> Result<Box<dyn Matcher>, Box<dyn Error>> {
-    let (_, top_level_matcher) = (build_matcher_tree(args, config, 0, false))?;
+) -> Result<Box<dyn Matcher>, ExprError> {
+    let expr = expr::parse(args)?;
+    let mut top_level_node = lower_node(&expr, config)?;
 
     // if the matcher doesn't have any side-effects, then we default to printing
-    if !top_level_matcher.has_side_effects() {
-        let mut new_and_matcher = AndMatcherBuilder::new();
-        new_and_matcher.new_and_condition(top_level_matcher);
-        new_and_matcher.new_and_condition(Printer::new(PrintDelimiter::Newline, None));
-        return Ok(new_and_matcher.build());
+    if !top_level_node.has_side_effects() {
+        let printer = Printer::new(PrintDelimiter::Newline, None).into_box();
+        let print = Node::Matcher(printer, "-print".to_string());
+        top_level_node = Node::And(vec![top_level_node, print]);
+    }
+
+    let debug = config.debug;
+    if debug.contains(DebugOption::Tree) {
+        debug::log(DebugOption::Tree, format_args!("parsed: {expr}"));
+    }
+    let mut optimized = Optimizer::new(config).optimize(top_level_node);
+    if debug.contains(DebugOption::Tree) {
+        debug::log(DebugOption::Tree, format_args!("optimized: {optimized}"));
     }
-    Ok(top_level_matcher)
+    if debug.contains(DebugOption::Rates) {
+        optimized = optimized.with_rates();
+    }
+    Ok(optimized.build())
 }
 
-/// Helper function for `build_matcher_tree`.
-fn are_more_expressions(args: &[&str], index: usize) -> bool {
-    (index < args.len() - 1) && args[index + 1] != ")"
+/// Builds the matcher for an expression, given as separate command-line
+/// arguments (e.g. `["-name", "*.rs", "-o", "-type", "d"]`).  Unlike
+/// [build_top_level_matcher()], no `-print` is added if the expression has no
+/// actions.  Options in the expression, like `-maxdepth`, are applied to
+/// `config`.
+pub fn build_matcher(args: &[&str], config: &mut Config) -> Result<Box<dyn Matcher>, ExprError> {
+    lower(&expr::parse(args)?, config)
 }
 
 fn convert_arg_to_number(
@@ -347,39 +471,6 @@ fn convert_arg_to_comparable_value_and_suffix(
     )))
 }
 
-/// This is a function that converts a specific string format into a timestamp.
-/// It allows converting a time string of
-/// "(week abbreviation) (date), (year) (time)" to a Unix timestamp.
-/// such as: "jan 01, 2025 00:00:01" -> 1735689601000
-/// When (time) is not provided, it will be automatically filled in as 00:00:00
-/// such as: "jan 01, 2025" = "jan 01, 2025 00:00:00" -> 1735689600000
-fn parse_date_str_to_timestamps(date_str: &str) -> Option<i64> {
-    let regex_pattern =
-        r"^(?P<month_day>\w{3} \d{2})?(?:, (?P<year>\d{4}))?(?: (?P<time>\d{2}:\d{2}:\d{2}))?$";
-    let re = Regex::new(regex_pattern);
-
-    if let Some(captures) = re.ok()?.captures(date_str) {
-        let now = Utc::now();
-        let month_day = captures
-            .get(1)
-            .map_or(format!("{} {}", now.format("%b"), now.format("%d")), |m| {
-                m.as_str().to_string()
-            });
-        // If no year input.
-        let year = captures
-            .get(2)
-            .map_or(now.year(), |m| m.as_str().parse().unwrap());
-        // If the user does not enter a specific time, it will be filled with 0
-        let time_str = captures.get(3).map_or("00:00:00", |m| m.as_str());
-        let date_time_str = format!("{month_day}, {year} {time_str}");
-        let datetime = NaiveDateTime::parse_from_str(&date_time_str, "%b %d, %Y %H:%M:%S").ok()?;
-        let utc_datetime = DateTime::<Utc>::from_naive_utc_and_offset(datetime, Utc);
-        Some(utc_datetime.timestamp_millis())
-    } else {
-        None
-    }
-}
-
 /// This function implements the function of matching substrings of
 /// X and Y from the -newerXY string.
 /// X and Y are constrained to a/B/c/m and t.
@@ -388,6 +479,7 @@ fn parse_date_str_to_timestamps(date_str: &str) -> Option<i64> {
 /// Additionally, there is support for the -anewer and -cnewer short arguments. as follows:
 /// 1. -anewer is equivalent to -neweram
 /// 2. -cnewer is equivalent to - newercm
+/// 3. -Bnewer is equivalent to -newerBm
 ///
 /// If -newer is used it will be resolved to -newermm.
 fn parse_str_to_newer_args(input: &str) -> Option<(String, String)> {
@@ -407,6 +499,10 @@ fn parse_str_to_newer_args(input: &str) -> Option<(String, String)> {
         return Some(("c".to_string(), "m".to_string()));
     }
 
+    if input == "-Bnewer" {
+        return Some(("B".to_string(), "m".to_string()));
+    }
+
     let re = Regex::new(r"-newer([aBcm])([aBcmt])").unwrap();
     if let Some(captures) = re.captures(input) {
         let x = captures.get(1)?.as_str().to_string();
@@ -424,532 +520,322 @@ fn get_or_create_file(path: &str) -> Result<File, Box<dyn Error>> {
     Ok(file)
 }
 
-/// The main "translate command-line args into a matcher" function. Will call
-/// itself recursively if it encounters an opening bracket. A successful return
-/// consists of a tuple containing the new index into the args array to use (if
-/// called recursively) and the resulting matcher.
-fn build_matcher_tree(
-    args: &[&str],
-    config: &mut Config,
-    arg_index: usize,
-    mut expecting_bracket: bool,
-) -> Result<(usize, Box<dyn Matcher>), Box<dyn Error>> {
-    let mut top_level_matcher = ListMatcherBuilder::new();
+/// Turns an expression into the matchers that evaluate it, applying options
+/// like `-maxdepth` to `config` along the way, and optimizing it as much as
+/// `config.optimization_level` says.
+pub fn lower(expr: &Expr, config: &mut Config) -> Result<Box<dyn Matcher>, ExprError> {
+    let node = lower_node(expr, config)?;
+    Ok(Optimizer::new(config).optimize(node).build())
+}
 
+/// Lowers an expression without building the final matcher, so that it can
+/// still be optimized.
+fn lower_node(expr: &Expr, config: &mut Config) -> Result<Node, ExprError> {
     let mut regex_type = regex::RegexType::default();
+    lower_expr(expr, config, &mut regex_type)
+}
 
-    // can't use getopts for a variety or reasons:
-    // order of arguments is important
-    // arguments can start with + as well as -
-    // multiple-character flags don't start with a double dash
-    let mut i = arg_index;
-    let mut invert_next_matcher = false;
-    while i < args.len() {
-        let possible_submatcher = match args[i] {
-            "-print" => Some(Printer::new(PrintDelimiter::Newline, None).into_box()),
-            "-print0" => Some(Printer::new(PrintDelimiter::Null, None).into_box()),
-            "-printf" => {
-                if i >= args.len() - 1 {
-                    return Err(From::from(format!("missing argument to {}", args[i])));
-                }
-                i += 1;
-                Some(Printf::new(args[i], None)?.into_box())
-            }
-            "-fprint" => {
-                if i >= args.len() - 1 {
-                    return Err(From::from(format!("missing argument to {}", args[i])));
-                }
-                i += 1;
-
-                let file = get_or_create_file(args[i])?;
-                Some(Printer::new(PrintDelimiter::Newline, Some(file)).into_box())
-            }
-            "-fprintf" => {
-                if i >= args.len() - 2 {
-                    return Err(From::from(format!("missing argument to {}", args[i])));
-                }
-
-                // Action: -fprintf file format
-                // Args + 1: output file path
-                // Args + 2: format string
-                i += 1;
-                let file = get_or_create_file(args[i])?;
-                i += 1;
-                Some(Printf::new(args[i], Some(file))?.into_box())
-            }
-            "-fprint0" => {
-                if i >= args.len() - 1 {
-                    return Err(From::from(format!("missing argument to {}", args[i])));
-                }
-                i += 1;
-
-                let file = get_or_create_file(args[i])?;
-                Some(Printer::new(PrintDelimiter::Null, Some(file)).into_box())
+/// Lowers an expression, in the order it was written, as options like
+/// -daystart and -regextype only affect what comes after them.
+fn lower_expr(
+    expr: &Expr,
+    config: &mut Config,
+    regex_type: &mut regex::RegexType,
+) -> Result<Node, ExprError> {
+    let mut lower_all = |exprs: &[Expr]| -> Result<Vec<Node>, ExprError> {
+        exprs
+            .iter()
+            .map(|expr| lower_expr(expr, config, regex_type))
+            .collect()
+    };
+
+    let node = match &expr.kind {
+        ExprKind::Primary { name, args } => {
+            let args: Vec<&str> = args.iter().map(String::as_str).collect();
+            let matcher = lower_primary(name, &args, config, regex_type)
+                .map_err(|e| ExprError::new(e.to_string(), expr.span))?;
+            match matcher.constant() {
+                Some(value) if matcher.is_pure() => Node::Const(value),
+                _ => Node::Matcher(matcher, expr.to_string()),
             }
-            "-ls" => Some(Ls::new(None).into_box()),
-            "-fls" => {
-                if i >= args.len() - 1 {
-                    return Err(From::from(format!("missing argument to {}", args[i])));
-                }
-                i += 1;
+        }
+        ExprKind::Not(expr) => Node::Not(Box::new(lower_expr(expr, config, regex_type)?)),
+        ExprKind::And(exprs) => Node::And(lower_all(exprs)?),
+        ExprKind::Or(exprs) => Node::Or(lower_all(exprs)?),
+        ExprKind::List(exprs) => Node::List(lower_all(exprs)?),
+        ExprKind::Group(expr) => lower_expr(expr, config, regex_type)?,
+    };
+    Ok(node)
+}
 
-                let file = get_or_create_file(args[i])?;
-                Some(Ls::new(Some(file)).into_box())
-            }
-            "-true" => Some(TrueMatcher.into_box()),
-            "-false" => Some(FalseMatcher.into_box()),
-            "-lname" | "-ilname" => {
-                if i >= args.len() - 1 {
-                    return Err(From::from(format!("missing argument to {}", args[i])));
-                }
-                i += 1;
-                Some(LinkNameMatcher::new(args[i], args[i - 1].starts_with("-i")).into_box())
-            }
-            "-name" | "-iname" => {
-                if i >= args.len() - 1 {
-                    return Err(From::from(format!("missing argument to {}", args[i])));
-                }
-                i += 1;
-                Some(NameMatcher::new(args[i], args[i - 1].starts_with("-i")).into_box())
-            }
-            "-path" | "-ipath" | "-wholename" | "-iwholename" => {
-                if i >= args.len() - 1 {
-                    return Err(From::from(format!("missing argument to {}", args[i])));
-                }
-                i += 1;
-                Some(PathMatcher::new(args[i], args[i - 1].starts_with("-i")).into_box())
-            }
-            "-readable" => Some(AccessMatcher::Readable.into_box()),
-            "-regextype" => {
-                if i >= args.len() - 1 {
-                    return Err(From::from(format!("missing argument to {}", args[i])));
-                }
-                i += 1;
-                regex_type = regex::RegexType::from_str(args[i])?;
-                Some(TrueMatcher.into_box())
-            }
-            "-regex" => {
-                if i >= args.len() - 1 {
-                    return Err(From::from(format!("missing argument to {}", args[i])));
-                }
-                i += 1;
-                Some(RegexMatcher::new(regex_type, args[i], false)?.into_box())
-            }
-            "-iregex" => {
-                if i >= args.len() - 1 {
-                    return Err(From::from(format!("missing argument to {}", args[i])));
-                }
-                i += 1;
-                Some(RegexMatcher::new(regex_type, args[i], true)?.into_box())
-            }
-            "-type" => {
-                if i >= args.len() - 1 {
-                    return Err(From::from(format!("missing argument to {}", args[i])));
-                }
-                i += 1;
-                Some(TypeMatcher::new(args[i])?.into_box())
-            }
-            "-xtype" => {
-                if i >= args.len() - 1 {
-                    return Err(From::from(format!("missing argument to {}", args[i])));
-                }
-                i += 1;
-                Some(XtypeMatcher::new(args[i])?.into_box())
-            }
-            "-fstype" => {
-                if i >= args.len() - 1 {
-                    return Err(From::from(format!("missing argument to {}", args[i])));
-                }
-                i += 1;
-                Some(FileSystemMatcher::new(args[i].to_string()).into_box())
-            }
-            "-delete" => {
-                // -delete implicitly requires -depth
-                config.depth_first = true;
-                Some(DeleteMatcher::new().into_box())
-            }
-            "-newer" => {
-                if i >= args.len() - 1 {
-                    return Err(From::from(format!("missing argument to {}", args[i])));
-                }
-                i += 1;
-                Some(NewerMatcher::new(args[i], config.follow)?.into_box())
-            }
-            "-mtime" | "-atime" | "-ctime" => {
-                if i >= args.len() - 1 {
-                    return Err(From::from(format!("missing argument to {}", args[i])));
-                }
-                let file_time_type = match args[i] {
-                    "-atime" => FileTimeType::Accessed,
-                    "-ctime" => FileTimeType::Changed,
-                    "-mtime" => FileTimeType::Modified,
-                    // This shouldn't be possible. We've already checked the value
-                    // is one of those three values.
-                    _ => unreachable!("Encountered unexpected value {}", args[i]),
-                };
-                let days = convert_arg_to_comparable_value(args[i], args[i + 1])?;
-                i += 1;
-                Some(FileTimeMatcher::new(file_time_type, days, config.today_start).into_box())
-            }
-            "-amin" | "-cmin" | "-mmin" => {
-                if i >= args.len() - 1 {
-                    return Err(From::from(format!("missing argument to {}", args[i])));
+/// Makes the matcher for a single test, action or option, given the arguments
+/// the parser found for it.
+fn lower_primary(
+    name: &str,
+    args: &[&str],
+    config: &mut Config,
+    regex_type: &mut regex::RegexType,
+) -> Result<Box<dyn Matcher>, Box<dyn Error>> {
+    let matcher = match name {
+        "-print" => Printer::new(PrintDelimiter::Newline, None).into_box(),
+        "-print0" => Printer::new(PrintDelimiter::Null, None).into_box(),
+        "-printf" => Printf::new(args[0], None)?.into_box(),
+        "-fprint" => {
+            let file = get_or_create_file(args[0])?;
+            Printer::new(PrintDelimiter::Newline, Some(file)).into_box()
+        }
+        "-fprintf" => {
+            // Action: -fprintf file format
+            let file = get_or_create_file(args[0])?;
+            Printf::new(args[1], Some(file))?.into_box()
+        }
+        "-fprint0" => {
+            let file = get_or_create_file(args[0])?;
+            Printer::new(PrintDelimiter::Null, Some(file)).into_box()
+        }
+        "-ls" => Ls::new(None).into_box(),
+        "-fls" => {
+            let file = get_or_create_file(args[0])?;
+            Ls::new(Some(file)).into_box()
+        }
+        "-true" => TrueMatcher.into_box(),
+        "-false" => FalseMatcher.into_box(),
+        "-lname" | "-ilname" => LinkNameMatcher::new(args[0], name.starts_with("-i")).into_box(),
+        "-name" | "-iname" => NameMatcher::new(args[0], name.starts_with("-i")).into_box(),
+        "-path" | "-ipath" | "-wholename" | "-iwholename" => {
+            PathMatcher::new(args[0], name.starts_with("-i")).into_box()
+        }
+        "-readable" => AccessMatcher::Readable.into_box(),
+        "-regextype" => {
+            *regex_type = regex::RegexType::from_str(args[0])?;
+            TrueMatcher.into_box()
+        }
+        "-regex" => RegexMatcher::new(*regex_type, args[0], false)?.into_box(),
+        "-iregex" => RegexMatcher::new(*regex_type, args[0], true)?.into_box(),
+        "-type" => TypeMatcher::new(args[0])?.into_box(),
+        "-xtype" => XtypeMatcher::new(args[0])?.into_box(),
+        "-fstype" => FileSystemMatcher::new(args[0].to_string()).into_box(),
+        "-delete" => {
+            // -delete implicitly requires -depth
+            config.depth_first = true;
+            DeleteMatcher::new().into_box()
+        }
+        "-newer" => NewerMatcher::new(args[0], config.follow)?.into_box(),
+        "-mtime" | "-atime" | "-ctime" | "-Btime" => {
+            let file_time_type = match name {
+                "-atime" => FileTimeType::Accessed,
+                "-Btime" => FileTimeType::Birthed,
+                "-ctime" => FileTimeType::Changed,
+                "-mtime" => FileTimeType::Modified,
+                // This shouldn't be possible. We've already checked the value
+                // is one of those three values.
+                _ => unreachable!("Encountered unexpected value {name}"),
+            };
+            let days = convert_arg_to_comparable_value(name, args[0])?;
+            FileTimeMatcher::new(file_time_type, days, config.today_start).into_box()
+        }
+        "-amin" | "-cmin" | "-mmin" | "-Bmin" => {
+            let file_time_type = match name {
+                "-amin" => FileTimeType::Accessed,
+                "-Bmin" => FileTimeType::Birthed,
+                "-cmin" => FileTimeType::Changed,
+                "-mmin" => FileTimeType::Modified,
+                _ => unreachable!("Encountered unexpected value {name}"),
+            };
+            let minutes = convert_arg_to_comparable_value(name, args[0])?;
+            FileAgeRangeMatcher::new(file_time_type, minutes, config.today_start).into_box()
+        }
+        "-size" => {
+            let (size, unit) = convert_arg_to_comparable_value_and_suffix(name, args[0])?;
+            SizeMatcher::new(size, &unit)?.into_box()
+        }
+        "-empty" => EmptyMatcher::new().into_box(),
+        "-exec" | "-execdir" => {
+            let executable = args[0];
+            let in_parent_dir = name == "-execdir";
+            match args[1..] {
+                // The trailing {} is implicit for a MultiExecMatcher
+                [ref exec_args @ .., "{}", "+"] => {
+                    MultiExecMatcher::new(executable, exec_args, in_parent_dir)?.into_box()
                 }
-                let file_time_type = match args[i] {
-                    "-amin" => FileTimeType::Accessed,
-                    "-cmin" => FileTimeType::Changed,
-                    "-mmin" => FileTimeType::Modified,
-                    _ => unreachable!("Encountered unexpected value {}", args[i]),
-                };
-                let minutes = convert_arg_to_comparable_value(args[i], args[i + 1])?;
-                i += 1;
-                Some(
-                    FileAgeRangeMatcher::new(file_time_type, minutes, config.today_start)
-                        .into_box(),
-                )
-            }
-            "-size" => {
-                if i >= args.len() - 1 {
-                    return Err(From::from(format!("missing argument to {}", args[i])));
+                [ref exec_args @ .., _] => {
+                    SingleExecMatcher::new(executable, exec_args, in_parent_dir)?.into_box()
                 }
-                let (size, unit) =
-                    convert_arg_to_comparable_value_and_suffix(args[i], args[i + 1])?;
-                i += 1;
-                Some(SizeMatcher::new(size, &unit)?.into_box())
+                [] => unreachable!("the parser includes the terminator"),
             }
-            "-empty" => Some(EmptyMatcher::new().into_box()),
-            "-exec" | "-execdir" => {
-                let mut arg_index = i + 1;
-                while arg_index < args.len() && args[arg_index] != ";" {
-                    if args[arg_index - 1] == "{}" && args[arg_index] == "+" {
-                        // MultiExecMatcher isn't written yet
-                        return Err(From::from(format!(
-                            "{} [args...] + isn't supported yet. \
-                             Only {} [args...] ;",
-                            args[i], args[i]
-                        )));
-                    }
-                    arg_index += 1;
-                }
-                if arg_index < i + 2 || arg_index == args.len() {
-                    // at the minimum we need the executable and the ';'
-                    return Err(From::from(format!("missing argument to {}", args[i])));
-                }
-                let expression = args[i];
-                let executable = args[i + 1];
-                let exec_args = &args[i + 2..arg_index];
-                i = arg_index;
-                Some(
-                    SingleExecMatcher::new(executable, exec_args, expression == "-execdir")?
-                        .into_box(),
-                )
+        }
+        "-ok" | "-okdir" => {
+            let exec_args = &args[1..args.len() - 1];
+            config.prompts_on_stdin = true;
+            SingleExecMatcher::new_interactive(args[0], exec_args, name == "-okdir")?.into_box()
+        }
+        #[cfg(unix)]
+        "-inum" => {
+            let inum = convert_arg_to_comparable_value(name, args[0])?;
+            InodeMatcher::new(inum).into_box()
+        }
+        #[cfg(not(unix))]
+        "-inum" => {
+            return Err(From::from(
+                "Inode numbers are not available on this platform",
+            ));
+        }
+        #[cfg(unix)]
+        "-links" => {
+            let inum = convert_arg_to_comparable_value(name, args[0])?;
+            LinksMatcher::new(inum).into_box()
+        }
+        #[cfg(not(unix))]
+        "-links" => {
+            return Err(From::from("Link counts are not available on this platform"));
+        }
+        "-samefile" => {
+            let path = args[0];
+            SameFileMatcher::new(path, config.follow)
+                .map_err(|e| format!("{path}: {e}"))?
+                .into_box()
+        }
+        "-user" => {
+            let user = args[0];
+            if user.is_empty() {
+                return Err(From::from("The argument to -user should not be empty"));
             }
-            #[cfg(unix)]
-            "-inum" => {
-                if i >= args.len() - 1 {
-                    return Err(From::from(format!("missing argument to {}", args[i])));
-                }
-                let inum = convert_arg_to_comparable_value(args[i], args[i + 1])?;
-                i += 1;
-                Some(InodeMatcher::new(inum).into_box())
+            let matcher = UserMatcher::from_user_name(user);
+            if matcher.uid().is_none() {
+                return Err(From::from(format!(
+                    "{user} is not the name of a known user"
+                )));
             }
-            #[cfg(not(unix))]
-            "-inum" => {
+            matcher.into_box()
+        }
+        "-nouser" => NoUserMatcher {}.into_box(),
+        "-uid" => {
+            // check if the argument is a number
+            let Ok(uid) = args[0].parse::<u32>() else {
+                return Err(From::from(format!("{} is not a number", args[0])));
+            };
+            UserMatcher::from_uid(uid).into_box()
+        }
+        "-group" => {
+            let group = args[0];
+            if group.is_empty() {
                 return Err(From::from(
-                    "Inode numbers are not available on this platform",
+                    "Argument to -group is empty, but should be a group name",
                 ));
             }
-            #[cfg(unix)]
-            "-links" => {
-                if i >= args.len() - 1 {
-                    return Err(From::from(format!("missing argument to {}", args[i])));
-                }
-                let inum = convert_arg_to_comparable_value(args[i], args[i + 1])?;
-                i += 1;
-                Some(LinksMatcher::new(inum).into_box())
-            }
-            #[cfg(not(unix))]
-            "-links" => {
-                return Err(From::from("Link counts are not available on this platform"));
-            }
-            "-samefile" => {
-                if i >= args.len() - 1 {
-                    return Err(From::from(format!("missing argument to {}", args[i])));
-                }
-                i += 1;
-                let path = args[i];
-                let matcher = SameFileMatcher::new(path, config.follow)
-                    .map_err(|e| format!("{path}: {e}"))?;
-                Some(matcher.into_box())
-            }
-            "-user" => {
-                if i >= args.len() - 1 {
-                    return Err(From::from(format!("missing argument to {}", args[i])));
-                }
-
-                let user = args[i + 1];
-
-                if user.is_empty() {
-                    return Err(From::from("The argument to -user should not be empty"));
-                }
-
-                i += 1;
-                let matcher = UserMatcher::from_user_name(user);
-                match matcher.uid() {
-                    Some(_) => Some(matcher.into_box()),
-                    None => {
-                        return Err(From::from(format!(
-                            "{} is not the name of a known user",
-                            user
-                        )))
-                    }
-                }
-            }
-            "-nouser" => Some(NoUserMatcher {}.into_box()),
-            "-uid" => {
-                if i >= args.len() - 1 {
-                    return Err(From::from(format!("missing argument to {}", args[i])));
-                }
-                // check if the argument is a number
-                let uid = args[i + 1].parse::<u32>();
-                if uid.is_err() {
-                    return Err(From::from(format!("{} is not a number", args[i + 1])));
-                }
-                i += 1;
-                Some(UserMatcher::from_uid(uid.unwrap()).into_box())
-            }
-            "-group" => {
-                if i >= args.len() - 1 {
-                    return Err(From::from(format!("missing argument to {}", args[i])));
-                }
-
-                let group = args[i + 1];
-
-                if group.is_empty() {
-                    return Err(From::from(
-                        "Argument to -group is empty, but should be a group name",
-                    ));
-                }
-
-                i += 1;
-                let matcher = GroupMatcher::from_group_name(group);
-                match matcher.gid() {
-                    Some(_) => Some(matcher.into_box()),
-                    None => {
-                        return Err(From::from(format!(
-                            "{} is not the name of an existing group",
-                            group
-                        )))
-                    }
-                }
-            }
-            "-nogroup" => Some(NoGroupMatcher {}.into_box()),
-            "-gid" => {
-                if i >= args.len() - 1 {
-                    return Err(From::from(format!("missing argument to {}", args[i])));
-                }
-                // check if the argument is a number
-                let gid = args[i + 1].parse::<u32>();
-                if gid.is_err() {
-                    return Err(From::from(format!(
-                        "find: invalid argument `{}' to `-gid'",
-                        args[i + 1]
-                    )));
-                }
-                i += 1;
-                Some(GroupMatcher::from_gid(gid.unwrap()).into_box())
-            }
-            "-executable" => Some(AccessMatcher::Executable.into_box()),
-            "-perm" => {
-                if i >= args.len() - 1 {
-                    return Err(From::from(format!("missing argument to {}", args[i])));
-                }
-                i += 1;
-                Some(PermMatcher::new(args[i])?.into_box())
-            }
-            "-prune" => Some(PruneMatcher::new().into_box()),
-            "-quit" => Some(QuitMatcher.into_box()),
-            "-writable" => Some(AccessMatcher::Writable.into_box()),
-            "-not" | "!" => {
-                if !are_more_expressions(args, i) {
-                    return Err(From::from(format!(
-                        "expected an expression after {}",
-                        args[i]
-                    )));
-                }
-                invert_next_matcher = !invert_next_matcher;
-                None
-            }
-            "-and" | "-a" => {
-                if !are_more_expressions(args, i) {
-                    return Err(From::from(format!(
-                        "expected an expression after {}",
-                        args[i]
-                    )));
-                }
-                top_level_matcher.check_new_and_condition()?;
-                None
+            let matcher = GroupMatcher::from_group_name(group);
+            if matcher.gid().is_none() {
+                return Err(From::from(format!(
+                    "{group} is not the name of an existing group"
+                )));
             }
-            "-or" | "-o" => {
-                if !are_more_expressions(args, i) {
-                    return Err(From::from(format!(
-                        "expected an expression after {}",
-                        args[i]
-                    )));
-                }
-                top_level_matcher.new_or_condition(args[i])?;
-                None
-            }
-            "," => {
-                if !are_more_expressions(args, i) {
+            matcher.into_box()
+        }
+        "-nogroup" => NoGroupMatcher {}.into_box(),
+        "-gid" => {
+            // check if the argument is a number
+            let Ok(gid) = args[0].parse::<u32>() else {
+                return Err(From::from(format!(
+                    "find: invalid argument `{}' to `-gid'",
+                    args[0]
+                )));
+            };
+            GroupMatcher::from_gid(gid).into_box()
+        }
+        "-executable" => AccessMatcher::Executable.into_box(),
+        "-perm" => PermMatcher::new(args[0])?.into_box(),
+        "-prune" => PruneMatcher::new().into_box(),
+        "-quit" => QuitMatcher.into_box(),
+        "-writable" => AccessMatcher::Writable.into_box(),
+        "-follow" => {
+            // This option affects multiple matchers.
+            // 1. It will use noleaf by default. (but -noleaf No change of behavior)
+            // Unless -L or -H is specified:
+            // 2. changes the behaviour of the -newer predicate.
+            // 3. consideration applies to -newerXY, -anewer and -cnewer
+            // 4. -type predicate will always match against the type of
+            //    the file that a symbolic link points to rather than the link itself.
+            //
+            // 5. causes the -lname and -ilname predicates always to return false.
+            //    (unless they happen to match broken symbolic links)
+            config.follow = Follow::Always;
+            config.no_leaf_dirs = true;
+            TrueMatcher.into_box()
+        }
+        "-daystart" => {
+            config.today_start = true;
+            TrueMatcher.into_box()
+        }
+        "-ignore_readdir_race" => {
+            config.ignore_readdir_race = true;
+            TrueMatcher.into_box()
+        }
+        "-noignore_readdir_race" => {
+            config.ignore_readdir_race = false;
+            TrueMatcher.into_box()
+        }
+        "-noleaf" => {
+            // No change of behavior
+            config.no_leaf_dirs = true;
+            TrueMatcher.into_box()
+        }
+        "-d" | "-depth" => {
+            // TODO add warning if it appears after actual testing criterion
+            config.depth_first = true;
+            TrueMatcher.into_box()
+        }
+        "-mount" | "-xdev" => {
+            // TODO add warning if it appears after actual testing criterion
+            config.same_file_system = true;
+            TrueMatcher.into_box()
+        }
+        "-sorted" => {
+            // TODO add warning if it appears after actual testing criterion
+            config.sorted_output = true;
+            TrueMatcher.into_box()
+        }
+        "-maxdepth" => {
+            config.max_depth = convert_arg_to_number(name, args[0])?;
+            TrueMatcher.into_box()
+        }
+        "-mindepth" => {
+            config.min_depth = convert_arg_to_number(name, args[0])?;
+            TrueMatcher.into_box()
+        }
+        "-files0-from" => {
+            config.files0_from = Some(args[0].to_string());
+            TrueMatcher.into_box()
+        }
+        "-help" | "--help" => {
+            config.help_requested = true;
+            TrueMatcher.into_box()
+        }
+        "-version" | "--version" => {
+            config.version_requested = true;
+            TrueMatcher.into_box()
+        }
+        _ => {
+            let Some((x_option, y_option)) = parse_str_to_newer_args(name) else {
+                return Err(From::from(format!("Unrecognized flag: '{name}'")));
+            };
+            if y_option == "t" {
+                let newer_time_type = NewerOptionType::from_str(x_option.as_str());
+                // Relative dates are relative to the time find started
+                let Some(time) =
+                    DateSpec::parse(args[0]).and_then(|date| date.resolve(config.start_time))
+                else {
                     return Err(From::from(format!(
-                        "expected an expression after {}",
-                        args[i]
+                        "find: I cannot figure out how to interpret ‘{}’ as a date or time",
+                        args[0]
                     )));
-                }
-                top_level_matcher.new_list_condition()?;
-                None
-            }
-            "(" => {
-                let (new_arg_index, sub_matcher) = build_matcher_tree(args, config, i + 1, true)?;
-                i = new_arg_index;
-                Some(sub_matcher)
-            }
-            ")" => {
-                if !expecting_bracket {
-                    return Err(From::from("you have too many ')'"));
-                }
-
-                let bracket = args[i - 1];
-                if bracket == "(" {
-                    return Err(From::from(
-                        "invalid expression; empty parentheses are not allowed.",
-                    ));
-                }
-
-                return Ok((i, top_level_matcher.build()));
-            }
-            "-follow" => {
-                // This option affects multiple matchers.
-                // 1. It will use noleaf by default. (but -noleaf No change of behavior)
-                // Unless -L or -H is specified:
-                // 2. changes the behaviour of the -newer predicate.
-                // 3. consideration applies to -newerXY, -anewer and -cnewer
-                // 4. -type predicate will always match against the type of
-                //    the file that a symbolic link points to rather than the link itself.
-                //
-                // 5. causes the -lname and -ilname predicates always to return false.
-                //    (unless they happen to match broken symbolic links)
-                config.follow = Follow::Always;
-                config.no_leaf_dirs = true;
-                Some(TrueMatcher.into_box())
-            }
-            "-daystart" => {
-                config.today_start = true;
-                Some(TrueMatcher.into_box())
-            }
-            "-noleaf" => {
-                // No change of behavior
-                config.no_leaf_dirs = true;
-                Some(TrueMatcher.into_box())
-            }
-            "-d" | "-depth" => {
-                // TODO add warning if it appears after actual testing criterion
-                config.depth_first = true;
-                Some(TrueMatcher.into_box())
-            }
-            "-mount" | "-xdev" => {
-                // TODO add warning if it appears after actual testing criterion
-                config.same_file_system = true;
-                Some(TrueMatcher.into_box())
-            }
-            "-sorted" => {
-                // TODO add warning if it appears after actual testing criterion
-                config.sorted_output = true;
-                Some(TrueMatcher.into_box())
-            }
-            "-maxdepth" => {
-                if i >= args.len() - 1 {
-                    return Err(From::from(format!("missing argument to {}", args[i])));
-                }
-                config.max_depth = convert_arg_to_number(args[i], args[i + 1])?;
-                i += 1;
-                Some(TrueMatcher.into_box())
-            }
-            "-mindepth" => {
-                if i >= args.len() - 1 {
-                    return Err(From::from(format!("missing argument to {}", args[i])));
-                }
-                config.min_depth = convert_arg_to_number(args[i], args[i + 1])?;
-                i += 1;
-                Some(TrueMatcher.into_box())
-            }
-            "-help" | "--help" => {
-                config.help_requested = true;
-                None
-            }
-            "-version" | "--version" => {
-                config.version_requested = true;
-                None
-            }
-
-            _ => {
-                match parse_str_to_newer_args(args[i]) {
-                    Some((x_option, y_option)) => {
-                        if i >= args.len() - 1 {
-                            return Err(From::from(format!("missing argument to {}", args[i])));
-                        }
-                        #[cfg(target_os = "linux")]
-                        if x_option == "B" {
-                            return Err(From::from("find: This system does not provide a way to find the birth time of a file."));
-                        }
-                        if y_option == "t" {
-                            let time = args[i + 1];
-                            let newer_time_type = NewerOptionType::from_str(x_option.as_str());
-                            // Convert args to unix timestamps. (expressed in numeric types)
-                            let comparable_time = match parse_date_str_to_timestamps(time) {
-                                Some(timestamp) => timestamp,
-                                None => {
-                                    return Err(From::from(format!(
-                                        "find: I cannot figure out how to interpret ‘{}’ as a date or time",
-                                        args[i + 1]
-                                    )))
-                                }
-                            };
-                            i += 1;
-                            Some(NewerTimeMatcher::new(newer_time_type, comparable_time).into_box())
-                        } else {
-                            let file_path = args[i + 1];
-                            i += 1;
-                            Some(NewerOptionMatcher::new(x_option, y_option, file_path)?.into_box())
-                        }
-                    }
-                    None => return Err(From::from(format!("Unrecognized flag: '{}'", args[i]))),
-                }
-            }
-        };
-        i += 1;
-        if config.help_requested || config.version_requested {
-            // Ignore anything, even invalid expressions, after -help/-version
-            expecting_bracket = false;
-            break;
-        }
-        if let Some(submatcher) = possible_submatcher {
-            if invert_next_matcher {
-                top_level_matcher.new_and_condition(NotMatcher::new(submatcher));
-                invert_next_matcher = false;
+                };
+                NewerTimeMatcher::new(newer_time_type, time).into_box()
             } else {
-                top_level_matcher.new_and_condition(submatcher);
+                NewerOptionMatcher::new(x_option, y_option, args[0])?.into_box()
             }
         }
-    }
-    if expecting_bracket {
-        return Err(From::from(
-            "invalid expression; I was expecting to find a ')' somewhere but \
-             did not see one.",
-        ));
-    }
-    Ok((i, top_level_matcher.build()))
+    };
+    Ok(matcher)
 }
 
 #[cfg(test)]
@@ -1033,6 +919,35 @@ mod tests {
         }
     }
 
+    #[test]
+    fn build_matcher_errors_have_spans() {
+        let mut config = Config::default();
+        let e = build_matcher(&["-name", "a", "-type", "q"], &mut config)
+            .err()
+            .expect("lowering should fail");
+        assert_eq!(e.span(), expr::Span::new(2, 4));
+    }
+
+    #[test]
+    fn lower_applies_options_in_order() {
+        let expr = expr::parse(&["-mindepth", "1", "(", "-regextype", "posix-basic", ")"]).unwrap();
+        let mut config = Config::default();
+        lower(&expr, &mut config).unwrap();
+        assert_eq!(config.min_depth, 1);
+
+        // -regextype applies to everything after it, even outside its
+        // brackets.  "+" is only special in the default (emacs) syntax.
+        let entry = get_dir_entry_for("./test_data/simple", "abbbc");
+        let deps = FakeDependencies::new();
+        let expr = expr::parse(&["-regex", ".*/ab+c"]).unwrap();
+        let matcher = lower(&expr, &mut config).unwrap();
+        assert!(matcher.matches(&entry, &mut deps.new_matcher_io()));
+
+        let args = ["(", "-regextype", "posix-basic", ")", "-regex", ".*/ab+c"];
+        let matcher = lower(&expr::parse(&args).unwrap(), &mut config).unwrap();
+        assert!(!matcher.matches(&entry, &mut deps.new_matcher_io()));
+    }
+
     #[test]
     fn build_top_level_matcher_not_needs_expression() {
         for arg in &["-not", "!"] {
@@ -1535,6 +1450,51 @@ mod tests {
             .expect("only {} + should be considered a multi-exec");
     }
 
+    #[test]
+    fn build_top_level_multi_exec() {
+        let mut config = Config::default();
+        for arg in &["-exec", "-execdir"] {
+            build_top_level_matcher(&[arg, "foo", "-o", "{}", "+"], &mut config)
+                .expect("{} + should be parsed as a multi-exec");
+            build_top_level_matcher(&[arg, "foo", "{}", "+", "-print"], &mut config)
+                .expect("expressions may follow {} +");
+        }
+
+        if let Err(e) = build_top_level_matcher(&["-exec", "{}", "+"], &mut config) {
+            assert!(e.to_string().contains("missing argument"));
+        } else {
+            panic!("parsing argument list with multi-exec and no executable should fail");
+        }
+
+        if let Err(e) = build_top_level_matcher(&["-exec", "foo", "x{}", "{}", "+"], &mut config) {
+            assert!(e.to_string().contains("must appear by itself"));
+        } else {
+            panic!("multi-exec with embedded {{}} should fail");
+        }
+    }
+
+    #[test]
+    fn build_top_level_ok() {
+        let mut config = Config::default();
+        for arg in &["-ok", "-okdir"] {
+            let matcher = build_top_level_matcher(&[arg, "foo", "{}", ";"], &mut config)
+                .expect("-ok should be parsed");
+            assert!(matcher.has_side_effects());
+
+            if let Err(e) = build_top_level_matcher(&[arg, "foo", "{}", "+"], &mut config) {
+                assert!(e.to_string().contains("missing argument"));
+            } else {
+                panic!("{arg} doesn't support {{}} +");
+            }
+
+            if let Err(e) = build_top_level_matcher(&[arg, ";"], &mut config) {
+                assert!(e.to_string().contains("missing argument"));
+            } else {
+                panic!("parsing argument list with {arg} and no executable should fail");
+            }
+        }
+    }
+
     #[test]
     #[cfg(unix)]
     fn build_top_level_matcher_perm() {
@@ -1603,28 +1563,6 @@ mod tests {
         }
     }
 
-    #[test]
-    fn parse_date_str_to_timestamps_test() {
-        let full_date_timestamps = parse_date_str_to_timestamps("jan 01, 2025 00:00:01").unwrap();
-        assert!(full_date_timestamps.to_string().contains("1735689601000"));
-
-        let not_include_time_date_timestamps =
-            parse_date_str_to_timestamps("jan 01, 2025").unwrap();
-        assert!(not_include_time_date_timestamps
-            .to_string()
-            .contains("1735689600000"));
-
-        // pass if return current time.
-        let none_date_timestamps = parse_date_str_to_timestamps("");
-        let now_but_zero_hour_min_sec = Utc::now()
-            .date_naive()
-            .and_hms_opt(0, 0, 0)
-            .unwrap()
-            .and_utc()
-            .timestamp_millis();
-        assert_eq!(none_date_timestamps, Some(now_but_zero_hour_min_sec));
-    }
-
     #[test]
     fn parse_str_to_newer_args_test() {
         // test for error case
@@ -1680,6 +1618,64 @@ mod tests {
         assert!(config.version_requested);
     }
 
+    #[test]
+    fn build_top_level_matcher_ignore_readdir_race() {
+        let mut config = Config::default();
+        build_top_level_matcher(&["-ignore_readdir_race", "-print"], &mut config).unwrap();
+        assert!(config.ignore_readdir_race);
+
+        build_top_level_matcher(
+            &["-ignore_readdir_race", "-noignore_readdir_race"],
+            &mut config,
+        )
+        .unwrap();
+        assert!(!config.ignore_readdir_race);
+    }
+
+    #[test]
+    fn ignore_readdir_race_vanished_file() {
+        let temp_dir = tempfile::Builder::new()
+            .prefix("ignore_readdir_race")
+            .tempdir()
+            .unwrap();
+        let path = temp_dir.path().join("gone");
+        let deps = FakeDependencies::new();
+
+        let entry = WalkEntry::with_file_type(&path, 1, Follow::Never, FileType::Regular);
+        let mut matcher_io = deps.new_matcher_io();
+        matcher_io.set_ignore_readdir_race(true);
+        // Nothing has gone wrong yet
+        assert!(!matcher_io.ignores_error_for(&entry));
+        assert!(entry.metadata().is_err());
+        assert!(matcher_io.ignores_error_for(&entry));
+        let not_found = io::Error::from(io::ErrorKind::NotFound);
+        let denied = io::Error::from(io::ErrorKind::PermissionDenied);
+        assert!(matcher_io.ignores_io_error(&not_found, &entry));
+        assert!(!matcher_io.ignores_io_error(&denied, &entry));
+
+        let matcher = SizeMatcher::new(ComparableValue::MoreThan(0), "c").unwrap();
+        assert!(!matcher.matches(&entry, &mut matcher_io));
+        assert!(Ls::new(None).matches(&entry, &mut matcher_io));
+        assert!(DeleteMatcher::new().matches(&entry, &mut matcher_io));
+        assert_eq!(matcher_io.exit_code(), 0);
+        assert_eq!(deps.get_output_as_string(), "");
+
+        let mut matcher_io = deps.new_matcher_io();
+        assert!(!matcher_io.ignores_error_for(&entry));
+        assert!(!DeleteMatcher::new().matches(&entry, &mut matcher_io));
+        assert_eq!(matcher_io.exit_code(), 1);
+
+        // Starting points are always reported
+        let entry = WalkEntry::with_file_type(&path, 0, Follow::Never, FileType::Regular);
+        let mut matcher_io = deps.new_matcher_io();
+        matcher_io.set_ignore_readdir_race(true);
+        assert!(entry.metadata().is_err());
+        assert!(!matcher_io.ignores_error_for(&entry));
+        assert!(!matcher_io.ignores_io_error(&not_found, &entry));
+        assert!(!DeleteMatcher::new().matches(&entry, &mut matcher_io));
+        assert_eq!(matcher_io.exit_code(), 1);
+    }
+
     #[test]
     fn get_or_create_file_test() {
         use std::fs;
@@ -5,7 +5,7 @@
 // https://opensource.org/licenses/MIT.
 
 use super::glob::Pattern;
-use super::{Matcher, MatcherIO, WalkEntry};
+use super::{Cost, Matcher, MatcherIO, WalkEntry};
 
 /// This matcher makes a comparison of the name against a shell wildcard
 /// pattern. See `glob::Pattern` for details on the exact syntax.
@@ -25,6 +25,10 @@ impl Matcher for NameMatcher {
         let name = file_info.file_name().to_string_lossy();
         self.pattern.matches(&name)
     }
+
+    fn cost(&self) -> Cost {
+        Cost::Name
+    }
 }
 
 #[cfg(test)]
@@ -0,0 +1,413 @@
+// This file is part of the uutils findutils package.
+//
+// For the full copyright and license information, please view the LICENSE
+// file that was distributed with this source code.
+
+//! Rewrites expressions to be cheaper to evaluate (-O1, -O2, -O3), using
+//! what each matcher says about its [Cost] and whether it's pure:
+//!
+//! * -O1 folds constants (`-true`, `-false` and options like `-depth`), and
+//!   moves tests that only look at file names before other tests.
+//! * -O2 also removes pure tests whose results can't matter, and moves tests
+//!   of the file type before tests that need its metadata.
+//! * -O3 orders all adjacent pure tests by cost.
+//!
+//! Side effects always happen in the same order, and only ever for the same
+//! files, as without optimization.
+
+use std::fmt::{self, Display, Formatter};
+
+use super::logical_matchers::{
+    AndMatcherBuilder, FalseMatcher, ListMatcherBuilder, NotMatcher, OrMatcherBuilder, TrueMatcher,
+};
+use super::rates::RatesMatcher;
+use super::{Cost, Matcher};
+use crate::find::debug::{self, DebugOption};
+use crate::find::Config;
+
+/// An expression with its primaries already turned into matchers, but with
+/// its structure still open to rewriting.
+pub enum Node {
+    /// A matcher, with the arguments it was made from (e.g. `-name '*.rs'`).
+    Matcher(Box<dyn Matcher>, String),
+    /// -true, -false, or an option.
+    Const(bool),
+    Not(Box<Node>),
+    And(Vec<Node>),
+    Or(Vec<Node>),
+    List(Vec<Node>),
+}
+
+impl Node {
+    fn is_pure(&self) -> bool {
+        match self {
+            Self::Matcher(matcher, _) => matcher.is_pure(),
+            Self::Const(_) => true,
+            Self::Not(node) => node.is_pure(),
+            Self::And(nodes) | Self::Or(nodes) | Self::List(nodes) => {
+                nodes.iter().all(Self::is_pure)
+            }
+        }
+    }
+
+    fn cost(&self) -> Cost {
+        match self {
+            Self::Matcher(matcher, _) => matcher.cost(),
+            Self::Const(_) => Cost::Free,
+            Self::Not(node) => node.cost(),
+            Self::And(nodes) | Self::Or(nodes) | Self::List(nodes) => {
+                nodes.iter().map(Self::cost).max().unwrap_or(Cost::Free)
+            }
+        }
+    }
+
+    pub fn has_side_effects(&self) -> bool {
+        match self {
+            Self::Matcher(matcher, _) => matcher.has_side_effects(),
+            Self::Const(_) => false,
+            Self::Not(node) => node.has_side_effects(),
+            Self::And(nodes) | Self::Or(nodes) | Self::List(nodes) => {
+                nodes.iter().any(Self::has_side_effects)
+            }
+        }
+    }
+
+    /// Wraps each matcher to count how often it matches, for -D rates.
+    pub fn with_rates(self) -> Self {
+        let all = |nodes: Vec<Self>| nodes.into_iter().map(Self::with_rates).collect();
+        match self {
+            Self::Matcher(matcher, text) => {
+                let rates = RatesMatcher::new(matcher, text.clone()).into_box();
+                Self::Matcher(rates, text)
+            }
+            Self::Const(value) => Self::Const(value),
+            Self::Not(node) => Self::Not(Box::new(node.with_rates())),
+            Self::And(nodes) => Self::And(all(nodes)),
+            Self::Or(nodes) => Self::Or(all(nodes)),
+            Self::List(nodes) => Self::List(all(nodes)),
+        }
+    }
+
+    /// Builds the matcher for this expression.
+    pub fn build(self) -> Box<dyn Matcher> {
+        match self {
+            Self::Matcher(matcher, _) => matcher,
+            Self::Const(true) => TrueMatcher.into_box(),
+            Self::Const(false) => FalseMatcher.into_box(),
+            Self::Not(node) => NotMatcher::new(node.build()).into_box(),
+            Self::And(nodes) => {
+                let mut builder = AndMatcherBuilder::new();
+                for node in nodes {
+                    builder.new_and_condition(node.build());
+                }
+                builder.build()
+            }
+            Self::Or(nodes) => {
+                let mut builder = OrMatcherBuilder::new();
+                for (i, node) in nodes.into_iter().enumerate() {
+                    if i > 0 {
+                        builder
+                            .new_or_condition("-o")
+                            .expect("the parser checks for empty operands");
+                    }
+                    builder.new_and_condition(node.build());
+                }
+                builder.build()
+            }
+            Self::List(nodes) => {
+                let mut builder = ListMatcherBuilder::new();
+                for (i, node) in nodes.into_iter().enumerate() {
+                    if i > 0 {
+                        builder
+                            .new_list_condition()
+                            .expect("the parser checks for empty operands");
+                    }
+                    builder.new_and_condition(node.build());
+                }
+                builder.build()
+            }
+        }
+    }
+}
+
+/// Writes the expression with explicit operators and parentheses, e.g.
+/// `( -name a -o -name b ) -a -print`.
+impl Display for Node {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        // Operands that are junctions themselves need parentheses
+        let operand = |node: &Self, f: &mut Formatter<'_>| match node {
+            Self::And(_) | Self::Or(_) | Self::List(_) => write!(f, "( {node} )"),
+            _ => write!(f, "{node}"),
+        };
+        let junction = |nodes: &[Self], op: &str, f: &mut Formatter<'_>| {
+            for (i, node) in nodes.iter().enumerate() {
+                if i > 0 {
+                    write!(f, " {op} ")?;
+                }
+                operand(node, f)?;
+            }
+            Ok(())
+        };
+
+        match self {
+            Self::Matcher(_, text) => f.write_str(text),
+            Self::Const(true) => f.write_str("-true"),
+            Self::Const(false) => f.write_str("-false"),
+            Self::Not(node) => {
+                f.write_str("! ")?;
+                operand(node, f)
+            }
+            Self::And(nodes) => junction(nodes, "-a", f),
+            Self::Or(nodes) => junction(nodes, "-o", f),
+            Self::List(nodes) => junction(nodes, ",", f),
+        }
+    }
+}
+
+/// Optimizes expressions at some level (see the module docs).
+pub struct Optimizer {
+    level: u8,
+    /// Whether to report each rewrite (-D opt).
+    verbose: bool,
+}
+
+impl Optimizer {
+    /// Makes an optimizer for the level (0 for no optimization) and debugging
+    /// output that `config` asks for.
+    pub fn new(config: &Config) -> Self {
+        Self {
+            level: config.optimization_level,
+            verbose: config.debug.contains(DebugOption::Opt),
+        }
+    }
+
+    /// Optimizes an expression.
+    pub fn optimize(&self, node: Node) -> Node {
+        if self.level == 0 {
+            return node;
+        }
+
+        // Operands first, so they can be flattened into their parents
+        let all = |nodes: Vec<Node>| nodes.into_iter().map(|n| self.optimize(n)).collect();
+        let node = match node {
+            Node::Not(node) => Node::Not(Box::new(self.optimize(*node))),
+            Node::And(nodes) => Node::And(all(nodes)),
+            Node::Or(nodes) => Node::Or(all(nodes)),
+            Node::List(nodes) => Node::List(all(nodes)),
+            node => return node,
+        };
+
+        let before = self.verbose.then(|| node.to_string());
+        let optimized = match node {
+            Node::Not(node) => match *node {
+                Node::Const(value) => Node::Const(!value),
+                Node::Not(node) => *node,
+                node => Node::Not(Box::new(node)),
+            },
+            Node::And(nodes) => self.optimize_junction(nodes, false),
+            Node::Or(nodes) => self.optimize_junction(nodes, true),
+            Node::List(nodes) => self.optimize_list(nodes),
+            node => node,
+        };
+        if let Some(before) = before {
+            let after = optimized.to_string();
+            if after != before {
+                debug::log(DebugOption::Opt, format_args!("{before} => {after}"));
+            }
+        }
+        optimized
+    }
+
+    /// Optimizes an And (`short_circuit` = false) or an Or (true), which stops
+    /// evaluating as soon as something returns `short_circuit`.
+    fn optimize_junction(&self, nodes: Vec<Node>, short_circuit: bool) -> Node {
+        let mut optimized = vec![];
+        // -false -a a == -false
+        let mut short_circuited = false;
+        for node in nodes {
+            match node {
+                // (a -a b) -a c == a -a b -a c
+                Node::And(nested) if !short_circuit => optimized.extend(nested),
+                Node::Or(nested) if short_circuit => optimized.extend(nested),
+                // -true -a a == a
+                Node::Const(value) if value != short_circuit => {}
+                Node::Const(_) => {
+                    short_circuited = true;
+                    break;
+                }
+                node => optimized.push(node),
+            }
+        }
+
+        if short_circuited && self.level >= 2 {
+            // Pure tests right before -false only decide whether we get to it,
+            // but it's false either way.
+            while optimized.last().is_some_and(Node::is_pure) {
+                optimized.pop();
+            }
+        }
+        self.sort_by_cost(&mut optimized);
+        if short_circuited {
+            optimized.push(Node::Const(short_circuit));
+        }
+
+        match optimized.len() {
+            0 => Node::Const(!short_circuit),
+            1 => optimized.pop().unwrap(),
+            _ if short_circuit => Node::Or(optimized),
+            _ => Node::And(optimized),
+        }
+    }
+
+    /// Optimizes a `,` list, whose result is the result of the last expression.
+    fn optimize_list(&self, nodes: Vec<Node>) -> Node {
+        let mut optimized = vec![];
+        let count = nodes.len();
+        for (i, node) in nodes.into_iter().enumerate() {
+            let is_last = i == count - 1;
+            match node {
+                Node::List(nested) => optimized.extend(nested),
+                Node::Const(_) if !is_last => {}
+                // Only the last result is used, so the others only matter for
+                // their side effects.
+                node if !is_last && self.level >= 2 && node.is_pure() => {}
+                node => optimized.push(node),
+            }
+        }
+
+        match optimized.len() {
+            1 => optimized.pop().unwrap(),
+            _ => Node::List(optimized),
+        }
+    }
+
+    /// Moves cheaper tests first, within each run of pure tests (which can be
+    /// evaluated in any order without changing the result).
+    fn sort_by_cost(&self, nodes: &mut [Node]) {
+        let cutoff = match self.level {
+            1 => Cost::Name,
+            2 => Cost::Type,
+            _ => Cost::Expensive,
+        };
+        // Tests more expensive than the cutoff keep their relative order
+        let key = |node: &Node| match node.cost() {
+            cost if cost <= cutoff => cost,
+            _ => Cost::Expensive,
+        };
+
+        for run in nodes.split_mut(|node| !node.is_pure()) {
+            run.sort_by_key(key);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::find::matchers::expr::parse;
+    use crate::find::matchers::lower_node;
+
+    /// Optimizes an expression, returning a description of the result.
+    fn optimized(args: &[&str], level: u8) -> String {
+        let mut config = Config::builder().optimization_level(level).build();
+        let node = lower_node(&parse(args).unwrap(), &mut config).unwrap();
+        describe(&Optimizer::new(&config).optimize(node))
+    }
+
+    fn describe(node: &Node) -> String {
+        let describe_all = |nodes: &[Node], op: &str| {
+            let nodes: Vec<String> = nodes.iter().map(describe).collect();
+            format!("({})", nodes.join(op))
+        };
+        match node {
+            Node::Matcher(matcher, _) if matcher.is_pure() => format!("{:?}", matcher.cost()),
+            Node::Matcher(_, _) => "Action".to_string(),
+            Node::Const(value) => value.to_string(),
+            Node::Not(node) => format!("!{}", describe(node)),
+            Node::And(nodes) => describe_all(nodes, " & "),
+            Node::Or(nodes) => describe_all(nodes, " | "),
+            Node::List(nodes) => describe_all(nodes, ", "),
+        }
+    }
+
+    #[test]
+    fn display_shows_the_structure() {
+        let mut config = Config::default();
+        let expr = parse(&["!", "(", "-name", "*.rs", "-o", "-true", ")", ",", "-print"]).unwrap();
+        let node = lower_node(&expr, &mut config).unwrap();
+        assert_eq!(node.to_string(), "! ( -name '*.rs' -o -true ) , -print");
+    }
+
+    #[test]
+    fn no_optimization() {
+        assert_eq!(
+            optimized(&["-true", "-size", "1", "-name", "a"], 0),
+            "(true & Stat & Name)"
+        );
+    }
+
+    #[test]
+    fn fold_constants() {
+        for (args, expected) in [
+            (&["-true", "-name", "a", "-depth"][..], "Name"),
+            (&["-name", "a", "-o", "-false"], "Name"),
+            (
+                &["-name", "a", "-o", "-true", "-o", "-print"],
+                "(Name | true)",
+            ),
+            (&["-false", "-print"], "false"),
+            (&["!", "-true", "-o", "!", "!", "-name", "a"], "Name"),
+            (&["-true", ",", "-false", ",", "-print"], "Action"),
+            (&["-false", "-o", "(", "-true", "-false", ")"], "false"),
+        ] {
+            assert_eq!(optimized(args, 1), expected, "{args:?}");
+        }
+    }
+
+    #[test]
+    fn eliminate_dead_code() {
+        for (args, expected) in [
+            // The -name test can't change the result
+            (&["-print", "-name", "a", "-false"][..], "(Action & false)"),
+            (&["-name", "a", ",", "-print"], "Action"),
+            // ... but here it decides whether -print happens
+            (
+                &["-name", "a", "-print", "-false"],
+                "(Name & Action & false)",
+            ),
+            (
+                &["-name", "a", "-print", ",", "-print"],
+                "((Name & Action), Action)",
+            ),
+        ] {
+            assert_eq!(optimized(args, 2), expected, "{args:?}");
+        }
+
+        // Only with -O2
+        assert_eq!(
+            optimized(&["-print", "-name", "a", "-false"], 1),
+            "(Action & Name & false)"
+        );
+    }
+
+    #[test]
+    fn reorder_by_cost() {
+        let args = ["-regex", "a", "-size", "1", "-type", "f", "-name", "a"];
+        assert_eq!(optimized(&args, 1), "(Name & Expensive & Stat & Type)");
+        assert_eq!(optimized(&args, 2), "(Name & Type & Expensive & Stat)");
+        assert_eq!(optimized(&args, 3), "(Name & Type & Stat & Expensive)");
+
+        // Nothing moves past an action
+        let args = [
+            "-size", "1", "-print", "-size", "2", "-name", "a", "-o", "-name", "b",
+        ];
+        assert_eq!(
+            optimized(&args, 3),
+            "((Stat & Action & Name & Stat) | Name)"
+        );
+
+        // -prune isn't pure, even though it doesn't have side effects
+        let args = ["-size", "1", "-prune", "-name", "a"];
+        assert_eq!(optimized(&args, 3), "(Stat & Action & Name)");
+    }
+}
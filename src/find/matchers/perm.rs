@@ -13,7 +13,7 @@ use std::io::{stderr, Write};
 #[cfg(unix)]
 use uucore::mode::{parse_numeric, parse_symbolic};
 
-use super::{Matcher, MatcherIO, WalkEntry};
+use super::{Cost, Matcher, MatcherIO, WalkEntry};
 
 #[derive(Clone, Copy, Debug, Eq, PartialEq)]
 #[cfg(unix)]
@@ -100,7 +100,7 @@ impl PermMatcher {
 
 impl Matcher for PermMatcher {
     #[cfg(unix)]
-    fn matches(&self, file_info: &WalkEntry, _: &mut MatcherIO) -> bool {
+    fn matches(&self, file_info: &WalkEntry, matcher_io: &mut MatcherIO) -> bool {
         use std::os::unix::fs::PermissionsExt;
         match file_info.metadata() {
             Ok(metadata) => {
@@ -112,6 +112,7 @@ impl Matcher for PermMatcher {
                 self.comparison_type
                     .mode_bits_match(pattern, metadata.permissions().mode())
             }
+            Err(_) if matcher_io.ignores_error_for(file_info) => false,
             Err(e) => {
                 writeln!(
                     &mut stderr(),
@@ -134,6 +135,10 @@ impl Matcher for PermMatcher {
         .unwrap();
         return false;
     }
+
+    fn cost(&self) -> Cost {
+        Cost::Stat
+    }
 }
 
 #[cfg(test)]
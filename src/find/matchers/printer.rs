@@ -4,10 +4,27 @@
 // license that can be found in the LICENSE file or at
 // https://opensource.org/licenses/MIT.
 
+use std::ffi::OsStr;
 use std::fs::File;
-use std::io::{stderr, Write};
+use std::io::{self, stderr, IsTerminal, Write};
 
-use super::{Matcher, MatcherIO, WalkEntry};
+use super::quoting::{quote, QuotingStyle};
+use super::{Cost, Matcher, MatcherIO, WalkEntry};
+
+/// Writes a file name (or any other `OsStr`) without any conversion, so that
+/// names which aren't valid UTF-8 come out exactly as they are on disk.
+#[cfg(unix)]
+pub fn write_os_str(out: &mut impl Write, s: &OsStr) -> io::Result<()> {
+    use std::os::unix::ffi::OsStrExt;
+    out.write_all(s.as_bytes())
+}
+
+/// Writes a file name (or any other `OsStr`). Windows names are UTF-16, so
+/// they have to be converted first.
+#[cfg(not(unix))]
+pub fn write_os_str(out: &mut impl Write, s: &OsStr) -> io::Result<()> {
+    write!(out, "{}", s.to_string_lossy())
+}
 
 pub enum PrintDelimiter {
     Newline,
@@ -27,23 +44,36 @@ impl std::fmt::Display for PrintDelimiter {
 pub struct Printer {
     delimiter: PrintDelimiter,
     output_file: Option<File>,
+    output_file_is_terminal: bool,
 }
 
 impl Printer {
     pub fn new(delimiter: PrintDelimiter, output_file: Option<File>) -> Self {
         Self {
             delimiter,
+            output_file_is_terminal: output_file.as_ref().is_some_and(File::is_terminal),
             output_file,
         }
     }
 
-    fn print(&self, file_info: &WalkEntry, mut out: impl Write, print_error_message: bool) {
-        match write!(
-            out,
-            "{}{}",
-            file_info.path().to_string_lossy(),
-            self.delimiter
-        ) {
+    fn quoting_style(&self, is_terminal: bool) -> QuotingStyle {
+        match self.delimiter {
+            // -print0 output is meant for other programs, so it's never quoted.
+            PrintDelimiter::Null => QuotingStyle::Literal,
+            PrintDelimiter::Newline => QuotingStyle::for_output(is_terminal, QuotingStyle::Qmark),
+        }
+    }
+
+    fn print(
+        &self,
+        file_info: &WalkEntry,
+        mut out: impl Write,
+        style: QuotingStyle,
+        print_error_message: bool,
+    ) {
+        match write_os_str(&mut out, &quote(file_info.path().as_os_str(), style))
+            .and_then(|()| write!(out, "{}", self.delimiter))
+        {
             Ok(_) => {}
             Err(e) => {
                 if print_error_message {
@@ -65,11 +95,14 @@ impl Printer {
 impl Matcher for Printer {
     fn matches(&self, file_info: &WalkEntry, matcher_io: &mut MatcherIO) -> bool {
         if let Some(file) = &self.output_file {
-            self.print(file_info, file, true);
+            let style = self.quoting_style(self.output_file_is_terminal);
+            self.print(file_info, file, style, true);
         } else {
+            let style = self.quoting_style(matcher_io.deps.output_is_terminal());
             self.print(
                 file_info,
                 &mut *matcher_io.deps.get_output().borrow_mut(),
+                style,
                 false,
             );
         }
@@ -79,6 +112,10 @@ impl Matcher for Printer {
     fn has_side_effects(&self) -> bool {
         true
     }
+
+    fn cost(&self) -> Cost {
+        Cost::Name
+    }
 }
 
 #[cfg(test)]
@@ -101,6 +138,26 @@ mod tests {
         );
     }
 
+    #[test]
+    fn quotes_for_terminal() {
+        let abbbc = get_dir_entry_for("./test_data/simple", "abbbc");
+
+        // There's nothing to quote in a plain name
+        let matcher = Printer::new(PrintDelimiter::Newline, None);
+        let mut deps = FakeDependencies::new();
+        deps.set_output_is_terminal(true);
+        assert!(matcher.matches(&abbbc, &mut deps.new_matcher_io()));
+        assert_eq!(
+            fix_up_slashes("./test_data/simple/abbbc\n"),
+            deps.get_output_as_string()
+        );
+
+        assert_eq!(matcher.quoting_style(true), QuotingStyle::Qmark);
+        assert_eq!(matcher.quoting_style(false), QuotingStyle::Literal);
+        let matcher = Printer::new(PrintDelimiter::Null, None);
+        assert_eq!(matcher.quoting_style(true), QuotingStyle::Literal);
+    }
+
     #[test]
     fn prints_null() {
         let abbbc = get_dir_entry_for("./test_data/simple", "abbbc");
@@ -5,14 +5,20 @@
 // https://opensource.org/licenses/MIT.
 
 use std::error::Error;
+use std::ffi::{OsStr, OsString};
 use std::fs::{self, File};
 use std::path::Path;
 use std::time::SystemTime;
-use std::{borrow::Cow, io::Write};
+use std::{
+    borrow::Cow,
+    io::{self, IsTerminal, Write},
+};
 
 use chrono::{format::StrftimeItems, DateTime, Local};
 
-use super::{FileType, Matcher, MatcherIO, WalkEntry, WalkError};
+use super::quoting::{quote, QuotingStyle};
+use super::{printer::write_os_str, FileType, Matcher, MatcherIO, WalkEntry, WalkError};
+use crate::find::debug::count_stat;
 
 #[cfg(unix)]
 use std::os::unix::prelude::MetadataExt;
@@ -74,6 +80,8 @@ enum PermissionsFormat {
 enum FormatDirective {
     // %a, %Ak
     AccessTime(TimeFormat),
+    // %Bk
+    BirthTime(TimeFormat),
     // %b, %k
     Blocks { large_blocks: bool },
     // %c, %Ck
@@ -265,6 +273,7 @@ impl FormatStringParser<'_> {
             'b' => FormatDirective::Blocks {
                 large_blocks: false,
             },
+            'B' => FormatDirective::BirthTime(self.parse_time_specifier(first)?),
             'c' => FormatDirective::ChangeTime(TimeFormat::Ctime),
             'C' => FormatDirective::ChangeTime(self.parse_time_specifier(first)?),
             'd' => FormatDirective::Depth,
@@ -371,10 +380,19 @@ fn format_non_link_file_type(file_type: FileType) -> char {
     }
 }
 
+/// Gets the I/O error behind an error from [format_directive()], if any.
+fn io_error(e: &(dyn Error + 'static)) -> Option<io::Error> {
+    match e.downcast_ref::<WalkError>() {
+        Some(e) => Some(e.into()),
+        None => e.downcast_ref::<io::Error>().map(|e| e.kind().into()),
+    }
+}
+
 fn format_directive<'entry>(
     file_info: &'entry WalkEntry,
     directive: &FormatDirective,
-) -> Result<Cow<'entry, str>, Box<dyn Error>> {
+    style: QuotingStyle,
+) -> Result<Cow<'entry, OsStr>, Box<dyn Error>> {
     let meta = || file_info.metadata();
 
     // NOTE ON QUOTING:
@@ -383,10 +401,20 @@ fn format_directive<'entry>(
     // practice, thus the set of rules is undoubtedly very different (if this is
     // still done at all).
 
+    // Directives that print names return them directly, so that they aren't
+    // corrupted if they aren't valid UTF-8. Like GNU find, only the names
+    // themselves (%f, %h, %l, %p and %P) are quoted when writing to a terminal.
     let res: Cow<'entry, str> = match directive {
         FormatDirective::AccessTime(tf) => tf.apply(meta()?.accessed()?)?,
 
-        FormatDirective::Basename => file_info.file_name().to_string_lossy(),
+        FormatDirective::Basename => return Ok(quote(file_info.file_name(), style)),
+
+        // Like GNU find, this is empty if the file system doesn't record birth
+        // times
+        FormatDirective::BirthTime(tf) => match meta()?.created() {
+            Ok(time) => tf.apply(time)?,
+            Err(_) => "".into(),
+        },
 
         FormatDirective::Blocks { large_blocks } => {
             #[cfg(unix)]
@@ -401,7 +429,7 @@ fn format_directive<'entry>(
             // even though it's arguably not 100% correct.
             if *large_blocks {
                 // Ceiling divide in half.
-                (blocks + 1) / 2
+                blocks.div_ceil(2)
             } else {
                 blocks
             }
@@ -435,12 +463,14 @@ fn format_directive<'entry>(
         // - "." also returns "."
         // - ".." returns "." (???)
         // These are all (thankfully) documented on the find(1) man page.
-        FormatDirective::Dirname => match file_info.path().parent() {
-            None => "".into(),
-            Some(p) if p == Path::new("/") => "".into(),
-            Some(p) if p == Path::new("") => ".".into(),
-            Some(parent) => parent.to_string_lossy(),
-        },
+        FormatDirective::Dirname => {
+            return Ok(match file_info.path().parent() {
+                None => OsStr::new("").into(),
+                Some(p) if p == Path::new("/") => OsStr::new("").into(),
+                Some(p) if p == Path::new("") => OsStr::new(".").into(),
+                Some(parent) => quote(parent.as_os_str(), style),
+            })
+        }
 
         #[cfg(not(unix))]
         FormatDirective::Filesystem => "".into(),
@@ -483,17 +513,20 @@ fn format_directive<'entry>(
 
         FormatDirective::Path {
             strip_starting_point,
-        } => file_info
-            .path()
-            .strip_prefix(if *strip_starting_point {
-                get_starting_point(file_info)
-            } else {
-                Path::new("")
-            })
-            // safe to unwrap: the prefix is derived *from* the path to begin
-            // with, so it cannot be invalid.
-            .unwrap()
-            .to_string_lossy(),
+        } => {
+            let path = file_info
+                .path()
+                .strip_prefix(if *strip_starting_point {
+                    get_starting_point(file_info)
+                } else {
+                    Path::new("")
+                })
+                // safe to unwrap: the prefix is derived *from* the path to begin
+                // with, so it cannot be invalid.
+                .unwrap()
+                .as_os_str();
+            return Ok(quote(path, style));
+        }
 
         FormatDirective::Permissions(PermissionsFormat::Symbolic) => {
             uucore::fs::display_permissions(meta()?, true).into()
@@ -526,21 +559,22 @@ fn format_directive<'entry>(
             }
         }
 
-        FormatDirective::StartingPoint => get_starting_point(file_info).to_string_lossy(),
+        FormatDirective::StartingPoint => {
+            return Ok(get_starting_point(file_info).as_os_str().into())
+        }
 
         FormatDirective::SymlinkTarget => {
-            if file_info.path_is_symlink() {
-                fs::read_link(file_info.path())?
-                    .to_string_lossy()
-                    .into_owned()
-                    .into()
+            return Ok(if file_info.path_is_symlink() {
+                let target = fs::read_link(file_info.path())?;
+                quote(target.as_os_str(), style).into_owned().into()
             } else {
-                "".into()
-            }
+                OsStr::new("").into()
+            })
         }
 
         FormatDirective::Type { follow_links } => if file_info.path_is_symlink() {
             if *follow_links {
+                count_stat(file_info.debugger(), file_info.path());
                 match file_info.path().metadata().map_err(WalkError::from) {
                     Ok(meta) => format_non_link_file_type(meta.file_type().into()),
                     Err(e) if e.is_not_found() => 'N',
@@ -570,7 +604,28 @@ fn format_directive<'entry>(
         }
     };
 
-    Ok(res)
+    Ok(match res {
+        Cow::Borrowed(s) => OsStr::new(s).into(),
+        Cow::Owned(s) => OsString::from(s).into(),
+    })
+}
+
+/// Writes `content`, padded with spaces to at least `width` characters.
+fn write_padded(
+    out: &mut impl Write,
+    content: &OsStr,
+    width: usize,
+    justify: &Justify,
+) -> std::io::Result<()> {
+    let padding = width.saturating_sub(content.to_string_lossy().chars().count());
+    if *justify == Justify::Right {
+        write!(out, "{:padding$}", "")?;
+    }
+    write_os_str(out, content)?;
+    if *justify == Justify::Left {
+        write!(out, "{:padding$}", "")?;
+    }
+    Ok(())
 }
 
 /// This matcher prints information about its files to stdout, following GNU
@@ -578,17 +633,25 @@ fn format_directive<'entry>(
 pub struct Printf {
     format: FormatString,
     output_file: Option<File>,
+    output_file_is_terminal: bool,
 }
 
 impl Printf {
     pub fn new(format: &str, output_file: Option<File>) -> Result<Self, Box<dyn Error>> {
         Ok(Self {
             format: FormatString::parse(format)?,
+            output_file_is_terminal: output_file.as_ref().is_some_and(File::is_terminal),
             output_file,
         })
     }
 
-    fn print(&self, file_info: &WalkEntry, mut out: impl Write) {
+    fn print(
+        &self,
+        file_info: &WalkEntry,
+        mut out: impl Write,
+        style: QuotingStyle,
+        matcher_io: &MatcherIO,
+    ) {
         for component in &self.format.components {
             match component {
                 FormatComponent::Literal(literal) => write!(out, "{literal}").unwrap(),
@@ -597,27 +660,24 @@ impl Printf {
                     directive,
                     width,
                     justify,
-                } => match format_directive(file_info, directive) {
+                } => match format_directive(file_info, directive, style) {
                     Ok(content) => {
                         if let Some(width) = width {
-                            match justify {
-                                Justify::Left => {
-                                    write!(out, "{content:<width$}").unwrap();
-                                }
-                                Justify::Right => {
-                                    write!(out, "{content:>width$}").unwrap();
-                                }
-                            }
+                            write_padded(&mut out, &content, *width, justify).unwrap();
                         } else {
-                            write!(out, "{content}").unwrap();
+                            write_os_str(&mut out, &content).unwrap();
                         }
                     }
                     Err(e) => {
-                        eprintln!(
-                            "Error processing '{}': {}",
-                            file_info.path().to_string_lossy(),
-                            e
-                        );
+                        let ignored = io_error(&*e)
+                            .is_some_and(|e| matcher_io.ignores_io_error(&e, file_info));
+                        if !ignored {
+                            eprintln!(
+                                "Error processing '{}': {}",
+                                file_info.path().to_string_lossy(),
+                                e
+                            );
+                        }
                         break;
                     }
                 },
@@ -629,9 +689,17 @@ impl Printf {
 impl Matcher for Printf {
     fn matches(&self, file_info: &WalkEntry, matcher_io: &mut MatcherIO) -> bool {
         if let Some(file) = &self.output_file {
-            self.print(file_info, file);
+            let style = QuotingStyle::for_output(self.output_file_is_terminal, QuotingStyle::Qmark);
+            self.print(file_info, file, style, matcher_io);
         } else {
-            self.print(file_info, &mut *matcher_io.deps.get_output().borrow_mut());
+            let style =
+                QuotingStyle::for_output(matcher_io.deps.output_is_terminal(), QuotingStyle::Qmark);
+            self.print(
+                file_info,
+                &mut *matcher_io.deps.get_output().borrow_mut(),
+                style,
+                matcher_io,
+            );
         }
 
         true
@@ -768,8 +836,19 @@ mod tests {
             ]
         );
 
+        assert_eq!(
+            FormatString::parse("%B@%BY").unwrap().components,
+            vec![
+                unaligned_directive(FormatDirective::BirthTime(TimeFormat::SinceEpoch)),
+                unaligned_directive(FormatDirective::BirthTime(TimeFormat::Strftime(
+                    "%Y".to_string()
+                ))),
+            ]
+        );
+
         assert!(FormatString::parse("%").is_err());
         assert!(FormatString::parse("%A!").is_err());
+        assert!(FormatString::parse("%B").is_err());
     }
 
     #[test]
@@ -1056,6 +1135,19 @@ mod tests {
             ),
             deps.get_output_as_string()
         );
+
+        // Setting the modification time doesn't change the birth time
+        let deps = FakeDependencies::new();
+        let matcher = Printf::new("%BY", None).unwrap();
+        assert!(matcher.matches(&file_info, &mut deps.new_matcher_io()));
+        let expected = match file_info.metadata().unwrap().created() {
+            Ok(btime) => chrono::DateTime::<chrono::Local>::from(btime)
+                .format("%Y")
+                .to_string(),
+            Err(_) => String::new(),
+        };
+        assert_eq!(expected, deps.get_output_as_string());
+        assert_ne!("2000", deps.get_output_as_string());
     }
 
     #[test]
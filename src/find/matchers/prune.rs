@@ -4,7 +4,7 @@
 // license that can be found in the LICENSE file or at
 // https://opensource.org/licenses/MIT.
 
-use super::{Matcher, MatcherIO, WalkEntry};
+use super::{Cost, Matcher, MatcherIO, WalkEntry};
 
 /// This matcher checks the type of the file.
 pub struct PruneMatcher;
@@ -23,9 +23,16 @@ impl Matcher for PruneMatcher {
 
         true
     }
+
+    fn is_pure(&self) -> bool {
+        false
+    }
+
+    fn cost(&self) -> Cost {
+        Cost::Type
+    }
 }
 #[cfg(test)]
-
 mod tests {
     use super::*;
     use crate::find::matchers::tests::get_dir_entry_for;
@@ -4,7 +4,7 @@
 // license that can be found in the LICENSE file or at
 // https://opensource.org/licenses/MIT.
 
-use super::{Matcher, MatcherIO, WalkEntry};
+use super::{Cost, Matcher, MatcherIO, WalkEntry};
 
 /// This matcher quits the search immediately.
 pub struct QuitMatcher;
@@ -14,6 +14,14 @@ impl Matcher for QuitMatcher {
         matcher_io.quit();
         true
     }
+
+    fn is_pure(&self) -> bool {
+        false
+    }
+
+    fn cost(&self) -> Cost {
+        Cost::Free
+    }
 }
 
 #[cfg(test)]
@@ -0,0 +1,139 @@
+// This file is part of the uutils findutils package.
+//
+// For the full copyright and license information, please view the LICENSE
+// file that was distributed with this source code.
+
+//! Quoting of file names that are written to a terminal, so that names
+//! containing control characters can't mess with the user's terminal.
+
+use std::borrow::Cow;
+use std::ffi::{OsStr, OsString};
+
+/// How to write file names that may contain unprintable characters.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum QuotingStyle {
+    /// Write names exactly as they are. Used when the output isn't a terminal.
+    Literal,
+    /// Replace unprintable characters with '?', like GNU find's -print and
+    /// -printf do when writing to a terminal.
+    Qmark,
+    /// Escape whitespace, backslashes and double quotes C-style, and anything
+    /// else outside of printable ASCII with octal escapes, like GNU find's -ls.
+    Escape,
+}
+
+impl QuotingStyle {
+    /// Picks the style to use for output that may or may not go to a terminal.
+    pub fn for_output(is_terminal: bool, terminal_style: Self) -> Self {
+        if is_terminal {
+            terminal_style
+        } else {
+            Self::Literal
+        }
+    }
+}
+
+/// Quotes a name according to `style`, only allocating if the name needs
+/// changing.
+pub fn quote(name: &OsStr, style: QuotingStyle) -> Cow<'_, OsStr> {
+    let bytes = name.as_encoded_bytes();
+    let needs_quoting = match style {
+        QuotingStyle::Literal => false,
+        QuotingStyle::Qmark => name
+            .to_str()
+            .is_none_or(|s| s.chars().any(char::is_control)),
+        QuotingStyle::Escape => !bytes
+            .iter()
+            .all(|&b| (0o41..=0o176).contains(&b) && b != b'\\' && b != b'"'),
+    };
+    if !needs_quoting {
+        return Cow::Borrowed(name);
+    }
+
+    let quoted = match style {
+        QuotingStyle::Literal => unreachable!(),
+        QuotingStyle::Qmark => qmark(bytes),
+        QuotingStyle::Escape => escape(bytes),
+    };
+    Cow::Owned(OsString::from(quoted))
+}
+
+fn qmark(bytes: &[u8]) -> String {
+    let mut quoted = String::with_capacity(bytes.len());
+    for chunk in bytes.utf8_chunks() {
+        for c in chunk.valid().chars() {
+            quoted.push(if c.is_control() { '?' } else { c });
+        }
+        if !chunk.invalid().is_empty() {
+            quoted.push('?');
+        }
+    }
+    quoted
+}
+
+fn escape(bytes: &[u8]) -> String {
+    let mut quoted = String::with_capacity(bytes.len());
+    for &b in bytes {
+        match b {
+            b'\\' => quoted.push_str("\\\\"),
+            b'"' => quoted.push_str("\\\""),
+            b' ' => quoted.push_str("\\ "),
+            b'\x07' => quoted.push_str("\\a"),
+            b'\x08' => quoted.push_str("\\b"),
+            b'\x0C' => quoted.push_str("\\f"),
+            b'\n' => quoted.push_str("\\n"),
+            b'\r' => quoted.push_str("\\r"),
+            b'\t' => quoted.push_str("\\t"),
+            b'\x0B' => quoted.push_str("\\v"),
+            0o41..=0o176 => quoted.push(b as char),
+            _ => quoted.push_str(&format!("\\{b:03o}")),
+        }
+    }
+    quoted
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn literal_is_unchanged() {
+        let name = OsStr::new("a\nb\x1b[31m");
+        assert_eq!(quote(name, QuotingStyle::Literal), name);
+    }
+
+    #[test]
+    fn qmark_replaces_control_characters() {
+        assert!(matches!(
+            quote(OsStr::new("plain name é"), QuotingStyle::Qmark),
+            Cow::Borrowed(_)
+        ));
+        assert_eq!(
+            quote(OsStr::new("a\nb\x1b[31m\t"), QuotingStyle::Qmark),
+            OsStr::new("a?b?[31m?")
+        );
+    }
+
+    #[test]
+    #[cfg(unix)]
+    fn qmark_replaces_invalid_utf8() {
+        use std::os::unix::ffi::OsStrExt;
+
+        assert_eq!(
+            quote(OsStr::from_bytes(b"caf\xe9 ok"), QuotingStyle::Qmark),
+            OsStr::new("caf? ok")
+        );
+    }
+
+    #[test]
+    fn escape_uses_c_and_octal_escapes() {
+        assert!(matches!(
+            quote(OsStr::new("./plain/name"), QuotingStyle::Escape),
+            Cow::Borrowed(_)
+        ));
+        assert_eq!(
+            quote(OsStr::new("a b\\\"c\n\x1b\u{e9}"), QuotingStyle::Escape),
+            OsStr::new("a\\ b\\\\\\\"c\\n\\033\\303\\251")
+        );
+    }
+}
@@ -0,0 +1,102 @@
+// This file is part of the uutils findutils package.
+//
+// For the full copyright and license information, please view the LICENSE
+// file that was distributed with this source code.
+
+use std::cell::Cell;
+use std::path::Path;
+
+use super::{Cost, Matcher, MatcherIO, WalkEntry};
+use crate::find::debug::{self, DebugOption};
+
+/// Counts how often another matcher is evaluated, and how often it matches,
+/// reporting both once find is finished (-D rates).
+pub struct RatesMatcher {
+    matcher: Box<dyn Matcher>,
+    /// The arguments the matcher was made from.
+    text: String,
+    evaluations: Cell<u64>,
+    successes: Cell<u64>,
+}
+
+impl RatesMatcher {
+    pub fn new(matcher: Box<dyn Matcher>, text: String) -> Self {
+        Self {
+            matcher,
+            text,
+            evaluations: Cell::new(0),
+            successes: Cell::new(0),
+        }
+    }
+
+    fn report(&self) -> String {
+        let evaluations = self.evaluations.get();
+        let successes = self.successes.get();
+        let mut report = format!("{}: {successes}/{evaluations} succeeded", self.text);
+        if evaluations > 0 {
+            let percent = 100.0 * successes as f64 / evaluations as f64;
+            report.push_str(&format!(" ({percent:.1}%)"));
+        }
+        report
+    }
+}
+
+impl Matcher for RatesMatcher {
+    fn matches(&self, entry: &WalkEntry, matcher_io: &mut MatcherIO) -> bool {
+        let matched = self.matcher.matches(entry, matcher_io);
+        self.evaluations.set(self.evaluations.get() + 1);
+        if matched {
+            self.successes.set(self.successes.get() + 1);
+        }
+        matched
+    }
+
+    fn has_side_effects(&self) -> bool {
+        self.matcher.has_side_effects()
+    }
+
+    fn is_pure(&self) -> bool {
+        self.matcher.is_pure()
+    }
+
+    fn cost(&self) -> Cost {
+        self.matcher.cost()
+    }
+
+    fn constant(&self) -> Option<bool> {
+        self.matcher.constant()
+    }
+
+    fn finished_dir(&self, finished_directory: &Path, matcher_io: &mut MatcherIO) {
+        self.matcher.finished_dir(finished_directory, matcher_io);
+    }
+
+    fn finished(&self, matcher_io: &mut MatcherIO) {
+        self.matcher.finished(matcher_io);
+        debug::log(DebugOption::Rates, self.report());
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::find::matchers::name::NameMatcher;
+    use crate::find::matchers::tests::get_dir_entry_for;
+    use crate::find::tests::FakeDependencies;
+
+    #[test]
+    fn counts_evaluations_and_successes() {
+        let name = NameMatcher::new("abbb", false).into_box();
+        let matcher = RatesMatcher::new(name, "-name abbb".to_string());
+        assert_eq!(matcher.report(), "-name abbb: 0/0 succeeded");
+
+        let deps = FakeDependencies::new();
+        let abbb = get_dir_entry_for("test_data/simple", "abbb");
+        let abbbc = get_dir_entry_for("test_data/simple", "subdir/abbbc");
+        assert!(matcher.matches(&abbb, &mut deps.new_matcher_io()));
+        assert!(!matcher.matches(&abbbc, &mut deps.new_matcher_io()));
+        assert!(!matcher.matches(&abbbc, &mut deps.new_matcher_io()));
+        assert_eq!(matcher.report(), "-name abbb: 1/3 succeeded (33.3%)");
+        assert_eq!(matcher.cost(), Cost::Name);
+    }
+}
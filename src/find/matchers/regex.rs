@@ -30,8 +30,9 @@ impl fmt::Display for ParseRegexTypeError {
     }
 }
 
-#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
 pub enum RegexType {
+    #[default]
     Emacs,
     Grep,
     PosixBasic,
@@ -45,6 +46,23 @@ impl RegexType {
         Self::PosixBasic,
         Self::PosixExtended,
     ];
+
+    /// Compiles a regular expression of this type.
+    pub fn compile(self, pattern: &str, ignore_case: bool) -> Result<Regex, onig::Error> {
+        let syntax = match self {
+            RegexType::Emacs => Syntax::emacs(),
+            RegexType::Grep => Syntax::grep(),
+            RegexType::PosixBasic => Syntax::posix_basic(),
+            RegexType::PosixExtended => Syntax::posix_extended(),
+        };
+
+        let options = if ignore_case {
+            RegexOptions::REGEX_OPTION_IGNORECASE
+        } else {
+            RegexOptions::REGEX_OPTION_NONE
+        };
+        Regex::with_options(pattern, options, syntax)
+    }
 }
 
 impl fmt::Display for RegexType {
@@ -74,12 +92,6 @@ impl FromStr for RegexType {
     }
 }
 
-impl Default for RegexType {
-    fn default() -> Self {
-        Self::Emacs
-    }
-}
-
 pub struct RegexMatcher {
     regex: Regex,
 }
@@ -90,22 +102,7 @@ impl RegexMatcher {
         pattern: &str,
         ignore_case: bool,
     ) -> Result<Self, Box<dyn Error>> {
-        let syntax = match regex_type {
-            RegexType::Emacs => Syntax::emacs(),
-            RegexType::Grep => Syntax::grep(),
-            RegexType::PosixBasic => Syntax::posix_basic(),
-            RegexType::PosixExtended => Syntax::posix_extended(),
-        };
-
-        let regex = Regex::with_options(
-            pattern,
-            if ignore_case {
-                RegexOptions::REGEX_OPTION_IGNORECASE
-            } else {
-                RegexOptions::REGEX_OPTION_NONE
-            },
-            syntax,
-        )?;
+        let regex = regex_type.compile(pattern, ignore_case)?;
         Ok(Self { regex })
     }
 }
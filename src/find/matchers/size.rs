@@ -8,7 +8,7 @@ use std::error::Error;
 use std::io::{stderr, Write};
 use std::str::FromStr;
 
-use super::{ComparableValue, Matcher, MatcherIO, WalkEntry};
+use super::{ComparableValue, Cost, Matcher, MatcherIO, WalkEntry};
 
 #[derive(Clone, Copy, Debug)]
 enum Unit {
@@ -82,11 +82,12 @@ impl SizeMatcher {
 }
 
 impl Matcher for SizeMatcher {
-    fn matches(&self, file_info: &WalkEntry, _: &mut MatcherIO) -> bool {
+    fn matches(&self, file_info: &WalkEntry, matcher_io: &mut MatcherIO) -> bool {
         match file_info.metadata() {
             Ok(metadata) => self
                 .value_to_match
                 .matches(byte_size_to_unit_size(self.unit, metadata.len())),
+            Err(_) if matcher_io.ignores_error_for(file_info) => false,
             Err(e) => {
                 writeln!(
                     &mut stderr(),
@@ -99,6 +100,10 @@ impl Matcher for SizeMatcher {
             }
         }
     }
+
+    fn cost(&self) -> Cost {
+        Cost::Stat
+    }
 }
 
 #[cfg(test)]
@@ -6,7 +6,7 @@
 
 use std::os::unix::fs::MetadataExt;
 
-use super::{ComparableValue, Matcher, MatcherIO, WalkEntry};
+use super::{ComparableValue, Cost, Matcher, MatcherIO, WalkEntry};
 
 /// Inode number matcher.
 pub struct InodeMatcher {
@@ -26,6 +26,10 @@ impl Matcher for InodeMatcher {
             Err(_) => false,
         }
     }
+
+    fn cost(&self) -> Cost {
+        Cost::Stat
+    }
 }
 
 /// Link count matcher.
@@ -46,6 +50,10 @@ impl Matcher for LinksMatcher {
             Err(_) => false,
         }
     }
+
+    fn cost(&self) -> Cost {
+        Cost::Stat
+    }
 }
 
 #[cfg(test)]
@@ -4,9 +4,11 @@
 // license that can be found in the LICENSE file or at
 // https://opensource.org/licenses/MIT.
 
+use std::cell::Cell;
 use std::error::Error;
+use std::fmt::{self, Display, Formatter};
 use std::fs::{self, Metadata};
-use std::io::{stderr, Write};
+use std::io::{stderr, ErrorKind, Write};
 use std::time::{Duration, SystemTime, UNIX_EPOCH};
 
 use chrono::{DateTime, Local, Timelike};
@@ -14,7 +16,7 @@ use chrono::{DateTime, Local, Timelike};
 #[cfg(unix)]
 use std::os::unix::fs::MetadataExt;
 
-use super::{ComparableValue, Follow, Matcher, MatcherIO, WalkEntry};
+use super::{ComparableValue, Cost, Follow, Matcher, MatcherIO, WalkEntry};
 
 const SECONDS_PER_DAY: i64 = 60 * 60 * 24;
 
@@ -63,8 +65,9 @@ impl NewerMatcher {
 }
 
 impl Matcher for NewerMatcher {
-    fn matches(&self, file_info: &WalkEntry, _: &mut MatcherIO) -> bool {
+    fn matches(&self, file_info: &WalkEntry, matcher_io: &mut MatcherIO) -> bool {
         match self.matches_impl(file_info) {
+            Err(_) if matcher_io.ignores_error_for(file_info) => false,
             Err(e) => {
                 writeln!(
                     &mut stderr(),
@@ -78,6 +81,10 @@ impl Matcher for NewerMatcher {
             Ok(t) => t,
         }
     }
+
+    fn cost(&self) -> Cost {
+        Cost::Stat
+    }
 }
 
 /// `-newerXY` option.
@@ -85,7 +92,9 @@ impl Matcher for NewerMatcher {
 /// B is meaning Birthed time
 /// c is meaning Changed time
 /// m is meaning Modified time
-/// It should be noted that not every file system supports birthed time.
+/// It should be noted that not every file system supports birthed time.  On
+/// Linux, std gets it with statx(), which needs a 4.11 kernel and a file
+/// system that records it, like ext4 or btrfs.
 #[derive(Clone, Copy, Debug)]
 pub enum NewerOptionType {
     Accessed,
@@ -104,23 +113,24 @@ impl NewerOptionType {
             _ => NewerOptionType::Modified,
         }
     }
+}
 
-    fn get_file_time(self, metadata: &Metadata) -> std::io::Result<SystemTime> {
-        match self {
-            NewerOptionType::Accessed => metadata.accessed(),
-            NewerOptionType::Birthed => metadata.created(),
-            NewerOptionType::Changed => metadata.changed(),
-            NewerOptionType::Modified => metadata.modified(),
+impl From<NewerOptionType> for FileTimeType {
+    fn from(option: NewerOptionType) -> Self {
+        match option {
+            NewerOptionType::Accessed => FileTimeType::Accessed,
+            NewerOptionType::Birthed => FileTimeType::Birthed,
+            NewerOptionType::Changed => FileTimeType::Changed,
+            NewerOptionType::Modified => FileTimeType::Modified,
         }
     }
 }
 
-/// This matcher checks whether the file is newer than the file time of any combination of
-/// two comparison types from the target file's `NewerOptionType`.
+/// This matcher checks whether one of the file's times (X) is newer than one of
+/// the times (Y) of a reference file, for `-newerXY`.
 pub struct NewerOptionMatcher {
-    x_option: NewerOptionType,
-    y_option: NewerOptionType,
-    given_modification_time: SystemTime,
+    x_time: FileTimeGetter,
+    given_time: SystemTime,
 }
 
 impl NewerOptionMatcher {
@@ -131,38 +141,33 @@ impl NewerOptionMatcher {
     ) -> Result<Self, Box<dyn Error>> {
         let metadata = fs::metadata(path_to_file)?;
         let x_option = NewerOptionType::from_str(x_option.as_str());
-        let y_option = NewerOptionType::from_str(y_option.as_str());
+        let y_option = FileTimeType::from(NewerOptionType::from_str(y_option.as_str()));
+        let given_time = y_option
+            .get_file_time(&metadata)
+            .map_err(|e| format!("Error getting {y_option} time for {path_to_file}: {e}"))?;
         Ok(Self {
-            x_option,
-            y_option,
-            given_modification_time: metadata.modified()?,
+            x_time: FileTimeGetter::new(x_option.into()),
+            given_time,
         })
     }
 
     fn matches_impl(&self, file_info: &WalkEntry) -> Result<bool, Box<dyn Error>> {
-        let x_option_time = self.x_option.get_file_time(file_info.metadata()?)?;
-        let y_option_time = self.y_option.get_file_time(file_info.metadata()?)?;
-
-        Ok(self
-            .given_modification_time
-            .duration_since(x_option_time)
-            .is_err()
-            && self
-                .given_modification_time
-                .duration_since(y_option_time)
-                .is_err())
+        let Some(x_option_time) = self.x_time.get(file_info)? else {
+            return Ok(false);
+        };
+        Ok(self.given_time.duration_since(x_option_time).is_err())
     }
 }
 
 impl Matcher for NewerOptionMatcher {
-    fn matches(&self, file_info: &WalkEntry, _: &mut MatcherIO) -> bool {
+    fn matches(&self, file_info: &WalkEntry, matcher_io: &mut MatcherIO) -> bool {
         match self.matches_impl(file_info) {
+            Err(_) if matcher_io.ignores_error_for(file_info) => false,
             Err(e) => {
                 writeln!(
                     &mut stderr(),
-                    "Error getting {:?} and {:?} time for {}: {}",
-                    self.x_option,
-                    self.y_option,
+                    "Error getting {} time for {}: {}",
+                    self.x_time.file_time_type,
                     file_info.path().to_string_lossy(),
                     e
                 )
@@ -172,47 +177,44 @@ impl Matcher for NewerOptionMatcher {
             Ok(t) => t,
         }
     }
+
+    fn cost(&self) -> Cost {
+        Cost::Stat
+    }
 }
 
 /// This matcher checks whether files's accessed|creation|modification time is
 /// newer than the given times.
 pub struct NewerTimeMatcher {
-    time: i64,
-    newer_time_type: NewerOptionType,
+    time: SystemTime,
+    newer_time: FileTimeGetter,
 }
 
 impl NewerTimeMatcher {
-    pub fn new(newer_time_type: NewerOptionType, time: i64) -> Self {
+    pub fn new(newer_time_type: NewerOptionType, time: SystemTime) -> Self {
         Self {
             time,
-            newer_time_type,
+            newer_time: FileTimeGetter::new(newer_time_type.into()),
         }
     }
 
     fn matches_impl(&self, file_info: &WalkEntry) -> Result<bool, Box<dyn Error>> {
-        let this_time = self.newer_time_type.get_file_time(file_info.metadata()?)?;
-        let timestamp = this_time
-            .duration_since(UNIX_EPOCH)
-            .unwrap_or_else(|e| e.duration());
-
-        // timestamp.as_millis() return u128 but time is i64
-        // This may leave memory implications. :(
-        Ok(self.time
-            <= timestamp
-                .as_millis()
-                .try_into()
-                .expect("timestamp memory implications"))
+        let Some(this_time) = self.newer_time.get(file_info)? else {
+            return Ok(false);
+        };
+        Ok(self.time <= this_time)
     }
 }
 
 impl Matcher for NewerTimeMatcher {
-    fn matches(&self, file_info: &WalkEntry, _: &mut MatcherIO) -> bool {
+    fn matches(&self, file_info: &WalkEntry, matcher_io: &mut MatcherIO) -> bool {
         match self.matches_impl(file_info) {
+            Err(_) if matcher_io.ignores_error_for(file_info) => false,
             Err(e) => {
                 writeln!(
                     &mut stderr(),
-                    "Error getting {:?} time for {}: {}",
-                    self.newer_time_type,
+                    "Error getting {} time for {}: {}",
+                    self.newer_time.file_time_type,
                     file_info.path().to_string_lossy(),
                     e
                 )
@@ -222,6 +224,10 @@ impl Matcher for NewerTimeMatcher {
             Ok(t) => t,
         }
     }
+
+    fn cost(&self) -> Cost {
+        Cost::Stat
+    }
 }
 
 /// Provide access to the *change* timestamp, since std::fs::Metadata doesn't expose it.
@@ -256,6 +262,7 @@ impl ChangeTime for Metadata {
 #[derive(Clone, Copy, Debug)]
 pub enum FileTimeType {
     Accessed,
+    Birthed,
     Changed,
     Modified,
 }
@@ -264,17 +271,69 @@ impl FileTimeType {
     fn get_file_time(self, metadata: &Metadata) -> std::io::Result<SystemTime> {
         match self {
             FileTimeType::Accessed => metadata.accessed(),
+            FileTimeType::Birthed => metadata.created(),
             FileTimeType::Changed => metadata.changed(),
             FileTimeType::Modified => metadata.modified(),
         }
     }
 }
 
+impl Display for FileTimeType {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            FileTimeType::Accessed => "access",
+            FileTimeType::Birthed => "birth",
+            FileTimeType::Changed => "change",
+            FileTimeType::Modified => "modification",
+        })
+    }
+}
+
+/// Gets one of the times of the files a matcher looks at.  Files on file
+/// systems that don't record birth times don't match, with a warning the
+/// first time it happens rather than an error for every file.
+struct FileTimeGetter {
+    file_time_type: FileTimeType,
+    warned: Cell<bool>,
+}
+
+impl FileTimeGetter {
+    fn new(file_time_type: FileTimeType) -> Self {
+        Self {
+            file_time_type,
+            warned: Cell::new(false),
+        }
+    }
+
+    /// Gets the file's time, or `None` if it doesn't have a birth time.
+    fn get(&self, file_info: &WalkEntry) -> Result<Option<SystemTime>, Box<dyn Error>> {
+        match self.file_time_type.get_file_time(file_info.metadata()?) {
+            Ok(time) => Ok(Some(time)),
+            Err(e)
+                if matches!(self.file_time_type, FileTimeType::Birthed)
+                    && e.kind() == ErrorKind::Unsupported =>
+            {
+                if !self.warned.replace(true) {
+                    writeln!(
+                        &mut stderr(),
+                        "find: warning: the birth time of {} is not available, so files \
+                         without one will not match",
+                        file_info.path().to_string_lossy()
+                    )
+                    .unwrap();
+                }
+                Ok(None)
+            }
+            Err(e) => Err(e.into()),
+        }
+    }
+}
+
 /// This matcher checks whether a file's accessed|creation|modification time is
 /// {less than | exactly | more than} N days old.
 pub struct FileTimeMatcher {
     days: ComparableValue,
-    file_time_type: FileTimeType,
+    file_time: FileTimeGetter,
     today_start: bool,
 }
 
@@ -282,11 +341,12 @@ impl Matcher for FileTimeMatcher {
     fn matches(&self, file_info: &WalkEntry, matcher_io: &mut MatcherIO) -> bool {
         let start_time = get_time(matcher_io, self.today_start);
         match self.matches_impl(file_info, start_time) {
+            Err(_) if matcher_io.ignores_error_for(file_info) => false,
             Err(e) => {
                 writeln!(
                     &mut stderr(),
-                    "Error getting {:?} time for {}: {}",
-                    self.file_time_type,
+                    "Error getting {} time for {}: {}",
+                    self.file_time.file_time_type,
                     file_info.path().to_string_lossy(),
                     e
                 )
@@ -296,6 +356,10 @@ impl Matcher for FileTimeMatcher {
             Ok(t) => t,
         }
     }
+
+    fn cost(&self) -> Cost {
+        Cost::Stat
+    }
 }
 
 impl FileTimeMatcher {
@@ -306,7 +370,9 @@ impl FileTimeMatcher {
         file_info: &WalkEntry,
         start_time: SystemTime,
     ) -> Result<bool, Box<dyn Error>> {
-        let this_time = self.file_time_type.get_file_time(file_info.metadata()?)?;
+        let Some(this_time) = self.file_time.get(file_info)? else {
+            return Ok(false);
+        };
         let mut is_negative = false;
         // durations can't be negative. So duration_since returns a duration
         // wrapped in an error if now < this_time.
@@ -339,7 +405,7 @@ impl FileTimeMatcher {
     pub fn new(file_time_type: FileTimeType, days: ComparableValue, today_start: bool) -> Self {
         Self {
             days,
-            file_time_type,
+            file_time: FileTimeGetter::new(file_time_type),
             today_start,
         }
     }
@@ -347,7 +413,7 @@ impl FileTimeMatcher {
 
 pub struct FileAgeRangeMatcher {
     minutes: ComparableValue,
-    file_time_type: FileTimeType,
+    file_time: FileTimeGetter,
     today_start: bool,
 }
 
@@ -355,11 +421,12 @@ impl Matcher for FileAgeRangeMatcher {
     fn matches(&self, file_info: &WalkEntry, matcher_io: &mut MatcherIO) -> bool {
         let start_time = get_time(matcher_io, self.today_start);
         match self.matches_impl(file_info, start_time) {
+            Err(_) if matcher_io.ignores_error_for(file_info) => false,
             Err(e) => {
                 writeln!(
                     &mut stderr(),
-                    "Error getting {:?} time for {}: {}",
-                    self.file_time_type,
+                    "Error getting {} time for {}: {}",
+                    self.file_time.file_time_type,
                     file_info.path().to_string_lossy(),
                     e
                 )
@@ -369,6 +436,10 @@ impl Matcher for FileAgeRangeMatcher {
             Ok(t) => t,
         }
     }
+
+    fn cost(&self) -> Cost {
+        Cost::Stat
+    }
 }
 
 impl FileAgeRangeMatcher {
@@ -377,7 +448,9 @@ impl FileAgeRangeMatcher {
         file_info: &WalkEntry,
         start_time: SystemTime,
     ) -> Result<bool, Box<dyn Error>> {
-        let this_time = self.file_time_type.get_file_time(file_info.metadata()?)?;
+        let Some(this_time) = self.file_time.get(file_info)? else {
+            return Ok(false);
+        };
         let mut is_negative = false;
         let age = match start_time.duration_since(this_time) {
             Ok(duration) => duration,
@@ -394,7 +467,7 @@ impl FileAgeRangeMatcher {
     pub fn new(file_time_type: FileTimeType, minutes: ComparableValue, today_start: bool) -> Self {
         Self {
             minutes,
-            file_time_type,
+            file_time: FileTimeGetter::new(file_time_type),
             today_start,
         }
     }
@@ -692,6 +765,10 @@ mod tests {
         if let Ok(modified_time) = metadata.modified() {
             test_matcher_for_file_time_type(&file_info, modified_time, FileTimeType::Modified);
         }
+
+        if let Ok(birth_time) = metadata.created() {
+            test_matcher_for_file_time_type(&file_info, birth_time, FileTimeType::Birthed);
+        }
     }
 
     /// helper function for `file_time_matcher_modified_changed_accessed`
@@ -756,17 +833,40 @@ mod tests {
         }
     }
 
+    #[test]
+    fn newer_option_matcher_birth_time() {
+        let temp_dir = Builder::new().prefix("example").tempdir().unwrap();
+        let temp_dir_path = temp_dir.path().to_string_lossy();
+        File::create(temp_dir.path().join("old")).expect("create temp file");
+        thread::sleep(Duration::from_millis(100));
+        File::create(temp_dir.path().join("new")).expect("create temp file");
+        let old_file = get_dir_entry_for(&temp_dir_path, "old");
+        let new_file = get_dir_entry_for(&temp_dir_path, "new");
+        let old_path = old_file.path().to_string_lossy();
+        let deps = FakeDependencies::new();
+
+        if old_file.metadata().unwrap().created().is_err() {
+            // Not every file system records birth times
+            let result = NewerOptionMatcher::new("m".to_string(), "B".to_string(), &old_path);
+            assert!(result.is_err(), "birth time should be unavailable");
+            return;
+        }
+
+        // -Bnewer
+        let matcher = NewerOptionMatcher::new("B".to_string(), "m".to_string(), &old_path)
+            .expect("birth time should be available");
+        assert!(matcher.matches(&new_file, &mut deps.new_matcher_io()));
+        assert!(!matcher.matches(&old_file, &mut deps.new_matcher_io()));
+
+        let matcher = NewerOptionMatcher::new("m".to_string(), "B".to_string(), &old_path)
+            .expect("birth time should be available");
+        assert!(matcher.matches(&new_file, &mut deps.new_matcher_io()));
+    }
+
     #[test]
     fn newer_time_matcher() {
         let deps = FakeDependencies::new();
-        let time = deps
-            .new_matcher_io()
-            .now()
-            .duration_since(SystemTime::UNIX_EPOCH)
-            .unwrap()
-            .as_millis()
-            .try_into()
-            .unwrap();
+        let time = deps.new_matcher_io().now();
 
         let created_matcher = NewerTimeMatcher::new(NewerOptionType::Birthed, time);
 
@@ -865,6 +965,7 @@ mod tests {
                     "more minutes old file should not match more than 1 minute old in {} test.",
                     match *time_type {
                         FileTimeType::Accessed => "accessed",
+                        FileTimeType::Birthed => "birthed",
                         FileTimeType::Changed => "changed",
                         FileTimeType::Modified => "modified",
                     }
@@ -895,6 +996,7 @@ mod tests {
                     "less minutes old file should match less than 1 minute old in {} test.",
                     match *time_type {
                         FileTimeType::Accessed => "accessed",
+                        FileTimeType::Birthed => "birthed",
                         FileTimeType::Changed => "changed",
                         FileTimeType::Modified => "modified",
                     }
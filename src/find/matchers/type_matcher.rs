@@ -6,59 +6,117 @@
 
 use std::error::Error;
 
-use super::{FileType, Follow, Matcher, MatcherIO, WalkEntry};
+use super::{Cost, FileType, Follow, Matcher, MatcherIO, WalkEntry};
 
-/// This matcher checks the type of the file.
-pub struct TypeMatcher {
-    file_type: FileType,
+/// A set of file types, as a bit mask.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+struct FileTypes(u16);
+
+impl FileTypes {
+    fn bit(file_type: FileType) -> u16 {
+        1 << file_type as u16
+    }
+
+    fn contains(self, file_type: FileType) -> bool {
+        self.0 & Self::bit(file_type) != 0
+    }
+
+    /// Adds a file type, returning false if it was already there.
+    fn insert(&mut self, file_type: FileType) -> bool {
+        let found = self.contains(file_type);
+        self.0 |= Self::bit(file_type);
+        !found
+    }
 }
 
-fn parse(type_string: &str) -> Result<FileType, Box<dyn Error>> {
-    let file_type = match type_string {
-        "f" => FileType::Regular,
-        "d" => FileType::Directory,
-        "l" => FileType::Symlink,
-        "b" => FileType::BlockDevice,
-        "c" => FileType::CharDevice,
-        "p" => FileType::Fifo, // named pipe (FIFO)
-        "s" => FileType::Socket,
-        // D: door (Solaris)
-        "D" => {
+/// Parses a comma-separated list of type letters, like `f,d,l`, for
+/// `option` (-type or -xtype).
+fn parse(type_string: &str, option: &str) -> Result<FileTypes, Box<dyn Error>> {
+    if type_string.is_empty() {
+        return Err(From::from(format!(
+            "Arguments to {option} should contain at least one letter"
+        )));
+    }
+
+    let mut file_types = FileTypes::default();
+    let mut chars = type_string.chars().peekable();
+    while let Some(letter) = chars.next() {
+        let file_type = match letter {
+            'f' => FileType::Regular,
+            'd' => FileType::Directory,
+            'l' => FileType::Symlink,
+            'b' => FileType::BlockDevice,
+            'c' => FileType::CharDevice,
+            'p' => FileType::Fifo, // named pipe (FIFO)
+            's' => FileType::Socket,
+            // D: door (Solaris)
+            'D' => {
+                return Err(From::from(format!(
+                    "Type argument {letter} not supported yet"
+                )))
+            }
+            _ => {
+                return Err(From::from(format!(
+                    "Unknown argument to {option}: {letter}"
+                )))
+            }
+        };
+        if !file_types.insert(file_type) {
             return Err(From::from(format!(
-                "Type argument {type_string} not supported yet"
-            )))
+                "Duplicate file type '{letter}' in the argument list to {option}"
+            )));
         }
-        _ => {
-            return Err(From::from(format!(
-                "Unrecognised type argument {type_string}"
-            )))
+
+        match chars.next() {
+            None => {}
+            Some(',') if chars.peek().is_none() => {
+                return Err(From::from(format!(
+                    "Last file type in list argument to {option} is missing, \
+                     i.e., list is ending on: ','"
+                )))
+            }
+            Some(',') => {}
+            Some(_) => {
+                return Err(From::from(format!(
+                    "Must separate multiple arguments to {option} using: ','"
+                )))
+            }
         }
-    };
-    Ok(file_type)
+    }
+    Ok(file_types)
+}
+
+/// This matcher checks the type of the file.
+pub struct TypeMatcher {
+    file_types: FileTypes,
 }
 
 impl TypeMatcher {
     pub fn new(type_string: &str) -> Result<Self, Box<dyn Error>> {
-        let file_type = parse(type_string)?;
-        Ok(Self { file_type })
+        let file_types = parse(type_string, "-type")?;
+        Ok(Self { file_types })
     }
 }
 
 impl Matcher for TypeMatcher {
     fn matches(&self, file_info: &WalkEntry, _: &mut MatcherIO) -> bool {
-        file_info.file_type() == self.file_type
+        self.file_types.contains(file_info.file_type())
+    }
+
+    fn cost(&self) -> Cost {
+        Cost::Type
     }
 }
 
 /// Like [TypeMatcher], but toggles whether symlinks are followed.
 pub struct XtypeMatcher {
-    file_type: FileType,
+    file_types: FileTypes,
 }
 
 impl XtypeMatcher {
     pub fn new(type_string: &str) -> Result<Self, Box<dyn Error>> {
-        let file_type = parse(type_string)?;
-        Ok(Self { file_type })
+        let file_types = parse(type_string, "-xtype")?;
+        Ok(Self { file_types })
     }
 }
 
@@ -76,12 +134,15 @@ impl Matcher for XtypeMatcher {
             .map(FileType::from);
 
         match file_type {
-            Ok(file_type) if file_type == self.file_type => true,
+            Ok(file_type) => self.file_types.contains(file_type),
             // Since GNU find 4.10, ELOOP will match -xtype l
-            Err(e) if self.file_type.is_symlink() && e.is_loop() => true,
-            _ => false,
+            Err(e) => e.is_loop() && self.file_types.contains(FileType::Symlink),
         }
     }
+
+    fn cost(&self) -> Cost {
+        Cost::Stat
+    }
 }
 
 #[cfg(test)]
@@ -186,12 +247,60 @@ mod tests {
         }
     }
 
+    #[test]
+    fn type_list_matcher() {
+        let file = get_dir_entry_for("test_data/simple", "abbbc");
+        let dir = get_dir_entry_for("test_data", "simple");
+        let deps = FakeDependencies::new();
+
+        let matcher = TypeMatcher::new("f,d").unwrap();
+        assert!(matcher.matches(&dir, &mut deps.new_matcher_io()));
+        assert!(matcher.matches(&file, &mut deps.new_matcher_io()));
+
+        let matcher = TypeMatcher::new("l,d,p").unwrap();
+        assert!(matcher.matches(&dir, &mut deps.new_matcher_io()));
+        assert!(!matcher.matches(&file, &mut deps.new_matcher_io()));
+    }
+
     #[test]
     fn cant_create_with_invalid_pattern() {
         let result = TypeMatcher::new("xxx");
         assert!(result.is_err());
     }
 
+    #[test]
+    fn cant_create_with_invalid_list() {
+        for (types, error) in [
+            ("", "Arguments to -type should contain at least one letter"),
+            ("fd", "Must separate multiple arguments to -type using: ','"),
+            (
+                "f,f",
+                "Duplicate file type 'f' in the argument list to -type",
+            ),
+            (
+                "f,d,l,d",
+                "Duplicate file type 'd' in the argument list to -type",
+            ),
+            ("f,x", "Unknown argument to -type: x"),
+            (",f", "Unknown argument to -type: ,"),
+            ("f,,d", "Unknown argument to -type: ,"),
+            (
+                "f,",
+                "Last file type in list argument to -type is missing, \
+                 i.e., list is ending on: ','",
+            ),
+        ] {
+            let result = TypeMatcher::new(types);
+            assert_eq!(result.err().map(|e| e.to_string()).as_deref(), Some(error));
+        }
+
+        let result = XtypeMatcher::new("f,f");
+        assert_eq!(
+            result.err().map(|e| e.to_string()).as_deref(),
+            Some("Duplicate file type 'f' in the argument list to -xtype")
+        );
+    }
+
     #[cfg(unix)]
     #[test]
     fn xtype_file() {
@@ -230,6 +339,25 @@ mod tests {
         assert!(matcher.matches(&entry, &mut deps.new_matcher_io()));
     }
 
+    #[cfg(unix)]
+    #[test]
+    fn xtype_list() {
+        let matcher = XtypeMatcher::new("d,l").unwrap();
+        let deps = FakeDependencies::new();
+
+        let entry = get_dir_entry_follow("test_data/links", "link-f", Follow::Never);
+        assert!(!matcher.matches(&entry, &mut deps.new_matcher_io()));
+
+        let entry = get_dir_entry_follow("test_data/links", "link-d", Follow::Never);
+        assert!(matcher.matches(&entry, &mut deps.new_matcher_io()));
+
+        let entry = get_dir_entry_follow("test_data/links", "link-missing", Follow::Never);
+        assert!(matcher.matches(&entry, &mut deps.new_matcher_io()));
+
+        let entry = get_dir_entry_for("test_data/links", "link-loop");
+        assert!(matcher.matches(&entry, &mut deps.new_matcher_io()));
+    }
+
     #[cfg(unix)]
     #[test]
     fn xtype_loop() {
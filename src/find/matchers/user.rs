@@ -3,7 +3,7 @@
 // For the full copyright and license information, please view the LICENSE
 // file that was distributed with this source code.
 
-use super::{Matcher, MatcherIO, WalkEntry};
+use super::{Cost, Matcher, MatcherIO, WalkEntry};
 
 #[cfg(unix)]
 use nix::unistd::User;
@@ -74,6 +74,10 @@ impl Matcher for UserMatcher {
     fn matches(&self, _file_info: &WalkEntry, _: &mut MatcherIO) -> bool {
         false
     }
+
+    fn cost(&self) -> Cost {
+        Cost::Stat
+    }
 }
 
 pub struct NoUserMatcher {}
@@ -4,16 +4,49 @@
 // license that can be found in the LICENSE file or at
 // https://opensource.org/licenses/MIT.
 
+mod debug;
+#[cfg(unix)]
+mod fdwalk;
 pub mod matchers;
-
-use matchers::{Follow, WalkEntry};
-use std::cell::RefCell;
+#[cfg(unix)]
+mod search;
+#[cfg(unix)]
+mod walker;
+
+use debug::{DebugFlags, DebugOption, Debugger};
+use matchers::expr::ExprError;
+use matchers::{Follow, Matcher, MatcherIO, WalkEntry, WalkError};
+use std::cell::{Cell, RefCell};
+#[cfg(not(unix))]
+use std::collections::VecDeque;
 use std::error::Error;
-use std::io::{stderr, stdout, Write};
+use std::fs::File;
+use std::io::{self, stderr, stdin, stdout, BufRead, BufReader, IsTerminal, Write};
+use std::path::{Path, PathBuf};
 use std::rc::Rc;
+use std::sync::Arc;
 use std::time::SystemTime;
+#[cfg(not(unix))]
 use walkdir::WalkDir;
 
+/// The order to search the directory tree in (-S).
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum SearchStrategy {
+    /// Breadth-first search.
+    Bfs,
+    /// Depth-first search (the default).
+    Dfs,
+    /// Iterative deepening search.
+    Ids,
+    /// Exponential deepening search.
+    Eds,
+}
+
+/// Options that control how directory trees are walked.
+///
+/// Use [Config::builder()] to create one, then pass it to
+/// [matchers::build_matcher()], as options in the expression (like `-maxdepth`
+/// or `-daystart`) may change it.
 pub struct Config {
     same_file_system: bool,
     depth_first: bool,
@@ -24,7 +57,23 @@ pub struct Config {
     version_requested: bool,
     today_start: bool,
     no_leaf_dirs: bool,
+    /// Don't report files that vanish during the walk (-ignore_readdir_race).
+    ignore_readdir_race: bool,
     follow: Follow,
+    /// Read NUL-separated starting points from this file ("-" for stdin).
+    files0_from: Option<String>,
+    /// Whether some action (-ok, -okdir) reads its answers from stdin.
+    prompts_on_stdin: bool,
+    /// The number of threads to read directories with (-j).
+    threads: usize,
+    strategy: SearchStrategy,
+    /// How much to optimize the expression (-O0 to -O3).
+    optimization_level: u8,
+    /// What debugging output to write (-D).
+    debug: DebugFlags,
+    debug_help_requested: bool,
+    /// When find started, which dates in the expression are relative to.
+    start_time: SystemTime,
 }
 
 impl Default for Config {
@@ -42,22 +91,141 @@ impl Default for Config {
             // and this configuration field will exist as
             // a compatibility item for GNU findutils.
             no_leaf_dirs: false,
+            ignore_readdir_race: false,
             follow: Follow::Never,
+            files0_from: None,
+            prompts_on_stdin: false,
+            threads: 1,
+            strategy: SearchStrategy::Dfs,
+            optimization_level: 1,
+            debug: DebugFlags::default(),
+            debug_help_requested: false,
+            start_time: SystemTime::now(),
         }
     }
 }
 
+impl Config {
+    /// Starts building a [Config], with the same defaults as the command line.
+    #[must_use]
+    pub fn builder() -> ConfigBuilder {
+        ConfigBuilder::default()
+    }
+}
+
+/// Builds a [Config] (see [Config::builder()]).
+#[derive(Default)]
+pub struct ConfigBuilder {
+    config: Config,
+}
+
+impl ConfigBuilder {
+    /// Don't descend into directories on other file systems (-xdev).
+    #[must_use]
+    pub fn same_file_system(mut self, same_file_system: bool) -> Self {
+        self.config.same_file_system = same_file_system;
+        self
+    }
+
+    /// Process each directory's contents before the directory itself (-depth).
+    #[must_use]
+    pub fn depth_first(mut self, depth_first: bool) -> Self {
+        self.config.depth_first = depth_first;
+        self
+    }
+
+    /// Don't process anything shallower than `min_depth` (-mindepth).
+    #[must_use]
+    pub fn min_depth(mut self, min_depth: usize) -> Self {
+        self.config.min_depth = min_depth;
+        self
+    }
+
+    /// Don't descend more than `max_depth` levels below the starting points
+    /// (-maxdepth).
+    #[must_use]
+    pub fn max_depth(mut self, max_depth: usize) -> Self {
+        self.config.max_depth = max_depth;
+        self
+    }
+
+    /// Process directory contents in order of their names (-sorted).
+    #[must_use]
+    pub fn sorted_output(mut self, sorted_output: bool) -> Self {
+        self.config.sorted_output = sorted_output;
+        self
+    }
+
+    /// Measure times from the beginning of today rather than from now
+    /// (-daystart).
+    #[must_use]
+    pub fn today_start(mut self, today_start: bool) -> Self {
+        self.config.today_start = today_start;
+        self
+    }
+
+    /// Don't report errors for files that are deleted after their directory
+    /// is read, only for the starting points (-ignore_readdir_race).
+    #[must_use]
+    pub fn ignore_readdir_race(mut self, ignore_readdir_race: bool) -> Self {
+        self.config.ignore_readdir_race = ignore_readdir_race;
+        self
+    }
+
+    /// Which symlinks to follow (-P, -H or -L).
+    #[must_use]
+    pub fn follow(mut self, follow: Follow) -> Self {
+        self.config.follow = follow;
+        self
+    }
+
+    /// The number of threads to read directories with (-j).  Zero is treated
+    /// as one.  Only depth-first searches ([SearchStrategy::Dfs]) use them.
+    #[must_use]
+    pub fn threads(mut self, threads: usize) -> Self {
+        self.config.threads = threads.max(1);
+        self
+    }
+
+    /// The order to search the directory tree in (-S).
+    #[must_use]
+    pub fn strategy(mut self, strategy: SearchStrategy) -> Self {
+        self.config.strategy = strategy;
+        self
+    }
+
+    /// How much to optimize expressions built with this config (-O), from 0
+    /// (not at all) to 3.
+    #[must_use]
+    pub fn optimization_level(mut self, level: u8) -> Self {
+        self.config.optimization_level = level.min(3);
+        self
+    }
+
+    #[must_use]
+    pub fn build(self) -> Config {
+        self.config
+    }
+}
+
 /// Trait that encapsulates various dependencies (output, clocks, etc.) that we
 /// might want to fake out for unit tests.
 pub trait Dependencies {
     fn get_output(&self) -> &RefCell<dyn Write>;
     fn now(&self) -> SystemTime;
+
+    /// Whether the output is a terminal, in which case unprintable characters
+    /// in file names are quoted.
+    fn output_is_terminal(&self) -> bool {
+        false
+    }
 }
 
 /// Struct that holds the dependencies we use when run as the real executable.
 pub struct StandardDependencies {
     output: Rc<RefCell<dyn Write>>,
     now: SystemTime,
+    output_is_terminal: bool,
 }
 
 impl StandardDependencies {
@@ -66,6 +234,7 @@ impl StandardDependencies {
         Self {
             output: Rc::new(RefCell::new(stdout())),
             now: SystemTime::now(),
+            output_is_terminal: stdout().is_terminal(),
         }
     }
 }
@@ -84,6 +253,10 @@ impl Dependencies for StandardDependencies {
     fn now(&self) -> SystemTime {
         self.now
     }
+
+    fn output_is_terminal(&self) -> bool {
+        self.output_is_terminal
+    }
 }
 
 /// The result of parsing the command-line arguments into useful forms.
@@ -94,19 +267,62 @@ struct ParsedInfo {
 }
 
 /// Function to generate a `ParsedInfo` from the strings supplied on the command-line.
-fn parse_args(args: &[&str]) -> Result<ParsedInfo, Box<dyn Error>> {
+fn parse_args(args: &[&str], deps: &dyn Dependencies) -> Result<ParsedInfo, Box<dyn Error>> {
     let mut paths = vec![];
     let mut i = 0;
-    let mut config = Config::default();
+    let mut config = Config {
+        start_time: deps.now(),
+        ..Config::default()
+    };
 
     while i < args.len() {
         match args[i] {
             "-O0" | "-O1" | "-O2" | "-O3" => {
-                // GNU find optimization level flag (ignored)
+                config.optimization_level = args[i][2..].parse().unwrap();
             }
             "-H" => config.follow = Follow::Roots,
             "-L" => config.follow = Follow::Always,
             "-P" => config.follow = Follow::Never,
+            "-D" => {
+                i += 1;
+                let opts = args.get(i).ok_or("missing argument to -D")?;
+                for opt in opts.split(',') {
+                    match (opt, DebugFlags::parse(opt)) {
+                        ("help", _) => config.debug_help_requested = true,
+                        (_, Some(flags)) => config.debug.insert(flags),
+                        (_, None) => {
+                            return Err(From::from(format!("invalid argument to -D: '{opt}'")))
+                        }
+                    }
+                }
+            }
+            arg if arg.starts_with("-j") => {
+                let threads = if arg.len() > 2 {
+                    &arg[2..]
+                } else {
+                    i += 1;
+                    args.get(i).ok_or("missing argument to -j")?
+                };
+                config.threads = match threads.parse::<usize>() {
+                    Ok(threads) if threads > 0 => threads,
+                    _ => return Err(From::from(format!("invalid argument to -j: '{threads}'"))),
+                };
+            }
+            arg if arg.starts_with("-S") => {
+                let strategy = if arg.len() > 2 {
+                    &arg[2..]
+                } else {
+                    i += 1;
+                    args.get(i).ok_or("missing argument to -S")?
+                };
+                config.strategy = match strategy {
+                    "bfs" => SearchStrategy::Bfs,
+                    "dfs" => SearchStrategy::Dfs,
+                    "ids" => SearchStrategy::Ids,
+                    "eds" => SearchStrategy::Eds,
+                    _ => return Err(From::from(format!("invalid argument to -S: '{strategy}'"))),
+                };
+            }
             "--" => {
                 // End of flags
                 i += 1;
@@ -118,6 +334,10 @@ fn parse_args(args: &[&str]) -> Result<ParsedInfo, Box<dyn Error>> {
         i += 1;
     }
 
+    if config.threads > 1 && config.strategy != SearchStrategy::Dfs {
+        return Err(From::from("-j can only be used with -S dfs"));
+    }
+
     let paths_start = i;
     while i < args.len()
         && (args[i] == "-" || !args[i].starts_with('-'))
@@ -127,10 +347,23 @@ fn parse_args(args: &[&str]) -> Result<ParsedInfo, Box<dyn Error>> {
         paths.push(args[i].to_string());
         i += 1;
     }
-    if i == paths_start {
+    let matcher =
+        matchers::build_top_level_matcher(&args[i..], &mut config).map_err(|e| e.shifted(i))?;
+    if let Some(files0_from) = &config.files0_from {
+        if !paths.is_empty() {
+            return Err(From::from(format!(
+                "extra operand '{}'; file operands cannot be combined with -files0-from",
+                paths[0]
+            )));
+        }
+        if files0_from == "-" && config.prompts_on_stdin {
+            return Err(From::from(
+                "option -files0-from reading from standard input cannot be combined with -ok, -okdir",
+            ));
+        }
+    } else if i == paths_start {
         paths.push(".".to_string());
     }
-    let matcher = matchers::build_top_level_matcher(&args[i..], &mut config)?;
     Ok(ParsedInfo {
         matcher,
         paths,
@@ -138,59 +371,444 @@ fn parse_args(args: &[&str]) -> Result<ParsedInfo, Box<dyn Error>> {
     })
 }
 
-fn process_dir(
-    dir: &str,
+/// Reads the NUL-separated starting points given to -files0-from.
+struct Files0Reader {
+    rd: Box<dyn BufRead>,
+    done: bool,
+}
+
+impl Files0Reader {
+    fn open(path: &str) -> Result<Self, Box<dyn Error>> {
+        let rd: Box<dyn BufRead> = if path == "-" {
+            Box::new(stdin().lock())
+        } else {
+            let file =
+                File::open(path).map_err(|e| format!("cannot open '{path}' for reading: {e}"))?;
+            Box::new(BufReader::new(file))
+        };
+        Ok(Self { rd, done: false })
+    }
+}
+
+impl Iterator for Files0Reader {
+    type Item = io::Result<PathBuf>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.done {
+            return None;
+        }
+
+        let mut name = vec![];
+        match self.rd.read_until(b'\0', &mut name) {
+            Ok(0) => None,
+            Ok(_) => {
+                if name.last() == Some(&b'\0') {
+                    name.pop();
+                }
+                if name.is_empty() {
+                    return Some(Err(io::Error::new(
+                        io::ErrorKind::InvalidInput,
+                        "invalid zero-length file name",
+                    )));
+                }
+
+                #[cfg(unix)]
+                let path = {
+                    use std::os::unix::ffi::OsStringExt;
+                    PathBuf::from(std::ffi::OsString::from_vec(name))
+                };
+                #[cfg(not(unix))]
+                let path = PathBuf::from(String::from_utf8_lossy(&name).into_owned());
+
+                Some(Ok(path))
+            }
+            Err(e) => {
+                self.done = true;
+                Some(Err(e))
+            }
+        }
+    }
+}
+
+/// A walk over the files under a single starting point.
+trait Walk: Iterator<Item = Result<WalkEntry, WalkError>> {
+    /// Stop walking the directory that was most recently entered.
+    fn skip_current_dir(&mut self);
+
+    /// Takes the next directory that everything has been produced from, in
+    /// the order they were finished.  They're finished before the entry
+    /// that was produced after them.
+    fn pop_finished_dir(&mut self) -> Option<PathBuf>;
+}
+
+/// The default, single-threaded walk.
+#[cfg(not(unix))]
+struct SequentialWalk {
+    it: walkdir::IntoIter,
+    follow: Follow,
+    /// The directories above the last entry, outermost first, once they're
+    /// known.
+    dirs: Vec<Option<PathBuf>>,
+    /// Directories that everything has been produced from.
+    finished: VecDeque<PathBuf>,
+    debugger: Option<Arc<Debugger>>,
+}
+
+#[cfg(not(unix))]
+impl SequentialWalk {
+    fn new(dir: &Path, config: &Config, debugger: Option<Arc<Debugger>>) -> Self {
+        let mut walkdir = WalkDir::new(dir)
+            .contents_first(config.depth_first)
+            .max_depth(config.max_depth)
+            .min_depth(config.min_depth)
+            .same_file_system(config.same_file_system)
+            .follow_links(config.follow == Follow::Always)
+            .follow_root_links(config.follow != Follow::Never);
+        if config.sorted_output {
+            walkdir = walkdir.sort_by(|a, b| a.file_name().cmp(b.file_name()));
+        }
+
+        Self {
+            it: walkdir.into_iter(),
+            follow: config.follow,
+            dirs: vec![],
+            finished: VecDeque::new(),
+            debugger,
+        }
+    }
+
+    /// Finishes the directories that are as deep as `depth` or deeper.
+    fn leave_dirs(&mut self, depth: usize) {
+        while self.dirs.len() > depth {
+            if let Some(dir) = self.dirs.pop().unwrap() {
+                self.finished.push_back(dir);
+            }
+        }
+    }
+}
+
+#[cfg(not(unix))]
+impl Iterator for SequentialWalk {
+    type Item = Result<WalkEntry, WalkError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let Some(result) = self.it.next() else {
+            self.leave_dirs(0);
+            return None;
+        };
+
+        // walkdir doesn't say when it leaves a directory, but everything
+        // after it is shallower (or with -depth, is the directory itself).
+        if let Ok(entry) = &result {
+            let depth = entry.depth();
+            self.leave_dirs(depth);
+            if depth > 0 {
+                self.dirs.resize(depth, None);
+                if self.dirs[depth - 1].is_none() {
+                    self.dirs[depth - 1] = entry.path().parent().map(Path::to_path_buf);
+                }
+            }
+        }
+        let entry = WalkEntry::from_walkdir(result, self.follow);
+        Some(entry.map(|entry| entry.with_debugger(self.debugger.clone())))
+    }
+}
+
+#[cfg(not(unix))]
+impl Walk for SequentialWalk {
+    fn skip_current_dir(&mut self) {
+        self.it.skip_current_dir();
+    }
+
+    fn pop_finished_dir(&mut self) -> Option<PathBuf> {
+        self.finished.pop_front()
+    }
+}
+
+/// On Unix, the default walk works relative to open directories.
+#[cfg(unix)]
+type SequentialWalk = fdwalk::FdWalk;
+
+#[cfg(unix)]
+impl Walk for fdwalk::FdWalk {
+    fn skip_current_dir(&mut self) {
+        fdwalk::FdWalk::skip_current_dir(self);
+    }
+
+    fn pop_finished_dir(&mut self) -> Option<PathBuf> {
+        fdwalk::FdWalk::pop_finished_dir(self).map(|(dir, _)| dir)
+    }
+}
+
+#[cfg(unix)]
+impl Walk for search::BreadthFirstWalk {
+    fn skip_current_dir(&mut self) {
+        search::BreadthFirstWalk::skip_current_dir(self);
+    }
+
+    fn pop_finished_dir(&mut self) -> Option<PathBuf> {
+        search::BreadthFirstWalk::pop_finished_dir(self)
+    }
+}
+
+#[cfg(unix)]
+impl Walk for search::DeepeningWalk {
+    fn skip_current_dir(&mut self) {
+        search::DeepeningWalk::skip_current_dir(self);
+    }
+
+    fn pop_finished_dir(&mut self) -> Option<PathBuf> {
+        search::DeepeningWalk::pop_finished_dir(self)
+    }
+}
+
+#[cfg(unix)]
+impl Walk for walker::ParallelWalk {
+    fn skip_current_dir(&mut self) {
+        walker::ParallelWalk::skip_current_dir(self);
+    }
+
+    fn pop_finished_dir(&mut self) -> Option<PathBuf> {
+        walker::ParallelWalk::pop_finished_dir(self)
+    }
+}
+
+/// The threads used to read directories when -j is given.
+#[cfg(unix)]
+type ReadDirPool = walker::ReadDirPool;
+
+/// On other platforms, -j is accepted but the walk is always sequential.
+#[cfg(not(unix))]
+struct ReadDirPool;
+
+#[cfg_attr(not(unix), allow(unused_variables))]
+fn new_read_dir_pool(
     config: &Config,
-    deps: &dyn Dependencies,
-    matcher: &dyn matchers::Matcher,
-    quit: &mut bool,
-) -> i32 {
-    let mut walkdir = WalkDir::new(dir)
-        .contents_first(config.depth_first)
-        .max_depth(config.max_depth)
-        .min_depth(config.min_depth)
-        .same_file_system(config.same_file_system)
-        .follow_links(config.follow == Follow::Always)
-        .follow_root_links(config.follow != Follow::Never);
-    if config.sorted_output {
-        walkdir = walkdir.sort_by(|a, b| a.file_name().cmp(b.file_name()));
-    }
-
-    let mut ret = 0;
-
-    // Slightly yucky loop handling here :-(. See docs for
-    // WalkDirIterator::skip_current_dir for explanation.
-    let mut it = walkdir.into_iter();
-    while let Some(result) = it.next() {
-        match WalkEntry::from_walkdir(result, config.follow) {
-            Err(err) => {
-                ret = 1;
-                writeln!(&mut stderr(), "Error: {err}").unwrap()
+    matcher: &dyn Matcher,
+    debugger: &Option<Arc<Debugger>>,
+) -> Option<ReadDirPool> {
+    #[cfg(unix)]
+    return (config.threads > 1 && config.strategy == SearchStrategy::Dfs).then(|| {
+        // Only fetch metadata ahead of time if the matchers will need it
+        let stat = matcher.cost() >= matchers::Cost::Stat;
+        let options = fdwalk::WalkOptions::new(config, debugger.clone());
+        ReadDirPool::new(config.threads, options, stat)
+    });
+
+    #[cfg(not(unix))]
+    return None;
+}
+
+/// An iterator over the files under some starting points that match an
+/// expression, in the order find would process them.
+///
+/// Walk errors are produced as they're found.  The matcher's side effects
+/// (printing, -exec, -delete, etc.) happen as the iterator is advanced, and
+/// [Matcher::finished()] is called once it's exhausted (including after
+/// -quit), so anything still pending, like -exec ... +, is only run then.
+pub struct FindIter<'a> {
+    paths: Box<dyn Iterator<Item = PathBuf> + 'a>,
+    config: &'a Config,
+    matcher: &'a dyn Matcher,
+    deps: &'a dyn Dependencies,
+    pool: Option<Rc<RefCell<ReadDirPool>>>,
+    walk: Option<Box<dyn Walk>>,
+    /// What -D keeps track of during this run, if anything.
+    debugger: Option<Arc<Debugger>>,
+    exit_code: i32,
+    quit: bool,
+    done: bool,
+}
+
+impl<'a> FindIter<'a> {
+    /// Walks each of `paths` in turn, producing the entries that `matcher`
+    /// matches.
+    pub fn new<I>(
+        paths: I,
+        config: &'a Config,
+        matcher: &'a dyn Matcher,
+        deps: &'a dyn Dependencies,
+    ) -> Self
+    where
+        I: IntoIterator,
+        I::IntoIter: 'a,
+        I::Item: Into<PathBuf> + 'a,
+    {
+        let debugger = (!config.debug.is_empty()).then(|| Arc::new(Debugger::new(config.debug)));
+        let pool = new_read_dir_pool(config, matcher, &debugger);
+        Self {
+            paths: Box::new(paths.into_iter().map(Into::into)),
+            config,
+            matcher,
+            deps,
+            pool: pool.map(|pool| Rc::new(RefCell::new(pool))),
+            walk: None,
+            debugger,
+            exit_code: 0,
+            quit: false,
+            done: false,
+        }
+    }
+
+    /// The exit status find would have so far: 1 after any walk errors, or
+    /// whatever status was set by the matchers (e.g. when -exec ... + fails).
+    #[must_use]
+    pub fn exit_code(&self) -> i32 {
+        self.exit_code
+    }
+
+    fn new_matcher_io(
+        config: &Config,
+        deps: &'a dyn Dependencies,
+        debugger: &Option<Arc<Debugger>>,
+    ) -> MatcherIO<'a> {
+        let mut matcher_io = MatcherIO::new(deps);
+        matcher_io.set_ignore_readdir_race(config.ignore_readdir_race);
+        matcher_io.set_debugger(debugger.clone());
+        matcher_io
+    }
+
+    fn set_exit_code(&mut self, matcher_io: &MatcherIO) {
+        if matcher_io.exit_code() != 0 {
+            self.exit_code = matcher_io.exit_code();
+        }
+    }
+
+    fn start_walk(&self, root: &Path) -> Box<dyn Walk> {
+        let config = self.config;
+        trace(self.config, format_args!("starting at {}", root.display()));
+        #[cfg(unix)]
+        let options = fdwalk::WalkOptions::new(config, self.debugger.clone());
+        match (&self.pool, config.strategy) {
+            #[cfg(unix)]
+            (_, SearchStrategy::Bfs) => Box::new(search::BreadthFirstWalk::new(root, options)),
+            #[cfg(unix)]
+            (_, SearchStrategy::Ids) => Box::new(search::DeepeningWalk::new(root, options, false)),
+            #[cfg(unix)]
+            (_, SearchStrategy::Eds) => Box::new(search::DeepeningWalk::new(root, options, true)),
+            #[cfg(unix)]
+            (Some(pool), _) => Box::new(walker::ParallelWalk::new(root, options, pool.clone())),
+            #[cfg(unix)]
+            _ => Box::new(SequentialWalk::new(root, options)),
+            #[cfg(not(unix))]
+            _ => Box::new(SequentialWalk::new(root, config, self.debugger.clone())),
+        }
+    }
+
+    /// Lets the matcher know about the directories the walk has finished.
+    fn finish_dirs(&mut self) {
+        let Some(walk) = &mut self.walk else {
+            return;
+        };
+        while let Some(dir) = walk.pop_finished_dir() {
+            let mut matcher_io = Self::new_matcher_io(self.config, self.deps, &self.debugger);
+            self.matcher.finished_dir(&dir, &mut matcher_io);
+            if matcher_io.exit_code() != 0 {
+                self.exit_code = matcher_io.exit_code();
             }
-            Ok(entry) => {
-                let mut matcher_io = matchers::MatcherIO::new(deps);
+        }
+    }
+
+    /// Gives matchers like -exec ... + a chance to run any pending commands.
+    fn finish(&mut self) {
+        let mut matcher_io = Self::new_matcher_io(self.config, self.deps, &self.debugger);
+        self.matcher.finished(&mut matcher_io);
+        self.set_exit_code(&matcher_io);
+        self.done = true;
+
+        if let Some(debugger) = self
+            .debugger
+            .as_ref()
+            .filter(|d| d.enabled(DebugOption::Stat))
+        {
+            let calls = debugger.stat_calls();
+            debug::log(DebugOption::Stat, format_args!("{calls} stat() calls"));
+        }
+    }
+}
 
-                matcher.matches(&entry, &mut matcher_io);
-                match matcher_io.exit_code() {
-                    0 => {}
-                    code => ret = code,
+/// Traces the walk, for -D search.
+fn trace(config: &Config, message: std::fmt::Arguments) {
+    if config.debug.contains(DebugOption::Search) {
+        debug::log(DebugOption::Search, message);
+    }
+}
+
+impl Iterator for FindIter<'_> {
+    type Item = Result<WalkEntry, WalkError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        while !self.done {
+            let Some(walk) = &mut self.walk else {
+                match self.paths.next() {
+                    Some(root) if !self.quit => self.walk = Some(self.start_walk(&root)),
+                    _ => self.finish(),
+                }
+                continue;
+            };
+
+            let next = walk.next();
+            self.finish_dirs();
+            let entry = match next {
+                Some(Ok(entry)) => entry,
+                // Files deleted after their directory was read aren't errors
+                // with -ignore_readdir_race, but missing starting points are
+                Some(Err(e))
+                    if self.config.ignore_readdir_race
+                        && e.kind() == io::ErrorKind::NotFound
+                        && e.depth().is_some_and(|depth| depth > 0) =>
+                {
+                    continue;
+                }
+                Some(Err(e)) => {
+                    self.exit_code = 1;
+                    return Some(Err(e));
                 }
-                if matcher_io.should_quit() {
-                    *quit = true;
-                    break;
+                None => {
+                    self.walk = None;
+                    continue;
                 }
-                if matcher_io.should_skip_current_dir() {
-                    it.skip_current_dir();
+            };
+
+            trace(
+                self.config,
+                format_args!(
+                    "visiting {} (depth {})",
+                    entry.path().display(),
+                    entry.depth()
+                ),
+            );
+
+            let mut matcher_io = Self::new_matcher_io(self.config, self.deps, &self.debugger);
+            let matched = self.matcher.matches(&entry, &mut matcher_io);
+            if matcher_io.should_quit() {
+                trace(
+                    self.config,
+                    format_args!("quitting at {}", entry.path().display()),
+                );
+                self.quit = true;
+                self.walk = None;
+            } else if matcher_io.should_skip_current_dir() {
+                trace(
+                    self.config,
+                    format_args!("pruning {}", entry.path().display()),
+                );
+                if let Some(walk) = &mut self.walk {
+                    walk.skip_current_dir();
                 }
             }
+            self.set_exit_code(&matcher_io);
+            if matched {
+                return Some(Ok(entry));
+            }
         }
-    }
 
-    ret
+        None
+    }
 }
 
 fn do_find(args: &[&str], deps: &dyn Dependencies) -> Result<i32, Box<dyn Error>> {
-    let paths_and_matcher = parse_args(args)?;
+    let paths_and_matcher = parse_args(args, deps)?;
     if paths_and_matcher.config.help_requested {
         print_help();
         return Ok(0);
@@ -199,26 +817,38 @@ fn do_find(args: &[&str], deps: &dyn Dependencies) -> Result<i32, Box<dyn Error>
         print_version();
         return Ok(0);
     }
+    if paths_and_matcher.config.debug_help_requested {
+        debug::print_help();
+        return Ok(0);
+    }
 
-    let mut ret = 0;
-    let mut quit = false;
-    for path in paths_and_matcher.paths {
-        let dir_ret = process_dir(
-            &path,
-            &paths_and_matcher.config,
-            deps,
-            &*paths_and_matcher.matcher,
-            &mut quit,
-        );
-        if dir_ret != 0 {
-            ret = dir_ret;
-        }
-        if quit {
-            break;
+    let config = &paths_and_matcher.config;
+
+    // Errors reading -files0-from are reported as the names are needed.
+    let bad_starting_point = Cell::new(false);
+    let starting_points: Box<dyn Iterator<Item = PathBuf>> = match &config.files0_from {
+        Some(files0_from) => Box::new(Files0Reader::open(files0_from)?.filter_map(|path| {
+            path.map_err(|e| {
+                bad_starting_point.set(true);
+                writeln!(&mut stderr(), "Error: {e}").unwrap();
+            })
+            .ok()
+        })),
+        None => Box::new(paths_and_matcher.paths.iter().map(PathBuf::from)),
+    };
+
+    let mut found = FindIter::new(starting_points, config, &*paths_and_matcher.matcher, deps);
+    for result in &mut found {
+        // Matched files have already been handled by the matchers.
+        if let Err(e) = result {
+            writeln!(&mut stderr(), "Error: {e}").unwrap();
         }
     }
 
-    Ok(ret)
+    match found.exit_code() {
+        0 if bad_starting_point.get() => Ok(1),
+        ret => Ok(ret),
+    }
 }
 
 fn print_help() {
@@ -238,8 +868,9 @@ Early alpha implementation. Currently the only expressions supported are
  -regextype type
  -regex pattern
  -iregex pattern
- -type type_char
-    currently type_char can only be f (for file) or d (for directory)
+ -type type_chars
+    a comma-separated list of f (for file), d (for directory), l (for
+    symlink), b, c, p or s
  -size [+-]N[bcwkMG]
  -delete
  -prune
@@ -254,15 +885,44 @@ Early alpha implementation. Currently the only expressions supported are
  -mindepth N
  -d[epth]
  -xdev
+ -ignore_readdir_race
+ -noignore_readdir_race
+    don't report files that are deleted while find is running, except for
+    the starting points
  -ctime [+-]N
  -atime [+-]N
  -mtime [+-]N
+ -Btime [+-]N
+ -Bmin [+-]N
+ -Bnewer path_to_file
+    match on file birth times, where the file system records them
  -perm [-/]{{octal|u=rwx,go=w}}
  -newer path_to_file
  -exec[dir] executable [args] [{{}}] [more args] ;
+ -exec[dir] executable [args] {{}} +
+ -ok[dir] executable [args] [{{}}] [more args] ;
+ -files0-from file
+    read NUL-separated starting points from file, or from standard input if file is -
  -sorted
     a non-standard extension that sorts directory contents by name before
     processing them. Less efficient, but allows for deterministic output.
+ -jN
+    read directories and file metadata using N threads (must come before
+    any paths). Results are still processed in the same order as without -j.
+ -S bfs|dfs|ids|eds
+    search breadth-first, depth-first (the default), by iterative deepening
+    or by exponential deepening (must come before any paths). -j can only
+    be used with dfs. With -depth, ids and eds first walk the whole tree an
+    extra time to find out how deep it is.
+ -D debugopts
+    write debugging information to standard error, for a comma-separated
+    list of: exec, opt, rates, search, stat, tree or all. -D help lists
+    them (must come before any paths).
+ -O0|-O1|-O2|-O3
+    optimize the expression: -O1 (the default) skips options and -true,
+    and tests file names first; -O2 also skips tests that can't change the
+    result; -O3 runs all tests in order of how expensive they are. Side
+    effects are never reordered (must come before any paths).
 "
     );
 }
@@ -281,6 +941,10 @@ pub fn find_main(args: &[&str], deps: &dyn Dependencies) -> i32 {
         Ok(ret) => ret,
         Err(e) => {
             writeln!(&mut stderr(), "Error: {e}").unwrap();
+            // Point at the problem, if it's in the expression
+            if let Some(e) = e.downcast_ref::<ExprError>() {
+                write!(&mut stderr(), "{}", e.highlight(&args[1..])).unwrap();
+            }
             1
         }
     }
@@ -322,114 +986,927 @@ mod tests {
     pub struct FakeDependencies {
         pub output: RefCell<Cursor<Vec<u8>>>,
         now: SystemTime,
+        output_is_terminal: bool,
+    }
+
+    impl<'a> FakeDependencies {
+        pub fn new() -> Self {
+            Self {
+                output: RefCell::new(Cursor::new(Vec::<u8>::new())),
+                now: SystemTime::now(),
+                output_is_terminal: false,
+            }
+        }
+
+        pub fn set_time(&mut self, new_time: SystemTime) {
+            self.now = new_time;
+        }
+
+        /// Pretend that the output is a terminal.
+        pub fn set_output_is_terminal(&mut self, output_is_terminal: bool) {
+            self.output_is_terminal = output_is_terminal;
+        }
+
+        pub fn new_matcher_io(&'a self) -> MatcherIO<'a> {
+            MatcherIO::new(self)
+        }
+
+        pub fn get_output_as_string(&self) -> String {
+            let mut cursor = self.output.borrow_mut();
+            cursor.set_position(0);
+            let mut contents = String::new();
+            cursor.read_to_string(&mut contents).unwrap();
+            contents
+        }
+    }
+
+    impl Dependencies for FakeDependencies {
+        fn get_output(&self) -> &RefCell<dyn Write> {
+            &self.output
+        }
+
+        fn now(&self) -> SystemTime {
+            self.now
+        }
+
+        fn output_is_terminal(&self) -> bool {
+            self.output_is_terminal
+        }
+    }
+
+    fn create_file_link() {
+        #[cfg(unix)]
+        if let Err(e) = symlink("abbbc", "test_data/links/link-f") {
+            assert!(
+                e.kind() == ErrorKind::AlreadyExists,
+                "Failed to create sym link: {e:?}"
+            );
+        }
+        #[cfg(windows)]
+        if let Err(e) = symlink_file("abbbc", "test_data/links/link-f") {
+            assert!(
+                e.kind() == ErrorKind::AlreadyExists,
+                "Failed to create sym link: {:?}",
+                e
+            );
+        }
+    }
+
+    #[test]
+    fn parse_args_handles_single_dash() {
+        // Apparently "-" should be treated as a directory name.
+        let parsed_info =
+            super::parse_args(&["-"], &FakeDependencies::new()).expect("parsing should succeed");
+        assert_eq!(parsed_info.paths, ["-"]);
+    }
+
+    #[test]
+    fn parse_args_bad_flag() {
+        //
+        let result = super::parse_args(&["-asdadsafsfsadcs"], &FakeDependencies::new());
+        if let Err(e) = result {
+            assert_eq!(e.to_string(), "Unrecognized flag: '-asdadsafsfsadcs'");
+        } else {
+            panic!("parse_args should have returned an error");
+        }
+    }
+
+    #[test]
+    fn parse_optimize_flag() {
+        let parsed_info = super::parse_args(&["-O0", ".", "-print"], &FakeDependencies::new())
+            .expect("parsing should succeed");
+        assert_eq!(parsed_info.paths, ["."]);
+        assert_eq!(parsed_info.config.optimization_level, 0);
+
+        let parsed_info =
+            super::parse_args(&["-O3"], &FakeDependencies::new()).expect("parsing should succeed");
+        assert_eq!(parsed_info.config.optimization_level, 3);
+    }
+
+    #[test]
+    fn parse_debug_flag() {
+        let parsed_info = super::parse_args(&["-D", "stat", "."], &FakeDependencies::new())
+            .expect("parsing should succeed");
+        assert!(parsed_info.config.debug.contains(DebugOption::Stat));
+        assert!(!parsed_info.config.debug.contains(DebugOption::Tree));
+        assert_eq!(parsed_info.paths, ["."]);
+
+        let parsed_info =
+            super::parse_args(&["-D", "tree,opt", "-D", "exec"], &FakeDependencies::new())
+                .expect("parsing should succeed");
+        let debug = parsed_info.config.debug;
+        assert!(debug.contains(DebugOption::Tree));
+        assert!(debug.contains(DebugOption::Opt));
+        assert!(debug.contains(DebugOption::Exec));
+        assert!(!debug.contains(DebugOption::Stat));
+
+        let parsed_info = super::parse_args(&["-D", "all"], &FakeDependencies::new())
+            .expect("parsing should succeed");
+        assert!(parsed_info.config.debug.contains(DebugOption::Rates));
+        assert!(parsed_info.config.debug.contains(DebugOption::Search));
+
+        let parsed_info = super::parse_args(&["-D", "help"], &FakeDependencies::new())
+            .expect("parsing should succeed");
+        assert!(parsed_info.config.debug_help_requested);
+        assert_eq!(parsed_info.config.debug, DebugFlags::default());
+
+        assert!(super::parse_args(&["-D", "stat,nonsense"], &FakeDependencies::new()).is_err());
+
+        assert!(super::parse_args(&["-D", "nonsense"], &FakeDependencies::new()).is_err());
+        assert!(super::parse_args(&["-D"], &FakeDependencies::new()).is_err());
+    }
+
+    #[test]
+    fn parse_h_flag() {
+        let parsed_info =
+            super::parse_args(&["-H"], &FakeDependencies::new()).expect("parsing should succeed");
+        assert_eq!(parsed_info.config.follow, Follow::Roots);
+    }
+
+    #[test]
+    fn parse_l_flag() {
+        let parsed_info =
+            super::parse_args(&["-L"], &FakeDependencies::new()).expect("parsing should succeed");
+        assert_eq!(parsed_info.config.follow, Follow::Always);
+    }
+
+    #[test]
+    fn parse_p_flag() {
+        let parsed_info =
+            super::parse_args(&["-P"], &FakeDependencies::new()).expect("parsing should succeed");
+        assert_eq!(parsed_info.config.follow, Follow::Never);
+    }
+
+    #[test]
+    fn parse_flag_then_double_dash() {
+        super::parse_args(&["-P", "--"], &FakeDependencies::new()).expect("parsing should succeed");
+    }
+
+    #[test]
+    fn parse_double_dash_then_flag() {
+        super::parse_args(&["--", "-P"], &FakeDependencies::new())
+            .err()
+            .expect("parsing should fail");
+    }
+
+    #[test]
+    fn parse_j_flag() {
+        let parsed_info = super::parse_args(&["-j4", "."], &FakeDependencies::new())
+            .expect("parsing should succeed");
+        assert_eq!(parsed_info.config.threads, 4);
+        assert_eq!(parsed_info.paths, ["."]);
+
+        let parsed_info = super::parse_args(&["-j", "2", "-L"], &FakeDependencies::new())
+            .expect("parsing should succeed");
+        assert_eq!(parsed_info.config.threads, 2);
+        assert_eq!(parsed_info.config.follow, Follow::Always);
+
+        for args in [&["-j0"][..], &["-jx"], &["-j"]] {
+            super::parse_args(args, &FakeDependencies::new())
+                .err()
+                .expect("parsing should fail");
+        }
+    }
+
+    #[test]
+    #[allow(non_snake_case)]
+    fn parse_S_flag() {
+        let parsed_info = super::parse_args(&["-S", "bfs", "."], &FakeDependencies::new())
+            .expect("parsing should succeed");
+        assert_eq!(parsed_info.config.strategy, SearchStrategy::Bfs);
+        assert_eq!(parsed_info.paths, ["."]);
+
+        let parsed_info = super::parse_args(&["-Sids", "-L"], &FakeDependencies::new())
+            .expect("parsing should succeed");
+        assert_eq!(parsed_info.config.strategy, SearchStrategy::Ids);
+        assert_eq!(parsed_info.config.follow, Follow::Always);
+
+        let parsed_info =
+            super::parse_args(&[], &FakeDependencies::new()).expect("parsing should succeed");
+        assert_eq!(parsed_info.config.strategy, SearchStrategy::Dfs);
+
+        let parsed_info = super::parse_args(&["-j2", "-S", "dfs"], &FakeDependencies::new())
+            .expect("parsing should succeed");
+        assert_eq!(parsed_info.config.strategy, SearchStrategy::Dfs);
+
+        for args in [
+            &["-S", "xyz"][..],
+            &["-S"],
+            &["-j2", "-S", "bfs"],
+            &["-Sids", "-j2"],
+        ] {
+            super::parse_args(args, &FakeDependencies::new())
+                .err()
+                .expect("parsing should fail");
+        }
+    }
+
+    #[test]
+    fn parse_files0_from() {
+        let parsed_info = super::parse_args(
+            &["-files0-from", "list", "-print"],
+            &FakeDependencies::new(),
+        )
+        .expect("parsing should succeed");
+        assert_eq!(parsed_info.config.files0_from.as_deref(), Some("list"));
+        // No implicit "." when the starting points come from a file
+        assert!(parsed_info.paths.is_empty());
+
+        if let Err(e) = super::parse_args(&["-files0-from"], &FakeDependencies::new()) {
+            assert!(e.to_string().contains("missing argument"));
+        } else {
+            panic!("parse_args should have returned an error");
+        }
+    }
+
+    #[test]
+    fn parse_files0_from_with_paths() {
+        if let Err(e) =
+            super::parse_args(&["foo", "-files0-from", "list"], &FakeDependencies::new())
+        {
+            assert!(e
+                .to_string()
+                .contains("cannot be combined with -files0-from"));
+        } else {
+            panic!("parse_args should have returned an error");
+        }
+    }
+
+    #[test]
+    fn parse_files0_from_stdin_with_ok() {
+        for ok in ["-ok", "-okdir"] {
+            if let Err(e) = super::parse_args(
+                &["-files0-from", "-", ok, "rm", "{}", ";"],
+                &FakeDependencies::new(),
+            ) {
+                assert!(e.to_string().contains("cannot be combined with -ok"));
+            } else {
+                panic!("parse_args should have returned an error");
+            }
+
+            super::parse_args(
+                &["-files0-from", "list", ok, "rm", "{}", ";"],
+                &FakeDependencies::new(),
+            )
+            .expect("-ok should work when starting points aren't on stdin");
+        }
+    }
+
+    #[test]
+    fn find_files0_from() {
+        let temp_dir = Builder::new().prefix("find_files0_from").tempdir().unwrap();
+        let list = temp_dir.path().join("list");
+        fs::write(
+            &list,
+            fix_up_slashes("./test_data/simple/subdir\0./test_data/depth/f0"),
+        )
+        .unwrap();
+
+        let deps = FakeDependencies::new();
+        let rc = find_main(
+            &["find", "-files0-from", &list.to_string_lossy(), "-sorted"],
+            &deps,
+        );
+
+        assert_eq!(rc, 0);
+        assert_eq!(
+            deps.get_output_as_string(),
+            fix_up_slashes(
+                "./test_data/simple/subdir\n\
+                 ./test_data/simple/subdir/ABBBC\n\
+                 ./test_data/depth/f0\n"
+            )
+        );
+    }
+
+    #[test]
+    fn find_files0_from_empty_name() {
+        let temp_dir = Builder::new()
+            .prefix("find_files0_from_empty_name")
+            .tempdir()
+            .unwrap();
+        let list = temp_dir.path().join("list");
+        fs::write(
+            &list,
+            fix_up_slashes("./test_data/depth/f0\0\0./test_data/simple/abbbc\0"),
+        )
+        .unwrap();
+
+        let deps = FakeDependencies::new();
+        let rc = find_main(&["find", "-files0-from", &list.to_string_lossy()], &deps);
+
+        // The empty name is diagnosed, but the rest are still searched
+        assert_eq!(rc, 1);
+        assert_eq!(
+            deps.get_output_as_string(),
+            fix_up_slashes("./test_data/depth/f0\n./test_data/simple/abbbc\n")
+        );
+    }
+
+    #[test]
+    fn find_files0_from_missing_file() {
+        let deps = FakeDependencies::new();
+        let rc = find_main(
+            &["find", "-files0-from", "./test_data/does_not_exist"],
+            &deps,
+        );
+
+        assert_eq!(rc, 1);
+        assert_eq!(deps.get_output_as_string(), "");
+    }
+
+    #[test]
+    #[cfg(unix)]
+    fn find_quotes_names_for_terminal() {
+        let temp_dir = Builder::new()
+            .prefix("find_quotes_names_for_terminal")
+            .tempdir()
+            .unwrap();
+        File::create(temp_dir.path().join("a\x1b[2Jb")).unwrap();
+        let temp_dir_path = temp_dir.path().to_string_lossy();
+
+        for (args, expected) in [
+            (&["-print"][..], format!("{temp_dir_path}/a?[2Jb\n")),
+            (
+                &["-printf", "%f %H\\n"],
+                format!("a?[2Jb {temp_dir_path}\n"),
+            ),
+            // -print0 is always left alone
+            (&["-print0"], format!("{temp_dir_path}/a\x1b[2Jb\0")),
+        ] {
+            let mut deps = FakeDependencies::new();
+            deps.set_output_is_terminal(true);
+            let mut find_args = vec!["find", &temp_dir_path, "-type", "f"];
+            find_args.extend(args);
+            let rc = find_main(&find_args, &deps);
+
+            assert_eq!(rc, 0);
+            assert_eq!(deps.get_output_as_string(), expected);
+        }
+
+        // Output that isn't going to a terminal is written as-is
+        let deps = FakeDependencies::new();
+        let rc = find_main(&["find", &temp_dir_path, "-type", "f"], &deps);
+        assert_eq!(rc, 0);
+        assert_eq!(
+            deps.get_output_as_string(),
+            format!("{temp_dir_path}/a\x1b[2Jb\n")
+        );
+    }
+
+    /// Runs find with and without -j, checking that the results are the same.
+    fn assert_parallel_matches_sequential(paths: &[&str], args: &[&str]) {
+        let sequential = FakeDependencies::new();
+        let mut sequential_args = vec!["find"];
+        sequential_args.extend(paths);
+        sequential_args.extend(args);
+        let sequential_rc = find_main(&sequential_args, &sequential);
+
+        let parallel = FakeDependencies::new();
+        let mut parallel_args = vec!["find", "-j4"];
+        parallel_args.extend(paths);
+        parallel_args.extend(args);
+        let parallel_rc = find_main(&parallel_args, &parallel);
+
+        assert_eq!(sequential_rc, parallel_rc, "{args:?}");
+        assert_eq!(
+            sequential.get_output_as_string(),
+            parallel.get_output_as_string(),
+            "{args:?}"
+        );
+    }
+
+    #[test]
+    fn find_parallel() {
+        let paths = ["./test_data/depth", "./test_data/simple"];
+        for args in [
+            &["-sorted"][..],
+            &["-sorted", "-depth"],
+            &["-sorted", "-mindepth", "2"],
+            &["-sorted", "-maxdepth", "1"],
+            &["-sorted", "-mindepth", "1", "-maxdepth", "2", "-depth"],
+            &["-sorted", "-name", "2", "-prune", "-o", "-print"],
+            &["-sorted", "-depth", "-name", "2", "-prune", "-o", "-print"],
+            &["-sorted", "-name", "f1", "-print", "-quit"],
+            &["-sorted", "-type", "f", "-printf", "%d %p %s\\n"],
+            &["-sorted", "-xdev"],
+        ] {
+            assert_parallel_matches_sequential(&paths, args);
+        }
+
+        // Missing starting points
+        assert_parallel_matches_sequential(
+            &["./test_data/does_not_exist", "./test_data/simple"],
+            &["-sorted"],
+        );
+    }
+
+    #[test]
+    #[cfg(unix)]
+    fn find_parallel_symlinks() {
+        let temp_dir = Builder::new()
+            .prefix("find_parallel_symlinks")
+            .tempdir()
+            .unwrap();
+        let temp_dir_path = temp_dir.path().to_string_lossy();
+        fs::create_dir_all(temp_dir.path().join("a/b")).unwrap();
+        File::create(temp_dir.path().join("a/b/file")).unwrap();
+        symlink("../..", temp_dir.path().join("a/b/loop")).unwrap();
+        symlink("a/b", temp_dir.path().join("link")).unwrap();
+        symlink("missing", temp_dir.path().join("broken")).unwrap();
+
+        for follow in ["-P", "-H", "-L"] {
+            let sequential = FakeDependencies::new();
+            let sequential_rc =
+                find_main(&["find", follow, &temp_dir_path, "-sorted"], &sequential);
+            let parallel = FakeDependencies::new();
+            let parallel_rc = find_main(
+                &["find", "-j3", follow, &temp_dir_path, "-sorted"],
+                &parallel,
+            );
+
+            assert_eq!(sequential_rc, parallel_rc, "{follow}");
+            assert_eq!(
+                sequential.get_output_as_string(),
+                parallel.get_output_as_string(),
+                "{follow}"
+            );
+        }
+    }
+
+    /// Runs find with each -S strategy, checking that the same files are found
+    /// as with the default depth-first search (in any order).
+    #[cfg(unix)]
+    fn assert_strategies_match_dfs(paths: &[&str], args: &[&str]) {
+        let run = |strategy| {
+            let deps = FakeDependencies::new();
+            let mut argv = vec!["find", "-S", strategy];
+            argv.extend(paths);
+            argv.extend(args);
+            let rc = find_main(&argv, &deps);
+            let mut lines: Vec<String> = deps
+                .get_output_as_string()
+                .lines()
+                .map(String::from)
+                .collect();
+            lines.sort();
+            (rc, lines)
+        };
+
+        let expected = run("dfs");
+        for strategy in ["bfs", "ids", "eds"] {
+            assert_eq!(run(strategy), expected, "-S {strategy} {args:?}");
+        }
     }
 
-    impl<'a> FakeDependencies {
-        pub fn new() -> Self {
-            Self {
-                output: RefCell::new(Cursor::new(Vec::<u8>::new())),
-                now: SystemTime::now(),
-            }
+    #[test]
+    #[cfg(unix)]
+    fn find_search_strategies() {
+        let paths = ["./test_data/depth", "./test_data/simple"];
+        for args in [
+            &[][..],
+            &["-depth"],
+            &["-mindepth", "2"],
+            &["-maxdepth", "1"],
+            &["-mindepth", "1", "-maxdepth", "2", "-depth"],
+            &["-name", "2", "-prune", "-o", "-print"],
+            &["-type", "f", "-printf", "%d %p %s\\n"],
+            &["-xdev"],
+        ] {
+            assert_strategies_match_dfs(&paths, args);
         }
 
-        pub fn set_time(&mut self, new_time: SystemTime) {
-            self.now = new_time;
-        }
+        assert_strategies_match_dfs(&["./test_data/does_not_exist", "./test_data/simple"], &[]);
+    }
 
-        pub fn new_matcher_io(&'a self) -> MatcherIO<'a> {
-            MatcherIO::new(self)
+    #[test]
+    #[cfg(unix)]
+    fn find_breadth_first() {
+        for strategy in ["bfs", "ids"] {
+            let deps = FakeDependencies::new();
+            let rc = find_main(
+                &["find", "-S", strategy, "./test_data/depth", "-sorted"],
+                &deps,
+            );
+
+            assert_eq!(rc, 0);
+            assert_eq!(
+                deps.get_output_as_string(),
+                "./test_data/depth\n\
+                 ./test_data/depth/1\n\
+                 ./test_data/depth/f0\n\
+                 ./test_data/depth/1/2\n\
+                 ./test_data/depth/1/f1\n\
+                 ./test_data/depth/1/2/3\n\
+                 ./test_data/depth/1/2/f2\n\
+                 ./test_data/depth/1/2/3/f3\n",
+                "-S {strategy}"
+            );
         }
 
-        pub fn get_output_as_string(&self) -> String {
-            let mut cursor = self.output.borrow_mut();
-            cursor.set_position(0);
-            let mut contents = String::new();
-            cursor.read_to_string(&mut contents).unwrap();
-            contents
+        // With -depth, directories still come after their contents.
+        let deps = FakeDependencies::new();
+        let rc = find_main(
+            &[
+                "find",
+                "-S",
+                "bfs",
+                "./test_data/depth",
+                "-sorted",
+                "-depth",
+            ],
+            &deps,
+        );
+
+        assert_eq!(rc, 0);
+        assert_eq!(
+            deps.get_output_as_string(),
+            "./test_data/depth/f0\n\
+             ./test_data/depth/1/f1\n\
+             ./test_data/depth/1/2/f2\n\
+             ./test_data/depth/1/2/3/f3\n\
+             ./test_data/depth/1/2/3\n\
+             ./test_data/depth/1/2\n\
+             ./test_data/depth/1\n\
+             ./test_data/depth\n"
+        );
+    }
+
+    #[test]
+    #[cfg(unix)]
+    fn find_deepening_depth_first() {
+        // With -depth, the deepest level is searched first, so directories
+        // still come after their contents.
+        for strategy in ["ids", "eds"] {
+            let deps = FakeDependencies::new();
+            let rc = find_main(
+                &[
+                    "find",
+                    "-S",
+                    strategy,
+                    "./test_data/depth",
+                    "-sorted",
+                    "-depth",
+                ],
+                &deps,
+            );
+
+            assert_eq!(rc, 0);
+            assert_eq!(
+                deps.get_output_as_string(),
+                "./test_data/depth/1/2/3/f3\n\
+                 ./test_data/depth/1/2/3\n\
+                 ./test_data/depth/1/2/f2\n\
+                 ./test_data/depth/1/2\n\
+                 ./test_data/depth/1/f1\n\
+                 ./test_data/depth/1\n\
+                 ./test_data/depth/f0\n\
+                 ./test_data/depth\n",
+                "-S {strategy}"
+            );
         }
     }
 
-    impl Dependencies for FakeDependencies {
-        fn get_output(&self) -> &RefCell<dyn Write> {
-            &self.output
+    #[test]
+    #[cfg(unix)]
+    fn find_parallel_wide() {
+        // More subdirectories than the threads read ahead of time
+        let temp_dir = Builder::new().prefix("find_wide").tempdir().unwrap();
+        let temp_dir_path = temp_dir.path().to_string_lossy();
+        for i in 0..100 {
+            let dir = temp_dir.path().join(format!("d{i:03}"));
+            fs::create_dir_all(dir.join("sub")).unwrap();
+            File::create(dir.join("sub").join("file")).unwrap();
         }
 
-        fn now(&self) -> SystemTime {
-            self.now
+        let run = |threads, args: &[&str]| {
+            let deps = FakeDependencies::new();
+            let mut argv = vec!["find", threads, &temp_dir_path, "-sorted"];
+            argv.extend_from_slice(args);
+            assert_eq!(find_main(&argv, &deps), 0, "{threads} {args:?}");
+            deps.get_output_as_string()
+        };
+        for args in [
+            &[][..],
+            &["-name", "sub", "-prune"],
+            &["-name", "file", "-quit"],
+        ] {
+            assert_eq!(run("-j2", args), run("-j1", args), "{args:?}");
         }
     }
 
-    fn create_file_link() {
-        #[cfg(unix)]
-        if let Err(e) = symlink("abbbc", "test_data/links/link-f") {
-            assert!(
-                e.kind() == ErrorKind::AlreadyExists,
-                "Failed to create sym link: {e:?}"
-            );
+    #[test]
+    fn find_prune_at_max_depth() {
+        // -prune on a directory that wouldn't be descended into anyway
+        // mustn't skip the rest of its parent.
+        let mut strategies = vec![&["-j1"][..], &["-j4"]];
+        if cfg!(unix) {
+            strategies.extend([&["-S", "bfs"][..], &["-S", "ids"], &["-S", "eds"]]);
         }
-        #[cfg(windows)]
-        if let Err(e) = symlink_file("abbbc", "test_data/links/link-f") {
-            assert!(
-                e.kind() == ErrorKind::AlreadyExists,
-                "Failed to create sym link: {:?}",
-                e
+        let path = fix_up_slashes("./test_data/depth");
+        for strategy in strategies {
+            let deps = FakeDependencies::new();
+            let mut argv = vec!["find"];
+            argv.extend(strategy);
+            argv.extend([
+                &path,
+                "-sorted",
+                "-maxdepth",
+                "1",
+                "-name",
+                "1",
+                "-prune",
+                "-o",
+                "-print",
+            ]);
+            let rc = find_main(&argv, &deps);
+
+            assert_eq!(rc, 0);
+            assert_eq!(
+                deps.get_output_as_string(),
+                fix_up_slashes("./test_data/depth\n./test_data/depth/f0\n"),
+                "{strategy:?}"
             );
         }
     }
 
-    #[test]
-    fn parse_args_handles_single_dash() {
-        // Apparently "-" should be treated as a directory name.
-        let parsed_info = super::parse_args(&["-"]).expect("parsing should succeed");
-        assert_eq!(parsed_info.paths, ["-"]);
+    /// Creates `levels` nested directories under `root`, each containing a
+    /// file named "file", without ever using the full (too long) path.
+    #[cfg(unix)]
+    fn create_deep_tree(root: &Path, levels: usize) {
+        use nix::fcntl::{open, openat, OFlag};
+        use nix::sys::stat::{mkdirat, Mode};
+        use nix::unistd::close;
+
+        let name = "d".repeat(100);
+        let mode = Mode::from_bits_truncate(0o755);
+        let mut dir = open(root, OFlag::O_RDONLY | OFlag::O_DIRECTORY, Mode::empty()).unwrap();
+        for _ in 0..levels {
+            let flags = OFlag::O_CREAT | OFlag::O_WRONLY;
+            close(openat(Some(dir), "file", flags, mode).unwrap()).unwrap();
+            mkdirat(Some(dir), name.as_str(), mode).unwrap();
+            let subdir = openat(Some(dir), name.as_str(), OFlag::O_RDONLY, Mode::empty()).unwrap();
+            close(dir).unwrap();
+            dir = subdir;
+        }
+        close(dir).unwrap();
     }
 
     #[test]
-    fn parse_args_bad_flag() {
-        //
-        let result = super::parse_args(&["-asdadsafsfsadcs"]);
-        if let Err(e) = result {
-            assert_eq!(e.to_string(), "Unrecognized flag: '-asdadsafsfsadcs'");
-        } else {
-            panic!("parse_args should have returned an error");
+    #[cfg(unix)]
+    fn find_deeper_than_path_max() {
+        let strategies: [&[&str]; 5] = [
+            &["-S", "dfs"],
+            &["-S", "bfs"],
+            &["-S", "ids"],
+            &["-S", "eds"],
+            &["-j4"],
+        ];
+        for strategy in strategies {
+            let temp_dir = Builder::new().prefix("find_deep").tempdir().unwrap();
+            let temp_dir_path = temp_dir.path().to_string_lossy();
+            // Over 10 KiB of path, and more directories than are kept open at once.
+            create_deep_tree(temp_dir.path(), 100);
+
+            let args: [&[&str]; 4] = [
+                &["-name", "file", "-type", "f"],
+                &["-mindepth", "1", "-type", "d"],
+                &["-name", "file", "-depth", "-size", "0"],
+                &[
+                    "-name", "file", "-execdir", "test", "-f", "{}", ";", "-print",
+                ],
+            ];
+            for args in args {
+                let deps = FakeDependencies::new();
+                let mut argv = vec!["find"];
+                argv.extend_from_slice(strategy);
+                argv.push(&temp_dir_path);
+                argv.extend_from_slice(args);
+                let rc = find_main(&argv, &deps);
+
+                assert_eq!(rc, 0, "{strategy:?} {args:?}");
+                assert_eq!(
+                    deps.get_output_as_string().lines().count(),
+                    100,
+                    "{strategy:?} {args:?}"
+                );
+            }
+
+            let deps = FakeDependencies::new();
+            let mut argv = vec!["find"];
+            argv.extend_from_slice(strategy);
+            argv.extend([&*temp_dir_path, "-mindepth", "1", "-delete"]);
+            let rc = find_main(&argv, &deps);
+            assert_eq!(rc, 0, "{strategy:?}");
+            assert_eq!(fs::read_dir(temp_dir.path()).unwrap().count(), 0);
         }
     }
 
+    /// Collects the paths produced by a [FindIter], failing on any errors.
+    fn find_iter_paths(found: FindIter) -> Vec<String> {
+        found
+            .map(|entry| entry.unwrap().path().to_string_lossy().into_owned())
+            .collect()
+    }
+
     #[test]
-    fn parse_optimize_flag() {
-        let parsed_info =
-            super::parse_args(&["-O0", ".", "-print"]).expect("parsing should succeed");
-        assert_eq!(parsed_info.paths, ["."]);
+    fn find_iter() {
+        let mut config = Config::builder().sorted_output(true).build();
+        let matcher = matchers::build_matcher(&["-name", "f*"], &mut config).unwrap();
+        let deps = FakeDependencies::new();
+        let found = FindIter::new(
+            [fix_up_slashes("./test_data/depth")],
+            &config,
+            &*matcher,
+            &deps,
+        );
+
+        assert_eq!(
+            find_iter_paths(found),
+            [
+                "./test_data/depth/1/2/3/f3",
+                "./test_data/depth/1/2/f2",
+                "./test_data/depth/1/f1",
+                "./test_data/depth/f0",
+            ]
+            .map(fix_up_slashes)
+        );
+        // There's no implicit -print
+        assert_eq!(deps.get_output_as_string(), "");
     }
 
     #[test]
-    fn parse_h_flag() {
-        let parsed_info = super::parse_args(&["-H"]).expect("parsing should succeed");
-        assert_eq!(parsed_info.config.follow, Follow::Roots);
+    fn find_iter_config_builder() {
+        let mut config = Config::builder()
+            .sorted_output(true)
+            .depth_first(true)
+            .min_depth(1)
+            .build();
+        // Options in the expression still apply
+        let matcher = matchers::build_matcher(&["-maxdepth", "2"], &mut config).unwrap();
+        let deps = FakeDependencies::new();
+        let found = FindIter::new(
+            [fix_up_slashes("./test_data/depth")],
+            &config,
+            &*matcher,
+            &deps,
+        );
+
+        assert_eq!(
+            find_iter_paths(found),
+            [
+                "./test_data/depth/1/2",
+                "./test_data/depth/1/f1",
+                "./test_data/depth/1",
+                "./test_data/depth/f0",
+            ]
+            .map(fix_up_slashes)
+        );
     }
 
     #[test]
-    fn parse_l_flag() {
-        let parsed_info = super::parse_args(&["-L"]).expect("parsing should succeed");
-        assert_eq!(parsed_info.config.follow, Follow::Always);
+    fn find_iter_counts_stat_calls_per_run() {
+        let deps = FakeDependencies::new();
+        let args = ["-D", "stat", "./test_data/simple", "-size", "-1k"];
+        let parsed = parse_args(&args, &deps).unwrap();
+        let stat_calls = || {
+            let mut found = FindIter::new(
+                parsed.paths.iter().map(PathBuf::from),
+                &parsed.config,
+                &*parsed.matcher,
+                &deps,
+            );
+            for result in &mut found {
+                result.unwrap();
+            }
+            found.debugger.as_ref().unwrap().stat_calls()
+        };
+
+        let calls = stat_calls();
+        assert!(calls > 0);
+        // Each run starts counting from zero
+        assert_eq!(stat_calls(), calls);
     }
 
     #[test]
-    fn parse_p_flag() {
-        let parsed_info = super::parse_args(&["-P"]).expect("parsing should succeed");
-        assert_eq!(parsed_info.config.follow, Follow::Never);
+    fn find_iter_debug_flags_per_run() {
+        let deps = FakeDependencies::new();
+        let debugged = parse_args(&["-D", "exec", "./test_data/simple"], &deps).unwrap();
+        let found = FindIter::new(
+            debugged.paths.iter().map(PathBuf::from),
+            &debugged.config,
+            &*debugged.matcher,
+            &deps,
+        );
+        let matcher_io = FindIter::new_matcher_io(found.config, found.deps, &found.debugger);
+        assert!(matcher_io.debug_enabled(DebugOption::Exec));
+        assert!(!matcher_io.debug_enabled(DebugOption::Stat));
+
+        // A later run without -D doesn't pick up the earlier one's flags
+        let quiet = parse_args(&["./test_data/simple"], &deps).unwrap();
+        let found = FindIter::new(
+            quiet.paths.iter().map(PathBuf::from),
+            &quiet.config,
+            &*quiet.matcher,
+            &deps,
+        );
+        assert!(found.debugger.is_none());
+        let matcher_io = FindIter::new_matcher_io(found.config, found.deps, &found.debugger);
+        assert!(!matcher_io.debug_enabled(DebugOption::Exec));
     }
 
     #[test]
-    fn parse_flag_then_double_dash() {
-        super::parse_args(&["-P", "--"]).expect("parsing should succeed");
+    fn find_iter_errors_and_quit() {
+        let mut config = Config::builder().sorted_output(true).build();
+        let matcher = matchers::build_matcher(&["-print", "-quit"], &mut config).unwrap();
+        let deps = FakeDependencies::new();
+        let mut found = FindIter::new(
+            [
+                fix_up_slashes("./test_data/does_not_exist"),
+                fix_up_slashes("./test_data/simple"),
+                fix_up_slashes("./test_data/depth"),
+            ],
+            &config,
+            &*matcher,
+            &deps,
+        );
+
+        let e = found.next().unwrap().unwrap_err();
+        assert!(e.is_not_found());
+        assert_eq!(found.exit_code(), 1);
+        let entry = found.next().unwrap().unwrap();
+        assert_eq!(
+            entry.path(),
+            Path::new(&fix_up_slashes("./test_data/simple"))
+        );
+        // -quit stops the walk, including the remaining starting points
+        assert!(found.next().is_none());
+        assert!(found.next().is_none());
+        assert_eq!(
+            deps.get_output_as_string(),
+            fix_up_slashes("./test_data/simple\n")
+        );
+    }
+
+    /// Logs the files a [FindIter] matches, and the directories it finishes.
+    #[derive(Default)]
+    struct LogMatcher(RefCell<Vec<String>>);
+
+    impl Matcher for LogMatcher {
+        fn matches(&self, file_info: &WalkEntry, _: &mut MatcherIO) -> bool {
+            let line = format!("match {}", file_info.path().display());
+            self.0.borrow_mut().push(line);
+            true
+        }
+
+        fn finished_dir(&self, dir: &Path, _: &mut MatcherIO) {
+            self.0
+                .borrow_mut()
+                .push(format!("finish {}", dir.display()));
+        }
     }
 
     #[test]
-    fn parse_double_dash_then_flag() {
-        super::parse_args(&["--", "-P"])
-            .err()
-            .expect("parsing should fail");
+    fn find_iter_finished_dir() {
+        let strategies = [
+            (1, SearchStrategy::Dfs),
+            (2, SearchStrategy::Dfs),
+            (1, SearchStrategy::Bfs),
+            (1, SearchStrategy::Ids),
+            (1, SearchStrategy::Eds),
+        ];
+        let dirs =
+            ["", "/1", "/1/2", "/1/2/3"].map(|d| fix_up_slashes(&format!("./test_data/depth{d}")));
+
+        for ((threads, strategy), depth_first) in
+            strategies.into_iter().flat_map(|s| [(s, false), (s, true)])
+        {
+            let config = Config::builder()
+                .sorted_output(true)
+                .threads(threads)
+                .strategy(strategy)
+                .depth_first(depth_first)
+                .build();
+            let matcher = LogMatcher::default();
+            let deps = FakeDependencies::new();
+            let found = FindIter::new([&dirs[0]], &config, &matcher, &deps);
+            assert_eq!(find_iter_paths(found).len(), 8);
+
+            // Each directory is finished once, after everything in it, and
+            // not just because something in a subdirectory came next.
+            let log = matcher.0.take();
+            let context = format!("-j{threads} {strategy:?} depth_first={depth_first}: {log:#?}");
+            for dir in &dirs {
+                let finish = format!("finish {dir}");
+                let finished: Vec<_> = (0..log.len()).filter(|&i| log[i] == finish).collect();
+                assert_eq!(finished.len(), 1, "{dir} {context}");
+                for line in &log[finished[0]..] {
+                    let path = line.strip_prefix("match ").map(Path::new);
+                    let in_dir = path.and_then(Path::parent) == Some(Path::new(dir));
+                    assert!(!in_dir, "{dir} {context}");
+                }
+            }
+        }
     }
 
     #[test]
@@ -921,8 +2398,9 @@ mod tests {
     #[test]
     fn test_find_newer_xy_all_args() {
         // 1. The t parameter is not allowed at the X position.
-        // 2. Current Linux filesystem do not support Birthed Time queries,
-        //    so the B parameter will be excluded in linux.
+        // 2. Not every Linux filesystem supports Birthed Time queries,
+        //    so the B parameter will be excluded in linux, and tested
+        //    separately.
         #[cfg(target_os = "linux")]
         let x_options = ["a", "c", "m"];
         #[cfg(not(target_os = "linux"))]
@@ -966,23 +2444,49 @@ mod tests {
     }
 
     #[test]
-    #[cfg(target_os = "linux")]
-    fn test_find_newer_xy_have_not_birthed_time_filesystem() {
-        let y_options = ["a", "c", "m"];
-        for &y in &y_options {
-            let arg = &format!("-newerB{y}").to_string();
+    fn test_find_newer_xy_birth_time() {
+        let reference = "./test_data/simple/subdir/ABBBC";
+        let has_birth_time = fs::metadata(reference).unwrap().created().is_ok();
+
+        // Files without a birth time don't match, with a warning
+        for arg in ["-newerBa", "-newerBc", "-newerBm", "-Bnewer"] {
             let deps = FakeDependencies::new();
             let rc = find_main(
-                &[
-                    "find",
-                    "./test_data/simple/subdir",
-                    arg,
-                    "./test_data/simple/subdir/ABBBC",
-                ],
+                &["find", "./test_data/simple/subdir", arg, reference],
                 &deps,
             );
 
-            assert_eq!(rc, 1);
+            assert_eq!(rc, 0);
+            if !has_birth_time {
+                assert_eq!(deps.get_output_as_string(), "");
+            }
+        }
+
+        // Nothing in /proc has one, whatever file system the tests run on
+        #[cfg(target_os = "linux")]
+        for args in [
+            ["-newerBm", reference],
+            ["-Bnewer", reference],
+            ["-newerBt", "@0"],
+            ["-Bmin", "+0"],
+            ["-Btime", "+0"],
+        ] {
+            let deps = FakeDependencies::new();
+            let rc = find_main(&["find", "/proc/self/status", args[0], args[1]], &deps);
+
+            assert_eq!(rc, 0);
+            assert_eq!(deps.get_output_as_string(), "");
+        }
+
+        // But a reference file without one is an error
+        for arg in ["-neweraB", "-newercB", "-newermB"] {
+            let deps = FakeDependencies::new();
+            let rc = find_main(
+                &["find", "./test_data/simple/subdir", arg, reference],
+                &deps,
+            );
+
+            assert_eq!(rc, if has_birth_time { 0 } else { 1 });
         }
     }
 
@@ -1069,6 +2573,76 @@ mod tests {
         }
     }
 
+    #[test]
+    fn test_find_newer_xy_relative_time() {
+        let file_time = fs::metadata("./test_data/simple/subdir/ABBBC")
+            .unwrap()
+            .modified()
+            .unwrap();
+        let hours = |n: u64| Duration::from_secs(n * 60 * 60);
+        let seconds = file_time
+            .duration_since(SystemTime::UNIX_EPOCH)
+            .unwrap()
+            .as_secs();
+
+        // Relative times count from the time find started
+        let cases = [
+            (file_time + hours(1), "2 hours ago".to_string(), true),
+            (file_time + hours(3), "2 hours ago".to_string(), false),
+            (file_time + hours(24), "25 hours ago".to_string(), true),
+            (file_time + hours(23), "yesterday".to_string(), true),
+            (file_time + hours(25), "yesterday".to_string(), false),
+            (file_time - hours(49), "2 days hence".to_string(), true),
+            (file_time - hours(47), "2 days hence".to_string(), false),
+            (file_time + hours(6 * 24), "last week".to_string(), true),
+            (file_time + hours(8 * 24), "last week".to_string(), false),
+            // Timestamps don't depend on the time at all
+            (SystemTime::UNIX_EPOCH, format!("@{}", seconds - 1), true),
+            (SystemTime::UNIX_EPOCH, format!("@{}", seconds + 1), false),
+        ];
+        for (now, time, matches) in cases {
+            let mut deps = FakeDependencies::new();
+            deps.set_time(now);
+            let rc = find_main(
+                &[
+                    "find",
+                    "./test_data/simple/subdir",
+                    "-type",
+                    "f",
+                    "-newermt",
+                    &time,
+                ],
+                &deps,
+            );
+
+            assert_eq!(rc, 0);
+            let expected = if matches {
+                fix_up_slashes("./test_data/simple/subdir/ABBBC\n")
+            } else {
+                String::new()
+            };
+            assert_eq!(deps.get_output_as_string(), expected, "{time}");
+        }
+    }
+
+    #[test]
+    fn test_find_newer_xt_checked_against_start_time() {
+        // Whether February 29th exists depends on the year find started in
+        let leap_year = SystemTime::UNIX_EPOCH + Duration::from_secs(1_717_243_200); // 2024-06-01
+        let common_year = SystemTime::UNIX_EPOCH + Duration::from_secs(1_685_620_800); // 2023-06-01
+        let args = ["-newermt", "feb 29"];
+
+        let mut deps = FakeDependencies::new();
+        deps.set_time(leap_year);
+        assert!(super::parse_args(&args, &deps).is_ok());
+
+        deps.set_time(common_year);
+        let Err(e) = super::parse_args(&args, &deps) else {
+            panic!("parse_args should have returned an error");
+        };
+        assert!(e.to_string().contains("as a date or time"));
+    }
+
     #[test]
     #[cfg(target_os = "linux")]
     fn test_no_permission_file_error() {
@@ -1279,6 +2853,33 @@ mod tests {
         assert_eq!(rc, 0);
     }
 
+    #[test]
+    fn test_ignore_readdir_race() {
+        let deps = FakeDependencies::new();
+        let rc = find_main(
+            &[
+                "find",
+                &fix_up_slashes("./test_data/simple/subdir"),
+                "-ignore_readdir_race",
+            ],
+            &deps,
+        );
+        assert_eq!(rc, 0);
+
+        // Missing starting points are still errors
+        let deps = FakeDependencies::new();
+        let rc = find_main(
+            &[
+                "find",
+                &fix_up_slashes("./test_data/simple/missing"),
+                "-ignore_readdir_race",
+            ],
+            &deps,
+        );
+        assert_eq!(rc, 1);
+        assert_eq!(deps.get_output_as_string(), "");
+    }
+
     #[test]
     fn find_maxdepth_and() {
         let deps = FakeDependencies::new();
@@ -0,0 +1,608 @@
+// This file is part of the uutils findutils package.
+//
+// For the full copyright and license information, please view the LICENSE
+// file that was distributed with this source code.
+
+//! The search strategies other than plain depth-first search (-S), built on
+//! the same file-descriptor-relative primitives as [FdWalk].
+
+use std::cell::{Cell, RefCell};
+use std::collections::{HashSet, VecDeque};
+use std::path::{Path, PathBuf};
+use std::rc::Rc;
+use std::vec;
+
+use nix::dir::Dir;
+
+use super::fdwalk::{
+    dir_id, loop_error, open_dir, produce, read_dir, Child, Deferred, FdWalk, Root, Start,
+    WalkOptions, MAX_OPEN_DIRS,
+};
+use super::matchers::{DirFd, WalkEntry, WalkError};
+
+/// A directory waiting to be read by a breadth-first walk.
+struct Node {
+    path: PathBuf,
+    /// The depth of the directory itself.
+    depth: usize,
+    /// The directory's device and inode numbers, if known.  They're only
+    /// looked up when needed, e.g. to make sure a directory that was closed
+    /// is still the same when it's re-opened.
+    id: Cell<Option<(u64, u64)>>,
+    /// Whether the directory was reached through a symlink.
+    followed: bool,
+    parent: Option<Rc<Node>>,
+    /// The open directory, kept while there are subdirectories to open
+    /// relative to it, or with -depth, to produce (as long as not too many
+    /// directories are open).  Re-opened through its parent when needed.
+    dir: RefCell<Option<Rc<DirFd>>>,
+    /// The number of subdirectories that haven't been opened yet, plus one
+    /// while the directory itself is still being read.
+    unopened: Cell<usize>,
+    /// The number of subdirectories that haven't been finished yet, plus one
+    /// while the directory itself is still being read.
+    unfinished: Cell<usize>,
+    /// With -depth, the directory itself, which comes after everything in it.
+    deferred: RefCell<Option<Deferred>>,
+}
+
+impl Node {
+    fn new(path: PathBuf, depth: usize, id: Option<(u64, u64)>, followed: bool) -> Self {
+        Self {
+            path,
+            depth,
+            id: Cell::new(id),
+            followed,
+            parent: None,
+            dir: RefCell::new(None),
+            unopened: Cell::new(1),
+            unfinished: Cell::new(1),
+            deferred: RefCell::new(None),
+        }
+    }
+
+    /// Iterates over this directory and the ones above it.
+    fn ancestors(self: &Rc<Self>) -> impl Iterator<Item = &Node> {
+        std::iter::successors(Some(&**self), |node| node.parent.as_deref())
+    }
+}
+
+/// The directory a breadth-first walk is currently reading.
+struct Current {
+    node: Rc<Node>,
+    dir: Option<Rc<DirFd>>,
+    entries: vec::IntoIter<Result<Child, WalkError>>,
+}
+
+/// Walks a single starting point breadth-first (-S bfs), producing everything
+/// at depth N before anything at depth N+1.  With -depth, a directory is
+/// produced once everything inside it has been.
+pub struct BreadthFirstWalk {
+    root: Option<PathBuf>,
+    /// The starting point, if it's a directory that has been opened but not
+    /// read yet.
+    root_dir: Option<Dir>,
+    root_dev: u64,
+    options: WalkOptions,
+    queue: VecDeque<Rc<Node>>,
+    current: Option<Current>,
+    /// With -depth, directories that are ready to be produced, each once
+    /// everything in it has been.
+    ready: VecDeque<(PathBuf, WalkEntry)>,
+    /// The number of directories kept open in [Node::dir].
+    open_dirs: usize,
+    /// Whether the last thing produced was a directory that was queued.
+    entered: bool,
+    /// Directories that have been finished.
+    finished: VecDeque<PathBuf>,
+}
+
+impl BreadthFirstWalk {
+    pub fn new(root: &Path, options: WalkOptions) -> Self {
+        Self {
+            root: Some(root.to_path_buf()),
+            root_dir: None,
+            root_dev: 0,
+            options,
+            queue: VecDeque::new(),
+            current: None,
+            ready: VecDeque::new(),
+            open_dirs: 0,
+            entered: false,
+            finished: VecDeque::new(),
+        }
+    }
+
+    /// Takes the next directory that the walk has produced everything in
+    /// (see [FdWalk::pop_finished_dir()]).
+    pub fn pop_finished_dir(&mut self) -> Option<PathBuf> {
+        self.finished.pop_front()
+    }
+
+    /// Doesn't descend into the directory that was just produced (-prune).
+    pub fn skip_current_dir(&mut self) {
+        if !std::mem::take(&mut self.entered) {
+            return;
+        }
+        // The directory was the last thing queued.
+        if let Some(node) = self.queue.pop_back() {
+            if let Some(parent) = &node.parent {
+                parent.unopened.set(parent.unopened.get() - 1);
+                parent.unfinished.set(parent.unfinished.get() - 1);
+            }
+        }
+    }
+
+    /// Queues a directory to be read later.
+    fn enqueue(&mut self, node: Node, deferred: Deferred) {
+        if let Some(parent) = &node.parent {
+            parent.unopened.set(parent.unopened.get() + 1);
+            parent.unfinished.set(parent.unfinished.get() + 1);
+        }
+        if self.options.contents_first {
+            *node.deferred.borrow_mut() = Some(deferred);
+        }
+        self.queue.push_back(Rc::new(node));
+    }
+
+    /// Checks whether a directory is still needed for opening its
+    /// subdirectories, or with -depth, for producing them.
+    fn still_needed(&self, node: &Node) -> bool {
+        if self.options.contents_first {
+            node.unfinished.get() > 0
+        } else {
+            node.unopened.get() > 0
+        }
+    }
+
+    /// Keeps a directory open while it's needed, if not too many already are.
+    fn keep_open(&mut self, node: &Node, dir: &Rc<DirFd>) {
+        if self.open_dirs < MAX_OPEN_DIRS && self.still_needed(node) {
+            *node.dir.borrow_mut() = Some(dir.clone());
+            self.open_dirs += 1;
+        }
+    }
+
+    /// Closes a directory once it's no longer needed.
+    fn close_if_done(&mut self, node: &Node) {
+        if !self.still_needed(node) && node.dir.borrow_mut().take().is_some() {
+            self.open_dirs -= 1;
+        }
+    }
+
+    /// Gets a directory that has already been read, re-opening it (and the
+    /// directories above it, as necessary) if it was closed.  Returns None if
+    /// it can't be re-opened, or isn't the same directory any more.
+    fn reopen(&mut self, node: &Rc<Node>) -> Option<Rc<DirFd>> {
+        let mut closed = vec![];
+        let mut node = node.clone();
+        let mut dir = loop {
+            if let Some(dir) = node.dir.borrow().clone() {
+                break Some(dir);
+            }
+            closed.push(node.clone());
+            match node.parent.clone() {
+                Some(parent) => node = parent,
+                None => break None,
+            }
+        };
+
+        for node in closed.iter().rev() {
+            let reopened = open_dir(dir.as_deref(), &node.path, node.followed).ok()?;
+            let debugger = self.options.debugger.as_deref();
+            if dir_id(&reopened, &node.path, debugger).ok() != node.id.get() {
+                return None;
+            }
+            let reopened = Rc::new(DirFd::new(reopened));
+            self.keep_open(node, &reopened);
+            dir = Some(reopened);
+        }
+        dir
+    }
+
+    /// Starts reading the next directory in the queue.
+    fn open_next(&mut self, node: Rc<Node>) {
+        let result = match self.root_dir.take() {
+            Some(dir) => Ok(dir),
+            None => {
+                let parent_dir = node.parent.as_ref().and_then(|p| self.reopen(p));
+                open_dir(parent_dir.as_deref(), &node.path, node.followed)
+            }
+        };
+        if let Some(parent) = &node.parent {
+            parent.unopened.set(parent.unopened.get() - 1);
+            self.close_if_done(parent);
+        }
+
+        self.current = Some(match result {
+            Ok(mut dir) => {
+                let entries = read_dir(&mut dir, &node.path, node.depth + 1, self.options.sorted);
+                let dir = Rc::new(DirFd::new(dir));
+                self.keep_open(&node, &dir);
+                if node.id.get().is_none() && node.dir.borrow().is_none() {
+                    // Without its ID, it won't be re-opened
+                    let debugger = self.options.debugger.as_deref();
+                    node.id.set(dir_id(&*dir, &node.path, debugger).ok());
+                }
+                Current {
+                    node,
+                    dir: Some(dir),
+                    entries: entries.into_iter(),
+                }
+            }
+            Err(e) => {
+                let e = WalkError::from_io_at(&e.into(), &node.path, node.depth);
+                Current {
+                    node,
+                    dir: None,
+                    entries: vec![Err(e)].into_iter(),
+                }
+            }
+        });
+    }
+
+    /// Finishes reading the current directory.
+    fn close_current(&mut self) {
+        let current = self.current.take().unwrap();
+        let mut node = current.node;
+        node.unopened.set(node.unopened.get() - 1);
+        if !self.options.contents_first {
+            self.finished.push_back(node.path.clone());
+        }
+
+        // With -depth, produce each directory that is now completely done.
+        loop {
+            node.unfinished.set(node.unfinished.get() - 1);
+            self.close_if_done(&node);
+            if node.unfinished.get() > 0 {
+                break;
+            }
+            let deferred = node.deferred.borrow_mut().take();
+            if let Some(deferred) = deferred {
+                let parent_dir = node.parent.as_ref().and_then(|p| self.reopen(p));
+                let path = node.path.clone();
+                let entry =
+                    deferred.into_entry(path.clone(), node.depth, parent_dir, self.options.follow);
+                self.ready.push_back((path, entry));
+            }
+            match node.parent.clone() {
+                Some(parent) => node = parent,
+                None => break,
+            }
+        }
+    }
+
+    /// Handles an entry that has just been read from the current directory,
+    /// returning it if it should be produced now.
+    fn visit_child(&mut self, child: Child) -> Option<Result<WalkEntry, WalkError>> {
+        let current = self.current.as_ref().unwrap();
+        let node = current.node.clone();
+        let dir = current.dir.clone().expect("directory should be open");
+        let path = child.path_in(&node.path);
+        let depth = node.depth + 1;
+        let follow = self.options.follow;
+
+        let info = match child.stat(&dir, &path, depth, &self.options) {
+            Ok(info) => info,
+            Err(e) => return Some(Err(e)),
+        };
+        let (file_type, is_symlink) = (info.file_type, info.is_symlink);
+
+        if !info.should_descend(depth, &self.options, self.root_dev) {
+            let entry = WalkEntry::at(dir, path, depth, follow, file_type, is_symlink);
+            return produce(entry, &self.options);
+        }
+
+        let follows = follow.follow_at_depth(depth);
+        let id = info.id();
+        if follows && node.ancestors().any(|n| n.id.get() == id) {
+            return Some(Err(loop_error(&path, depth)));
+        }
+
+        let mut subdir = Node::new(path.clone(), depth, id, is_symlink && follows);
+        subdir.parent = Some(node);
+        let deferred = Deferred::Child {
+            file_type,
+            is_symlink,
+        };
+        self.enqueue(subdir, deferred);
+
+        if self.options.contents_first {
+            None
+        } else {
+            let entry = WalkEntry::at(dir, path, depth, follow, file_type, is_symlink);
+            let result = produce(entry, &self.options);
+            self.entered = result.is_some();
+            result
+        }
+    }
+
+    /// Handles the starting point, returning it if it should be produced now.
+    fn visit_root(&mut self, root: PathBuf) -> Option<Result<WalkEntry, WalkError>> {
+        let (opened, id) = match Start::new(&root, &self.options) {
+            Ok(Start::Dir(opened, id)) => (opened, id),
+            Ok(Start::Leaf(entry)) => return produce(entry, &self.options),
+            Err(e) => return Some(Err(e)),
+        };
+        self.root_dev = id.map_or(0, |id| id.0);
+        let follow = self.options.follow;
+
+        let node = Node::new(root.clone(), 0, id, follow.follow_at_depth(0));
+        let entry = opened.entry(root, follow);
+        let deferred = opened.deferred();
+        if let Root::Dir(dir) = opened {
+            self.root_dir = Some(dir);
+        }
+
+        self.enqueue(node, deferred);
+        if self.options.contents_first {
+            None
+        } else {
+            let result = produce(entry, &self.options);
+            self.entered = result.is_some();
+            result
+        }
+    }
+}
+
+impl Iterator for BreadthFirstWalk {
+    type Item = Result<WalkEntry, WalkError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.entered = false;
+        if let Some(root) = self.root.take() {
+            if let Some(result) = self.visit_root(root) {
+                return Some(result);
+            }
+        }
+
+        loop {
+            while let Some((dir, entry)) = self.ready.pop_front() {
+                self.finished.push_back(dir);
+                if let Some(result) = produce(entry, &self.options) {
+                    return Some(result);
+                }
+            }
+
+            if self.current.is_none() {
+                let node = self.queue.pop_front()?;
+                self.open_next(node);
+            }
+
+            let current = self.current.as_mut().unwrap();
+            match current.entries.next() {
+                Some(Ok(child)) => {
+                    if let Some(result) = self.visit_child(child) {
+                        return Some(result);
+                    }
+                }
+                Some(Err(e)) => return Some(Err(e)),
+                None => self.close_current(),
+            }
+        }
+    }
+}
+
+/// Walks a single starting point with a series of depth-first walks, each
+/// producing the entries in a range of depths: one depth at a time for
+/// iterative deepening (-S ids), or doubling ranges for exponential
+/// deepening (-S eds).  Like breadth-first search, shallow files are found
+/// first, but without having to keep a queue of directories in memory.
+pub struct DeepeningWalk {
+    root: PathBuf,
+    options: WalkOptions,
+    exponential: bool,
+    /// The depths of the current walk, from `.0` up to (but not including)
+    /// `.1`.
+    window: (usize, usize),
+    walk: Option<FdWalk>,
+    /// Whether the current walk found anything in its window.
+    found: bool,
+    /// With -depth, the windows still to go, deepest first.
+    reverse_windows: Option<Vec<(usize, usize)>>,
+    /// Errors found while planning the windows, which haven't been reported
+    /// yet.
+    planning_errors: VecDeque<WalkError>,
+    /// Directories that were pruned, so later walks don't descend into them.
+    pruned: HashSet<PathBuf>,
+    /// The paths of errors that have already been reported.
+    reported: HashSet<PathBuf>,
+    /// The last directory produced, which skip_current_dir() applies to.
+    last_dir: Option<PathBuf>,
+    /// Directories whose contents have all been produced.
+    finished: VecDeque<PathBuf>,
+}
+
+impl DeepeningWalk {
+    pub fn new(root: &Path, options: WalkOptions, exponential: bool) -> Self {
+        Self {
+            root: root.to_path_buf(),
+            options,
+            exponential,
+            window: (0, 1),
+            walk: None,
+            found: false,
+            reverse_windows: None,
+            planning_errors: VecDeque::new(),
+            pruned: HashSet::new(),
+            reported: HashSet::new(),
+            last_dir: None,
+            finished: VecDeque::new(),
+        }
+    }
+
+    /// Takes the next directory that the walk has produced everything in
+    /// (see [FdWalk::pop_finished_dir()]).
+    pub fn pop_finished_dir(&mut self) -> Option<PathBuf> {
+        self.collect_finished_dirs();
+        self.finished.pop_front()
+    }
+
+    /// Notes the directories the current depth-first walk has left, whose
+    /// contents were in its window.
+    fn collect_finished_dirs(&mut self) {
+        let Some(walk) = &mut self.walk else {
+            return;
+        };
+        while let Some((dir, depth)) = walk.pop_finished_dir() {
+            if depth >= self.window.0 {
+                self.finished.push_back(dir);
+            }
+        }
+    }
+
+    /// Doesn't descend into the directory that was just produced (-prune).
+    pub fn skip_current_dir(&mut self) {
+        if let Some(path) = self.last_dir.take() {
+            if let Some(walk) = &mut self.walk {
+                walk.skip_current_dir();
+            }
+            self.pruned.insert(path);
+        }
+    }
+
+    fn next_window(&self, (_, end): (usize, usize)) -> (usize, usize) {
+        if self.exponential {
+            (end, end * 2)
+        } else {
+            (end, end + 1)
+        }
+    }
+
+    /// Starts a depth-first walk over the given window.
+    fn start_walk(&mut self, window: (usize, usize), contents_first: bool) {
+        let options = WalkOptions {
+            min_depth: if contents_first { window.0 } else { 0 },
+            max_depth: self.options.max_depth.min(window.1 - 1),
+            contents_first,
+            ..self.options.clone()
+        };
+        self.collect_finished_dirs();
+        self.window = window;
+        self.found = false;
+        self.walk = Some(FdWalk::new(&self.root, options));
+    }
+
+    /// Checks whether an error should be reported, or has been already.
+    fn is_new_error(&mut self, e: &WalkError) -> bool {
+        match e.path() {
+            Some(path) => self.reported.insert(path.to_path_buf()),
+            None => true,
+        }
+    }
+
+    /// With -depth, finds out how deep the tree goes (reporting any errors
+    /// along the way), and works out the windows to walk in reverse.
+    fn plan_reverse_windows(&mut self) {
+        let options = WalkOptions {
+            min_depth: 0,
+            contents_first: false,
+            ..self.options.clone()
+        };
+        let mut deepest = 0;
+        for result in FdWalk::new(&self.root, options) {
+            match result {
+                Ok(entry) => deepest = deepest.max(entry.depth()),
+                Err(e) => {
+                    if self.is_new_error(&e) {
+                        self.planning_errors.push_back(e);
+                    }
+                }
+            }
+        }
+
+        let mut windows = vec![];
+        let mut window = (0, 1);
+        while window.0 <= deepest {
+            windows.push(window);
+            window = self.next_window(window);
+        }
+        self.reverse_windows = Some(windows);
+    }
+
+    /// Gets the next result in pre-order.
+    fn next_pre_order(&mut self) -> Option<Result<WalkEntry, WalkError>> {
+        loop {
+            let Some(walk) = &mut self.walk else {
+                self.start_walk((0, 1), false);
+                continue;
+            };
+
+            match walk.next() {
+                Some(Ok(entry)) => {
+                    let depth = entry.depth();
+                    let is_dir = entry.file_type().is_dir();
+                    if depth < self.window.0 {
+                        // Already produced by an earlier walk
+                        if is_dir && self.pruned.contains(entry.path()) {
+                            walk.skip_current_dir();
+                        }
+                        continue;
+                    }
+                    self.found = true;
+                    if depth < self.options.min_depth {
+                        continue;
+                    }
+                    if is_dir {
+                        self.last_dir = Some(entry.path().to_path_buf());
+                    }
+                    return Some(Ok(entry));
+                }
+                Some(Err(e)) => {
+                    if self.is_new_error(&e) {
+                        return Some(Err(e));
+                    }
+                }
+                None => {
+                    let last_depth = self.window.1 - 1;
+                    if !self.found || last_depth >= self.options.max_depth {
+                        return None;
+                    }
+                    self.start_walk(self.next_window(self.window), false);
+                }
+            }
+        }
+    }
+
+    /// Gets the next result with -depth, deepest windows first.
+    fn next_post_order(&mut self) -> Option<Result<WalkEntry, WalkError>> {
+        if self.reverse_windows.is_none() {
+            self.plan_reverse_windows();
+        }
+        if let Some(e) = self.planning_errors.pop_front() {
+            return Some(Err(e));
+        }
+
+        loop {
+            if let Some(walk) = &mut self.walk {
+                match walk.next() {
+                    Some(Ok(entry)) if entry.depth() < self.options.min_depth => continue,
+                    Some(Ok(entry)) => return Some(Ok(entry)),
+                    Some(Err(e)) => {
+                        if self.is_new_error(&e) {
+                            return Some(Err(e));
+                        }
+                        continue;
+                    }
+                    None => {}
+                }
+            }
+
+            let window = self.reverse_windows.as_mut().unwrap().pop()?;
+            self.start_walk(window, true);
+        }
+    }
+}
+
+impl Iterator for DeepeningWalk {
+    type Item = Result<WalkEntry, WalkError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.last_dir = None;
+        if self.options.contents_first {
+            self.next_post_order()
+        } else {
+            self.next_pre_order()
+        }
+    }
+}
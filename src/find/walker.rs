@@ -0,0 +1,656 @@
+// This file is part of the uutils findutils package.
+//
+// For the full copyright and license information, please view the LICENSE
+// file that was distributed with this source code.
+
+//! A directory walker that reads directories and fetches metadata (if the
+//! matchers need it) on a pool of threads (-j). Entries are still produced on the calling thread, in the
+//! same order as a sequential walk, so matchers don't need to be thread safe
+//! and -prune, -quit etc. behave exactly as they do without -j.
+//!
+//! Like [FdWalk](super::fdwalk::FdWalk), everything is accessed relative to
+//! the open directory it's in.  Worker threads open each directory through a
+//! duplicate of its parent's file descriptor, and hand the open directory
+//! back along with its contents.
+
+use std::cell::RefCell;
+use std::collections::{HashMap, HashSet, VecDeque};
+use std::fs::Metadata;
+use std::io;
+use std::os::unix::io::AsRawFd;
+use std::panic::{self, AssertUnwindSafe};
+use std::path::{Path, PathBuf};
+use std::rc::Rc;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::mpsc::{self, Receiver, Sender};
+use std::sync::{Arc, Mutex};
+use std::thread::{self, JoinHandle};
+use std::vec;
+
+use nix::dir::Dir;
+use nix::errno::Errno;
+use nix::fcntl::{fcntl, FcntlArg};
+
+use super::fdwalk::{
+    dir_id, loop_error, open_dir, produce, read_dir, reopen_dir, ChildInfo, Deferred, Root, Start,
+    WalkOptions, MAX_OPEN_DIRS,
+};
+use super::matchers::{metadata_at, DirFd, Follow, WalkEntry, WalkError};
+
+/// How many directories to read ahead of time per thread.  Reads that turn
+/// out not to be needed (e.g. after -prune) are wasted, and each finished one
+/// holds a file descriptor until it's used, so this is kept small.
+const JOBS_PER_THREAD: usize = 4;
+
+/// An entry read from a directory by a worker thread.
+struct Child {
+    path: PathBuf,
+    /// The type etc., as found by [fdwalk::Child::stat()](super::fdwalk::Child::stat).
+    info: ChildInfo,
+    /// The metadata, if the matchers will need it.
+    meta: Option<Result<Metadata, WalkError>>,
+}
+
+impl Child {
+    fn into_entry(self, dir: Rc<DirFd>, depth: usize, follow: Follow) -> WalkEntry {
+        let (file_type, is_symlink) = (self.info.file_type, self.info.is_symlink);
+        let entry = WalkEntry::at(dir, self.path, depth, follow, file_type, is_symlink);
+        match self.meta {
+            Some(meta) => entry.with_prefetched_metadata(meta),
+            None => entry,
+        }
+    }
+}
+
+/// A directory that has been opened and read by a worker thread.
+struct Listing {
+    /// The open directory, unless opening it failed.
+    dir: Option<DirFd>,
+    /// The directory's contents, or the error from trying to read it.
+    entries: Vec<Result<Child, WalkError>>,
+}
+
+impl Listing {
+    /// The listing of a directory (whose contents are at `depth`) that
+    /// couldn't be opened or read.
+    fn error(e: &io::Error, path: &Path, depth: usize) -> Self {
+        Self {
+            dir: None,
+            entries: vec![Err(WalkError::from_io_at(e, path, depth - 1))],
+        }
+    }
+}
+
+/// The parts of [WalkOptions] that affect reading directories.
+#[derive(Clone)]
+struct ReadOptions {
+    walk: WalkOptions,
+    /// Whether to fetch the metadata of everything.
+    stat: bool,
+}
+
+/// Where a worker thread finds the directory to read.
+enum Source {
+    /// A starting point that has already been opened.
+    Opened(Dir),
+    /// A directory to open by name, relative to (a duplicate of) its parent
+    /// directory if that's open.
+    At {
+        parent: Option<DirFd>,
+        followed: bool,
+    },
+}
+
+struct Job {
+    id: usize,
+    source: Source,
+    path: PathBuf,
+    /// The depth of the directory's contents.
+    depth: usize,
+}
+
+/// Reads directories on a pool of worker threads.
+pub struct ReadDirPool {
+    jobs: Option<Sender<Job>>,
+    results: Receiver<(usize, Listing)>,
+    workers: Vec<JoinHandle<()>>,
+    /// Set when the pool is shutting down, so that queued jobs are dropped.
+    cancelled: Arc<AtomicBool>,
+    next_id: usize,
+    /// The most jobs to have outstanding when reading ahead of time.
+    capacity: usize,
+    /// The number of jobs whose results haven't been used or thrown away yet.
+    outstanding: usize,
+    /// Results that arrived before they were waited for.
+    ready: HashMap<usize, Listing>,
+    /// Jobs whose results are no longer wanted.
+    abandoned: HashSet<usize>,
+}
+
+impl ReadDirPool {
+    /// Starts `threads` worker threads, which fetch the metadata of
+    /// everything they read if `stat` is set, or otherwise only what the walk
+    /// itself needs.
+    pub fn new(threads: usize, walk: WalkOptions, stat: bool) -> Self {
+        let options = ReadOptions { walk, stat };
+        let (job_sender, job_receiver) = mpsc::channel::<Job>();
+        let (result_sender, results) = mpsc::channel();
+        let job_receiver = Arc::new(Mutex::new(job_receiver));
+        let cancelled = Arc::new(AtomicBool::new(false));
+
+        let workers = (0..threads)
+            .map(|_| {
+                let job_receiver = Arc::clone(&job_receiver);
+                let result_sender = result_sender.clone();
+                let cancelled = Arc::clone(&cancelled);
+                let options = options.clone();
+                thread::spawn(move || loop {
+                    let job = job_receiver.lock().unwrap().recv();
+                    let Ok(job) = job else {
+                        break;
+                    };
+                    if cancelled.load(Ordering::Relaxed) {
+                        break;
+                    }
+                    let (id, path, depth) = (job.id, job.path.clone(), job.depth);
+                    let listing = panic::catch_unwind(AssertUnwindSafe(|| read(job, &options)))
+                        .unwrap_or_else(|_| {
+                            let e = io::Error::other("directory reading thread panicked");
+                            Listing::error(&e, &path, depth)
+                        });
+                    if result_sender.send((id, listing)).is_err() {
+                        break;
+                    }
+                })
+            })
+            .collect();
+
+        Self {
+            jobs: Some(job_sender),
+            results,
+            workers,
+            cancelled,
+            next_id: 0,
+            capacity: threads * JOBS_PER_THREAD,
+            outstanding: 0,
+            ready: HashMap::new(),
+            abandoned: HashSet::new(),
+        }
+    }
+
+    /// Queues a directory to be read, returning an id to wait for.
+    fn submit(&mut self, source: Source, path: &Path, depth: usize) -> usize {
+        let id = self.next_id;
+        self.next_id += 1;
+        let job = Job {
+            id,
+            source,
+            path: path.to_path_buf(),
+            depth,
+        };
+        // If the workers are gone, wait() reports it
+        let _ = self.jobs.as_ref().unwrap().send(job);
+        self.outstanding += 1;
+        id
+    }
+
+    /// Checks whether there's room to read another directory ahead of time.
+    fn has_room(&mut self) -> bool {
+        while let Ok((id, listing)) = self.results.try_recv() {
+            self.receive(id, listing);
+        }
+        self.outstanding < self.capacity
+    }
+
+    /// Waits for the directory with the given id to be read.
+    fn wait(&mut self, id: usize) -> io::Result<Listing> {
+        loop {
+            if let Some(listing) = self.ready.remove(&id) {
+                self.outstanding -= 1;
+                return Ok(listing);
+            }
+            let Ok((result_id, listing)) = self.results.recv() else {
+                return Err(io::Error::other("directory reading threads exited early"));
+            };
+            self.receive(result_id, listing);
+        }
+    }
+
+    /// Keeps a result until it's waited for, unless it's no longer needed.
+    fn receive(&mut self, id: usize, listing: Listing) {
+        if self.abandoned.remove(&id) {
+            self.outstanding -= 1;
+        } else {
+            self.ready.insert(id, listing);
+        }
+    }
+
+    /// Marks a job as no longer needed.
+    fn abandon(&mut self, id: usize) {
+        if self.ready.remove(&id).is_some() {
+            self.outstanding -= 1;
+        } else {
+            self.abandoned.insert(id);
+        }
+    }
+}
+
+impl Drop for ReadDirPool {
+    fn drop(&mut self) {
+        self.cancelled.store(true, Ordering::Relaxed);
+        self.jobs = None;
+        for worker in self.workers.drain(..) {
+            let _ = worker.join();
+        }
+    }
+}
+
+/// Duplicates an open directory, so that its subdirectories can be opened
+/// relative to it on another thread.
+fn dup_dir(dir: &DirFd) -> Result<DirFd, Errno> {
+    let fd = fcntl(dir.as_raw_fd(), FcntlArg::F_DUPFD_CLOEXEC(0))?;
+    Dir::from_fd(fd).map(DirFd::new)
+}
+
+/// Opens and reads a directory, and looks at what's in it as necessary.
+fn read(job: Job, options: &ReadOptions) -> Listing {
+    let opened = match job.source {
+        Source::Opened(dir) => Ok(dir),
+        Source::At { parent, followed } => open_dir(parent.as_ref(), &job.path, followed),
+    };
+    let mut dir = match opened {
+        Ok(dir) => dir,
+        Err(e) => return Listing::error(&e.into(), &job.path, job.depth),
+    };
+
+    let children = read_dir(&mut dir, &job.path, job.depth, options.walk.sorted);
+    let dir = DirFd::new(dir);
+    let follows = options.walk.follow.follow_at_depth(job.depth);
+    let debugger = options.walk.debugger.as_deref();
+    let entries = children
+        .into_iter()
+        .map(|child| {
+            let child = child?;
+            let path = child.path_in(&job.path);
+            let info = child.stat(&dir, &path, job.depth, &options.walk)?;
+            let meta = options.stat.then(|| {
+                metadata_at(&dir, child.name(), &path, follows, debugger).map_err(WalkError::from)
+            });
+            Ok(Child { path, info, meta })
+        })
+        .collect();
+
+    Listing {
+        dir: Some(dir),
+        entries,
+    }
+}
+
+enum ListingState {
+    /// Still being read, with the given job id.
+    Pending(usize),
+    Ready(vec::IntoIter<Result<Child, WalkError>>),
+}
+
+/// A directory that is being walked.
+struct Frame {
+    path: PathBuf,
+    listing: ListingState,
+    /// The depth of the directory's contents.
+    depth: usize,
+    /// The open directory, once it has been read, unless it has been closed
+    /// to save file descriptors.
+    dir: Option<Rc<DirFd>>,
+    /// The directory's device and inode numbers, if known (see
+    /// [FdWalk](super::fdwalk::FdWalk)).
+    id: Option<(u64, u64)>,
+    /// Whether the directory was reached through a symlink.
+    followed: bool,
+    /// Subdirectories that will be walked, but haven't been read yet.
+    unread: VecDeque<(PathBuf, bool)>,
+    /// Reads that have already been started for subdirectories.
+    prefetched: HashMap<PathBuf, usize>,
+    /// With -depth, the directory itself, which comes after its contents.
+    deferred: Option<Deferred>,
+}
+
+impl Frame {
+    /// Abandons any directory reads this frame started that are still
+    /// needed.
+    fn abandon_reads(&mut self, pool: &mut ReadDirPool) {
+        if let ListingState::Pending(id) = self.listing {
+            pool.abandon(id);
+        }
+        self.unread.clear();
+        for (_, id) in self.prefetched.drain() {
+            pool.abandon(id);
+        }
+    }
+}
+
+/// Walks a single starting point, in the same order as walkdir would.
+pub struct ParallelWalk {
+    pool: Rc<RefCell<ReadDirPool>>,
+    root: Option<PathBuf>,
+    root_dev: u64,
+    options: WalkOptions,
+    stack: Vec<Frame>,
+    /// Whether the innermost directory was the last thing produced, i.e.
+    /// whether there is anything for skip_current_dir() to skip.
+    entered: bool,
+    /// Directories that have been left.
+    finished: VecDeque<PathBuf>,
+}
+
+impl ParallelWalk {
+    pub fn new(root: &Path, options: WalkOptions, pool: Rc<RefCell<ReadDirPool>>) -> Self {
+        Self {
+            pool,
+            root: Some(root.to_path_buf()),
+            root_dev: 0,
+            options,
+            stack: vec![],
+            entered: false,
+            finished: VecDeque::new(),
+        }
+    }
+
+    /// Takes the next directory that the walk has left (see
+    /// [FdWalk::pop_finished_dir()](super::fdwalk::FdWalk::pop_finished_dir)).
+    pub fn pop_finished_dir(&mut self) -> Option<PathBuf> {
+        self.finished.pop_front()
+    }
+
+    /// Doesn't descend into the directory that was just produced (-prune).
+    /// Does nothing if the last entry wasn't a directory that would have
+    /// been descended into, or with -depth, when it's too late.
+    pub fn skip_current_dir(&mut self) {
+        if !std::mem::take(&mut self.entered) {
+            return;
+        }
+        if let Some(mut frame) = self.stack.pop() {
+            frame.abandon_reads(&mut self.pool.borrow_mut());
+        }
+    }
+
+    /// Starts reading the directory at `path` in the open directory
+    /// `parent`.
+    fn read_at(&self, parent: &DirFd, path: &Path, depth: usize, followed: bool) -> ListingState {
+        match dup_dir(parent) {
+            Ok(parent) => {
+                let source = Source::At {
+                    parent: Some(parent),
+                    followed,
+                };
+                ListingState::Pending(self.pool.borrow_mut().submit(source, path, depth))
+            }
+            Err(e) => {
+                let e = WalkError::from_io_at(&e.into(), path, depth - 1);
+                ListingState::Ready(vec![Err(e)].into_iter())
+            }
+        }
+    }
+
+    /// Makes sure the listing of the innermost directory has been read, and
+    /// notes which subdirectories it contains.
+    fn ready_listing(&mut self) {
+        let Some(frame) = self.stack.last_mut() else {
+            return;
+        };
+        let ListingState::Pending(id) = frame.listing else {
+            return;
+        };
+
+        let listing = self.pool.borrow_mut().wait(id);
+        let listing = listing.unwrap_or_else(|e| Listing::error(&e, &frame.path, frame.depth));
+        frame.dir = listing.dir.map(Rc::new);
+        if frame.dir.is_some() {
+            let follows = self.options.follow.follow_at_depth(frame.depth);
+            for child in listing.entries.iter().flatten() {
+                let info = &child.info;
+                if info.should_descend(frame.depth, &self.options, self.root_dev) {
+                    let followed = info.is_symlink && follows;
+                    frame.unread.push_back((child.path.clone(), followed));
+                }
+            }
+        }
+        frame.listing = ListingState::Ready(listing.entries.into_iter());
+
+        // Entries that are still in use keep their directory open until
+        // they're dropped, but that's at most a few.
+        let open = self.stack.iter().filter(|f| f.dir.is_some()).count();
+        if open > MAX_OPEN_DIRS {
+            if let Some(oldest) = self.stack.iter_mut().find(|f| f.dir.is_some()) {
+                let dir = oldest.dir.take().unwrap();
+                if oldest.id.is_none() {
+                    // Without its ID, it won't be re-opened
+                    oldest.id = dir_id(&*dir, &oldest.path, self.options.debugger.as_deref()).ok();
+                }
+            }
+        }
+    }
+
+    /// Starts reading the next subdirectories that will be walked, innermost
+    /// first, as long as the pool has room for them.
+    fn prefetch(&mut self) {
+        let mut pool = self.pool.borrow_mut();
+        for frame in self.stack.iter_mut().rev() {
+            // Directories that were closed to save file descriptors are
+            // caught up with once they're re-opened
+            let Some(dir) = &frame.dir else {
+                continue;
+            };
+            while !frame.unread.is_empty() && pool.has_room() {
+                let (path, followed) = frame.unread.pop_front().unwrap();
+                if let Ok(parent) = dup_dir(dir) {
+                    let source = Source::At {
+                        parent: Some(parent),
+                        followed,
+                    };
+                    let id = pool.submit(source, &path, frame.depth + 1);
+                    frame.prefetched.insert(path, id);
+                }
+            }
+            if !pool.has_room() {
+                break;
+            }
+        }
+    }
+
+    /// Handles an entry that has just been read from the innermost
+    /// directory, returning it if it should be produced now.
+    fn visit_child(&mut self, child: Child) -> Option<Result<WalkEntry, WalkError>> {
+        let frame = self.stack.last_mut().unwrap();
+        let depth = frame.depth;
+        let dir = frame.dir.clone().expect("directory should be open");
+        if frame
+            .unread
+            .front()
+            .is_some_and(|(path, _)| *path == child.path)
+        {
+            frame.unread.pop_front();
+        }
+        let prefetched = frame.prefetched.remove(&child.path);
+        let follow = self.options.follow;
+        let info = &child.info;
+
+        if !info.should_descend(depth, &self.options, self.root_dev) {
+            return produce(child.into_entry(dir, depth, follow), &self.options);
+        }
+
+        let follows = follow.follow_at_depth(depth);
+        let id = info.id();
+        if follows && self.stack.iter().any(|frame| frame.id == id) {
+            if let Some(job) = prefetched {
+                self.pool.borrow_mut().abandon(job);
+            }
+            return Some(Err(loop_error(&child.path, depth)));
+        }
+
+        let followed = info.is_symlink && follows;
+        let listing = match prefetched {
+            Some(job) => ListingState::Pending(job),
+            None => self.read_at(&dir, &child.path, depth + 1, followed),
+        };
+        let mut frame = Frame {
+            path: child.path.clone(),
+            listing,
+            depth: depth + 1,
+            dir: None,
+            id,
+            followed,
+            unread: VecDeque::new(),
+            prefetched: HashMap::new(),
+            deferred: None,
+        };
+
+        if self.options.contents_first {
+            frame.deferred = Some(Deferred::Child {
+                file_type: info.file_type,
+                is_symlink: info.is_symlink,
+            });
+            self.stack.push(frame);
+            None
+        } else {
+            self.stack.push(frame);
+            let entry = child.into_entry(dir, depth, follow);
+            self.produce_dir(entry)
+        }
+    }
+
+    /// Handles the starting point, returning it if it should be produced now.
+    fn visit_root(&mut self, root: PathBuf) -> Option<Result<WalkEntry, WalkError>> {
+        let (opened, id) = match Start::new(&root, &self.options) {
+            Ok(Start::Dir(opened, id)) => (opened, id),
+            Ok(Start::Leaf(entry)) => return produce(entry, &self.options),
+            Err(e) => return Some(Err(e)),
+        };
+        self.root_dev = id.map_or(0, |id| id.0);
+        let follow = self.options.follow;
+        let followed = follow.follow_at_depth(0);
+
+        let entry = opened.entry(root.clone(), follow);
+        let deferred = opened.deferred();
+        let source = match opened {
+            Root::Dir(dir) => Source::Opened(dir),
+            Root::Other(_) => Source::At {
+                parent: None,
+                followed,
+            },
+        };
+        let job = self.pool.borrow_mut().submit(source, &root, 1);
+        let mut frame = Frame {
+            path: root,
+            listing: ListingState::Pending(job),
+            depth: 1,
+            dir: None,
+            id,
+            followed,
+            unread: VecDeque::new(),
+            prefetched: HashMap::new(),
+            deferred: None,
+        };
+
+        if self.options.contents_first {
+            frame.deferred = Some(deferred);
+            self.stack.push(frame);
+            None
+        } else {
+            self.stack.push(frame);
+            self.produce_dir(entry)
+        }
+    }
+
+    /// Finishes walking the innermost directory, returning anything that
+    /// should be produced now.
+    fn pop(&mut self) -> Option<Result<WalkEntry, WalkError>> {
+        let mut frame = self.stack.pop().unwrap();
+        self.finished.push_back(frame.path.clone());
+        let mut pool = self.pool.borrow_mut();
+        frame.abandon_reads(&mut pool);
+
+        if let Some(parent) = self.stack.last_mut() {
+            if parent.dir.is_none() {
+                let child_dir = frame.dir.as_deref().filter(|_| !frame.followed);
+                match reopen_dir(
+                    &parent.path,
+                    parent.depth,
+                    parent.followed,
+                    parent.id,
+                    child_dir,
+                    self.options.debugger.as_deref(),
+                ) {
+                    Ok(dir) => parent.dir = Some(Rc::new(dir)),
+                    Err(e) => {
+                        parent.abandon_reads(&mut pool);
+                        parent.listing = ListingState::Ready(vec![Err(e)].into_iter());
+                    }
+                }
+            }
+        }
+        drop(pool);
+
+        let parent_dir = self.stack.last().and_then(|parent| parent.dir.clone());
+        let entry = frame.deferred?.into_entry(
+            frame.path,
+            frame.depth - 1,
+            parent_dir,
+            self.options.follow,
+        );
+        produce(entry, &self.options)
+    }
+
+    /// Produces a directory that has just been entered.
+    fn produce_dir(&mut self, entry: WalkEntry) -> Option<Result<WalkEntry, WalkError>> {
+        let result = produce(entry, &self.options);
+        self.entered = result.is_some();
+        result
+    }
+}
+
+impl Iterator for ParallelWalk {
+    type Item = Result<WalkEntry, WalkError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.entered = false;
+        if let Some(root) = self.root.take() {
+            if let Some(result) = self.visit_root(root) {
+                return Some(result);
+            }
+        }
+
+        loop {
+            self.ready_listing();
+            self.prefetch();
+            let frame = self.stack.last_mut()?;
+            let ListingState::Ready(listing) = &mut frame.listing else {
+                unreachable!("listing should have been read");
+            };
+
+            match listing.next() {
+                Some(Ok(child)) => {
+                    if let Some(result) = self.visit_child(child) {
+                        return Some(result);
+                    }
+                }
+                Some(Err(e)) => return Some(Err(e)),
+                None => {
+                    if let Some(result) = self.pop() {
+                        return Some(result);
+                    }
+                }
+            }
+        }
+    }
+}
+
+impl Drop for ParallelWalk {
+    fn drop(&mut self) {
+        // Make sure results for directories that will never be walked (e.g.
+        // after -quit) don't pile up in the pool.
+        let mut pool = self.pool.borrow_mut();
+        while let Some(mut frame) = self.stack.pop() {
+            frame.abandon_reads(&mut pool);
+        }
+    }
+}
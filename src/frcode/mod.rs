@@ -0,0 +1,667 @@
+// This file is part of the uutils findutils package.
+//
+// For the full copyright and license information, please view the LICENSE
+// file that was distributed with this source code.
+
+//! frcode: front-compresses lists of file names into locate databases.
+//!
+//! Each name in a database is stored as the change in how much of the
+//! previous name it shares, followed by the rest of the name.  There are
+//! three formats:
+//!
+//! - LOCATE02, GNU's format.  It starts with a dummy "LOCATE02" entry, and
+//!   each change is a signed byte, or [LONG_COUNT] followed by a big-endian
+//!   16-bit number.  Names end with a NUL.
+//! - slocate's format, which is the same except that it starts with a
+//!   security level byte and a NUL instead, and the first name has no change.
+//! - The old format of Unix locate and GNU findutils before 4.0.  It starts
+//!   with a table of 128 common bigrams (pairs of bytes), and each byte with
+//!   the high bit set stands for one of them.  Changes from -14 to 14 are
+//!   stored as a byte from 0 to 28, and others as [OLD_LONG_COUNT] followed
+//!   by a native-endian 32-bit number.  Names have no terminator, as each
+//!   change byte is less than any byte in a name, so only ASCII can be stored.
+
+use std::error::Error;
+use std::io::{self, stdin, stdout, BufRead, BufWriter, Write};
+
+use clap::{crate_version, error::ErrorKind, Arg, ArgAction};
+
+mod options {
+    pub const NULL: &str = "null";
+    pub const SECURITY_LEVEL: &str = "security-level";
+}
+
+/// The name of the dummy entry that every LOCATE02 database starts with.
+const LOCATE02: &[u8] = b"LOCATE02";
+
+/// The count byte that means the real count follows in the next two bytes.
+pub const LONG_COUNT: u8 = 0x80;
+
+/// The count byte that means the real count follows in the next word, in the
+/// old format.
+pub const OLD_LONG_COUNT: u8 = 30;
+
+/// What's added to counts in the old format, so that they're never negative.
+const OLD_OFFSET: i32 = 14;
+
+/// The number of bigrams in an old database.
+const BIGRAMS: usize = 128;
+
+/// The formats of locate databases.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Format {
+    Locate02,
+    Slocate,
+    Old,
+}
+
+fn corrupt() -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, "the database is corrupt")
+}
+
+fn invalid_name(msg: &str) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidInput, msg)
+}
+
+/// Replaces the bytes that the old format can't store with '?'.
+fn old_byte(byte: u8) -> u8 {
+    if byte <= OLD_LONG_COUNT || byte >= 0x80 {
+        b'?'
+    } else {
+        byte
+    }
+}
+
+/// Counts how many bytes two names start with in common.
+fn shared_len(a: &[u8], b: &[u8]) -> usize {
+    a.iter().zip(b).take_while(|(a, b)| a == b).count()
+}
+
+/// Finds the bigrams that are most worth putting in an old database of
+/// `names`, i.e. the most common in the parts of the names that aren't
+/// shared with the one before.
+pub fn common_bigrams<'a>(names: impl IntoIterator<Item = &'a [u8]>) -> Vec<[u8; 2]> {
+    let mut counts = std::collections::HashMap::<[u8; 2], usize>::new();
+    let mut prev = vec![];
+    for name in names {
+        let name: Vec<u8> = name.iter().copied().map(old_byte).collect();
+        let shared = shared_len(&prev, &name);
+        for pair in name[shared..].chunks_exact(2) {
+            *counts.entry([pair[0], pair[1]]).or_default() += 1;
+        }
+        prev = name;
+    }
+
+    let mut bigrams: Vec<_> = counts.into_iter().collect();
+    bigrams.sort_by(|(a, a_count), (b, b_count)| b_count.cmp(a_count).then(a.cmp(b)));
+    bigrams
+        .into_iter()
+        .take(BIGRAMS)
+        .map(|(bigram, _)| bigram)
+        .collect()
+}
+
+/// Writes file names to a locate database, sharing as much of each as
+/// possible with the one before.  The database is smallest when the names
+/// are sorted.
+pub struct Encoder<W> {
+    out: W,
+    format: Format,
+    /// The last name written.
+    name: Vec<u8>,
+    /// How much of its predecessor the last name shared.
+    shared: usize,
+    /// Whether the next name is the first in an slocate database, which is
+    /// written without a count.
+    first: bool,
+    /// The bigram table, in the old format.
+    bigrams: Vec<[u8; 2]>,
+}
+
+impl<W: Write> Encoder<W> {
+    fn start(out: W, format: Format) -> Self {
+        Self {
+            out,
+            format,
+            name: vec![],
+            shared: 0,
+            first: false,
+            bigrams: vec![],
+        }
+    }
+
+    /// Starts a LOCATE02 database, writing the entry it begins with.
+    pub fn new(out: W) -> io::Result<Self> {
+        let mut encoder = Self::start(out, Format::Locate02);
+        encoder.write_name(LOCATE02)?;
+        Ok(encoder)
+    }
+
+    /// Starts an slocate database with a security level of 0 or 1.
+    pub fn slocate(out: W, level: u8) -> io::Result<Self> {
+        if level > 1 {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                format!("the slocate security level must be 0 or 1, not {level}"),
+            ));
+        }
+        let mut encoder = Self::start(out, Format::Slocate);
+        encoder.out.write_all(&[b'0' + level, b'\0'])?;
+        encoder.first = true;
+        Ok(encoder)
+    }
+
+    /// Starts an old database, which compresses up to 128 `bigrams` (see
+    /// [common_bigrams]) into a single byte each.
+    pub fn old(out: W, bigrams: &[[u8; 2]]) -> io::Result<Self> {
+        let mut encoder = Self::start(out, Format::Old);
+        encoder.bigrams = bigrams.iter().take(BIGRAMS).copied().collect();
+        for i in 0..BIGRAMS {
+            let bigram = encoder.bigrams.get(i).copied().unwrap_or_default();
+            encoder.out.write_all(&bigram)?;
+        }
+        Ok(encoder)
+    }
+
+    /// Writes the next name.
+    pub fn write_name(&mut self, name: &[u8]) -> io::Result<()> {
+        if name.is_empty() || name.contains(&b'\0') {
+            return Err(invalid_name(
+                "file names in the database can't be empty or contain NUL",
+            ));
+        }
+
+        let old_name: Vec<u8>;
+        let name = if self.format == Format::Old {
+            old_name = name.iter().copied().map(old_byte).collect();
+            &old_name
+        } else {
+            name
+        };
+        let shared = shared_len(&self.name, name);
+        let change = shared as isize - self.shared as isize;
+
+        match self.format {
+            Format::Old => self.write_old(change, &name[shared..])?,
+            _ => {
+                let change =
+                    i16::try_from(change).map_err(|_| invalid_name("file name too long"))?;
+                if !self.first {
+                    match i8::try_from(change) {
+                        Ok(change) if change as u8 != LONG_COUNT => {
+                            self.out.write_all(&[change as u8])?;
+                        }
+                        _ => {
+                            self.out.write_all(&[LONG_COUNT])?;
+                            self.out.write_all(&change.to_be_bytes())?;
+                        }
+                    }
+                }
+                self.out.write_all(&name[shared..])?;
+                self.out.write_all(b"\0")?;
+            }
+        }
+
+        self.name.clear();
+        self.name.extend_from_slice(name);
+        self.shared = shared;
+        self.first = false;
+        Ok(())
+    }
+
+    fn write_old(&mut self, change: isize, rest: &[u8]) -> io::Result<()> {
+        let change = i32::try_from(change)
+            .ok()
+            .and_then(|change| change.checked_add(OLD_OFFSET))
+            .ok_or_else(|| invalid_name("file name too long"))?;
+        match u8::try_from(change) {
+            Ok(count) if count < OLD_LONG_COUNT - 1 => self.out.write_all(&[count])?,
+            _ => {
+                self.out.write_all(&[OLD_LONG_COUNT])?;
+                self.out.write_all(&change.to_ne_bytes())?;
+            }
+        }
+
+        let mut pairs = rest.chunks_exact(2);
+        for pair in &mut pairs {
+            match self.bigrams.iter().position(|bigram| bigram == pair) {
+                Some(i) => self.out.write_all(&[0x80 | i as u8])?,
+                None => self.out.write_all(pair)?,
+            }
+        }
+        self.out.write_all(pairs.remainder())
+    }
+
+    /// Flushes the database, returning the writer it was written to.
+    pub fn finish(mut self) -> io::Result<W> {
+        self.out.flush()?;
+        Ok(self.out)
+    }
+}
+
+/// Reads the file names in a locate database.
+pub struct Decoder<R> {
+    rd: R,
+    format: Format,
+    /// The last name read.
+    name: Vec<u8>,
+    /// How much of its predecessor the last name shared.
+    shared: usize,
+    /// Whether the next name is the first in an slocate database, which is
+    /// read without a count.
+    first: bool,
+    /// The security level of an slocate database.
+    level: Option<u8>,
+    /// The bigram table, in the old format.
+    bigrams: Vec<[u8; 2]>,
+}
+
+impl<R: BufRead> Decoder<R> {
+    fn start(rd: R, format: Format) -> Self {
+        Self {
+            rd,
+            format,
+            name: vec![],
+            shared: 0,
+            first: false,
+            level: None,
+            bigrams: vec![],
+        }
+    }
+
+    /// Starts reading a database, checking that it's in the LOCATE02 format.
+    pub fn new(rd: R) -> io::Result<Self> {
+        let mut decoder = Self::start(rd, Format::Locate02);
+        match decoder.next_name() {
+            Ok(Some(name)) if name == LOCATE02 => Ok(decoder),
+            Ok(_) | Err(_) => Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                "not a LOCATE02 database",
+            )),
+        }
+    }
+
+    /// Starts reading an slocate database, checking its security level.
+    pub fn slocate(mut rd: R) -> io::Result<Self> {
+        let mut header = [0; 2];
+        match rd.read_exact(&mut header) {
+            Ok(()) if matches!(header, [b'0' | b'1', b'\0']) => {}
+            Ok(()) | Err(_) => {
+                return Err(io::Error::new(
+                    io::ErrorKind::InvalidData,
+                    "not an slocate database",
+                ))
+            }
+        }
+        let mut decoder = Self::start(rd, Format::Slocate);
+        decoder.level = Some(header[0] - b'0');
+        decoder.first = true;
+        Ok(decoder)
+    }
+
+    /// Starts reading an old database, reading its bigram table.
+    pub fn old(mut rd: R) -> io::Result<Self> {
+        let mut table = [0; 2 * BIGRAMS];
+        rd.read_exact(&mut table).map_err(|e| match e.kind() {
+            io::ErrorKind::UnexpectedEof => {
+                io::Error::new(io::ErrorKind::InvalidData, "not an old locate database")
+            }
+            _ => e,
+        })?;
+        let mut decoder = Self::start(rd, Format::Old);
+        decoder.bigrams = table.chunks_exact(2).map(|b| [b[0], b[1]]).collect();
+        Ok(decoder)
+    }
+
+    pub fn format(&self) -> Format {
+        self.format
+    }
+
+    /// The security level of an slocate database: 1 if locate should only
+    /// list the files the user can see.
+    pub fn security_level(&self) -> Option<u8> {
+        self.level
+    }
+
+    fn read_exact(&mut self, buf: &mut [u8]) -> io::Result<()> {
+        self.rd.read_exact(buf).map_err(|e| match e.kind() {
+            io::ErrorKind::UnexpectedEof => corrupt(),
+            _ => e,
+        })
+    }
+
+    /// Reads the next name, if there is one.
+    pub fn next_name(&mut self) -> io::Result<Option<&[u8]>> {
+        if self.rd.fill_buf()?.is_empty() {
+            return Ok(None);
+        }
+
+        let change = if self.first {
+            0
+        } else {
+            let mut count = [0; 1];
+            self.read_exact(&mut count)?;
+            match (self.format, count[0]) {
+                (Format::Old, OLD_LONG_COUNT) => {
+                    let mut count = [0; 4];
+                    self.read_exact(&mut count)?;
+                    (i32::from_ne_bytes(count) - OLD_OFFSET) as isize
+                }
+                (Format::Old, count) if count < OLD_LONG_COUNT => {
+                    (i32::from(count) - OLD_OFFSET) as isize
+                }
+                (Format::Old, _) => return Err(corrupt()),
+                (_, LONG_COUNT) => {
+                    let mut count = [0; 2];
+                    self.read_exact(&mut count)?;
+                    i16::from_be_bytes(count).into()
+                }
+                (_, count) => isize::from(count as i8),
+            }
+        };
+        let shared = self
+            .shared
+            .checked_add_signed(change)
+            .filter(|&shared| shared <= self.name.len())
+            .ok_or_else(corrupt)?;
+        self.name.truncate(shared);
+
+        if self.format == Format::Old {
+            self.read_old_rest()?;
+        } else {
+            self.rd.read_until(b'\0', &mut self.name)?;
+            if self.name.len() == shared || self.name.pop() != Some(b'\0') {
+                // Cut off in the middle of a name
+                return Err(corrupt());
+            }
+        }
+        self.shared = shared;
+        self.first = false;
+        Ok(Some(&self.name))
+    }
+
+    /// Reads the rest of a name in the old format, up to the next count or
+    /// the end of the database.
+    fn read_old_rest(&mut self) -> io::Result<()> {
+        loop {
+            let buf = self.rd.fill_buf()?;
+            if buf.is_empty() {
+                return Ok(());
+            }
+            let end = buf.iter().position(|&byte| byte <= OLD_LONG_COUNT);
+            for &byte in &buf[..end.unwrap_or(buf.len())] {
+                if byte & 0x80 == 0 {
+                    self.name.push(byte);
+                } else {
+                    self.name
+                        .extend_from_slice(&self.bigrams[usize::from(byte & 0x7f)]);
+                }
+            }
+            let used = end.unwrap_or(buf.len());
+            self.rd.consume(used);
+            if end.is_some() {
+                return Ok(());
+            }
+        }
+    }
+
+    pub fn get_ref(&self) -> &R {
+        &self.rd
+    }
+}
+
+fn do_frcode(args: &[&str]) -> Result<i32, Box<dyn Error>> {
+    let matches = clap::Command::new("frcode")
+        .version(crate_version!())
+        .about(
+            "Front-compress a sorted list of file names from standard input into a \
+            locate database on standard output",
+        )
+        .arg(
+            Arg::new(options::NULL)
+                .short('0')
+                .long(options::NULL)
+                .help("The file names are terminated by a null character, not a newline")
+                .action(ArgAction::SetTrue),
+        )
+        .arg(
+            Arg::new(options::SECURITY_LEVEL)
+                .short('S')
+                .long(options::SECURITY_LEVEL)
+                .value_name("LEVEL")
+                .help(
+                    "Write an slocate database with this security level (0, or 1 if \
+                    locate should only list the files the user can see)",
+                )
+                .value_parser(clap::value_parser!(u8).range(0..=1)),
+        )
+        .try_get_matches_from(args);
+
+    let matches = match matches {
+        Ok(m) => m,
+        Err(e) => match e.kind() {
+            ErrorKind::DisplayHelp | ErrorKind::DisplayVersion => {
+                // The help/version text already has a newline, so use `print!` here, not `println!`
+                print!("{e}");
+
+                return Ok(0);
+            }
+            _ => return Err(From::from(e.to_string())),
+        },
+    };
+
+    let out = BufWriter::new(stdout().lock());
+    let mut encoder = match matches.get_one::<u8>(options::SECURITY_LEVEL) {
+        Some(&level) => Encoder::slocate(out, level)?,
+        None => Encoder::new(out)?,
+    };
+
+    let delimiter = if matches.get_flag(options::NULL) {
+        b'\0'
+    } else {
+        b'\n'
+    };
+    let mut input = stdin().lock();
+    let mut name = vec![];
+    loop {
+        name.clear();
+        if input.read_until(delimiter, &mut name)? == 0 {
+            break;
+        }
+        if name.last() == Some(&delimiter) {
+            name.pop();
+        }
+        // Blank lines aren't file names
+        if !name.is_empty() {
+            encoder.write_name(&name)?;
+        }
+    }
+    encoder.finish()?;
+
+    Ok(0)
+}
+
+/// Does all the work for frcode, returning the exit status.
+///
+/// Note that the first string in args is expected to be the name of the
+/// executable.
+#[must_use]
+pub fn frcode_main(args: &[&str]) -> i32 {
+    match do_frcode(args) {
+        Ok(ret) => ret,
+        Err(e) => {
+            eprintln!("Error: {e}");
+            1
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SAMPLE: [&str; 4] = [
+        "/usr/src",
+        "/usr/src/cmd/aardvark.c",
+        "/usr/src/cmd/armadillo.c",
+        "/usr/tmp/zoo",
+    ];
+
+    /// Reads all the names from a decoder.
+    fn read_all<R: BufRead>(mut decoder: Decoder<R>) -> io::Result<Vec<String>> {
+        let mut names = vec![];
+        while let Some(name) = decoder.next_name()? {
+            names.push(String::from_utf8(name.to_vec()).unwrap());
+        }
+        Ok(names)
+    }
+
+    /// Writes all the names with an encoder.
+    fn write_all<S: AsRef<str>>(mut encoder: Encoder<Vec<u8>>, names: &[S]) -> Vec<u8> {
+        for name in names {
+            encoder.write_name(name.as_ref().as_bytes()).unwrap();
+        }
+        encoder.finish().unwrap()
+    }
+
+    #[test]
+    fn read_sample_database() {
+        // The sample from the GNU findutils manual
+        let db = b"\x00LOCATE02\0\
+                   \x00/usr/src\0\
+                   \x08/cmd/aardvark.c\0\
+                   \x06rmadillo.c\0\
+                   \xf7tmp/zoo\0";
+        assert_eq!(read_all(Decoder::new(&db[..]).unwrap()).unwrap(), SAMPLE);
+    }
+
+    #[test]
+    fn read_long_counts() {
+        let long = "a".repeat(300);
+        let mut db = b"\x00LOCATE02\0\x00".to_vec();
+        db.extend_from_slice(long.as_bytes());
+        db.extend_from_slice(b"\0\x80\x01\x2cb\0\x80\xfe\xd4c\0");
+        assert_eq!(
+            read_all(Decoder::new(&db[..]).unwrap()).unwrap(),
+            [long.clone(), format!("{long}b"), "c".to_string()]
+        );
+    }
+
+    #[test]
+    fn write_sample_database() {
+        assert_eq!(
+            write_all(Encoder::new(vec![]).unwrap(), &SAMPLE),
+            b"\x00LOCATE02\0\
+              \x00/usr/src\0\
+              \x08/cmd/aardvark.c\0\
+              \x06rmadillo.c\0\
+              \xf7tmp/zoo\0"
+        );
+    }
+
+    #[test]
+    fn write_long_counts() {
+        let long = "a".repeat(300);
+        let names = [long.clone(), format!("{long}b"), "c".to_string()];
+        let db = write_all(Encoder::new(vec![]).unwrap(), &names);
+        assert_eq!(read_all(Decoder::new(&db[..]).unwrap()).unwrap(), names);
+
+        // -128 has to be written as a long count too
+        let names = [
+            long[..128].to_string(),
+            format!("{}x", &long[..128]),
+            "b".to_string(),
+        ];
+        let db = write_all(Encoder::new(vec![]).unwrap(), &names);
+        assert!(db.ends_with(b"\0\x80\xff\x80b\0"));
+        assert_eq!(read_all(Decoder::new(&db[..]).unwrap()).unwrap(), names);
+
+        let mut encoder = Encoder::new(vec![]).unwrap();
+        assert!(encoder.write_name(b"").is_err());
+        assert!(encoder.write_name(b"a\0b").is_err());
+    }
+
+    #[test]
+    fn read_bad_databases() {
+        let not_locate02 = b"\x00LOCATE03\0\x00/usr\0";
+        assert!(Decoder::new(&not_locate02[..]).is_err());
+        assert!(Decoder::new(&b""[..]).is_err());
+
+        // Sharing more than the previous name has
+        let too_long = b"\x00LOCATE02\0\x00/usr\0\x05/src\0";
+        let decoder = Decoder::new(&too_long[..]).unwrap();
+        assert_eq!(
+            read_all(decoder).unwrap_err().kind(),
+            io::ErrorKind::InvalidData
+        );
+
+        // Cut off in the middle
+        let truncated = b"\x00LOCATE02\0\x00/usr\0\x04/sr";
+        let decoder = Decoder::new(&truncated[..]).unwrap();
+        assert_eq!(
+            read_all(decoder).unwrap_err().kind(),
+            io::ErrorKind::InvalidData
+        );
+        let truncated = b"\x00LOCATE02\0\x80\x00";
+        let decoder = Decoder::new(&truncated[..]).unwrap();
+        assert_eq!(
+            read_all(decoder).unwrap_err().kind(),
+            io::ErrorKind::InvalidData
+        );
+    }
+
+    #[test]
+    fn slocate_format() {
+        let db = write_all(Encoder::slocate(vec![], 1).unwrap(), &SAMPLE);
+        assert_eq!(
+            db,
+            b"1\0\
+              /usr/src\0\
+              \x08/cmd/aardvark.c\0\
+              \x06rmadillo.c\0\
+              \xf7tmp/zoo\0"
+        );
+
+        let decoder = Decoder::slocate(&db[..]).unwrap();
+        assert_eq!(decoder.format(), Format::Slocate);
+        assert_eq!(decoder.security_level(), Some(1));
+        assert_eq!(read_all(decoder).unwrap(), SAMPLE);
+
+        assert!(Encoder::slocate(vec![], 2).is_err());
+        assert!(Decoder::slocate(&b"\x00LOCATE02\0"[..]).is_err());
+    }
+
+    #[test]
+    fn old_format() {
+        let bigrams = common_bigrams(SAMPLE.iter().map(|name| name.as_bytes()));
+        assert_eq!(bigrams[0], *b"ar");
+        let db = write_all(Encoder::old(vec![], &bigrams).unwrap(), &SAMPLE);
+        assert!(db.len() < 2 * BIGRAMS + SAMPLE.concat().len());
+        let decoder = Decoder::old(&db[..]).unwrap();
+        assert_eq!(decoder.format(), Format::Old);
+        assert_eq!(read_all(decoder).unwrap(), SAMPLE);
+
+        // Without bigrams, each count is a byte, and names aren't terminated
+        let db = write_all(
+            Encoder::old(vec![], &[]).unwrap(),
+            &["/usr/src", "/usr/tmp"],
+        );
+        assert_eq!(&db[..2 * BIGRAMS], [0; 2 * BIGRAMS]);
+        assert_eq!(&db[2 * BIGRAMS..], b"\x0e/usr/src\x13tmp");
+
+        // Long counts and bytes that can't be stored
+        let long = "a".repeat(20);
+        let names = [long.clone(), format!("{long}\u{e9}\t"), "b".to_string()];
+        let db = write_all(Encoder::old(vec![], &[]).unwrap(), &names);
+        let mut end = vec![OLD_LONG_COUNT];
+        end.extend_from_slice(&(-20 + OLD_OFFSET).to_ne_bytes());
+        end.push(b'b');
+        assert!(db.ends_with(&end));
+        assert_eq!(
+            read_all(Decoder::old(&db[..]).unwrap()).unwrap(),
+            [long.clone(), format!("{long}???"), "b".to_string()]
+        );
+
+        assert!(Decoder::old(&b"\x00LOCATE02\0"[..]).is_err());
+    }
+}
@@ -4,5 +4,56 @@
 // license that can be found in the LICENSE file or at
 // https://opensource.org/licenses/MIT.
 
+//! Implementations of find, xargs, locate, updatedb and frcode.
+//!
+//! Besides [find::find_main()], which runs find with command-line arguments,
+//! find expressions can be run in-process.  Build a [Config], a [Matcher] for
+//! the expression, then iterate over the files it matches with [FindIter]:
+//!
+//! ```
+//! use findutils::find::matchers::build_matcher;
+//! use findutils::{Config, FindIter, StandardDependencies};
+//!
+//! let mut config = Config::builder().max_depth(2).sorted_output(true).build();
+//! let matcher = build_matcher(&["-name", "*.rs"], &mut config)?;
+//! let deps = StandardDependencies::new();
+//! for entry in FindIter::new(["src"], &config, &*matcher, &deps) {
+//!     println!("{}", entry?.path().display());
+//! }
+//! # Ok::<(), Box<dyn std::error::Error>>(())
+//! ```
+//!
+//! Matchers can be combined with each other, and with your own:
+//!
+//! ```
+//! use findutils::find::matchers::{build_matcher, AndMatcher};
+//! use findutils::{Config, FindIter, Matcher, MatcherIO, StandardDependencies, WalkEntry};
+//!
+//! struct NonEmpty;
+//!
+//! impl Matcher for NonEmpty {
+//!     fn matches(&self, entry: &WalkEntry, _: &mut MatcherIO) -> bool {
+//!         entry.metadata().is_ok_and(|meta| meta.len() > 0)
+//!     }
+//! }
+//!
+//! let mut config = Config::builder().build();
+//! let files = build_matcher(&["-type", "f"], &mut config)?;
+//! let matcher = AndMatcher::new(vec![files, NonEmpty.into_box()]);
+//! let deps = StandardDependencies::new();
+//! let found = FindIter::new(["test_data"], &config, &matcher, &deps);
+//! assert!(found.count() > 0);
+//! # Ok::<(), Box<dyn std::error::Error>>(())
+//! ```
+
+mod common;
 pub mod find;
+pub mod frcode;
+pub mod locate;
+pub mod updatedb;
 pub mod xargs;
+
+pub use find::matchers::{Cost, FileType, Follow, Matcher, MatcherIO, WalkEntry, WalkError};
+pub use find::{
+    Config, ConfigBuilder, Dependencies, FindIter, SearchStrategy, StandardDependencies,
+};
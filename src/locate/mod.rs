@@ -0,0 +1,542 @@
+// This file is part of the uutils findutils package.
+//
+// For the full copyright and license information, please view the LICENSE
+// file that was distributed with this source code.
+
+//! locate: searches the databases of file names made by updatedb.
+
+use std::env;
+use std::error::Error;
+use std::fs::File;
+use std::io::{self, stdin, stdout, BufReader, BufWriter, Read, Write};
+use std::path::PathBuf;
+
+use clap::{crate_version, error::ErrorKind, Arg, ArgAction};
+use onig::Regex;
+
+use crate::find::matchers::glob::Pattern as Glob;
+use crate::find::matchers::regex::RegexType;
+use crate::frcode::Decoder;
+
+mod options {
+    pub const PATTERN: &str = "PATTERN";
+
+    pub const BASENAME: &str = "basename";
+    pub const COUNT: &str = "count";
+    pub const DATABASE: &str = "database";
+    pub const EXISTING: &str = "existing";
+    pub const IGNORE_CASE: &str = "ignore-case";
+    pub const LIMIT: &str = "limit";
+    pub const NON_EXISTING: &str = "non-existing";
+    pub const NULL: &str = "null";
+    pub const REGEX: &str = "regex";
+    pub const REGEXTYPE: &str = "regextype";
+    pub const STATISTICS: &str = "statistics";
+    pub const WHOLENAME: &str = "wholename";
+}
+
+/// The database to search if neither -d nor `LOCATE_PATH` name any.
+pub const DEFAULT_DB: &str = "/var/cache/locate/locatedb";
+
+/// Counts the bytes read through it, for the database size in -S.
+struct CountingReader<R> {
+    inner: R,
+    count: u64,
+}
+
+impl<R: Read> Read for CountingReader<R> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let n = self.inner.read(buf)?;
+        self.count += n as u64;
+        Ok(n)
+    }
+}
+
+/// A pattern to look for in the database.
+enum Pattern {
+    /// A pattern without wildcards matches names that contain it.
+    Substring { text: String, ignore_case: bool },
+    /// A pattern with wildcards has to match the whole name.
+    Glob(Glob),
+    /// -r matches names with a match anywhere in them.
+    Regex(Regex),
+}
+
+impl Pattern {
+    fn new(pattern: &str, options: &Options) -> Result<Self, Box<dyn Error>> {
+        if let Some(regex_type) = options.regex {
+            let regex = regex_type
+                .compile(pattern, options.ignore_case)
+                .map_err(|e| format!("invalid regular expression '{pattern}': {e}"))?;
+            Ok(Self::Regex(regex))
+        } else if pattern.contains(['*', '?', '[', '\\']) {
+            Ok(Self::Glob(Glob::new(pattern, options.ignore_case)))
+        } else if options.ignore_case {
+            Ok(Self::Substring {
+                text: pattern.to_lowercase(),
+                ignore_case: true,
+            })
+        } else {
+            Ok(Self::Substring {
+                text: pattern.to_string(),
+                ignore_case: false,
+            })
+        }
+    }
+
+    fn matches(&self, name: &str) -> bool {
+        match self {
+            Self::Substring {
+                text,
+                ignore_case: true,
+            } => name.to_lowercase().contains(text),
+            Self::Substring { text, .. } => name.contains(text),
+            Self::Glob(glob) => glob.matches(name),
+            Self::Regex(regex) => regex.find(name).is_some(),
+        }
+    }
+}
+
+struct Options {
+    basename: bool,
+    count: bool,
+    databases: Vec<String>,
+    /// Only show names that exist (Some(true), -e) or don't (Some(false), -E).
+    existing: Option<bool>,
+    ignore_case: bool,
+    limit: Option<usize>,
+    null: bool,
+    /// The kind of regular expressions the patterns are, with -r.
+    regex: Option<RegexType>,
+    statistics: bool,
+}
+
+/// Summary information about the names in a database, for -S.
+#[derive(Default)]
+struct Statistics {
+    names: u64,
+    total_len: u64,
+    whitespace: u64,
+    newlines: u64,
+    high_bit: u64,
+}
+
+impl Statistics {
+    fn add(&mut self, name: &[u8]) {
+        self.names += 1;
+        self.total_len += name.len() as u64;
+        if name.iter().any(|&c| c != b'\n' && c.is_ascii_whitespace()) {
+            self.whitespace += 1;
+        }
+        if name.contains(&b'\n') {
+            self.newlines += 1;
+        }
+        if !name.is_ascii() {
+            self.high_bit += 1;
+        }
+    }
+
+    /// Writes the statistics in the same format as GNU locate.
+    fn write(
+        &self,
+        out: &mut impl Write,
+        db: &str,
+        db_size: u64,
+        filtered: bool,
+    ) -> io::Result<()> {
+        let plural = |n: u64, one: &'static str, many: &'static str| match n {
+            1 => one,
+            _ => many,
+        };
+
+        writeln!(out, "Database {db} is in the LOCATE02 format.")?;
+        writeln!(
+            out,
+            "Locate database size: {db_size} {}",
+            plural(db_size, "byte", "bytes")
+        )?;
+        let label = if filtered {
+            "Matching Filenames"
+        } else {
+            "All Filenames"
+        };
+        writeln!(out, "{label}: {}", self.names)?;
+        writeln!(
+            out,
+            "File names have a cumulative length of {} {}.",
+            self.total_len,
+            plural(self.total_len, "byte", "bytes")
+        )?;
+        writeln!(out, "Of those file names,\n")?;
+        let verb = |n| plural(n, "contains", "contain");
+        writeln!(
+            out,
+            "\t{} {} whitespace, ",
+            self.whitespace,
+            verb(self.whitespace)
+        )?;
+        writeln!(
+            out,
+            "\t{} {} newline characters, ",
+            self.newlines,
+            verb(self.newlines)
+        )?;
+        writeln!(
+            out,
+            "\tand {} {} characters with the high bit set.",
+            self.high_bit,
+            verb(self.high_bit)
+        )?;
+        if db_size > 0 {
+            if self.total_len > 0 {
+                let saved = self.total_len as f64 - db_size as f64;
+                let ratio = 100.0 * saved / self.total_len as f64;
+                writeln!(out, "Compression ratio {ratio:4.2}% (higher is better)")?;
+            } else {
+                writeln!(out, "Compression ratio is undefined")?;
+            }
+        }
+        writeln!(out)
+    }
+}
+
+/// Converts a name from the database to a path.
+fn name_to_path(name: &[u8]) -> PathBuf {
+    #[cfg(unix)]
+    {
+        use std::os::unix::ffi::OsStrExt;
+        PathBuf::from(std::ffi::OsStr::from_bytes(name))
+    }
+    #[cfg(not(unix))]
+    PathBuf::from(String::from_utf8_lossy(name).into_owned())
+}
+
+/// The state of a search through all the databases.
+struct Search<'a, W> {
+    options: &'a Options,
+    patterns: Vec<Pattern>,
+    out: W,
+    found: usize,
+    read_stdin: bool,
+}
+
+impl<W: Write> Search<'_, W> {
+    fn limit_reached(&self) -> bool {
+        self.options.limit.is_some_and(|limit| self.found >= limit)
+    }
+
+    fn matches(&self, name: &[u8]) -> bool {
+        if !self.patterns.is_empty() {
+            let name = String::from_utf8_lossy(name);
+            let name = match name.rsplit('/').next() {
+                Some(base) if self.options.basename && !base.is_empty() => base,
+                _ => &name,
+            };
+            if !self.patterns.iter().any(|p| p.matches(name)) {
+                return false;
+            }
+        }
+
+        match self.options.existing {
+            Some(existing) => name_to_path(name).exists() == existing,
+            None => true,
+        }
+    }
+
+    /// Searches a single database (or standard input, for "-").
+    fn search(&mut self, db: &str) -> Result<(), Box<dyn Error>> {
+        let rd: Box<dyn Read> = if db == "-" {
+            if self.read_stdin {
+                writeln!(
+                    &mut io::stderr(),
+                    "Warning: the database can only be read from standard input once"
+                )?;
+                return Ok(());
+            }
+            self.read_stdin = true;
+            Box::new(stdin().lock())
+        } else {
+            Box::new(File::open(db)?)
+        };
+        let rd = CountingReader {
+            inner: rd,
+            count: 0,
+        };
+        let mut reader = Decoder::new(BufReader::new(rd))?;
+
+        let options = self.options;
+        let mut stats = Statistics::default();
+        while !self.limit_reached() {
+            let Some(name) = reader.next_name()? else {
+                break;
+            };
+            if !self.matches(name) {
+                continue;
+            }
+
+            self.found += 1;
+            if options.statistics {
+                stats.add(name);
+            } else if !options.count {
+                self.out.write_all(name)?;
+                self.out
+                    .write_all(if options.null { b"\0" } else { b"\n" })?;
+            }
+        }
+
+        if options.statistics {
+            let db_size = reader.get_ref().get_ref().count;
+            let filtered = !self.patterns.is_empty() || options.existing.is_some();
+            stats.write(&mut self.out, db, db_size, filtered)?;
+        }
+        Ok(())
+    }
+}
+
+/// Gets the databases to search, from -d or `LOCATE_PATH`.  Empty entries
+/// stand for the default database.
+fn databases(matches: &clap::ArgMatches) -> Vec<String> {
+    let paths: Vec<String> = match matches.get_many::<String>(options::DATABASE) {
+        Some(paths) => paths.cloned().collect(),
+        None => match env::var("LOCATE_PATH") {
+            Ok(path) => vec![path],
+            Err(_) => vec![String::new()],
+        },
+    };
+
+    paths
+        .iter()
+        .flat_map(|path| path.split(':'))
+        .map(|db| match db {
+            "" => DEFAULT_DB.to_string(),
+            db => db.to_string(),
+        })
+        .collect()
+}
+
+fn do_locate(args: &[&str]) -> Result<i32, Box<dyn Error>> {
+    let matches = clap::Command::new("locate")
+        .version(crate_version!())
+        .about("Search databases of file names made by updatedb")
+        .arg(
+            Arg::new(options::PATTERN)
+                .help("The patterns to search for")
+                .num_args(0..),
+        )
+        .arg(
+            Arg::new(options::BASENAME)
+                .short('b')
+                .long(options::BASENAME)
+                .help("Match only the base name of each file")
+                .overrides_with(options::WHOLENAME)
+                .action(ArgAction::SetTrue),
+        )
+        .arg(
+            Arg::new(options::WHOLENAME)
+                .short('w')
+                .long(options::WHOLENAME)
+                .help("Match the whole name of each file (the default)")
+                .overrides_with(options::BASENAME)
+                .action(ArgAction::SetTrue),
+        )
+        .arg(
+            Arg::new(options::COUNT)
+                .short('c')
+                .long(options::COUNT)
+                .help("Print the number of matches instead of the names")
+                .action(ArgAction::SetTrue),
+        )
+        .arg(
+            Arg::new(options::DATABASE)
+                .short('d')
+                .long(options::DATABASE)
+                .value_name("PATH")
+                .help(
+                    "Search the databases in PATH (a colon-separated list, in which \
+                    - is standard input) instead of the default",
+                )
+                .action(ArgAction::Append),
+        )
+        .arg(
+            Arg::new(options::EXISTING)
+                .short('e')
+                .long(options::EXISTING)
+                .help("Only print the names of files that still exist")
+                .overrides_with(options::NON_EXISTING)
+                .action(ArgAction::SetTrue),
+        )
+        .arg(
+            Arg::new(options::NON_EXISTING)
+                .short('E')
+                .long(options::NON_EXISTING)
+                .help("Only print the names of files that no longer exist")
+                .overrides_with(options::EXISTING)
+                .action(ArgAction::SetTrue),
+        )
+        .arg(
+            Arg::new(options::IGNORE_CASE)
+                .short('i')
+                .long(options::IGNORE_CASE)
+                .help("Ignore case distinctions in patterns and names")
+                .action(ArgAction::SetTrue),
+        )
+        .arg(
+            Arg::new(options::LIMIT)
+                .short('l')
+                .long(options::LIMIT)
+                .value_name("N")
+                .help("Stop after N matches")
+                .value_parser(clap::value_parser!(usize)),
+        )
+        .arg(
+            Arg::new(options::NULL)
+                .short('0')
+                .long(options::NULL)
+                .help("Separate the names with NUL characters instead of newlines")
+                .action(ArgAction::SetTrue),
+        )
+        .arg(
+            Arg::new(options::REGEX)
+                .short('r')
+                .long(options::REGEX)
+                .help("The patterns are regular expressions, not wildcards")
+                .action(ArgAction::SetTrue),
+        )
+        .arg(
+            Arg::new(options::REGEXTYPE)
+                .long(options::REGEXTYPE)
+                .value_name("TYPE")
+                .help("The type of regular expressions used with -r (default: emacs)")
+                .value_parser(|s: &str| s.parse::<RegexType>()),
+        )
+        .arg(
+            Arg::new(options::STATISTICS)
+                .short('S')
+                .long(options::STATISTICS)
+                .help("Print statistics about each database instead of the names")
+                .action(ArgAction::SetTrue),
+        )
+        .try_get_matches_from(args);
+
+    let matches = match matches {
+        Ok(m) => m,
+        Err(e) => match e.kind() {
+            ErrorKind::DisplayHelp | ErrorKind::DisplayVersion => {
+                // The help/version text already has a newline, so use `print!` here, not `println!`
+                print!("{e}");
+
+                return Ok(0);
+            }
+            _ => return Err(From::from(e.to_string())),
+        },
+    };
+
+    let regex_type = matches
+        .get_one::<RegexType>(options::REGEXTYPE)
+        .copied()
+        .unwrap_or_default();
+    let existing = if matches.get_flag(options::EXISTING) {
+        Some(true)
+    } else if matches.get_flag(options::NON_EXISTING) {
+        Some(false)
+    } else {
+        None
+    };
+    let options = Options {
+        basename: matches.get_flag(options::BASENAME),
+        count: matches.get_flag(options::COUNT),
+        databases: databases(&matches),
+        existing,
+        ignore_case: matches.get_flag(options::IGNORE_CASE),
+        limit: matches.get_one::<usize>(options::LIMIT).copied(),
+        null: matches.get_flag(options::NULL),
+        regex: matches.get_flag(options::REGEX).then_some(regex_type),
+        statistics: matches.get_flag(options::STATISTICS),
+    };
+
+    let patterns = matches
+        .get_many::<String>(options::PATTERN)
+        .unwrap_or_default()
+        .map(|pattern| Pattern::new(pattern, &options))
+        .collect::<Result<Vec<_>, _>>()?;
+    if patterns.is_empty() && !options.statistics {
+        return Err(From::from("no pattern to search for specified"));
+    }
+
+    let mut search = Search {
+        options: &options,
+        patterns,
+        out: BufWriter::new(stdout().lock()),
+        found: 0,
+        read_stdin: false,
+    };
+    let mut failed = false;
+    for db in &options.databases {
+        if search.limit_reached() {
+            break;
+        }
+        if let Err(e) = search.search(db) {
+            search.out.flush()?;
+            writeln!(&mut io::stderr(), "Error: {db}: {e}")?;
+            failed = true;
+        }
+    }
+    if options.count && !options.statistics {
+        writeln!(search.out, "{}", search.found)?;
+    }
+    search.out.flush()?;
+
+    Ok(if failed {
+        1
+    } else if search.found > 0 || (options.statistics && search.patterns.is_empty()) {
+        0
+    } else {
+        1
+    })
+}
+
+/// Does all the work for locate, returning the exit status: 0 if anything was
+/// found, or 1 if not (or if there was an error).
+///
+/// Note that the first string in args is expected to be the name of the
+/// executable.
+#[must_use]
+pub fn locate_main(args: &[&str]) -> i32 {
+    match do_locate(args) {
+        Ok(ret) => ret,
+        Err(e) => {
+            eprintln!("Error: {e}");
+            1
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn statistics_format() {
+        let mut stats = Statistics::default();
+        stats.add(b"/usr/src");
+        stats.add(b"/usr/my file");
+        stats.add(b"/usr/\xe9t\xe9\n");
+
+        let mut out = vec![];
+        stats.write(&mut out, "db", 20, false).unwrap();
+        assert_eq!(
+            String::from_utf8(out).unwrap(),
+            "Database db is in the LOCATE02 format.\n\
+             Locate database size: 20 bytes\n\
+             All Filenames: 3\n\
+             File names have a cumulative length of 29 bytes.\n\
+             Of those file names,\n\
+             \n\
+             \t1 contains whitespace, \n\
+             \t1 contains newline characters, \n\
+             \tand 1 contains characters with the high bit set.\n\
+             Compression ratio 31.03% (higher is better)\n\
+             \n"
+        );
+    }
+}
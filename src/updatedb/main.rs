@@ -0,0 +1,14 @@
+// This file is part of the uutils findutils package.
+//
+// For the full copyright and license information, please view the LICENSE
+// file that was distributed with this source code.
+
+fn main() {
+    let args = std::env::args().collect::<Vec<String>>();
+    std::process::exit(findutils::updatedb::updatedb_main(
+        &args
+            .iter()
+            .map(std::convert::AsRef::as_ref)
+            .collect::<Vec<&str>>(),
+    ))
+}
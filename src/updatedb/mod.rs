@@ -0,0 +1,247 @@
+// This file is part of the uutils findutils package.
+//
+// For the full copyright and license information, please view the LICENSE
+// file that was distributed with this source code.
+
+//! updatedb: makes the databases of file names that locate searches.
+
+use std::env;
+use std::error::Error;
+use std::fs::{self, File};
+use std::io::{self, BufWriter, Write};
+use std::path::{Path, PathBuf};
+use std::process;
+
+use clap::{crate_version, error::ErrorKind, Arg};
+
+use crate::find::matchers::build_matcher;
+use crate::find::{Config, FindIter, StandardDependencies};
+use crate::frcode::Encoder;
+use crate::locate::DEFAULT_DB;
+
+mod options {
+    pub const FINDOPTIONS: &str = "findoptions";
+    pub const LOCALPATHS: &str = "localpaths";
+    pub const OUTPUT: &str = "output";
+    pub const PRUNEFS: &str = "prunefs";
+    pub const PRUNEPATHS: &str = "prunepaths";
+}
+
+/// Gets a space-separated list from an option, or else an environment
+/// variable, or else the default.
+fn list(matches: &clap::ArgMatches, option: &str, var: &str, default: &str) -> Vec<String> {
+    let list = match matches.get_one::<String>(option) {
+        Some(list) => list.clone(),
+        None => env::var(var).unwrap_or_else(|_| default.to_string()),
+    };
+    list.split_whitespace().map(String::from).collect()
+}
+
+/// Makes the find expression that skips the directories that shouldn't be in
+/// the database, e.g. for the default lists:
+///
+/// ```text
+/// ! ( -type d ( -fstype nfs -o -fstype NFS -o -fstype proc -o -regex /tmp -o ... ) -prune )
+/// ```
+fn prune_expression(
+    find_options: &[String],
+    prune_fs: &[String],
+    prune_paths: &[String],
+) -> Vec<String> {
+    let mut args = find_options.to_vec();
+
+    let tests: Vec<[&str; 2]> = prune_fs
+        .iter()
+        .map(|fs| ["-fstype", fs.as_str()])
+        .chain(prune_paths.iter().map(|path| ["-regex", path.as_str()]))
+        .collect();
+    if !tests.is_empty() {
+        args.extend(["!", "(", "-type", "d", "("].map(String::from));
+        for (i, test) in tests.iter().enumerate() {
+            if i > 0 {
+                args.push("-o".to_string());
+            }
+            args.extend(test.map(String::from));
+        }
+        args.extend([")", "-prune", ")"].map(String::from));
+    }
+    args
+}
+
+/// Gets the bytes of a path, as they're stored in the database.
+fn path_bytes(path: &Path) -> Vec<u8> {
+    #[cfg(unix)]
+    {
+        use std::os::unix::ffi::OsStrExt;
+        path.as_os_str().as_bytes().to_vec()
+    }
+    #[cfg(not(unix))]
+    path.to_string_lossy().into_owned().into_bytes()
+}
+
+/// Writes a database of `names` to `output`.  It's written to a temporary
+/// file first and then renamed, so that locate never sees half a database.
+fn write_db(output: &Path, names: &[Vec<u8>]) -> io::Result<()> {
+    let file_name = output
+        .file_name()
+        .ok_or_else(|| io::Error::new(io::ErrorKind::InvalidInput, "the output must be a file"))?;
+    let mut temp_name = file_name.to_os_string();
+    temp_name.push(format!(".{}.tmp", process::id()));
+    let temp = output.with_file_name(temp_name);
+
+    let write = || -> io::Result<()> {
+        let file = File::options().write(true).create_new(true).open(&temp)?;
+        let mut writer = Encoder::new(BufWriter::new(file))?;
+        for name in names {
+            writer.write_name(name)?;
+        }
+        let file = writer.finish()?.into_inner().map_err(|e| e.into_error())?;
+        file.sync_all()?;
+        fs::rename(&temp, output)
+    };
+    write().inspect_err(|_| {
+        let _ = fs::remove_file(&temp);
+    })
+}
+
+fn do_updatedb(args: &[&str]) -> Result<i32, Box<dyn Error>> {
+    let matches = clap::Command::new("updatedb")
+        .version(crate_version!())
+        .about("Update the database of file names that locate searches")
+        .arg(
+            Arg::new(options::FINDOPTIONS)
+                .long(options::FINDOPTIONS)
+                .value_name("OPTIONS")
+                .help(
+                    "Options to pass on to find, like -xdev (default: $FINDOPTIONS, \
+                    or none)",
+                ),
+        )
+        .arg(
+            Arg::new(options::LOCALPATHS)
+                .long(options::LOCALPATHS)
+                .value_name("PATHS")
+                .help("The directories to put in the database (default: /)"),
+        )
+        .arg(
+            Arg::new(options::OUTPUT)
+                .long(options::OUTPUT)
+                .value_name("DBFILE")
+                .help(format!("The database to write (default: {DEFAULT_DB})")),
+        )
+        .arg(
+            Arg::new(options::PRUNEFS)
+                .long(options::PRUNEFS)
+                .value_name("TYPES")
+                .help(
+                    "File system types to leave out of the database (default: \
+                    $PRUNEFS, or 'nfs NFS proc')",
+                ),
+        )
+        .arg(
+            Arg::new(options::PRUNEPATHS)
+                .long(options::PRUNEPATHS)
+                .value_name("PATHS")
+                .help(
+                    "Directories to leave out of the database, as regular \
+                    expressions that match the whole path (default: $PRUNEPATHS, or \
+                    '/tmp /usr/tmp /var/tmp /afs')",
+                ),
+        )
+        .try_get_matches_from(args);
+
+    let matches = match matches {
+        Ok(m) => m,
+        Err(e) => match e.kind() {
+            ErrorKind::DisplayHelp | ErrorKind::DisplayVersion => {
+                // The help/version text already has a newline, so use `print!` here, not `println!`
+                print!("{e}");
+
+                return Ok(0);
+            }
+            _ => return Err(From::from(e.to_string())),
+        },
+    };
+
+    let local_paths = list(&matches, options::LOCALPATHS, "LOCALPATHS", "/");
+    let find_options = list(&matches, options::FINDOPTIONS, "FINDOPTIONS", "");
+    let prune_fs = list(&matches, options::PRUNEFS, "PRUNEFS", "nfs NFS proc");
+    let prune_paths = list(
+        &matches,
+        options::PRUNEPATHS,
+        "PRUNEPATHS",
+        "/tmp /usr/tmp /var/tmp /afs",
+    );
+    let output = PathBuf::from(
+        matches
+            .get_one::<String>(options::OUTPUT)
+            .map_or(DEFAULT_DB, String::as_str),
+    );
+
+    let expr = prune_expression(&find_options, &prune_fs, &prune_paths);
+    let expr: Vec<&str> = expr.iter().map(String::as_str).collect();
+    let mut config = Config::default();
+    let matcher = build_matcher(&expr, &mut config)
+        .map_err(|e| format!("invalid find options '{}': {e}", find_options.join(" ")))?;
+
+    let deps = StandardDependencies::new();
+    let mut names = vec![];
+    let mut found = FindIter::new(&local_paths, &config, &*matcher, &deps);
+    for result in &mut found {
+        match result {
+            Ok(entry) => names.push(path_bytes(entry.path())),
+            Err(e) => writeln!(&mut io::stderr(), "Error: {e}")?,
+        }
+    }
+
+    // Sorted case-insensitively, for the convenience of anyone reading the
+    // names in order
+    names.sort_by(|a, b| {
+        a.to_ascii_lowercase()
+            .cmp(&b.to_ascii_lowercase())
+            .then_with(|| a.cmp(b))
+    });
+    names.dedup();
+    write_db(&output, &names).map_err(|e| format!("{}: {e}", output.display()))?;
+
+    Ok(found.exit_code())
+}
+
+/// Does all the work for updatedb, returning the exit status.  The database
+/// is written even if some directories couldn't be read, but the exit status
+/// is then 1, like find's.
+///
+/// Note that the first string in args is expected to be the name of the
+/// executable.
+#[must_use]
+pub fn updatedb_main(args: &[&str]) -> i32 {
+    match do_updatedb(args) {
+        Ok(ret) => ret,
+        Err(e) => {
+            eprintln!("Error: {e}");
+            1
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn prune_expression_skips_file_systems_and_paths() {
+        let strings = |list: &[&str]| list.iter().map(|s| s.to_string()).collect::<Vec<_>>();
+        assert_eq!(
+            prune_expression(
+                &strings(&["-xdev"]),
+                &strings(&["nfs", "proc"]),
+                &strings(&["/tmp"])
+            ),
+            strings(&[
+                "-xdev", "!", "(", "-type", "d", "(", "-fstype", "nfs", "-o", "-fstype", "proc",
+                "-o", "-regex", "/tmp", ")", "-prune", ")"
+            ])
+        );
+        assert_eq!(prune_expression(&[], &[], &[]), Vec::<String>::new());
+    }
+}
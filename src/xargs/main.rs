@@ -5,6 +5,12 @@
 // https://opensource.org/licenses/MIT.
 
 fn main() {
+    // Use the user's locale to interpret answers to -p prompts.
+    #[cfg(unix)]
+    unsafe {
+        uucore::libc::setlocale(uucore::libc::LC_MESSAGES, c"".as_ptr());
+    }
+
     let args = std::env::args().collect::<Vec<String>>();
     std::process::exit(findutils::xargs::xargs_main(
         &args
@@ -5,44 +5,61 @@
 // https://opensource.org/licenses/MIT.
 
 use std::{
+    borrow::Cow,
+    cell::RefCell,
     collections::HashMap,
     error::Error,
     ffi::{OsStr, OsString},
     fmt::Display,
     fs,
-    io::{self, BufRead, BufReader, Read},
-    process::{Command, Stdio},
+    io::{self, BufRead, BufReader, IsTerminal, Read, Write},
+    process::{Child, Command, ExitStatus, Stdio},
 };
 
 use clap::{crate_version, error::ErrorKind, Arg, ArgAction};
 
+use crate::common::limits::{count_osstr_chars_for_exec, count_osstr_size_for_exec, SystemLimits};
+use crate::common::prompt::{is_affirmative, open_tty};
+
 mod options {
     pub const COMMAND: &str = "COMMAND";
 
     pub const ARG_FILE: &str = "arg-file";
     pub const DELIMITER: &str = "delimiter";
+    pub const EOF: &str = "eof";
+    pub const EOF_E: &str = "eof-E";
     pub const EXIT: &str = "exit";
+    pub const INTERACTIVE: &str = "interactive";
     pub const MAX_ARGS: &str = "max-args";
     pub const MAX_CHARS: &str = "max-chars";
     pub const MAX_LINES: &str = "max-lines";
     pub const MAX_PROCS: &str = "max-procs";
     pub const NO_RUN_IF_EMPTY: &str = "no-run-if-empty";
     pub const NULL: &str = "null";
+    pub const OPEN_TTY: &str = "open-tty";
+    pub const PROCESS_SLOT_VAR: &str = "process-slot-var";
     pub const REPLACE: &str = "replace";
     pub const REPLACE_I: &str = "replace-I";
+    pub const SHOW_LIMITS: &str = "show-limits";
     pub const VERBOSE: &str = "verbose";
 }
 
 struct Options {
     arg_file: Option<String>,
     delimiter: Option<u8>,
+    eof: Option<String>,
     exit_if_pass_char_limit: bool,
+    interactive: bool,
     max_args: Option<usize>,
     max_chars: Option<usize>,
     max_lines: Option<usize>,
+    max_procs: usize,
     no_run_if_empty: bool,
     null: bool,
+    open_tty: bool,
+    process_slot_var: Option<OsString>,
     replace: Option<String>,
+    show_limits: bool,
     verbose: bool,
 }
 
@@ -134,24 +151,12 @@ impl Clone for LimiterCollection {
     }
 }
 
-#[cfg(windows)]
-fn count_osstr_chars_for_exec(s: &OsStr) -> usize {
-    use std::os::windows::ffi::OsStrExt;
-    // Include +1 for either the null terminator or trailing space.
-    s.encode_wide().count() + 1
-}
-
-#[cfg(unix)]
-fn count_osstr_chars_for_exec(s: &OsStr) -> usize {
-    use std::os::unix::ffi::OsStrExt;
-    // Include +1 for the null terminator.
-    s.as_bytes().len() + 1
-}
-
 #[derive(Clone)]
 struct MaxCharsCommandSizeLimiter {
     current_size: usize,
     max_chars: usize,
+    /// How to count the size of an argument.
+    count: fn(&OsStr) -> usize,
 }
 
 impl MaxCharsCommandSizeLimiter {
@@ -159,30 +164,44 @@ impl MaxCharsCommandSizeLimiter {
         Self {
             current_size: 0,
             max_chars,
+            count: count_osstr_chars_for_exec,
         }
     }
 
-    #[cfg(windows)]
-    fn new_system(_env: &HashMap<OsString, OsString>) -> MaxCharsCommandSizeLimiter {
-        // Taken from the CreateProcess docs.
-        const MAX_CMDLINE: usize = 32767;
-        MaxCharsCommandSizeLimiter::new(MAX_CMDLINE)
+    fn new_system(limits: &SystemLimits) -> Self {
+        Self {
+            current_size: 0,
+            max_chars: limits.max_command_size,
+            count: count_osstr_size_for_exec,
+        }
     }
+}
 
-    #[cfg(unix)]
-    fn new_system(env: &HashMap<OsString, OsString>) -> Self {
-        // POSIX requires that we leave 2048 bytes of space so that the child processes
-        // can have room to set their own environment variables.
-        const ARG_HEADROOM: usize = 2048;
-        let arg_max = unsafe { uucore::libc::sysconf(uucore::libc::_SC_ARG_MAX) } as usize;
-
-        let env_size: usize = env
-            .iter()
-            .map(|(var, value)| count_osstr_chars_for_exec(var) + count_osstr_chars_for_exec(value))
-            .sum();
-
-        Self::new(arg_max - ARG_HEADROOM - env_size)
-    }
+/// Prints the limits to stderr in the same format as GNU xargs' --show-limits,
+/// where `max_chars` is the value of -s, if any.
+fn show_limits(limits: &SystemLimits, max_chars: Option<usize>) {
+    let buffer_size = max_chars.map_or(limits.max_command_size, |max_chars| {
+        max_chars.min(limits.max_command_size)
+    });
+
+    eprintln!(
+        "Your environment variables take up {} bytes",
+        limits.env_size
+    );
+    eprintln!(
+        "POSIX upper limit on argument length (this system): {}",
+        limits.arg_max
+    );
+    eprintln!(
+        "POSIX smallest allowable upper limit on argument length (all systems): {}",
+        SystemLimits::POSIX_ARG_MAX
+    );
+    eprintln!(
+        "Maximum length of command we could actually use: {}",
+        limits.max_command_size
+    );
+    eprintln!("Size of command buffer we are actually using: {buffer_size}");
+    eprintln!("Maximum parallelism (--max-procs must be no greater): {MAX_PROCS_LIMIT}");
 }
 
 impl CommandSizeLimiter for MaxCharsCommandSizeLimiter {
@@ -191,7 +210,7 @@ impl CommandSizeLimiter for MaxCharsCommandSizeLimiter {
         arg: Argument,
         cursor: LimiterCursor<'_>,
     ) -> Result<Argument, ExhaustedCommandSpace> {
-        let chars = count_osstr_chars_for_exec(&arg.arg);
+        let chars = (self.count)(&arg.arg);
         if self.current_size + chars <= self.max_chars {
             let arg = cursor.try_next(arg)?;
             self.current_size += chars;
@@ -333,17 +352,208 @@ impl Display for CommandExecutionError {
 
 impl Error for CommandExecutionError {}
 
+fn command_result(status: ExitStatus) -> Result<CommandResult, CommandExecutionError> {
+    if status.success() {
+        Ok(CommandResult::Success)
+    } else if let Some(err) = status.code() {
+        if err == 255 {
+            Err(CommandExecutionError::UrgentlyFailed)
+        } else {
+            Ok(CommandResult::Failure)
+        }
+    } else {
+        #[cfg(unix)]
+        {
+            use std::os::unix::process::ExitStatusExt;
+            if let Some(signal) = status.signal() {
+                Err(CommandExecutionError::Killed { signal })
+            } else {
+                Err(CommandExecutionError::Unknown)
+            }
+        }
+
+        #[cfg(not(unix))]
+        Err(CommandExecutionError::Unknown)
+    }
+}
+
+struct RunningCommand {
+    child: Child,
+    /// The process slot (see --process-slot-var) this command is using.
+    slot: usize,
+}
+
+/// Keeps up to `max_procs` commands running at once (-P), waiting for one
+/// to exit before starting another once the pool is full.
+struct ProcessPool {
+    /// The maximum number of concurrent children, or None for no limit.
+    max_procs: Option<usize>,
+    children: Vec<RunningCommand>,
+    result: CommandResult,
+}
+
+impl ProcessPool {
+    fn new(max_procs: usize) -> Self {
+        Self {
+            // -P 0 means run as many commands at once as possible.
+            max_procs: if max_procs == 0 {
+                None
+            } else {
+                Some(max_procs)
+            },
+            children: vec![],
+            result: CommandResult::Success,
+        }
+    }
+
+    /// Starts the command once there is room in the pool. If `slot_var` is
+    /// given, it is set in the command's environment to the lowest process
+    /// slot number not used by any other running command, replacing the
+    /// placeholder that was counted against the command size limit.
+    fn spawn(
+        &mut self,
+        command: &mut Command,
+        slot_var: Option<&OsStr>,
+    ) -> Result<(), CommandExecutionError> {
+        if let Some(max_procs) = self.max_procs {
+            while self.children.len() >= max_procs {
+                self.wait_any()?;
+            }
+        }
+
+        let slot = (0..)
+            .find(|slot| self.children.iter().all(|c| c.slot != *slot))
+            .unwrap();
+        if let Some(slot_var) = slot_var {
+            command.env(slot_var, slot.to_string());
+        }
+
+        match command.spawn() {
+            Ok(child) => {
+                self.children.push(RunningCommand { child, slot });
+                Ok(())
+            }
+            Err(e) if e.kind() == io::ErrorKind::NotFound => Err(CommandExecutionError::NotFound),
+            Err(e) => Err(CommandExecutionError::CannotRun(e)),
+        }
+    }
+
+    /// Waits for any one of the running children to exit.
+    fn wait_any(&mut self) -> Result<(), CommandExecutionError> {
+        let status = if self.children.len() == 1 {
+            let status = self.children[0]
+                .child
+                .wait()
+                .map_err(CommandExecutionError::CannotRun)?;
+            self.children.clear();
+            status
+        } else {
+            self.reap_any()?
+        };
+
+        self.result.combine(command_result(status)?);
+        Ok(())
+    }
+
+    #[cfg(unix)]
+    #[allow(clippy::zombie_processes)] // waitpid() has already reaped the child
+    fn reap_any(&mut self) -> Result<ExitStatus, CommandExecutionError> {
+        use std::os::unix::process::ExitStatusExt;
+        use uucore::libc::{pid_t, waitpid};
+
+        loop {
+            let mut status = 0;
+            let pid = unsafe { waitpid(-1, &mut status, 0) };
+            if pid < 0 {
+                let err = io::Error::last_os_error();
+                if err.kind() == io::ErrorKind::Interrupted {
+                    continue;
+                }
+                return Err(CommandExecutionError::CannotRun(err));
+            }
+
+            // Ignore any children that weren't started by the pool.
+            if let Some(i) = self
+                .children
+                .iter()
+                .position(|c| c.child.id() as pid_t == pid)
+            {
+                self.children.swap_remove(i);
+                return Ok(ExitStatus::from_raw(status));
+            }
+        }
+    }
+
+    #[cfg(not(unix))]
+    fn reap_any(&mut self) -> Result<ExitStatus, CommandExecutionError> {
+        // There's no portable way to wait for whichever child exits first, so poll.
+        loop {
+            for i in 0..self.children.len() {
+                if let Some(status) = self.children[i]
+                    .child
+                    .try_wait()
+                    .map_err(CommandExecutionError::CannotRun)?
+                {
+                    self.children.swap_remove(i);
+                    return Ok(status);
+                }
+            }
+            std::thread::sleep(std::time::Duration::from_millis(10));
+        }
+    }
+
+    /// Waits for all of the remaining children to exit. Like GNU xargs, if
+    /// any of them fail with an error, the first such error is returned only
+    /// after the rest have finished.
+    fn wait_all(mut self) -> Result<CommandResult, CommandExecutionError> {
+        let mut error = None;
+        while !self.children.is_empty() {
+            if let Err(e) = self.wait_any() {
+                error.get_or_insert(e);
+            }
+        }
+
+        match error {
+            Some(e) => Err(e),
+            None => Ok(self.result),
+        }
+    }
+}
+
 enum ExecAction {
     Command(Vec<OsString>),
     Echo,
 }
 
+/// Asks whether to run the given command (-p), reading the answer from `tty`.
+/// Reaching the end of the input counts as "no".
+fn confirm(command: &Command, tty: &mut impl BufRead, prompt: &mut impl Write) -> io::Result<bool> {
+    let command_line = std::iter::once(command.get_program())
+        .chain(command.get_args())
+        .map(OsStr::to_string_lossy)
+        .collect::<Vec<_>>()
+        .join(" ");
+    write!(prompt, "{command_line} ?...")?;
+    prompt.flush()?;
+
+    let mut response = vec![];
+    if tty.read_until(b'\n', &mut response)? == 0 {
+        return Ok(false);
+    }
+    Ok(is_affirmative(&String::from_utf8_lossy(&response)))
+}
+
 struct CommandBuilderOptions {
     action: ExecAction,
     env: HashMap<OsString, OsString>,
     limiters: LimiterCollection,
     verbose: bool,
     close_stdin: bool,
+    /// The terminal to read -p answers from.
+    prompt_tty: Option<RefCell<BufReader<fs::File>>>,
+    /// The terminal to use as each command's stdin (-o).
+    stdin_tty: Option<fs::File>,
+    process_slot_var: Option<OsString>,
     replace: Option<String>,
 }
 impl CommandBuilderOptions {
@@ -371,6 +581,9 @@ impl CommandBuilderOptions {
             limiters,
             verbose: false,
             close_stdin: false,
+            prompt_tty: None,
+            stdin_tty: None,
+            process_slot_var: None,
             replace,
         })
     }
@@ -397,7 +610,7 @@ impl CommandBuilder<'_> {
         Ok(())
     }
 
-    fn execute(self) -> Result<CommandResult, CommandExecutionError> {
+    fn execute(self, pool: &mut ProcessPool) -> Result<(), CommandExecutionError> {
         let (entry_point, initial_args): (&OsStr, &[OsString]) = match &self.options.action {
             ExecAction::Command(args) => (&args[0], &args[1..]),
             ExecAction::Echo => (OsStr::new("echo"), &[]),
@@ -430,45 +643,27 @@ impl CommandBuilder<'_> {
                 .envs(&self.options.env);
         };
 
-        if self.options.close_stdin {
+        if let Some(tty) = &self.options.stdin_tty {
+            command.stdin(tty.try_clone().map_err(CommandExecutionError::CannotRun)?);
+        } else if self.options.close_stdin {
             command.stdin(Stdio::null());
         }
 
-        if self.options.verbose {
+        if let Some(tty) = &self.options.prompt_tty {
+            // -p implies -t, since the prompt shows the command anyway.
+            if !confirm(&command, &mut *tty.borrow_mut(), &mut io::stderr())
+                .map_err(CommandExecutionError::CannotRun)?
+            {
+                return Ok(());
+            }
+        } else if self.options.verbose {
             eprintln!("{command:?}");
         }
 
         match &self.options.action {
-            ExecAction::Command(_) => match command.status() {
-                Ok(status) => {
-                    if status.success() {
-                        Ok(CommandResult::Success)
-                    } else if let Some(err) = status.code() {
-                        if err == 255 {
-                            Err(CommandExecutionError::UrgentlyFailed)
-                        } else {
-                            Ok(CommandResult::Failure)
-                        }
-                    } else {
-                        #[cfg(unix)]
-                        {
-                            use std::os::unix::process::ExitStatusExt;
-                            if let Some(signal) = status.signal() {
-                                Err(CommandExecutionError::Killed { signal })
-                            } else {
-                                Err(CommandExecutionError::Unknown)
-                            }
-                        }
-
-                        #[cfg(not(unix))]
-                        Err(CommandExecutionError::Unknown)
-                    }
-                }
-                Err(e) if e.kind() == io::ErrorKind::NotFound => {
-                    Err(CommandExecutionError::NotFound)
-                }
-                Err(e) => Err(CommandExecutionError::CannotRun(e)),
-            },
+            ExecAction::Command(_) => {
+                pool.spawn(&mut command, self.options.process_slot_var.as_deref())
+            }
             ExecAction::Echo => {
                 println!(
                     "{}",
@@ -478,12 +673,25 @@ impl CommandBuilder<'_> {
                         .collect::<Vec<_>>()
                         .join(" ")
                 );
-                Ok(CommandResult::Success)
+                Ok(())
             }
         }
     }
 }
 
+/// Converts an argument read from the input, keeping the exact bytes on Unix
+/// so that file names that aren't valid UTF-8 are passed on unchanged.
+#[cfg(unix)]
+fn os_string_from_bytes(bytes: Vec<u8>) -> OsString {
+    use std::os::unix::ffi::OsStringExt;
+    OsString::from_vec(bytes)
+}
+
+#[cfg(not(unix))]
+fn os_string_from_bytes(bytes: Vec<u8>) -> OsString {
+    String::from_utf8_lossy(&bytes).into_owned().into()
+}
+
 trait ArgumentReader {
     fn next(&mut self) -> io::Result<Option<Argument>>;
 }
@@ -579,7 +787,7 @@ where
         }
 
         Ok(Some(Argument {
-            arg: String::from_utf8_lossy(&result[..]).into_owned().into(),
+            arg: os_string_from_bytes(result),
             kind: if terminated_by_newline {
                 ArgumentKind::HardTerminated
             } else {
@@ -628,7 +836,7 @@ where
                     &buf[..]
                 };
                 break Some(Argument {
-                    arg: String::from_utf8_lossy(bytes).into_owned().into(),
+                    arg: os_string_from_bytes(bytes.to_vec()),
                     kind: ArgumentKind::HardTerminated,
                 });
             }
@@ -637,6 +845,40 @@ where
     }
 }
 
+/// Stops reading arguments at the logical end-of-file string given to -E/-e.
+struct EofArgumentReader {
+    inner: Box<dyn ArgumentReader>,
+    eof: OsString,
+    seen_eof: bool,
+}
+
+impl EofArgumentReader {
+    fn new(inner: Box<dyn ArgumentReader>, eof: impl Into<OsString>) -> Self {
+        Self {
+            inner,
+            eof: eof.into(),
+            seen_eof: false,
+        }
+    }
+}
+
+impl ArgumentReader for EofArgumentReader {
+    fn next(&mut self) -> io::Result<Option<Argument>> {
+        if self.seen_eof {
+            return Ok(None);
+        }
+
+        match self.inner.next()? {
+            Some(arg) if arg.arg == self.eof => {
+                // Everything after the end-of-file string is ignored.
+                self.seen_eof = true;
+                Ok(None)
+            }
+            arg => Ok(arg),
+        }
+    }
+}
+
 #[derive(Debug)]
 enum XargsError {
     ArgumentTooLarge,
@@ -686,6 +928,7 @@ struct InputProcessOptions {
     exit_if_pass_char_limit: bool,
     max_args: Option<usize>,
     max_lines: Option<usize>,
+    max_procs: usize,
     no_run_if_empty: bool,
 }
 
@@ -694,12 +937,14 @@ impl InputProcessOptions {
         exit_if_pass_char_limit: bool,
         max_args: Option<usize>,
         max_lines: Option<usize>,
+        max_procs: usize,
         no_run_if_empty: bool,
     ) -> Self {
         InputProcessOptions {
             exit_if_pass_char_limit,
             max_args,
             max_lines,
+            max_procs,
             no_run_if_empty,
         }
     }
@@ -707,12 +952,28 @@ impl InputProcessOptions {
 
 fn process_input(
     builder_options: CommandBuilderOptions,
-    mut args: Box<dyn ArgumentReader>,
+    args: Box<dyn ArgumentReader>,
     options: &InputProcessOptions,
 ) -> Result<CommandResult, XargsError> {
-    let mut current_builder = CommandBuilder::new(&builder_options);
+    let mut pool = ProcessPool::new(options.max_procs);
+    match run_commands(&builder_options, args, options, &mut pool) {
+        Ok(()) => Ok(pool.wait_all()?),
+        Err(e) => {
+            // Don't leave any commands that are still running behind.
+            let _ = pool.wait_all();
+            Err(e)
+        }
+    }
+}
+
+fn run_commands(
+    builder_options: &CommandBuilderOptions,
+    mut args: Box<dyn ArgumentReader>,
+    options: &InputProcessOptions,
+    pool: &mut ProcessPool,
+) -> Result<(), XargsError> {
+    let mut current_builder = CommandBuilder::new(builder_options);
     let mut have_pending_command = false;
-    let mut result = CommandResult::Success;
 
     while let Some(arg) = args.next()? {
         if let Err(ExhaustedCommandSpace { arg, out_of_chars }) = current_builder.add_arg(arg) {
@@ -723,10 +984,10 @@ fn process_input(
                 return Err(XargsError::ArgumentTooLarge);
             }
             if have_pending_command {
-                result.combine(current_builder.execute()?);
+                current_builder.execute(pool)?;
             }
 
-            current_builder = CommandBuilder::new(&builder_options);
+            current_builder = CommandBuilder::new(builder_options);
             if let Err(ExhaustedCommandSpace { .. }) = current_builder.add_arg(arg) {
                 return Err(XargsError::ArgumentTooLarge);
             }
@@ -736,10 +997,10 @@ fn process_input(
     }
 
     if !options.no_run_if_empty || have_pending_command {
-        result.combine(current_builder.execute()?);
+        current_builder.execute(pool)?;
     }
 
-    Ok(result)
+    Ok(())
 }
 
 fn parse_delimiter(s: &str) -> Result<u8, String> {
@@ -767,6 +1028,17 @@ fn parse_delimiter(s: &str) -> Result<u8, String> {
     }
 }
 
+/// The most commands -P can run at once, which is `INT_MAX` as in GNU xargs.
+const MAX_PROCS_LIMIT: usize = i32::MAX as usize;
+
+fn validate_max_procs(s: &str) -> Result<usize, String> {
+    match s.parse::<usize>() {
+        Ok(v) if v <= MAX_PROCS_LIMIT => Ok(v),
+        Ok(v) => Err(format!("Value must be <= {MAX_PROCS_LIMIT}, not: {v}")),
+        Err(e) => Err(e.to_string()),
+    }
+}
+
 fn validate_positive_usize(s: &str) -> Result<usize, String> {
     match s.parse::<usize>() {
         Ok(v) if v > 0 => Ok(v),
@@ -799,11 +1071,13 @@ fn normalize_options<'a>(
             );
                 let lines_index = matches
                     .indices_of(options::MAX_LINES)
-                    .and_then(|v| v.last());
-                let args_index = matches.indices_of(options::MAX_ARGS).and_then(|v| v.last());
+                    .and_then(|mut v| v.next_back());
+                let args_index = matches
+                    .indices_of(options::MAX_ARGS)
+                    .and_then(|mut v| v.next_back());
                 let replace_index = [options::REPLACE, options::REPLACE_I]
                     .iter()
-                    .flat_map(|o| matches.indices_of(o).and_then(|v| v.last()))
+                    .flat_map(|o| matches.indices_of(o).and_then(|mut v| v.next_back()))
                     .max();
                 if lines_index > args_index && lines_index > replace_index {
                     (None, options.max_lines, &None)
@@ -817,8 +1091,8 @@ fn normalize_options<'a>(
 
     let delimiter = match (options.delimiter, options.null) {
         (Some(delimiter), true) => {
-            if matches.indices_of(options::NULL).unwrap().last()
-                > matches.indices_of(options::DELIMITER).unwrap().last()
+            if matches.indices_of(options::NULL).unwrap().next_back()
+                > matches.indices_of(options::DELIMITER).unwrap().next_back()
             {
                 Some(b'\0')
             } else {
@@ -835,8 +1109,76 @@ fn normalize_options<'a>(
     (max_args, max_lines, replace, delimiter)
 }
 
+/// Checks whether an option takes a value from the next argument when it
+/// isn't attached (e.g. `-n 1`, but not `-e` or `--eof`).
+fn takes_separate_value(arg: &Arg) -> bool {
+    arg.get_action().takes_values() && !arg.is_require_equals_set()
+}
+
+/// Rewrites `-eEOF` as `--eof=EOF`.  Like GNU xargs, the value of -e is
+/// optional, so it has to be attached to the option, but clap only supports
+/// that for long options.  Nothing after the command is touched.
+fn normalize_eof_args<'a>(command: &clap::Command, args: &[&'a str]) -> Vec<Cow<'a, str>> {
+    let mut normalized = vec![];
+    let mut args = args.iter().copied();
+    normalized.extend(args.next().map(Cow::from));
+
+    while let Some(arg) = args.next() {
+        if arg == "--" || arg == "-" || !arg.starts_with('-') {
+            normalized.push(arg.into());
+            break;
+        }
+
+        let takes_next = if let Some(long) = arg.strip_prefix("--") {
+            normalized.push(arg.into());
+            !long.contains('=')
+                && command
+                    .get_arguments()
+                    .any(|a| a.get_long() == Some(long) && takes_separate_value(a))
+        } else {
+            // A group of short options, up to the first one that takes a value
+            let shorts = &arg[1..];
+            let option = shorts.char_indices().find_map(|(i, c)| {
+                command
+                    .get_arguments()
+                    .find(|a| a.get_short() == Some(c) && a.get_action().takes_values())
+                    .map(|a| (i, c, a))
+            });
+            match option {
+                Some((i, 'e', _)) => {
+                    if i > 0 {
+                        normalized.push(format!("-{}", &shorts[..i]).into());
+                    }
+                    let eof = &shorts[i + 1..];
+                    if eof.is_empty() {
+                        normalized.push("-e".into());
+                    } else {
+                        normalized.push(format!("--{}={eof}", options::EOF).into());
+                    }
+                    false
+                }
+                Some((i, c, option)) => {
+                    normalized.push(arg.into());
+                    i + c.len_utf8() == shorts.len() && takes_separate_value(option)
+                }
+                None => {
+                    normalized.push(arg.into());
+                    false
+                }
+            }
+        };
+
+        if takes_next {
+            normalized.extend(args.next().map(Cow::from));
+        }
+    }
+
+    normalized.extend(args.map(Cow::from));
+    normalized
+}
+
 fn do_xargs(args: &[&str]) -> Result<CommandResult, XargsError> {
-    let matches = clap::Command::new("xargs")
+    let mut command = clap::Command::new("xargs")
         .version(crate_version!())
         .about("Run commands using arguments derived from standard input")
         .arg(
@@ -859,6 +1201,29 @@ fn do_xargs(args: &[&str]) -> Result<CommandResult, XargsError> {
                 .help("Use the given delimiter to split the input")
                 .value_parser(parse_delimiter),
         )
+        .arg(
+            Arg::new(options::EOF)
+                .long(options::EOF)
+                .short('e')
+                .num_args(0..=1)
+                .require_equals(true)
+                .value_name("EOF")
+                .help(
+                    "If EOF is specified, the same as -E EOF; otherwise, there is no \
+                    end-of-file string",
+                ),
+        )
+        .arg(
+            Arg::new(options::EOF_E)
+                .short('E')
+                .num_args(1)
+                .value_name("EOF")
+                .help(
+                    "Stop reading input at a line or argument equal to EOF \
+                    (ignored with -0 and -d)",
+                )
+                .overrides_with(options::EOF),
+        )
         .arg(
             Arg::new(options::EXIT)
                 .short('x')
@@ -869,6 +1234,16 @@ fn do_xargs(args: &[&str]) -> Result<CommandResult, XargsError> {
                 )
                 .action(ArgAction::SetTrue),
         )
+        .arg(
+            Arg::new(options::INTERACTIVE)
+                .short('p')
+                .long(options::INTERACTIVE)
+                .help(
+                    "Prompt before running each command, and only run it if the \
+                    answer read from the terminal is affirmative (implies -t)",
+                )
+                .action(ArgAction::SetTrue),
+        )
         .arg(
             Arg::new(options::MAX_ARGS)
                 .short('n')
@@ -893,8 +1268,8 @@ fn do_xargs(args: &[&str]) -> Result<CommandResult, XargsError> {
             Arg::new(options::MAX_PROCS)
                 .short('P')
                 .long(options::MAX_PROCS)
-                .help("Run up to this many commands in parallel [NOT IMPLEMENTED]")
-                .value_parser(clap::value_parser!(usize)),
+                .help("Run up to this many commands in parallel, or as many as possible if 0")
+                .value_parser(validate_max_procs),
         )
         .arg(
             Arg::new(options::NO_RUN_IF_EMPTY)
@@ -910,6 +1285,26 @@ fn do_xargs(args: &[&str]) -> Result<CommandResult, XargsError> {
                 .help("Split the input by null terminators rather than whitespace")
                 .action(ArgAction::SetTrue),
         )
+        .arg(
+            Arg::new(options::OPEN_TTY)
+                .short('o')
+                .long(options::OPEN_TTY)
+                .help(
+                    "Reopen the terminal as standard input in each command, rather \
+                    than closing it",
+                )
+                .action(ArgAction::SetTrue),
+        )
+        .arg(
+            Arg::new(options::PROCESS_SLOT_VAR)
+                .long(options::PROCESS_SLOT_VAR)
+                .value_name("NAME")
+                .help(
+                    "Set the environment variable NAME in each command to a unique \
+                    number, from 0 up to one less than the number of parallel commands",
+                )
+                .value_parser(clap::value_parser!(OsString)),
+        )
         .arg(
             Arg::new(options::MAX_CHARS)
                 .short('s')
@@ -920,6 +1315,15 @@ fn do_xargs(args: &[&str]) -> Result<CommandResult, XargsError> {
                 )
                 .value_parser(validate_positive_usize),
         )
+        .arg(
+            Arg::new(options::SHOW_LIMITS)
+                .long(options::SHOW_LIMITS)
+                .help(
+                    "Display the limits on the command line length imposed by the \
+                    system, and continue",
+                )
+                .action(ArgAction::SetTrue),
+        )
         .arg(
             Arg::new(options::VERBOSE)
                 .short('t')
@@ -949,8 +1353,10 @@ fn do_xargs(args: &[&str]) -> Result<CommandResult, XargsError> {
                 )
                 .overrides_with(options::REPLACE)
                 .value_parser(clap::value_parser!(String)),
-        )
-        .try_get_matches_from(args);
+        );
+    command.build();
+    let args = normalize_eof_args(&command, args);
+    let matches = command.try_get_matches_from(args.iter().map(AsRef::<str>::as_ref));
 
     let matches = match matches {
         Ok(m) => m,
@@ -970,12 +1376,27 @@ fn do_xargs(args: &[&str]) -> Result<CommandResult, XargsError> {
             .get_one::<String>(options::ARG_FILE)
             .map(std::borrow::ToOwned::to_owned),
         delimiter: matches.get_one::<u8>(options::DELIMITER).copied(),
+        eof: [options::EOF_E, options::EOF]
+            .iter()
+            .find(|&&option| matches.contains_id(option))
+            .and_then(|&option| matches.get_one::<String>(option))
+            .filter(|eof| !eof.is_empty())
+            .map(std::borrow::ToOwned::to_owned),
         exit_if_pass_char_limit: matches.get_flag(options::EXIT),
+        interactive: matches.get_flag(options::INTERACTIVE),
         max_args: matches.get_one::<usize>(options::MAX_ARGS).copied(),
         max_chars: matches.get_one::<usize>(options::MAX_CHARS).copied(),
         max_lines: matches.get_one::<usize>(options::MAX_LINES).copied(),
+        max_procs: matches
+            .get_one::<usize>(options::MAX_PROCS)
+            .copied()
+            .unwrap_or(1),
         no_run_if_empty: matches.get_flag(options::NO_RUN_IF_EMPTY),
         null: matches.get_flag(options::NULL),
+        open_tty: matches.get_flag(options::OPEN_TTY),
+        process_slot_var: matches
+            .get_one::<OsString>(options::PROCESS_SLOT_VAR)
+            .map(std::borrow::ToOwned::to_owned),
         replace: [options::REPLACE_I, options::REPLACE]
             .iter()
             .find_map(|&option| {
@@ -985,6 +1406,7 @@ fn do_xargs(args: &[&str]) -> Result<CommandResult, XargsError> {
                         .map_or_else(|| "{}".to_string(), std::borrow::ToOwned::to_owned)
                 })
             }),
+        show_limits: matches.get_flag(options::SHOW_LIMITS),
         verbose: matches.get_flag(options::VERBOSE),
     };
 
@@ -996,7 +1418,17 @@ fn do_xargs(args: &[&str]) -> Result<CommandResult, XargsError> {
         }
         _ => ExecAction::Echo,
     };
-    let env = std::env::vars_os().collect();
+    let mut env: HashMap<OsString, OsString> = std::env::vars_os().collect();
+    if let Some(slot_var) = &options.process_slot_var {
+        // The slot number is only known when each command starts, so leave
+        // room in the environment for the largest one it could be.
+        let max_slot = if options.max_procs == 0 {
+            MAX_PROCS_LIMIT - 1
+        } else {
+            options.max_procs - 1
+        };
+        env.insert(slot_var.clone(), max_slot.to_string().into());
+    }
 
     let mut limiters = LimiterCollection::new();
     if let Some(max_args) = max_args {
@@ -1008,7 +1440,19 @@ fn do_xargs(args: &[&str]) -> Result<CommandResult, XargsError> {
     if let Some(max_chars) = options.max_chars {
         limiters.add(MaxCharsCommandSizeLimiter::new(max_chars));
     }
-    limiters.add(MaxCharsCommandSizeLimiter::new_system(&env));
+    let system_limits = SystemLimits::new(&env);
+    limiters.add(MaxCharsCommandSizeLimiter::new_system(&system_limits));
+
+    if options.show_limits {
+        show_limits(&system_limits, options.max_chars);
+        if options.arg_file.is_none() && io::stdin().is_terminal() {
+            eprintln!(
+                "\nExecution of xargs will continue now, and it will try to read its input \
+                and run commands; if this is not what you wanted to happen, please type \
+                the end-of-file keystroke.\n"
+            );
+        }
+    }
 
     let mut builder_options = CommandBuilderOptions::new(action, env, limiters, replace.clone())
         .map_err(|_| {
@@ -1017,6 +1461,13 @@ fn do_xargs(args: &[&str]) -> Result<CommandResult, XargsError> {
 
     builder_options.verbose = options.verbose;
     builder_options.close_stdin = options.arg_file.is_none();
+    builder_options.process_slot_var = options.process_slot_var;
+    if options.interactive {
+        builder_options.prompt_tty = Some(RefCell::new(BufReader::new(open_tty()?)));
+    }
+    if options.open_tty {
+        builder_options.stdin_tty = Some(open_tty()?);
+    }
 
     let args_file: Box<dyn Read> = if let Some(path) = &options.arg_file {
         Box::new(fs::File::open(path).map_err(|e| format!("Failed to open {path}: {e}"))?)
@@ -1024,11 +1475,19 @@ fn do_xargs(args: &[&str]) -> Result<CommandResult, XargsError> {
         Box::new(io::stdin())
     };
 
-    let args: Box<dyn ArgumentReader> = if let Some(delimiter) = delimiter {
+    let mut args: Box<dyn ArgumentReader> = if let Some(delimiter) = delimiter {
         Box::new(ByteDelimitedArgumentReader::new(args_file, delimiter))
     } else {
         Box::new(WhitespaceDelimitedArgumentReader::new(args_file))
     };
+    // Like GNU xargs, the end-of-file string only applies to the default
+    // input format, not to -0 and -d.
+    if let Some(eof) = options
+        .eof
+        .filter(|_| !options.null && options.delimiter.is_none())
+    {
+        args = Box::new(EofArgumentReader::new(args, eof));
+    }
 
     let result = process_input(
         builder_options,
@@ -1037,6 +1496,7 @@ fn do_xargs(args: &[&str]) -> Result<CommandResult, XargsError> {
             options.exit_if_pass_char_limit,
             max_args,
             max_lines,
+            options.max_procs,
             options.no_run_if_empty,
         ),
     )?;
@@ -1312,6 +1772,22 @@ mod tests {
         assert_eq!(reader.next().unwrap(), None);
     }
 
+    #[test]
+    fn test_confirm() {
+        let mut command = Command::new("echo");
+        command.args(["a", "b c"]);
+
+        let mut prompt = vec![];
+        assert!(confirm(&command, &mut io::Cursor::new("y\n"), &mut prompt).unwrap());
+        assert_eq!(String::from_utf8(prompt).unwrap(), "echo a b c ?...");
+
+        assert!(confirm(&command, &mut io::Cursor::new("yes"), &mut vec![]).unwrap());
+        assert!(!confirm(&command, &mut io::Cursor::new("n\n"), &mut vec![]).unwrap());
+        assert!(!confirm(&command, &mut io::Cursor::new("\n"), &mut vec![]).unwrap());
+        // End of file means no
+        assert!(!confirm(&command, &mut io::Cursor::new(""), &mut vec![]).unwrap());
+    }
+
     #[test]
     fn test_delimiter_parsing() {
         assert_eq!(parse_delimiter("a").unwrap(), b'a');
@@ -16,7 +16,7 @@ use tempfile::Builder;
 use common::test_helpers::{
     fix_up_slashes, get_dir_entry_for, path_to_testing_commandline, FakeDependencies,
 };
-use findutils::find::matchers::exec::SingleExecMatcher;
+use findutils::find::matchers::exec::{MultiExecMatcher, SingleExecMatcher};
 use findutils::find::matchers::Matcher;
 
 mod common;
@@ -223,3 +223,128 @@ fn matching_fails_if_executable_fails() {
         ))
     );
 }
+
+#[test]
+fn multi_exec_runs_once_when_finished() {
+    let temp_dir = Builder::new()
+        .prefix("multi_exec_runs_once_when_finished")
+        .tempdir()
+        .unwrap();
+    let temp_dir_path = temp_dir.path().to_string_lossy();
+
+    let abbbc = get_dir_entry_for("test_data/simple", "abbbc");
+    let upper_abbbc = get_dir_entry_for("test_data/simple/subdir", "ABBBC");
+    let matcher = MultiExecMatcher::new(
+        &path_to_testing_commandline(),
+        &[temp_dir_path.as_ref(), "abc"],
+        false,
+    )
+    .expect("Failed to create matcher");
+    let deps = FakeDependencies::new();
+    let mut matcher_io = deps.new_matcher_io();
+    assert!(matcher.matches(&abbbc, &mut matcher_io));
+    assert!(matcher.matches(&upper_abbbc, &mut matcher_io));
+    assert!(
+        !temp_dir.path().join("1.txt").exists(),
+        "the command shouldn't run before the batch is finished"
+    );
+
+    matcher.finished(&mut matcher_io);
+    assert_eq!(matcher_io.exit_code(), 0);
+
+    let mut f = File::open(temp_dir.path().join("1.txt")).expect("Failed to open output file");
+    let mut s = String::new();
+    f.read_to_string(&mut s)
+        .expect("failed to read output file");
+    assert_eq!(
+        s,
+        fix_up_slashes(&format!(
+            "cwd={}\nargs=\nabc\ntest_data/simple/abbbc\ntest_data/simple/subdir/ABBBC\n",
+            env::current_dir().unwrap().to_string_lossy()
+        ))
+    );
+    assert!(!temp_dir.path().join("2.txt").exists());
+}
+
+#[test]
+fn multi_execdir_groups_by_directory() {
+    let temp_dir = Builder::new()
+        .prefix("multi_execdir_groups_by_directory")
+        .tempdir()
+        .unwrap();
+    let temp_dir_path = temp_dir.path().to_string_lossy();
+
+    let abbbc = get_dir_entry_for("test_data/simple", "abbbc");
+    let subdir = get_dir_entry_for("test_data/simple", "subdir");
+    let upper_abbbc = get_dir_entry_for("test_data/simple/subdir", "ABBBC");
+    let matcher = MultiExecMatcher::new(
+        &path_to_testing_commandline(),
+        &[temp_dir_path.as_ref()],
+        true,
+    )
+    .expect("Failed to create matcher");
+    let deps = FakeDependencies::new();
+    let mut matcher_io = deps.new_matcher_io();
+    assert!(matcher.matches(&abbbc, &mut matcher_io));
+    assert!(matcher.matches(&subdir, &mut matcher_io));
+    // A file from another directory flushes the previous batch
+    assert!(matcher.matches(&upper_abbbc, &mut matcher_io));
+    matcher.finished(&mut matcher_io);
+    assert_eq!(matcher_io.exit_code(), 0);
+
+    let cwd = env::current_dir().unwrap();
+    let mut s = String::new();
+    File::open(temp_dir.path().join("1.txt"))
+        .expect("Failed to open output file")
+        .read_to_string(&mut s)
+        .expect("failed to read output file");
+    assert_eq!(
+        s,
+        fix_up_slashes(&format!(
+            "cwd={}/test_data/simple\nargs=\n./abbbc\n./subdir\n",
+            cwd.to_string_lossy()
+        ))
+    );
+
+    let mut s = String::new();
+    File::open(temp_dir.path().join("2.txt"))
+        .expect("Failed to open output file")
+        .read_to_string(&mut s)
+        .expect("failed to read output file");
+    assert_eq!(
+        s,
+        fix_up_slashes(&format!(
+            "cwd={}/test_data/simple/subdir\nargs=\n./ABBBC\n",
+            cwd.to_string_lossy()
+        ))
+    );
+}
+
+#[test]
+fn multi_exec_failure_sets_exit_code() {
+    let temp_dir = Builder::new()
+        .prefix("multi_exec_failure_sets_exit_code")
+        .tempdir()
+        .unwrap();
+    let temp_dir_path = temp_dir.path().to_string_lossy();
+
+    let abbbc = get_dir_entry_for("test_data/simple", "abbbc");
+    let matcher = MultiExecMatcher::new(
+        &path_to_testing_commandline(),
+        &[temp_dir_path.as_ref(), "--exit_with_failure"],
+        false,
+    )
+    .expect("Failed to create matcher");
+    let deps = FakeDependencies::new();
+    let mut matcher_io = deps.new_matcher_io();
+    // -exec ... + always matches, the failure is only visible in the exit code
+    assert!(matcher.matches(&abbbc, &mut matcher_io));
+    matcher.finished(&mut matcher_io);
+    assert_eq!(matcher_io.exit_code(), 1);
+}
+
+#[test]
+fn multi_exec_rejects_embedded_braces() {
+    let result = MultiExecMatcher::new(&path_to_testing_commandline(), &["a{}b"], false);
+    assert!(result.is_err());
+}
@@ -74,6 +74,33 @@ fn two_matchers_one_matches() {
         .stdout(predicate::str::is_empty());
 }
 
+#[serial(working_dir)]
+#[test]
+fn type_lists() {
+    Command::cargo_bin("find")
+        .expect("found binary")
+        .args(["test_data/simple", "-sorted", "-type", "f,d"])
+        .assert()
+        .success()
+        .stderr(predicate::str::is_empty())
+        .stdout(fix_up_slashes(
+            "test_data/simple\n\
+             test_data/simple/abbbc\n\
+             test_data/simple/subdir\n\
+             test_data/simple/subdir/ABBBC\n",
+        ));
+
+    Command::cargo_bin("find")
+        .expect("found binary")
+        .args(["test_data/simple", "-xtype", "d,f,d"])
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains(
+            "Duplicate file type 'd' in the argument list to -xtype",
+        ))
+        .stdout(predicate::str::is_empty());
+}
+
 #[test]
 fn matcher_with_side_effects_at_end() {
     let temp_dir = Builder::new().prefix("find_cmd_").tempdir().unwrap();
@@ -599,6 +626,112 @@ fn expression_empty_parentheses() {
         .stdout(predicate::str::is_empty());
 }
 
+#[test]
+fn expression_errors_point_at_arguments() {
+    Command::cargo_bin("find")
+        .expect("found binary")
+        .args(["test_data", "-name", "a", "-type", "q", "-print"])
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains(
+            "  test_data -name a -type q -print\n                    ^^^^^^^\n",
+        ))
+        .stdout(predicate::str::is_empty());
+}
+
+#[test]
+#[cfg(target_os = "linux")]
+#[serial(working_dir)]
+fn find_only_stats_when_needed() {
+    // Names and types come from the directory entries
+    for flags in [&[][..], &["-j2"], &["-S", "bfs"], &["-S", "ids"]] {
+        Command::cargo_bin("find")
+            .expect("found binary")
+            .args(flags)
+            .args(["-D", "stat", "test_data", "-name", "*.txt", "-type", "f"])
+            .assert()
+            .success()
+            .stderr("-D stat: 0 stat() calls\n");
+    }
+
+    Command::cargo_bin("find")
+        .expect("found binary")
+        .args(["-D", "stat", "test_data/simple", "-size", "-1k"])
+        .assert()
+        .success()
+        .stderr(predicate::str::contains(
+            "-D stat: stat(test_data/simple/abbbc)\n",
+        ))
+        .stderr(predicate::str::is_match("\n-D stat: [1-9][0-9]* stat\\(\\) calls\n$").unwrap());
+}
+
+#[test]
+#[serial(working_dir)]
+fn find_debug_options() {
+    Command::cargo_bin("find")
+        .expect("found binary")
+        .args(["-D", "help"])
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("\nrates "))
+        .stdout(predicate::str::contains("\ntree "))
+        .stderr(predicate::str::is_empty());
+
+    Command::cargo_bin("find")
+        .expect("found binary")
+        .args(["-D", "tree,opt,rates", "test_data/simple"])
+        .args(["-type", "f", "-true", "-name", "abbbc"])
+        .assert()
+        .success()
+        .stdout(fix_up_slashes("test_data/simple/abbbc\n"))
+        .stderr(
+            "-D tree: parsed: -type f -true -name abbbc\n\
+             -D opt: -type f -a -true -a -name abbbc => -name abbbc -a -type f\n\
+             -D opt: ( -name abbbc -a -type f ) -a -print => -name abbbc -a -type f -a -print\n\
+             -D tree: optimized: -name abbbc -a -type f -a -print\n\
+             -D rates: -name abbbc: 1/4 succeeded (25.0%)\n\
+             -D rates: -type f: 1/1 succeeded (100.0%)\n\
+             -D rates: -print: 1/1 succeeded (100.0%)\n",
+        );
+
+    Command::cargo_bin("find")
+        .expect("found binary")
+        .args(["-D", "search", "test_data/simple"])
+        .args(["-name", "subdir", "-prune"])
+        .assert()
+        .success()
+        .stderr(predicate::str::contains(
+            "-D search: starting at test_data/simple\n",
+        ))
+        .stderr(predicate::str::contains(fix_up_slashes(
+            "-D search: visiting test_data/simple/subdir (depth 1)\n\
+             -D search: pruning test_data/simple/subdir\n",
+        )));
+
+    Command::cargo_bin("find")
+        .expect("found binary")
+        .args(["-D", "tree,nonsense", "test_data"])
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains(
+            "invalid argument to -D: 'nonsense'",
+        ));
+}
+
+#[test]
+#[cfg(unix)]
+#[serial(working_dir)]
+fn find_debug_exec() {
+    Command::cargo_bin("find")
+        .expect("found binary")
+        .args(["-D", "exec", "test_data/simple", "-name", "abbbc"])
+        .args(["-exec", "echo", "found", "{}", ";"])
+        .assert()
+        .success()
+        .stdout("found test_data/simple/abbbc\n")
+        .stderr("-D exec: echo found test_data/simple/abbbc\n");
+}
+
 #[test]
 #[cfg(unix)]
 #[serial(working_dir)]
@@ -817,6 +950,84 @@ fn find_age_range() {
     }
 }
 
+#[test]
+fn find_birth_time() {
+    let temp_dir = Builder::new().prefix("find_cmd_").tempdir().unwrap();
+    let file_path = temp_dir.path().join("file");
+    File::create(&file_path).unwrap();
+    let file_path = file_path.to_string_lossy();
+
+    if fs::metadata(&*file_path).unwrap().created().is_err() {
+        // Not every file system records birth times
+        Command::cargo_bin("find")
+            .expect("found binary")
+            .args([&file_path, "-Bmin", "-60", "-printf", "%B@"])
+            .assert()
+            .success()
+            .stdout(predicate::str::is_empty())
+            .stderr(predicate::str::contains("birth time"));
+        return;
+    }
+
+    for args in [
+        ["-Bmin", "-60"],
+        ["-Btime", "0"],
+        ["-newerBt", "1 hour ago"],
+    ] {
+        Command::cargo_bin("find")
+            .expect("found binary")
+            .arg(&*file_path)
+            .args(args)
+            .assert()
+            .success()
+            .stdout(format!("{file_path}\n"))
+            .stderr(predicate::str::is_empty());
+    }
+
+    for args in [["-Bmin", "+60"], ["-Btime", "+0"], ["-newerBt", "1 hour"]] {
+        Command::cargo_bin("find")
+            .expect("found binary")
+            .arg(&*file_path)
+            .args(args)
+            .assert()
+            .success()
+            .stdout(predicate::str::is_empty())
+            .stderr(predicate::str::is_empty());
+    }
+
+    Command::cargo_bin("find")
+        .expect("found binary")
+        .args([&file_path, "-printf", "%BY"])
+        .assert()
+        .success()
+        .stdout(predicate::str::is_match("^[0-9]{4}$").unwrap());
+}
+
+#[test]
+#[cfg(target_os = "linux")]
+fn find_birth_time_unavailable() {
+    // Files in /proc never have birth times, so they don't match, with one
+    // warning rather than an error for each of them
+    for args in [["-Bmin", "+0"], ["-Btime", "+0"], ["-newerBt", "@0"]] {
+        let output = Command::cargo_bin("find")
+            .expect("found binary")
+            .args(["/proc/self", "-maxdepth", "1"])
+            .args(args)
+            .output()
+            .unwrap();
+
+        assert!(output.status.success(), "{args:?}");
+        assert!(output.stdout.is_empty(), "{args:?}");
+        let stderr = String::from_utf8(output.stderr).unwrap();
+        assert_eq!(
+            stderr,
+            "find: warning: the birth time of /proc/self is not available, so files without \
+             one will not match\n",
+            "{args:?}"
+        );
+    }
+}
+
 #[test]
 #[cfg(unix)]
 #[serial(working_dir)]
@@ -1052,3 +1263,164 @@ fn find_ls() {
         .success()
         .stderr(predicate::str::is_empty());
 }
+
+#[test]
+fn find_files0_from_stdin() {
+    Command::cargo_bin("find")
+        .expect("found binary")
+        .args(["-files0-from", "-", "-sorted"])
+        .write_stdin(fix_up_slashes(
+            "./test_data/simple/subdir\0./test_data/depth/f0\0",
+        ))
+        .assert()
+        .success()
+        .stderr(predicate::str::is_empty())
+        .stdout(fix_up_slashes(
+            "./test_data/simple/subdir\n./test_data/simple/subdir/ABBBC\n./test_data/depth/f0\n",
+        ));
+
+    Command::cargo_bin("find")
+        .expect("found binary")
+        .args(["-files0-from", "-", "-ok", "echo", "{}", ";"])
+        .write_stdin("./test_data\0")
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains("cannot be combined with -ok"))
+        .stdout(predicate::str::is_empty());
+}
+
+/// Creates a file with a Latin-1 (and so invalid UTF-8) name in a new
+/// temporary directory, returning the directory and the file's path.
+#[cfg(target_os = "linux")]
+fn create_latin1_file(prefix: &str) -> (tempfile::TempDir, Vec<u8>) {
+    use std::os::unix::ffi::OsStrExt;
+
+    let temp_dir = Builder::new().prefix(prefix).tempdir().unwrap();
+    let path = temp_dir
+        .path()
+        .join(std::ffi::OsStr::from_bytes(b"caf\xe9"));
+    File::create(&path).unwrap();
+    let path = path.as_os_str().as_bytes().to_vec();
+    (temp_dir, path)
+}
+
+#[test]
+#[cfg(target_os = "linux")]
+fn find_print_latin1_names() {
+    let (temp_dir, path) = create_latin1_file("find_print_latin1_names");
+    let temp_dir_path = temp_dir.path().to_string_lossy();
+
+    for (action, delimiter) in [("-print", b'\n'), ("-print0", b'\0')] {
+        let mut expected = path.clone();
+        expected.push(delimiter);
+
+        Command::cargo_bin("find")
+            .expect("found binary")
+            .args([&temp_dir_path, "-type", "f", action])
+            .assert()
+            .success()
+            .stderr(predicate::str::is_empty())
+            .stdout(expected);
+    }
+
+    let fprint_file = temp_dir.path().join("fprint");
+    Command::cargo_bin("find")
+        .expect("found binary")
+        .args([
+            &temp_dir_path,
+            "-name",
+            "caf*",
+            "-fprint",
+            &fprint_file.to_string_lossy(),
+        ])
+        .assert()
+        .success()
+        .stdout(predicate::str::is_empty());
+
+    let mut expected = path.clone();
+    expected.push(b'\n');
+    assert_eq!(fs::read(fprint_file).unwrap(), expected);
+}
+
+#[test]
+#[cfg(target_os = "linux")]
+fn find_printf_latin1_names() {
+    let (temp_dir, path) = create_latin1_file("find_printf_latin1_names");
+    let temp_dir_path = temp_dir.path().to_string_lossy();
+
+    let mut expected = b"[caf\xe9][ caf\xe9][caf\xe9 ][".to_vec();
+    expected.extend_from_slice(&path);
+    expected.extend_from_slice(b"]\n");
+
+    Command::cargo_bin("find")
+        .expect("found binary")
+        .args([
+            &temp_dir_path,
+            "-type",
+            "f",
+            "-printf",
+            "[%f][%5f][%-5f][%p]\\n",
+        ])
+        .assert()
+        .success()
+        .stderr(predicate::str::is_empty())
+        .stdout(expected);
+}
+
+#[test]
+#[cfg(target_os = "linux")]
+fn find_ls_latin1_names() {
+    let (temp_dir, path) = create_latin1_file("find_ls_latin1_names");
+
+    let mut expected = b" ".to_vec();
+    expected.extend_from_slice(&path);
+    expected.push(b'\n');
+
+    Command::cargo_bin("find")
+        .expect("found binary")
+        .args([&temp_dir.path().to_string_lossy(), "-type", "f", "-ls"])
+        .assert()
+        .success()
+        .stderr(predicate::str::is_empty())
+        .stdout(predicate::function(move |out: &[u8]| {
+            out.ends_with(&expected)
+        }));
+}
+
+#[test]
+fn find_ignore_readdir_race() {
+    let temp_dir = Builder::new().prefix("find_cmd_").tempdir().unwrap();
+    let dir = temp_dir.path().to_string_lossy();
+    let victim = temp_dir.path().join("b");
+    let victim = victim.to_string_lossy();
+
+    // Visiting a deletes b, after the directory listing already included it
+    for (option, quiet) in [
+        ("-noignore_readdir_race", false),
+        ("-ignore_readdir_race", true),
+    ] {
+        File::create(temp_dir.path().join("a")).unwrap();
+        File::create(temp_dir.path().join("b")).unwrap();
+
+        let assert = Command::cargo_bin("find")
+            .expect("found binary")
+            .args([&dir, "-sorted", option, "-name", "a", "-exec", "rm", "-f"])
+            .args([&victim, ";", "-o", "-size", "-1k", "-print"])
+            .assert()
+            .stdout(predicate::str::is_empty());
+        if quiet {
+            assert.stderr(predicate::str::is_empty());
+        } else {
+            assert.stderr(predicate::str::contains("/b"));
+        }
+    }
+
+    // Missing starting points are still reported
+    Command::cargo_bin("find")
+        .expect("found binary")
+        .args([&format!("{dir}/missing"), "-ignore_readdir_race"])
+        .assert()
+        .failure()
+        .stdout(predicate::str::is_empty())
+        .stderr(predicate::str::contains("missing"));
+}
@@ -9,6 +9,8 @@
 /// ! But as the tests require running an external executable, they need to be run
 /// ! as integration tests so we can ensure that our testing-commandline binary
 /// ! has been built.
+use assert_cmd::Command;
+use predicates::prelude::*;
 use std::env;
 use std::fs::File;
 use std::io::Read;
@@ -105,3 +107,245 @@ fn find_execdir() {
         ))
     );
 }
+
+#[test]
+fn find_exec_multi() {
+    let temp_dir = Builder::new().prefix("find_exec_multi").tempdir().unwrap();
+    let temp_dir_path = temp_dir.path().to_string_lossy();
+    let deps = FakeDependencies::new();
+
+    let rc = find_main(
+        &[
+            "find",
+            &fix_up_slashes("./test_data/simple"),
+            "-sorted",
+            "-type",
+            "f",
+            "-exec",
+            &path_to_testing_commandline(),
+            temp_dir_path.as_ref(),
+            "{}",
+            "+",
+        ],
+        &deps,
+    );
+
+    assert_eq!(rc, 0);
+    assert_eq!(deps.get_output_as_string(), "");
+
+    // Both files should be passed to a single invocation.
+    let mut f = File::open(temp_dir.path().join("1.txt")).expect("Failed to open output file");
+    let mut s = String::new();
+    f.read_to_string(&mut s)
+        .expect("failed to read output file");
+    assert_eq!(
+        s,
+        fix_up_slashes(&format!(
+            "cwd={}\nargs=\n./test_data/simple/abbbc\n./test_data/simple/subdir/ABBBC\n",
+            env::current_dir().unwrap().to_string_lossy()
+        ))
+    );
+    assert!(!temp_dir.path().join("2.txt").exists());
+}
+
+#[test]
+fn find_execdir_multi() {
+    let temp_dir = Builder::new()
+        .prefix("find_execdir_multi")
+        .tempdir()
+        .unwrap();
+    let temp_dir_path = temp_dir.path().to_string_lossy();
+    let deps = FakeDependencies::new();
+
+    let rc = find_main(
+        &[
+            "find",
+            &fix_up_slashes("./test_data/simple"),
+            "-sorted",
+            "-type",
+            "f",
+            "-execdir",
+            &path_to_testing_commandline(),
+            temp_dir_path.as_ref(),
+            "{}",
+            "+",
+        ],
+        &deps,
+    );
+
+    assert_eq!(rc, 0);
+
+    // One invocation per directory.
+    let cwd = env::current_dir().unwrap();
+    let mut s = String::new();
+    File::open(temp_dir.path().join("1.txt"))
+        .expect("Failed to open output file")
+        .read_to_string(&mut s)
+        .expect("failed to read output file");
+    assert_eq!(
+        s,
+        fix_up_slashes(&format!(
+            "cwd={}/test_data/simple\nargs=\n./abbbc\n",
+            cwd.to_string_lossy()
+        ))
+    );
+
+    let mut s = String::new();
+    File::open(temp_dir.path().join("2.txt"))
+        .expect("Failed to open output file")
+        .read_to_string(&mut s)
+        .expect("failed to read output file");
+    assert_eq!(
+        s,
+        fix_up_slashes(&format!(
+            "cwd={}/test_data/simple/subdir\nargs=\n./ABBBC\n",
+            cwd.to_string_lossy()
+        ))
+    );
+}
+
+#[test]
+fn find_exec_multi_failure() {
+    let temp_dir = Builder::new()
+        .prefix("find_exec_multi_failure")
+        .tempdir()
+        .unwrap();
+    let temp_dir_path = temp_dir.path().to_string_lossy();
+    let deps = FakeDependencies::new();
+
+    let rc = find_main(
+        &[
+            "find",
+            &fix_up_slashes("./test_data/simple"),
+            "-exec",
+            &path_to_testing_commandline(),
+            temp_dir_path.as_ref(),
+            "--exit_with_failure",
+            "{}",
+            "+",
+            "-print",
+        ],
+        &deps,
+    );
+
+    // A failed batch doesn't stop -exec ... + from matching, but it does
+    // affect the exit status.
+    assert_eq!(rc, 1);
+    assert!(deps
+        .get_output_as_string()
+        .contains(&fix_up_slashes("./test_data/simple/abbbc")));
+}
+
+#[test]
+fn find_ok() {
+    let temp_dir = Builder::new().prefix("find_ok").tempdir().unwrap();
+    let temp_dir_path = temp_dir.path().to_string_lossy();
+    let testing_commandline = path_to_testing_commandline();
+
+    Command::cargo_bin("find")
+        .expect("found binary")
+        .args([
+            &fix_up_slashes("./test_data/simple"),
+            "-sorted",
+            "-type",
+            "f",
+            "-ok",
+            &testing_commandline,
+            temp_dir_path.as_ref(),
+            "--print_stdin",
+            "{}",
+            ";",
+            "-print",
+        ])
+        .write_stdin("yes\nno\n")
+        .assert()
+        .success()
+        .stderr(predicate::str::contains(fix_up_slashes(&format!(
+            "< {testing_commandline} ... ./test_data/simple/abbbc > ? "
+        ))))
+        .stderr(predicate::str::contains(fix_up_slashes(&format!(
+            "< {testing_commandline} ... ./test_data/simple/subdir/ABBBC > ? "
+        ))))
+        // -ok is false when the user declines
+        .stdout(fix_up_slashes("./test_data/simple/abbbc\n"));
+
+    // The command should only have run once, with stdin closed.
+    let mut f = File::open(temp_dir.path().join("1.txt")).expect("Failed to open output file");
+    let mut s = String::new();
+    f.read_to_string(&mut s)
+        .expect("failed to read output file");
+    assert_eq!(
+        s,
+        fix_up_slashes(&format!(
+            "cwd={}\nstdin=\nargs=\n--print_stdin\n./test_data/simple/abbbc\n",
+            env::current_dir().unwrap().to_string_lossy()
+        ))
+    );
+    assert!(!temp_dir.path().join("2.txt").exists());
+}
+
+#[test]
+fn find_okdir() {
+    let temp_dir = Builder::new().prefix("find_okdir").tempdir().unwrap();
+    let temp_dir_path = temp_dir.path().to_string_lossy();
+    let testing_commandline = path_to_testing_commandline();
+
+    Command::cargo_bin("find")
+        .expect("found binary")
+        .args([
+            &fix_up_slashes("./test_data/simple/subdir"),
+            "-type",
+            "f",
+            "-okdir",
+            &testing_commandline,
+            temp_dir_path.as_ref(),
+            "{}",
+            ";",
+        ])
+        .write_stdin("y\n")
+        .assert()
+        .success()
+        .stderr(predicate::str::contains(fix_up_slashes(&format!(
+            "< {testing_commandline} ... ./ABBBC > ? "
+        ))))
+        .stdout(predicate::str::is_empty());
+
+    let mut f = File::open(temp_dir.path().join("1.txt")).expect("Failed to open output file");
+    let mut s = String::new();
+    f.read_to_string(&mut s)
+        .expect("failed to read output file");
+    assert_eq!(
+        s,
+        fix_up_slashes(&format!(
+            "cwd={}/test_data/simple/subdir\nargs=\n./ABBBC\n",
+            env::current_dir().unwrap().to_string_lossy()
+        ))
+    );
+}
+
+#[test]
+fn find_ok_at_end_of_input() {
+    let temp_dir = Builder::new()
+        .prefix("find_ok_at_end_of_input")
+        .tempdir()
+        .unwrap();
+    let temp_dir_path = temp_dir.path().to_string_lossy();
+
+    // Running out of answers is the same as saying no.
+    Command::cargo_bin("find")
+        .expect("found binary")
+        .args([
+            &fix_up_slashes("./test_data/simple"),
+            "-ok",
+            &path_to_testing_commandline(),
+            temp_dir_path.as_ref(),
+            "{}",
+            ";",
+        ])
+        .write_stdin("")
+        .assert()
+        .success()
+        .stdout(predicate::str::is_empty());
+
+    assert!(!temp_dir.path().join("1.txt").exists());
+}
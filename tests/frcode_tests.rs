@@ -0,0 +1,86 @@
+// This file is part of the uutils findutils package.
+//
+// For the full copyright and license information, please view the LICENSE
+// file that was distributed with this source code.
+
+/// ! This file contains integration tests for frcode.
+use assert_cmd::Command;
+use predicates::prelude::*;
+
+/// The sample database from the GNU findutils manual.
+const SAMPLE_DB: &[u8] = b"\x00LOCATE02\0\
+                           \x00/usr/src\0\
+                           \x08/cmd/aardvark.c\0\
+                           \x06rmadillo.c\0\
+                           \xf7tmp/zoo\0";
+
+fn frcode() -> Command {
+    Command::cargo_bin("frcode").expect("found binary")
+}
+
+#[test]
+fn frcode_lines() {
+    frcode()
+        .write_stdin("/usr/src\n/usr/src/cmd/aardvark.c\n/usr/src/cmd/armadillo.c\n/usr/tmp/zoo\n")
+        .assert()
+        .success()
+        .stderr("")
+        .stdout(SAMPLE_DB);
+
+    // The last line doesn't need a newline, and blank lines are skipped
+    frcode()
+        .write_stdin("/usr/src\n\n/usr/src/cmd/aardvark.c\n/usr/src/cmd/armadillo.c\n/usr/tmp/zoo")
+        .assert()
+        .success()
+        .stdout(SAMPLE_DB);
+
+    frcode()
+        .write_stdin("")
+        .assert()
+        .success()
+        .stdout(&b"\x00LOCATE02\0"[..]);
+}
+
+#[test]
+fn frcode_null() {
+    frcode()
+        .arg("-0")
+        .write_stdin("/usr/src\0/usr/src/cmd/aardvark.c\0/usr/src/cmd/armadillo.c\0/usr/tmp/zoo\0")
+        .assert()
+        .success()
+        .stdout(SAMPLE_DB);
+
+    // Names can have newlines in them
+    let output = frcode()
+        .arg("--null")
+        .write_stdin("/tmp/a\nb\0/tmp/c\0")
+        .output()
+        .unwrap();
+    assert_eq!(output.stdout, b"\x00LOCATE02\0\x00/tmp/a\nb\0\x05c\0");
+
+    Command::cargo_bin("locate")
+        .expect("found binary")
+        .args(["-d", "-", "-0", "a"])
+        .write_stdin(output.stdout)
+        .assert()
+        .success()
+        .stdout("/tmp/a\nb\0");
+}
+
+#[test]
+fn frcode_slocate() {
+    frcode()
+        .args(["-S", "1"])
+        .write_stdin("/usr/src\n/usr/tmp\n")
+        .assert()
+        .success()
+        .stdout(&b"1\0/usr/src\0\x05tmp\0"[..]);
+
+    frcode()
+        .args(["--security-level", "2"])
+        .write_stdin("/usr/src\n")
+        .assert()
+        .failure()
+        .stdout("")
+        .stderr(predicate::str::starts_with("Error: "));
+}
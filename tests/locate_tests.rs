@@ -0,0 +1,336 @@
+// This file is part of the uutils findutils package.
+//
+// For the full copyright and license information, please view the LICENSE
+// file that was distributed with this source code.
+
+/// ! This file contains integration tests for locate.
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use assert_cmd::Command;
+use predicates::prelude::*;
+use tempfile::TempDir;
+
+/// Writes a LOCATE02 database of `names` (which must be sorted) to `path`.
+fn write_db(path: &Path, names: &[&str]) {
+    let mut db = b"\x00LOCATE02\x00".to_vec();
+    let mut prev: &[u8] = b"LOCATE02";
+    let mut prev_shared = 0;
+    for name in names {
+        let name = name.as_bytes();
+        let shared = prev.iter().zip(name).take_while(|(a, b)| a == b).count();
+        let change = shared as i16 - prev_shared as i16;
+        match i8::try_from(change) {
+            Ok(change) if change != i8::MIN => db.push(change as u8),
+            _ => {
+                db.push(0x80);
+                db.extend_from_slice(&change.to_be_bytes());
+            }
+        }
+        db.extend_from_slice(&name[shared..]);
+        db.push(b'\0');
+        prev = name;
+        prev_shared = shared;
+    }
+    fs::write(path, db).unwrap();
+}
+
+/// Makes a temporary directory with a database called "db" in it.
+fn make_db(names: &[&str]) -> (TempDir, PathBuf) {
+    let dir = tempfile::Builder::new()
+        .prefix("locate_tests")
+        .tempdir()
+        .unwrap();
+    let db = dir.path().join("db");
+    write_db(&db, names);
+    (dir, db)
+}
+
+const NAMES: &[&str] = &[
+    "/usr/src",
+    "/usr/src/cmd/Aardvark.c",
+    "/usr/src/cmd/armadillo.c",
+    "/usr/src/cmd/armadillo.h",
+    "/usr/tmp/zoo",
+];
+
+fn locate(db: &Path) -> Command {
+    let mut cmd = Command::cargo_bin("locate").expect("found binary");
+    cmd.env_remove("LOCATE_PATH").arg("-d").arg(db);
+    cmd
+}
+
+#[test]
+fn locate_substrings_and_globs() {
+    let (_dir, db) = make_db(NAMES);
+
+    // Without wildcards, the pattern can be anywhere in the name
+    locate(&db)
+        .arg("armadillo")
+        .assert()
+        .success()
+        .stderr(predicate::str::is_empty())
+        .stdout("/usr/src/cmd/armadillo.c\n/usr/src/cmd/armadillo.h\n");
+
+    // With them, it has to match the whole name
+    locate(&db)
+        .arg("*.c")
+        .assert()
+        .success()
+        .stdout("/usr/src/cmd/Aardvark.c\n/usr/src/cmd/armadillo.c\n");
+    locate(&db).arg("arma*").assert().failure().stdout("");
+
+    // Any pattern can match
+    locate(&db)
+        .args(["zoo", "*.h"])
+        .assert()
+        .success()
+        .stdout("/usr/src/cmd/armadillo.h\n/usr/tmp/zoo\n");
+}
+
+#[test]
+fn locate_basename_and_case() {
+    let (_dir, db) = make_db(NAMES);
+
+    locate(&db)
+        .args(["-b", "src"])
+        .assert()
+        .success()
+        .stdout("/usr/src\n");
+    locate(&db)
+        .args(["--basename", "a*"])
+        .assert()
+        .success()
+        .stdout("/usr/src/cmd/armadillo.c\n/usr/src/cmd/armadillo.h\n");
+
+    locate(&db).arg("aardvark").assert().failure();
+    locate(&db)
+        .args(["-i", "aardvark"])
+        .assert()
+        .success()
+        .stdout("/usr/src/cmd/Aardvark.c\n");
+    locate(&db)
+        .args(["-i", "*AARD*"])
+        .assert()
+        .success()
+        .stdout("/usr/src/cmd/Aardvark.c\n");
+}
+
+#[test]
+fn locate_regex() {
+    let (_dir, db) = make_db(NAMES);
+
+    locate(&db)
+        .args(["-r", "cmd/a.*\\.h$"])
+        .assert()
+        .success()
+        .stdout("/usr/src/cmd/armadillo.h\n");
+    locate(&db)
+        .args(["--regex", "-i", "^/USR/SRC/CMD/A"])
+        .assert()
+        .success()
+        .stdout("/usr/src/cmd/Aardvark.c\n/usr/src/cmd/armadillo.c\n/usr/src/cmd/armadillo.h\n");
+
+    // Intervals are written differently in each type
+    locate(&db)
+        .args(["-r", "--regextype", "posix-extended", "l{2}"])
+        .assert()
+        .success()
+        .stdout("/usr/src/cmd/armadillo.c\n/usr/src/cmd/armadillo.h\n");
+    locate(&db)
+        .args(["-r", "--regextype", "posix-basic", "l\\{2\\}"])
+        .assert()
+        .success()
+        .stdout("/usr/src/cmd/armadillo.c\n/usr/src/cmd/armadillo.h\n");
+
+    locate(&db)
+        .args(["-r", "--regextype", "perl", "a"])
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains("Invalid regex type"));
+}
+
+#[test]
+fn locate_count_limit_and_null() {
+    let (_dir, db) = make_db(NAMES);
+
+    locate(&db)
+        .args(["-c", "/usr"])
+        .assert()
+        .success()
+        .stdout("5\n");
+    locate(&db)
+        .args(["-l", "2", "/usr"])
+        .assert()
+        .success()
+        .stdout("/usr/src\n/usr/src/cmd/Aardvark.c\n");
+    locate(&db)
+        .args(["--count", "--limit=3", "/usr"])
+        .assert()
+        .success()
+        .stdout("3\n");
+    locate(&db)
+        .args(["-c", "nothing"])
+        .assert()
+        .failure()
+        .stdout("0\n");
+
+    locate(&db)
+        .args(["-0", "zoo", "Aard"])
+        .assert()
+        .success()
+        .stdout("/usr/src/cmd/Aardvark.c\0/usr/tmp/zoo\0");
+}
+
+#[test]
+fn locate_existing() {
+    let (_dir, db) = make_db(&[
+        "test_data/simple",
+        "test_data/simple/abbbc",
+        "test_data/simple/gone",
+    ]);
+
+    locate(&db)
+        .args(["-e", "simple"])
+        .assert()
+        .success()
+        .stdout("test_data/simple\ntest_data/simple/abbbc\n");
+    locate(&db)
+        .args(["-E", "simple"])
+        .assert()
+        .success()
+        .stdout("test_data/simple/gone\n");
+    locate(&db)
+        .args(["--non-existing", "abbbc"])
+        .assert()
+        .failure()
+        .stdout("");
+}
+
+#[test]
+fn locate_multiple_databases() {
+    let (dir, db) = make_db(&["/one/a", "/one/b"]);
+    let db2 = dir.path().join("db2");
+    write_db(&db2, &["/two/a"]);
+    let both = format!("{}:{}", db.display(), db2.display());
+
+    Command::cargo_bin("locate")
+        .expect("found binary")
+        .args(["-d", &both, "a"])
+        .assert()
+        .success()
+        .stdout("/one/a\n/two/a\n");
+
+    // Repeated -d options add up
+    Command::cargo_bin("locate")
+        .expect("found binary")
+        .arg("-d")
+        .arg(&db2)
+        .arg("-d")
+        .arg(&db)
+        .arg("a")
+        .assert()
+        .success()
+        .stdout("/two/a\n/one/a\n");
+
+    Command::cargo_bin("locate")
+        .expect("found binary")
+        .env("LOCATE_PATH", &both)
+        .arg("b")
+        .assert()
+        .success()
+        .stdout("/one/b\n");
+
+    // -d takes precedence over LOCATE_PATH
+    Command::cargo_bin("locate")
+        .expect("found binary")
+        .env("LOCATE_PATH", &both)
+        .arg("-d")
+        .arg(&db2)
+        .arg("b")
+        .assert()
+        .failure()
+        .stdout("");
+
+    // The limit applies to all the databases together
+    Command::cargo_bin("locate")
+        .expect("found binary")
+        .args(["-d", &both, "-l", "2", "/"])
+        .assert()
+        .success()
+        .stdout("/one/a\n/one/b\n");
+
+    // A database can come from stdin
+    Command::cargo_bin("locate")
+        .expect("found binary")
+        .args(["-d", "-", "/one"])
+        .pipe_stdin(&db)
+        .unwrap()
+        .assert()
+        .success()
+        .stdout("/one/a\n/one/b\n");
+}
+
+#[test]
+fn locate_statistics() {
+    let (_dir, db) = make_db(NAMES);
+    let size = fs::metadata(&db).unwrap().len();
+
+    locate(&db).arg("-S").assert().success().stdout(format!(
+        "Database {} is in the LOCATE02 format.\n\
+             Locate database size: {size} bytes\n\
+             All Filenames: 5\n\
+             File names have a cumulative length of 91 bytes.\n\
+             Of those file names,\n\
+             \n\
+             \t0 contain whitespace, \n\
+             \t0 contain newline characters, \n\
+             \tand 0 contain characters with the high bit set.\n\
+             Compression ratio 31.87% (higher is better)\n\
+             \n",
+        db.display()
+    ));
+
+    locate(&db)
+        .args(["--statistics", "armadillo"])
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("Matching Filenames: 2\n"))
+        .stdout(predicate::str::contains("/usr/src/cmd").not());
+}
+
+#[test]
+fn locate_errors() {
+    let (dir, db) = make_db(NAMES);
+
+    locate(&db)
+        .assert()
+        .failure()
+        .stderr("Error: no pattern to search for specified\n");
+
+    let missing = dir.path().join("missing");
+    locate(&missing)
+        .arg("zoo")
+        .assert()
+        .failure()
+        .stderr(predicate::str::starts_with(format!(
+            "Error: {}: ",
+            missing.display()
+        )));
+
+    let bad = dir.path().join("bad");
+    fs::write(&bad, "/usr/src\n/usr/tmp\n").unwrap();
+    locate(&bad).arg("zoo").assert().failure().stderr(format!(
+        "Error: {}: not a LOCATE02 database\n",
+        bad.display()
+    ));
+
+    // Other databases are still searched
+    let both = format!("{}:{}", bad.display(), db.display());
+    Command::cargo_bin("locate")
+        .expect("found binary")
+        .args(["-d", &both, "zoo"])
+        .assert()
+        .failure()
+        .stdout("/usr/tmp/zoo\n");
+}
@@ -0,0 +1,190 @@
+// This file is part of the uutils findutils package.
+//
+// For the full copyright and license information, please view the LICENSE
+// file that was distributed with this source code.
+
+/// ! This file contains integration tests for updatedb.
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use assert_cmd::Command;
+use predicates::prelude::*;
+use tempfile::TempDir;
+
+/// Makes a temporary directory to put databases in.
+fn make_dir() -> (TempDir, PathBuf) {
+    let dir = tempfile::Builder::new()
+        .prefix("updatedb_tests")
+        .tempdir()
+        .unwrap();
+    let db = dir.path().join("db");
+    (dir, db)
+}
+
+fn updatedb(db: &Path) -> Command {
+    let mut cmd = Command::cargo_bin("updatedb").expect("found binary");
+    cmd.env_remove("FINDOPTIONS")
+        .env_remove("PRUNEFS")
+        .env_remove("PRUNEPATHS")
+        .arg("--output")
+        .arg(db);
+    cmd
+}
+
+/// Lists everything in a database.
+fn locate_all(db: &Path) -> String {
+    let output = Command::cargo_bin("locate")
+        .expect("found binary")
+        .arg("-d")
+        .arg(db)
+        .arg("/")
+        .arg("test_data")
+        .output()
+        .unwrap();
+    String::from_utf8(output.stdout).unwrap()
+}
+
+#[test]
+fn updatedb_lists_local_paths() {
+    let (_dir, db) = make_dir();
+
+    updatedb(&db)
+        .arg("--localpaths=test_data/simple test_data/depth/1/2")
+        .assert()
+        .success()
+        .stdout("")
+        .stderr("");
+
+    // Names are sorted case-insensitively
+    assert_eq!(
+        locate_all(&db),
+        "test_data/depth/1/2\n\
+         test_data/depth/1/2/3\n\
+         test_data/depth/1/2/3/f3\n\
+         test_data/depth/1/2/f2\n\
+         test_data/simple\n\
+         test_data/simple/abbbc\n\
+         test_data/simple/subdir\n\
+         test_data/simple/subdir/ABBBC\n"
+    );
+
+    // No temporary files are left behind
+    let entries = fs::read_dir(db.parent().unwrap()).unwrap().count();
+    assert_eq!(entries, 1);
+}
+
+#[test]
+fn updatedb_prunes() {
+    let (_dir, db) = make_dir();
+
+    updatedb(&db)
+        .args([
+            "--localpaths=test_data/depth",
+            "--prunepaths=test_data/depth/1/2 .*/nothing",
+        ])
+        .assert()
+        .success();
+    assert_eq!(
+        locate_all(&db),
+        "test_data/depth\n\
+         test_data/depth/1\n\
+         test_data/depth/1/f1\n\
+         test_data/depth/f0\n"
+    );
+
+    // The environment is used if there's no option
+    updatedb(&db)
+        .env("PRUNEPATHS", "test_data/depth/1")
+        .arg("--localpaths=test_data/depth")
+        .assert()
+        .success();
+    assert_eq!(locate_all(&db), "test_data/depth\ntest_data/depth/f0\n");
+
+    // Only directories are pruned
+    updatedb(&db)
+        .args(["--localpaths=test_data/depth", "--prunepaths=.*/f0"])
+        .assert()
+        .success();
+    assert!(locate_all(&db).contains("test_data/depth/f0\n"));
+}
+
+#[test]
+fn updatedb_prunefs() {
+    let (_dir, db) = make_dir();
+
+    // Whatever the file system of test_data is, it's pruned if it's listed
+    let fs_type = Command::cargo_bin("find")
+        .expect("found binary")
+        .args(["test_data", "-maxdepth", "0", "-printf", "%F"])
+        .output()
+        .unwrap()
+        .stdout;
+    let fs_type = String::from_utf8(fs_type).unwrap();
+
+    updatedb(&db)
+        .args(["--localpaths=test_data/simple", "--prunefs", &fs_type])
+        .assert()
+        .success();
+    assert_eq!(locate_all(&db), "");
+
+    updatedb(&db)
+        .args(["--localpaths=test_data/simple", "--prunefs=nosuchfs"])
+        .assert()
+        .success();
+    assert_eq!(locate_all(&db).lines().count(), 4);
+}
+
+#[test]
+fn updatedb_findoptions() {
+    let (_dir, db) = make_dir();
+
+    updatedb(&db)
+        .args(["--localpaths=test_data/depth", "--findoptions=-maxdepth 1"])
+        .assert()
+        .success();
+    assert_eq!(
+        locate_all(&db),
+        "test_data/depth\ntest_data/depth/1\ntest_data/depth/f0\n"
+    );
+
+    updatedb(&db)
+        .env("FINDOPTIONS", "-maxdepth 0")
+        .arg("--localpaths=test_data/depth")
+        .assert()
+        .success();
+    assert_eq!(locate_all(&db), "test_data/depth\n");
+
+    updatedb(&db)
+        .args([
+            "--localpaths=test_data/depth",
+            "--findoptions=-nosuchoption",
+        ])
+        .assert()
+        .failure()
+        .stderr(predicate::str::starts_with(
+            "Error: invalid find options '-nosuchoption': ",
+        ));
+}
+
+#[test]
+fn updatedb_errors() {
+    let (dir, db) = make_dir();
+
+    // Missing paths are reported, but the database is still written
+    updatedb(&db)
+        .args(["--localpaths=test_data/simple test_data/missing"])
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains("test_data/missing"));
+    assert_eq!(locate_all(&db).lines().count(), 4);
+
+    let missing = dir.path().join("missing").join("db");
+    updatedb(&missing)
+        .arg("--localpaths=test_data/simple")
+        .assert()
+        .failure()
+        .stderr(predicate::str::starts_with(format!(
+            "Error: {}: ",
+            missing.display()
+        )));
+}
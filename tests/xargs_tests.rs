@@ -39,6 +39,34 @@ fn xargs_null() {
         .stdout(predicate::str::diff("ab c\nd\tef\n"));
 }
 
+#[test]
+#[cfg(target_os = "linux")]
+fn xargs_null_latin1() {
+    use std::os::unix::ffi::OsStrExt;
+
+    let temp_dir = tempfile::Builder::new()
+        .prefix("xargs_null_latin1")
+        .tempdir()
+        .unwrap();
+    let path = temp_dir
+        .path()
+        .join(std::ffi::OsStr::from_bytes(b"caf\xe9"));
+    std::fs::File::create(&path).unwrap();
+
+    let mut input = path.as_os_str().as_bytes().to_vec();
+    input.push(b'\0');
+
+    // The name has to reach rm exactly as it is on disk
+    Command::cargo_bin("xargs")
+        .expect("found binary")
+        .args(["-0", "rm"])
+        .write_stdin(input)
+        .assert()
+        .success()
+        .stderr(predicate::str::is_empty());
+    assert!(!path.exists());
+}
+
 #[test]
 fn xargs_delim() {
     Command::cargo_bin("xargs")
@@ -81,6 +109,93 @@ fn xargs_null_conflict() {
         .stdout(predicate::str::diff("ab c\nd\tef\n"));
 }
 
+#[test]
+fn xargs_eof() {
+    for eof in [
+        &["-E", "_"][..],
+        &["-E_"],
+        &["-e_"],
+        &["-re_"],
+        &["--eof=_"],
+    ] {
+        Command::cargo_bin("xargs")
+            .expect("found binary")
+            .args(eof)
+            .write_stdin("a b\n_ c\nd")
+            .assert()
+            .success()
+            .stderr(predicate::str::is_empty())
+            .stdout(predicate::str::diff("a b\n"));
+    }
+
+    // Anything attached to -e is the end-of-file string, even an '='
+    Command::cargo_bin("xargs")
+        .expect("found binary")
+        .args(["-e=_", "-n1"])
+        .write_stdin("a\n_\n=_\nb")
+        .assert()
+        .success()
+        .stdout(predicate::str::diff("a\n_\n"));
+
+    // Options after the command are left alone
+    Command::cargo_bin("xargs")
+        .expect("found binary")
+        .args(["-n", "1", "echo", "-e_"])
+        .write_stdin("a\n_\nb")
+        .assert()
+        .success()
+        .stdout(predicate::str::diff("-e_ a\n-e_ _\n-e_ b\n"));
+
+    // -e without a value, and an empty -E, mean there is no end-of-file string
+    for eof in [&["-E", "_", "-e"][..], &["-E", ""]] {
+        Command::cargo_bin("xargs")
+            .expect("found binary")
+            .args(eof)
+            .write_stdin("a b\n_ c\nd")
+            .assert()
+            .success()
+            .stderr(predicate::str::is_empty())
+            .stdout(predicate::str::diff("a b _ c d\n"));
+    }
+
+    // A quoted end-of-file string is still the end-of-file string
+    Command::cargo_bin("xargs")
+        .expect("found binary")
+        .args(["-E", "_"])
+        .write_stdin("a '_' b")
+        .assert()
+        .success()
+        .stdout(predicate::str::diff("a\n"));
+
+    // With -I, the whole line has to match
+    Command::cargo_bin("xargs")
+        .expect("found binary")
+        .args(["-E", "_", "-I", "{}", "echo", "[{}]"])
+        .write_stdin("a _\n_\nb\n")
+        .assert()
+        .success()
+        .stdout(predicate::str::diff("[a _]\n"));
+}
+
+#[test]
+fn xargs_eof_ignored_with_delimiter() {
+    Command::cargo_bin("xargs")
+        .expect("found binary")
+        .args(["-0", "-E", "_"])
+        .write_stdin("a\0_\0b\0")
+        .assert()
+        .success()
+        .stdout(predicate::str::diff("a _ b\n"));
+
+    Command::cargo_bin("xargs")
+        .expect("found binary")
+        .args(["-d,", "-E", "_"])
+        .write_stdin("a,_,b")
+        .assert()
+        .success()
+        .stdout(predicate::str::diff("a _ b\n"));
+}
+
 #[test]
 fn xargs_if_empty() {
     Command::cargo_bin("xargs")
@@ -217,6 +332,57 @@ fn xargs_max_chars() {
         .stdout(predicate::str::is_empty());
 }
 
+#[test]
+fn xargs_show_limits() {
+    Command::cargo_bin("xargs")
+        .expect("found binary")
+        .args(["--show-limits", "-s", "1000"])
+        .write_stdin("a b c")
+        .assert()
+        .success()
+        .stderr(predicate::str::contains(
+            "Your environment variables take up ",
+        ))
+        .stderr(predicate::str::contains(
+            "POSIX upper limit on argument length (this system): ",
+        ))
+        .stderr(predicate::str::contains(
+            "POSIX smallest allowable upper limit on argument length (all systems): 4096\n",
+        ))
+        .stderr(predicate::str::contains(
+            "Maximum length of command we could actually use: ",
+        ))
+        .stderr(predicate::str::contains(
+            "Size of command buffer we are actually using: 1000\n",
+        ))
+        .stderr(predicate::str::contains(
+            "Maximum parallelism (--max-procs must be no greater): 2147483647\n",
+        ))
+        // Running the command continues as normal afterwards
+        .stdout(predicate::str::diff("a b c\n"));
+}
+
+#[test]
+#[cfg(unix)]
+fn xargs_many_short_args() {
+    // The argv pointers count against ARG_MAX too, which matters most when
+    // the arguments are short
+    let input: String = (0..150_000).map(|i| format!("{i}\n")).collect();
+    let output = Command::cargo_bin("xargs")
+        .expect("found binary")
+        .args(["sh", "-c", "echo $#", "sh"])
+        .write_stdin(input)
+        .assert()
+        .success()
+        .stderr(predicate::str::is_empty())
+        .get_output()
+        .stdout
+        .clone();
+    let counts = String::from_utf8(output).unwrap();
+    let total: usize = counts.lines().map(|n| n.parse::<usize>().unwrap()).sum();
+    assert_eq!(total, 150_000);
+}
+
 #[test]
 fn xargs_exit_on_large() {
     Command::cargo_bin("xargs")
@@ -398,6 +564,200 @@ fn xargs_exec_not_found() {
         .stdout(predicate::str::is_empty());
 }
 
+/// Reads the files written by testing-commandline into `dir`, in sorted order
+/// since parallel commands may finish in any order.
+fn read_sorted_outputs(dir: &std::path::Path) -> Vec<String> {
+    let mut outputs = std::fs::read_dir(dir)
+        .unwrap()
+        .map(|entry| std::fs::read_to_string(entry.unwrap().path()).unwrap())
+        .collect::<Vec<_>>();
+    outputs.sort();
+    outputs
+}
+
+#[test]
+fn xargs_max_procs() {
+    for max_procs in ["-P1", "-P3", "-P0", "--max-procs=2"] {
+        let temp_dir = tempfile::Builder::new()
+            .prefix("xargs_max_procs")
+            .tempdir()
+            .unwrap();
+        let temp_dir_path = temp_dir.path().to_string_lossy();
+
+        Command::cargo_bin("xargs")
+            .expect("found binary")
+            .args([
+                max_procs,
+                "-n1",
+                &path_to_testing_commandline(),
+                &temp_dir_path,
+                "--no_print_cwd",
+            ])
+            .write_stdin("a b c d e")
+            .assert()
+            .success()
+            .stderr(predicate::str::is_empty())
+            .stdout(predicate::str::is_empty());
+
+        assert_eq!(
+            read_sorted_outputs(temp_dir.path()),
+            ["a", "b", "c", "d", "e"]
+                .iter()
+                .map(|arg| format!("args=\n--no_print_cwd\n{arg}\n"))
+                .collect::<Vec<_>>(),
+        );
+    }
+
+    // Like GNU xargs, -P is limited to INT_MAX
+    Command::cargo_bin("xargs")
+        .expect("found binary")
+        .args(["-P2147483648", "echo"])
+        .write_stdin("a")
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains("2147483647"))
+        .stdout(predicate::str::is_empty());
+}
+
+#[test]
+fn xargs_max_procs_failure() {
+    let temp_dir = tempfile::Builder::new()
+        .prefix("xargs_max_procs_failure")
+        .tempdir()
+        .unwrap();
+
+    Command::cargo_bin("xargs")
+        .expect("found binary")
+        .args([
+            "-P2",
+            "-n1",
+            &path_to_testing_commandline(),
+            &temp_dir.path().to_string_lossy(),
+            "--no_print_cwd",
+            "--exit_with_failure",
+        ])
+        .write_stdin("a b c")
+        .assert()
+        .failure()
+        .code(123)
+        .stderr(predicate::str::is_empty());
+
+    // A normal failure doesn't stop the remaining commands from running
+    assert_eq!(read_sorted_outputs(temp_dir.path()).len(), 3);
+}
+
+#[test]
+fn xargs_max_procs_urgent_failure() {
+    let temp_dir = tempfile::Builder::new()
+        .prefix("xargs_max_procs_urgent_failure")
+        .tempdir()
+        .unwrap();
+
+    Command::cargo_bin("xargs")
+        .expect("found binary")
+        .args([
+            "-P2",
+            "-n1",
+            &path_to_testing_commandline(),
+            &temp_dir.path().to_string_lossy(),
+            "--no_print_cwd",
+            "--exit_with_urgent_failure",
+        ])
+        .write_stdin("a b c d e f")
+        .assert()
+        .failure()
+        .code(124)
+        .stderr(predicate::str::contains("Error:"));
+
+    // No new commands are started after the first urgent failure, but the
+    // ones already running are waited for.
+    let outputs = read_sorted_outputs(temp_dir.path()).len();
+    assert!((1..=3).contains(&outputs), "ran {outputs} commands");
+}
+
+#[test]
+#[cfg(unix)]
+fn xargs_max_procs_concurrent() {
+    // Each command waits for the other to start, so this only finishes if
+    // both are running at the same time.
+    let script = r#"touch "$0"; i=0
+        while [ ! -e "$(dirname "$0")/a" ] || [ ! -e "$(dirname "$0")/b" ]; do
+            i=$((i + 1)); [ $i -gt 100 ] && exit 1; sleep 0.1
+        done"#;
+
+    let temp_dir = tempfile::Builder::new()
+        .prefix("xargs_max_procs_concurrent")
+        .tempdir()
+        .unwrap();
+    let a = temp_dir.path().join("a");
+    let b = temp_dir.path().join("b");
+
+    Command::cargo_bin("xargs")
+        .expect("found binary")
+        .args(["-P2", "-n1", "sh", "-c", script])
+        .write_stdin(format!("{}\n{}\n", a.display(), b.display()))
+        .assert()
+        .success();
+}
+
+#[test]
+#[cfg(unix)]
+fn xargs_process_slot_var() {
+    Command::cargo_bin("xargs")
+        .expect("found binary")
+        .args([
+            "--process-slot-var=SLOT",
+            "-n1",
+            "sh",
+            "-c",
+            "echo \"$SLOT\"",
+        ])
+        .write_stdin("a b c")
+        .assert()
+        .success()
+        .stdout("0\n0\n0\n");
+
+    // Slots are reused as commands exit, so only 0 and 1 are ever handed out
+    let output = Command::cargo_bin("xargs")
+        .expect("found binary")
+        .args([
+            "-P2",
+            "--process-slot-var",
+            "SLOT",
+            "-n1",
+            "sh",
+            "-c",
+            "sleep 0.1; echo \"$SLOT\"",
+        ])
+        .write_stdin("a b c d e")
+        .assert()
+        .success()
+        .get_output()
+        .stdout
+        .clone();
+    let slots = String::from_utf8(output).unwrap();
+    assert_eq!(slots.lines().count(), 5);
+    assert!(
+        slots.lines().all(|slot| slot == "0" || slot == "1"),
+        "unexpected slots: {slots:?}"
+    );
+
+    // The variable counts against the size of the environment, with room
+    // for the largest slot number
+    for (procs, env_size) in [("-P1", 7), ("-P100", 8), ("-P0", 16)] {
+        Command::cargo_bin("xargs")
+            .expect("found binary")
+            .env_clear()
+            .args([procs, "--show-limits", "--process-slot-var=SLOT", "true"])
+            .write_stdin("")
+            .assert()
+            .success()
+            .stderr(predicate::str::contains(format!(
+                "Your environment variables take up {env_size} bytes\n"
+            )));
+    }
+}
+
 #[test]
 fn xargs_exec_verbose() {
     Command::cargo_bin("xargs")